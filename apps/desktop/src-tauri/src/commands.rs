@@ -1,18 +1,63 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
 
-use crate::state::AppState;
+use crate::state::{
+    AppState, job_retry_backoff_ms, job_retry_max, queue_aging_secs, queue_capacity,
+};
+use crate::worker::llm_config_from_profile;
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::health::{ConnectionFailure, connection_test_runtime};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::LlmClient;
+use ocr2md_core::ocr::{GlmConfig, GlmOcrClient};
 use ocr2md_core::profile_store::ProviderProfile;
+use ocr2md_core::queue::{Enqueued, JobRecord, JobState, Queue, QueueLoadReport};
 
-pub fn enqueue_files_inner(state: &AppState, files: Vec<String>) -> Vec<u64> {
-    let mut queue = state.queue.lock().expect("queue mutex poisoned");
-    let ids: Vec<u64> = files.into_iter().map(|file| queue.enqueue(file)).collect();
-    state.notify_worker.notify_one();
-    ids
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EnqueueReport {
+    // One id per accepted file, in request order.
+    pub ids: Vec<u64>,
+    pub new: Vec<u64>,
+    // Files already waiting or running; their existing job id is reused.
+    pub deduplicated: Vec<u64>,
+    // Files turned away because the queue was at capacity.
+    pub rejected: Vec<String>,
+}
+
+pub fn enqueue_files_inner(state: &AppState, files: Vec<String>) -> EnqueueReport {
+    let report = state.update_queue(|queue| {
+        let mut report = EnqueueReport::default();
+        for file in files {
+            let enqueued = match queue.enqueue_dedup(file.as_str()) {
+                Ok(enqueued) => enqueued,
+                Err(_) => {
+                    report.rejected.push(file);
+                    continue;
+                }
+            };
+            match enqueued {
+                Enqueued::New(id) => report.new.push(id),
+                Enqueued::Existing(id) => report.deduplicated.push(id),
+            }
+            report.ids.push(enqueued.id());
+        }
+        report
+    });
+    if !report.new.is_empty() {
+        state.notify_worker.notify_one();
+    }
+    report
 }
 
 #[tauri::command]
-pub fn enqueue_files(files: Vec<String>, state: State<'_, AppState>) -> Result<Vec<u64>, String> {
+pub fn enqueue_files(
+    files: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<EnqueueReport, String> {
     Ok(enqueue_files_inner(&state, files))
 }
 
@@ -24,12 +69,180 @@ pub fn start_queue(state: State<'_, AppState>) -> Result<(), String> {
 
 #[tauri::command]
 pub fn retry_job(id: u64, state: State<'_, AppState>) -> Result<(), String> {
-    let mut queue = state.queue.lock().expect("queue mutex poisoned");
-    queue.mark_running(id, "retry");
+    state.update_queue(|queue| queue.mark_running(id, "retry"));
     state.notify_worker.notify_one();
     Ok(())
 }
 
+// Requeues every failed or cancelled job and wakes the worker once.
+pub fn retry_all_failed_inner(state: &AppState) -> Vec<u64> {
+    let ids = state.update_queue(Queue::requeue_failed);
+    if !ids.is_empty() {
+        state.notify_worker.notify_one();
+    }
+    ids
+}
+
+#[tauri::command]
+pub fn retry_all_failed(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<Vec<u64>, String> {
+    let ids = retry_all_failed_inner(&state);
+    let _ = app_handle.emit("queue-updated", ());
+    Ok(ids)
+}
+
+// Salvages what it can from the queue file, swaps the recovered jobs in and
+// rewrites the file cleanly so the next start reads it without repair. Jobs
+// the worker is running right now stay as they are in memory.
+pub fn repair_queue_inner(state: &AppState) -> Result<QueueLoadReport, String> {
+    let (mut recovered, report) = Queue::load_from(state.queue_path())
+        .map_err(|error| format!("failed to read queue file: {error}"))?;
+    recovered.set_priority_aging(queue_aging_secs());
+    recovered.set_capacity(queue_capacity());
+    recovered.set_retry_max(job_retry_max());
+    recovered.set_retry_backoff(job_retry_backoff_ms());
+
+    let mut queue = state.lock_queue();
+    recovered.keep_running_from(&queue);
+    *queue = recovered;
+    queue
+        .save_to(state.queue_path())
+        .map_err(|error| format!("failed to write queue file: {error}"))?;
+    state.notify_worker.notify_one();
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn repair_queue(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<QueueLoadReport, String> {
+    let report = repair_queue_inner(&state)?;
+    let _ = app_handle.emit("queue-updated", ());
+    Ok(report)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSummary {
+    #[serde(flatten)]
+    pub job: JobRecord,
+    pub duration_ms: Option<u64>,
+}
+
+// Snapshot of the whole queue for the history view, optionally narrowed to
+// one state.
+pub fn list_jobs_inner(state: &AppState, filter: Option<JobState>) -> Vec<JobSummary> {
+    state
+        .lock_queue()
+        .all_jobs()
+        .into_iter()
+        .filter(|job| filter.as_ref().is_none_or(|wanted| job.state == *wanted))
+        .map(|job| JobSummary {
+            duration_ms: job.duration_ms(),
+            job,
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_jobs(
+    state_filter: Option<JobState>,
+    state: State<'_, AppState>,
+) -> Result<Vec<JobSummary>, String> {
+    Ok(list_jobs_inner(&state, state_filter))
+}
+
+pub fn clear_completed_inner(state: &AppState) -> usize {
+    state.update_queue(|queue| queue.clear_completed())
+}
+
+#[tauri::command]
+pub fn clear_completed(app_handle: AppHandle, state: State<'_, AppState>) -> Result<usize, String> {
+    let removed = clear_completed_inner(&state);
+    let _ = app_handle.emit("queue-updated", ());
+    Ok(removed)
+}
+
+// Queued jobs flip straight to cancelled; running ones are also signalled so
+// the worker aborts at its next await point.
+pub fn cancel_job_inner(state: &AppState, id: u64) -> Result<(), String> {
+    if !state.update_queue(|queue| queue.mark_cancelled(id)) {
+        return Err(format!("job {id} is not queued or running"));
+    }
+    state.cancel_running_job(id);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn cancel_job(
+    id: u64,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    cancel_job_inner(&state, id)?;
+    let _ = app_handle.emit("queue-updated", ());
+    Ok(())
+}
+
+// Reorders waiting work: the worker picks higher priorities first, so a
+// bumped job jumps ahead of an earlier bulk import.
+pub fn set_job_priority_inner(state: &AppState, id: u64, priority: u8) -> Result<(), String> {
+    if !state.update_queue(|queue| queue.set_priority(id, priority)) {
+        return Err(format!("job {id} is not queued or running"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_job_priority(
+    id: u64,
+    priority: u8,
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    set_job_priority_inner(&state, id, priority)?;
+    let _ = app_handle.emit("queue-updated", ());
+    Ok(())
+}
+
+// Where a finished job wrote its Markdown, checked against the disk so a
+// moved or deleted file is reported instead of handed to the OS.
+pub fn output_path_inner(state: &AppState, id: u64) -> Result<PathBuf, String> {
+    let queue = state.lock_queue();
+    let job = queue.get(id).ok_or_else(|| format!("job {id} not found"))?;
+    if job.state != JobState::Success {
+        return Err(format!("job {id} has not finished successfully"));
+    }
+    let path = job
+        .output
+        .as_deref()
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("job {id} has no recorded output"))?;
+    if !path.is_file() {
+        return Err(format!("output no longer exists: {}", path.display()));
+    }
+    Ok(path)
+}
+
+// Opens the output with the default handler, or with `reveal` selects it in
+// the file manager instead.
+#[tauri::command]
+pub fn open_output(
+    id: u64,
+    reveal: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let path = output_path_inner(&state, id)?;
+    let result = if reveal.unwrap_or(false) {
+        opener::reveal(&path)
+    } else {
+        opener::open(&path)
+    };
+    result.map_err(|error| format!("failed to open {}: {error}", path.display()))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ProviderProfilePayload {
     pub name: String,
@@ -38,6 +251,10 @@ pub struct ProviderProfilePayload {
     pub api_key: String,
     pub model: String,
     pub enabled: bool,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
 }
 
 impl From<ProviderProfile> for ProviderProfilePayload {
@@ -49,6 +266,8 @@ impl From<ProviderProfile> for ProviderProfilePayload {
             api_key: value.api_key,
             model: value.model,
             enabled: value.enabled,
+            system_prompt: value.system_prompt,
+            extra_headers: value.extra_headers,
         }
     }
 }
@@ -62,6 +281,8 @@ impl From<ProviderProfilePayload> for ProviderProfile {
             api_key: value.api_key,
             model: value.model,
             enabled: value.enabled,
+            system_prompt: value.system_prompt,
+            extra_headers: value.extra_headers,
         }
     }
 }
@@ -74,17 +295,39 @@ fn normalize_passphrase(passphrase: &str) -> Result<&str, String> {
     Ok(trimmed)
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileStores {
+    pub stores: Vec<String>,
+    pub active: String,
+}
+
+pub fn list_stores_inner(state: &AppState) -> ProfileStores {
+    ProfileStores {
+        stores: state.list_stores(),
+        active: state.active_store(),
+    }
+}
+
+// `store` picks a named store for this call only; without it the active
+// store is used.
 pub fn load_profiles_inner(
     state: &AppState,
     passphrase: &str,
+    store: Option<&str>,
 ) -> Result<Vec<ProviderProfilePayload>, String> {
     let passphrase = normalize_passphrase(passphrase)?;
     let profiles = state
-        .profile_store()
+        .named_profile_store(store)?
         .load_all(passphrase)
-        .map_err(|error| format!("failed to load profiles: {error}"))?;
+        .map_err(|error| match error.downcast_ref::<AppError>() {
+            Some(AppError::WrongPassphrase) => "incorrect passphrase".to_string(),
+            Some(AppError::CorruptProfileStore(reason)) => {
+                format!("profile file is corrupt: {reason}")
+            }
+            _ => format!("failed to load profiles: {error}"),
+        })?;
 
-    *state.active_profiles.lock().unwrap() = profiles.clone();
+    *state.lock_active_profiles() = profiles.clone();
 
     Ok(profiles
         .into_iter()
@@ -96,31 +339,152 @@ pub fn save_profiles_inner(
     state: &AppState,
     passphrase: &str,
     profiles: Vec<ProviderProfilePayload>,
+    store: Option<&str>,
 ) -> Result<(), String> {
     let passphrase = normalize_passphrase(passphrase)?;
     let mapped: Vec<ProviderProfile> = profiles.into_iter().map(ProviderProfile::from).collect();
+    for profile in &mapped {
+        llm_config_from_profile(profile)?;
+    }
     state
-        .profile_store()
+        .named_profile_store(store)?
         .save_all(passphrase, &mapped)
         .map_err(|error| format!("failed to save profiles: {error}"))?;
 
-    *state.active_profiles.lock().unwrap() = mapped;
+    *state.lock_active_profiles() = mapped;
     Ok(())
 }
 
+pub fn change_passphrase_inner(state: &AppState, old: &str, new: &str) -> Result<(), String> {
+    let old = normalize_passphrase(old)?;
+    let new = normalize_passphrase(new)?;
+    state
+        .profile_store()
+        .change_passphrase(old, new)
+        .map_err(|error| match error.downcast_ref::<AppError>() {
+            Some(AppError::WrongPassphrase) => "current passphrase is incorrect".to_string(),
+            _ => format!("failed to change passphrase: {error:#}"),
+        })
+}
+
+pub fn export_profiles_inner(state: &AppState, passphrase: &str) -> Result<String, String> {
+    let passphrase = normalize_passphrase(passphrase)?;
+    state
+        .profile_store()
+        .export_json(passphrase)
+        .map_err(|error| format!("failed to export profiles: {error:#}"))
+}
+
+pub fn import_profiles_inner(
+    state: &AppState,
+    passphrase: &str,
+    json: &str,
+) -> Result<Vec<ProviderProfilePayload>, String> {
+    let passphrase = normalize_passphrase(passphrase)?;
+    let profiles = state
+        .profile_store()
+        .import_json(passphrase, json)
+        .map_err(|error| format!("failed to import profiles: {error:#}"))?;
+
+    *state.lock_active_profiles() = profiles.clone();
+
+    Ok(profiles
+        .into_iter()
+        .map(ProviderProfilePayload::from)
+        .collect())
+}
+
+#[tauri::command]
+pub fn change_passphrase(
+    old_passphrase: String,
+    new_passphrase: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    change_passphrase_inner(&state, &old_passphrase, &new_passphrase)
+}
+
+#[tauri::command]
+pub fn list_stores(state: State<'_, AppState>) -> ProfileStores {
+    list_stores_inner(&state)
+}
+
+#[tauri::command]
+pub fn set_active_store(name: Option<String>, state: State<'_, AppState>) -> Result<(), String> {
+    state.set_active_store(name.as_deref())
+}
+
 #[tauri::command]
 pub fn load_profiles(
     passphrase: String,
+    store: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<Vec<ProviderProfilePayload>, String> {
-    load_profiles_inner(&state, &passphrase)
+    load_profiles_inner(&state, &passphrase, store.as_deref())
 }
 
 #[tauri::command]
 pub fn save_profiles(
     passphrase: String,
     profiles: Vec<ProviderProfilePayload>,
+    store: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    save_profiles_inner(&state, &passphrase, profiles)
+    save_profiles_inner(&state, &passphrase, profiles, store.as_deref())
+}
+
+#[tauri::command]
+pub fn export_profiles(passphrase: String, state: State<'_, AppState>) -> Result<String, String> {
+    export_profiles_inner(&state, &passphrase)
+}
+
+#[tauri::command]
+pub fn import_profiles(
+    passphrase: String,
+    json: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<ProviderProfilePayload>, String> {
+    import_profiles_inner(&state, &passphrase, &json)
+}
+
+// Sends one tiny prompt with the profile's settings so a wrong key, base URL
+// or model shows up before a real job fails on it.
+pub async fn test_profile_inner(
+    profile: ProviderProfilePayload,
+) -> Result<String, ConnectionFailure> {
+    let runtime = connection_test_runtime(RuntimeConfig::from_env());
+    let http = HttpEngine::new(runtime.clone()).map_err(|err| ConnectionFailure::classify(&err))?;
+    let llm_cfg = llm_config_from_profile(&profile.into()).map_err(ConnectionFailure::Other)?;
+    let client = LlmClient::new(http, llm_cfg, runtime);
+    client
+        .ping("test-profile")
+        .await
+        .map_err(|err| ConnectionFailure::classify(&err))
+}
+
+pub async fn test_glm_inner() -> Result<String, ConnectionFailure> {
+    let runtime = connection_test_runtime(RuntimeConfig::from_env());
+    let glm_cfg = GlmConfig::from_sources(
+        std::env::var("GLM_API_KEY").ok(),
+        std::env::var("GLM_BASE_URL").ok(),
+        std::env::var("GLM_OCR_MODEL").ok(),
+        std::env::var("GLM_OCR_URL").ok(),
+        std::env::var("GLM_FILE_PARSE_URL").ok(),
+        runtime.max_ocr_chars,
+    )
+    .map_err(|err| ConnectionFailure::classify(&err))?;
+    let http = HttpEngine::new(runtime).map_err(|err| ConnectionFailure::classify(&err))?;
+    GlmOcrClient::new(http, glm_cfg)
+        .ping("test-glm")
+        .await
+        .map_err(|err| ConnectionFailure::classify(&err))
+}
+
+#[tauri::command]
+pub async fn test_profile(profile: ProviderProfilePayload) -> Result<String, ConnectionFailure> {
+    test_profile_inner(profile).await
+}
+
+#[tauri::command]
+pub async fn test_glm() -> Result<String, ConnectionFailure> {
+    test_glm_inner().await
 }
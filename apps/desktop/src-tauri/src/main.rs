@@ -1,10 +1,17 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::time::Duration;
+
 use ocr2md_desktop::state::AppState;
+use tauri::RunEvent;
+
+// How long app exit waits for running jobs to finish writing.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
 
 fn main() {
     let state = AppState::default();
     let state_clone = state.clone();
+    let exit_state = state.clone();
 
     tauri::Builder::default()
         .manage(state)
@@ -16,9 +23,33 @@ fn main() {
             ocr2md_desktop::commands::enqueue_files,
             ocr2md_desktop::commands::start_queue,
             ocr2md_desktop::commands::retry_job,
+            ocr2md_desktop::commands::retry_all_failed,
+            ocr2md_desktop::commands::open_output,
+            ocr2md_desktop::commands::cancel_job,
+            ocr2md_desktop::commands::set_job_priority,
+            ocr2md_desktop::commands::list_jobs,
+            ocr2md_desktop::commands::clear_completed,
+            ocr2md_desktop::commands::change_passphrase,
+            ocr2md_desktop::commands::export_profiles,
+            ocr2md_desktop::commands::import_profiles,
+            ocr2md_desktop::commands::repair_queue,
+            ocr2md_desktop::commands::list_stores,
+            ocr2md_desktop::commands::set_active_store,
             ocr2md_desktop::commands::load_profiles,
-            ocr2md_desktop::commands::save_profiles
+            ocr2md_desktop::commands::save_profiles,
+            ocr2md_desktop::commands::test_profile,
+            ocr2md_desktop::commands::test_glm
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(move |_app, event| match event {
+            RunEvent::ExitRequested { .. } => exit_state.shutdown(),
+            RunEvent::Exit => {
+                exit_state.shutdown();
+                if !exit_state.wait_for_running_jobs(SHUTDOWN_GRACE) {
+                    eprintln!("exiting with jobs still running");
+                }
+            }
+            _ => {}
+        });
 }
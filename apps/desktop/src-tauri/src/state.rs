@@ -1,33 +1,250 @@
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
 
 use ocr2md_core::{
+    config::{env_u64, env_usize},
     profile_store::{ProfileStore, ProviderProfile},
-    queue::Queue,
+    queue::{JobId, Queue},
+    secret_backend::SecretBackendKind,
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub queue: Arc<Mutex<Queue>>,
-    profile_store: ProfileStore,
+    profile_path: PathBuf,
+    // Named store the profile commands use by default; `None` is the file at
+    // `profile_path`.
+    active_store: Arc<Mutex<Option<String>>>,
+    queue_path: PathBuf,
     pub notify_worker: Arc<Notify>,
     pub active_profiles: Arc<Mutex<Vec<ProviderProfile>>>,
+    running_jobs: Arc<Mutex<HashMap<JobId, CancellationToken>>>,
+    shutdown: CancellationToken,
 }
 
 impl AppState {
     pub fn for_profile_path(path: PathBuf) -> Self {
+        // Jobs interrupted by a crash come back as queued; a damaged file is
+        // salvaged or set aside by `Queue::load_from` rather than blocking start.
+        let queue_path = path.with_file_name("queue.json");
+        let mut queue = Queue::load_from(&queue_path)
+            .map(|(queue, _)| queue)
+            .unwrap_or_default();
+        queue.set_priority_aging(queue_aging_secs());
+        queue.set_capacity(queue_capacity());
+        queue.set_retry_max(job_retry_max());
+        queue.set_retry_backoff(job_retry_backoff_ms());
+
         Self {
-            queue: Arc::new(Mutex::new(Queue::default())),
-            profile_store: ProfileStore::new(path),
+            queue: Arc::new(Mutex::new(queue)),
+            profile_path: path,
+            active_store: Arc::new(Mutex::new(None)),
+            queue_path,
             notify_worker: Arc::new(Notify::new()),
             active_profiles: Arc::new(Mutex::new(Vec::new())),
+            running_jobs: Arc::new(Mutex::new(HashMap::new())),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    pub fn profile_store(&self) -> ProfileStore {
+        open_store(self.store_path(lock_recover(&self.active_store).as_deref()))
+    }
+
+    // `None` is the active store. Other stores are `<name>.enc` files beside
+    // the default one, which is itself reachable by its file stem.
+    pub fn named_profile_store(&self, name: Option<&str>) -> Result<ProfileStore, String> {
+        match name {
+            Some(name) => Ok(open_store(self.store_path(Some(valid_store_name(name)?)))),
+            None => Ok(self.profile_store()),
+        }
+    }
+
+    // Profiles already loaded stay in use until the next load, so switching
+    // stores never leaves running jobs without credentials.
+    pub fn set_active_store(&self, name: Option<&str>) -> Result<(), String> {
+        let name = name.map(valid_store_name).transpose()?;
+        *lock_recover(&self.active_store) = name
+            .filter(|name| *name != self.default_store_name())
+            .map(str::to_string);
+        Ok(())
+    }
+
+    pub fn active_store(&self) -> String {
+        lock_recover(&self.active_store)
+            .clone()
+            .unwrap_or_else(|| self.default_store_name().to_string())
+    }
+
+    // Every `*.enc` file beside the default store, by name; the default is
+    // listed even before its first save.
+    pub fn list_stores(&self) -> Vec<String> {
+        let mut stores = BTreeSet::from([self.default_store_name().to_string()]);
+        let dir = self
+            .profile_path
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            stores.extend(entries.flatten().filter_map(|entry| {
+                let path = entry.path();
+                if !path.is_file() || path.extension().is_none_or(|ext| ext != "enc") {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            }));
+        }
+        stores.into_iter().collect()
+    }
+
+    fn default_store_name(&self) -> &str {
+        self.profile_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("profiles")
+    }
+
+    fn store_path(&self, name: Option<&str>) -> PathBuf {
+        match name {
+            Some(name) if name != self.default_store_name() => {
+                self.profile_path.with_file_name(format!("{name}.enc"))
+            }
+            _ => self.profile_path.clone(),
         }
     }
 
-    pub fn profile_store(&self) -> &ProfileStore {
-        &self.profile_store
+    pub fn queue_path(&self) -> &Path {
+        &self.queue_path
+    }
+
+    pub fn lock_queue(&self) -> MutexGuard<'_, Queue> {
+        lock_recover(&self.queue)
+    }
+
+    // Applies a queue mutation and flushes the result to disk so job state
+    // survives an app restart. A failed flush keeps the in-memory change.
+    pub fn update_queue<R>(&self, update: impl FnOnce(&mut Queue) -> R) -> R {
+        let mut queue = self.lock_queue();
+        let result = update(&mut queue);
+        if let Err(error) = queue.save_to(&self.queue_path) {
+            eprintln!("failed to persist queue: {error:#}");
+        }
+        result
+    }
+
+    // Claims the next queued job for the worker, or nothing once shutdown
+    // was requested so remaining jobs stay queued for the next start.
+    pub fn claim_next_job(&self) -> Option<JobId> {
+        if self.is_shutting_down() {
+            return None;
+        }
+        self.update_queue(|queue| queue.claim_next_pending("starting"))
+    }
+
+    // Stops the worker from starting new jobs; running ones finish normally.
+    pub fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.notify_worker.notify_waiters();
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_cancelled()
     }
+
+    pub async fn shutdown_requested(&self) {
+        self.shutdown.cancelled().await;
+    }
+
+    // Blocks until running jobs have written their output, or `timeout`
+    // passes. Returns whether the worker is idle.
+    pub fn wait_for_running_jobs(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if lock_recover(&self.running_jobs).is_empty() {
+                return true;
+            }
+            if Instant::now() >= deadline {
+                return false;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    pub fn register_running_job(&self, id: JobId) -> CancellationToken {
+        let token = CancellationToken::new();
+        lock_recover(&self.running_jobs).insert(id, token.clone());
+        token
+    }
+
+    pub fn finish_running_job(&self, id: JobId) {
+        lock_recover(&self.running_jobs).remove(&id);
+    }
+
+    pub fn cancel_running_job(&self, id: JobId) {
+        if let Some(token) = lock_recover(&self.running_jobs).get(&id) {
+            token.cancel();
+        }
+    }
+
+    pub fn lock_active_profiles(&self) -> MutexGuard<'_, Vec<ProviderProfile>> {
+        lock_recover(&self.active_profiles)
+    }
+}
+
+pub fn queue_aging_secs() -> u64 {
+    env_u64("OCR2MD_PRIORITY_AGING_SECS", 60)
+}
+
+// Most jobs that may be waiting or running at once, so dropping thousands of
+// files cannot balloon the queue and the UI.
+pub fn queue_capacity() -> usize {
+    env_usize("OCR2MD_QUEUE_CAPACITY", 500)
+}
+
+// Whole-job reruns after a failed attempt, on top of the per-request HTTP
+// retries (`RETRY_MAX`). Unlike most knobs zero is allowed: fail on the first
+// error.
+pub fn job_retry_max() -> u8 {
+    std::env::var("OCR2MD_JOB_RETRY_MAX")
+        .ok()
+        .and_then(|value| value.trim().parse::<u8>().ok())
+        .unwrap_or(3)
+}
+
+// API keys go wherever `OCR2MD_SECRET_BACKEND` says; the rest of each
+// profile stays in the encrypted file.
+fn open_store(path: PathBuf) -> ProfileStore {
+    let secrets = SecretBackendKind::from_env().backend_for(&path);
+    ProfileStore::new(path).with_secrets(secrets)
+}
+
+pub fn job_retry_backoff_ms() -> u64 {
+    env_u64("OCR2MD_JOB_RETRY_BACKOFF_MS", 2_000)
+}
+
+// Store names become file names, so they are kept to one plain path segment.
+fn valid_store_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+    {
+        return Err(format!(
+            "invalid store name {name:?}: use letters, digits, - or _"
+        ));
+    }
+    Ok(name)
+}
+
+// A task that panics while holding a lock must not take the whole app down
+// with it, so poisoned guards are recovered instead of unwrapped.
+pub fn lock_recover<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 impl Default for AppState {
@@ -1,124 +1,261 @@
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use tauri::{AppHandle, Emitter};
+use tokio::sync::Semaphore;
 use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
 
-use ocr2md_core::config::{LlmProvider, RuntimeConfig};
-use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::config::{LlmPreset, LlmProvider, RuntimeConfig, env_usize};
+use ocr2md_core::error::AppError;
+use ocr2md_core::llm::{LlmConfig, resolve_system_prompt};
 use ocr2md_core::ocr::GlmConfig;
-use ocr2md_core::pipeline::process_file;
+use ocr2md_core::pipeline::{EmptyOcrPolicy, Pipeline, ProcessOptions};
+use ocr2md_core::profile_store::ProviderProfile;
+use ocr2md_core::progress::{ProgressEvent, ProgressSink};
+use ocr2md_core::webhook::{JobNotification, Webhook};
+use serde::Serialize;
 
-use crate::state::AppState;
+use crate::state::{AppState, lock_recover};
+
+// Payload of the `job-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    pub id: u64,
+    pub percent: u8,
+    #[serde(flatten)]
+    pub event: ProgressEvent,
+}
+
+pub fn llm_config_from_profile(p: &ProviderProfile) -> Result<LlmConfig, String> {
+    let provider = match p.provider.as_str() {
+        "openai" => LlmProvider::Openai,
+        "anthropic" | "claude" => LlmProvider::Anthropic,
+        "gemini" => LlmProvider::Gemini,
+        "ollama" => LlmProvider::Ollama,
+        "azure" | "azure-openai" | "azure_openai" => LlmProvider::Azure,
+        _ => LlmProvider::OpenaiCompatible,
+    };
+    // Profiles naming a vendor preset may leave its base URL and model blank.
+    let preset = LlmPreset::from_name(&p.provider);
+    let or_preset = |value: &str, default: fn(LlmPreset) -> &'static str| match preset {
+        Some(preset) if value.trim().is_empty() => default(preset).to_string(),
+        _ => value.to_string(),
+    };
+    LlmConfig {
+        provider,
+        api_key: p.api_key.clone(),
+        base_url: or_preset(&p.base_url, LlmPreset::base_url),
+        model: or_preset(&p.model, LlmPreset::model),
+        system_prompt: resolve_system_prompt(p.system_prompt.clone()),
+        stop: Vec::new(),
+        require_streaming: false,
+        temperature: None,
+        max_tokens: None,
+        document_language: None,
+        deployment: None,
+        api_version: None,
+        extra_headers: Default::default(),
+    }
+    .with_extra_headers(
+        p.extra_headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    )
+    .map_err(|error| format!("profile {:?}: {error:#}", p.name))
+}
+
+const IDLE_POLL: Duration = Duration::from_secs(2);
 
 fn get_trace_id(job_id: u64) -> String {
     format!("job-{}", job_id)
 }
 
+// Built once so every job shares its connection pool; each job only brings
+// the LLM profiles active when it starts.
+fn worker_pipeline() -> Result<Pipeline, String> {
+    let runtime = RuntimeConfig::from_env();
+    let glm_cfg = GlmConfig::from_sources(
+        std::env::var("GLM_API_KEY").ok(),
+        std::env::var("GLM_BASE_URL").ok(),
+        std::env::var("GLM_OCR_MODEL").ok(),
+        std::env::var("GLM_OCR_URL").ok(),
+        std::env::var("GLM_FILE_PARSE_URL").ok(),
+        runtime.max_ocr_chars,
+    )
+    .map_err(|_| "GLM API Config missing (check env variables)".to_string())?;
+    Pipeline::new(glm_cfg, runtime).map_err(|err| format!("{err:#}"))
+}
+
 pub fn spawn_worker(app_handle: AppHandle, state: AppState) {
+    let limit = Arc::new(Semaphore::new(env_usize("OCR2MD_MAX_CONCURRENCY", 3)));
+    let pipeline = worker_pipeline();
+
     tokio::spawn(async move {
         loop {
-            let job_id = {
-                let queue = state.queue.lock().unwrap();
-                queue.get_next_pending()
+            let permit = tokio::select! {
+                biased;
+                _ = state.shutdown_requested() => break,
+                permit = limit.clone().acquire_owned() => permit,
+            };
+            let Ok(permit) = permit else {
+                break;
             };
 
-            if let Some(id) = job_id {
-                let (input_path_str, retries) = {
-                    let mut queue = state.queue.lock().unwrap();
-                    queue.mark_running(id, "starting");
-                    let job = queue.get(id).unwrap();
-                    (job.input.clone(), job.retries)
-                };
+            let job_id = state.claim_next_job();
 
+            if let Some(id) = job_id {
                 let _ = app_handle.emit("queue-updated", ());
+                let app_handle = app_handle.clone();
+                let state = state.clone();
+                let cancel = state.register_running_job(id);
+                let pipeline = pipeline.clone();
+                tokio::spawn(async move {
+                    run_job(&app_handle, &state, &pipeline, id, cancel).await;
+                    state.finish_running_job(id);
+                    drop(permit);
+                    let _ = app_handle.emit("queue-updated", ());
+                    state.notify_worker.notify_one();
+                });
+            } else {
+                drop(permit);
+                // Wake in time for the next backed-off retry if it is due
+                // before the regular poll.
+                let idle = state
+                    .lock_queue()
+                    .next_retry_in()
+                    .map_or(IDLE_POLL, |wait| wait.min(IDLE_POLL));
+                tokio::select! {
+                    _ = state.shutdown_requested() => break,
+                    _ = state.notify_worker.notified() => {}
+                    _ = sleep(idle) => {}
+                }
+            }
+        }
+    });
+}
 
-                let input_path = PathBuf::from(&input_path_str);
-                let output_path = resolve_output_path(&input_path);
-                let trace_id = get_trace_id(id);
+async fn run_job(
+    app_handle: &AppHandle,
+    state: &AppState,
+    pipeline: &Result<Pipeline, String>,
+    id: u64,
+    cancel: CancellationToken,
+) {
+    let Some(input_path_str) = ({
+        let queue = state.lock_queue();
+        queue.get(id).map(|job| job.input.clone())
+    }) else {
+        return;
+    };
 
-                let runtime = RuntimeConfig::from_env();
+    let input_path = PathBuf::from(&input_path_str);
+    let output_path = resolve_output_path(&input_path);
+    let trace_id = get_trace_id(id);
 
-                let llm_cfg_opt = {
-                    let profiles = state.active_profiles.lock().unwrap();
-                    profiles.iter().find(|p| p.enabled).map(|p| {
-                        let provider = match p.provider.as_str() {
-                            "openai" => LlmProvider::Openai,
-                            "anthropic" | "claude" => LlmProvider::Anthropic,
-                            "gemini" => LlmProvider::Gemini,
-                            _ => LlmProvider::OpenaiCompatible,
-                        };
-                        LlmConfig {
-                            provider,
-                            api_key: p.api_key.clone(),
-                            base_url: p.base_url.clone(),
-                            model: p.model.clone(),
-                            system_prompt: std::env::var("SYSTEM_PROMPT").unwrap_or_else(|_| "你是一个严谨的文档结构化助手。将输入文本整理为高质量 Markdown，要求：\n1) 只输出 Markdown，不输出解释。\n2) 保留原文信息，不杜撰。\n3) 自动识别并组织标题层级、段落、列表、表格。\n4) 对明显噪声进行最小清洗（如重复页眉页脚）。\n5) 对公式、代码块、表格尽量保持可读性。".to_string()),
-                        }
-                    })
-                };
+    let runtime = RuntimeConfig::from_env();
+    let webhook = Webhook::from_env(&runtime).unwrap_or_else(|err| {
+        eprintln!("webhook disabled: {err:#}");
+        None
+    });
+
+    // Every enabled profile, in order, forms the LLM fallback chain.
+    let llm_cfgs: Result<Vec<LlmConfig>, String> = {
+        let profiles = state.lock_active_profiles();
+        profiles
+            .iter()
+            .filter(|p| p.enabled)
+            .map(llm_config_from_profile)
+            .collect()
+    };
 
-                let glm_cfg_res = GlmConfig::from_sources(
-                    std::env::var("GLM_API_KEY").ok(),
-                    std::env::var("GLM_BASE_URL").ok(),
-                    std::env::var("GLM_OCR_MODEL").ok(),
-                    std::env::var("GLM_OCR_URL").ok(),
-                    std::env::var("GLM_FILE_PARSE_URL").ok(),
-                    runtime.max_ocr_chars,
-                );
-
-                if let Some(llm_cfg) = llm_cfg_opt {
-                    if let Ok(glm_cfg) = glm_cfg_res {
-                        {
-                            let mut queue = state.queue.lock().unwrap();
-                            queue.mark_running(id, "processing");
+    match llm_cfgs {
+        Err(error) => state.update_queue(|queue| queue.mark_failed(id, error)),
+        Ok(llm_cfgs) if !llm_cfgs.is_empty() => {
+            if let Ok(pipeline) = pipeline {
+                state.update_queue(|queue| queue.mark_running(id, "processing"));
+                let _ = app_handle.emit("queue-updated", ());
+
+                let progress_handle = app_handle.clone();
+                // Set when the pipeline skips an input OCR found no text in.
+                let skipped = Arc::new(Mutex::new(None));
+                let skip_reason = skipped.clone();
+                let options = ProcessOptions {
+                    on_empty_ocr: EmptyOcrPolicy::from_env(),
+                    cancel,
+                    progress: Some(ProgressSink::new(move |event| {
+                        if let ProgressEvent::Skipped { reason } = &event {
+                            *lock_recover(&skip_reason) = Some(reason.clone());
                         }
-                        let _ = app_handle.emit("queue-updated", ());
-
-                        match process_file(
-                            &input_path,
-                            &output_path,
-                            glm_cfg,
-                            llm_cfg,
-                            runtime,
-                            &trace_id,
-                        )
-                        .await
-                        {
-                            Ok(_) => {
-                                let mut queue = state.queue.lock().unwrap();
-                                queue.mark_success(id);
-                            }
-                            Err(e) => {
-                                let mut queue = state.queue.lock().unwrap();
-                                if retries < 3 {
-                                    queue.mark_retrying(id, "failed_retry", e.to_string());
-                                } else {
-                                    queue.mark_failed(id, e.to_string());
-                                }
-                            }
+                        let progress = JobProgress {
+                            id,
+                            percent: event.percent(),
+                            event,
+                        };
+                        let _ = progress_handle.emit("job-progress", progress);
+                    })),
+                    ..ProcessOptions::default()
+                };
+                match pipeline
+                    .clone()
+                    .with_llm_configs(llm_cfgs)
+                    .convert_with(&input_path, &output_path, &options, &trace_id)
+                    .await
+                {
+                    Ok(_) => match lock_recover(&skipped).take() {
+                        Some(reason) => state.update_queue(|queue| queue.mark_skipped(id, reason)),
+                        None => {
+                            let output = output_path.display().to_string();
+                            state.update_queue(|queue| queue.mark_success(id, output));
                         }
-                    } else {
-                        let mut queue = state.queue.lock().unwrap();
-                        queue.mark_failed(id, "GLM API Config missing (check env variables)");
+                    },
+                    Err(e) if matches!(e.downcast_ref(), Some(AppError::Cancelled)) => {
+                        state.update_queue(|queue| queue.mark_cancelled(id));
+                    }
+                    // Retrying cannot shrink the file, fill an empty or
+                    // mistyped one, put text in a blank scan or get past
+                    // moderation, so fail straight away.
+                    Err(e)
+                        if matches!(
+                            e.downcast_ref(),
+                            Some(
+                                AppError::InputTooLarge { .. }
+                                    | AppError::EmptyInput(_)
+                                    | AppError::UnsupportedInputType(_)
+                                    | AppError::EmptyOcr
+                                    | AppError::ContentBlocked(_)
+                            )
+                        ) =>
+                    {
+                        state.update_queue(|queue| queue.mark_failed(id, format!("{e:#}")));
+                    }
+                    // Stage timeouts and other failures are requeued up to
+                    // `OCR2MD_JOB_RETRY_MAX` times, each after a growing
+                    // delay; see `Queue::set_retry_max` and `set_retry_backoff`.
+                    Err(e) => {
+                        state.update_queue(|queue| queue.mark_run_failed(id, format!("{e:#}")));
                     }
-                } else {
-                    let mut queue = state.queue.lock().unwrap();
-                    queue.mark_failed(
-                        id,
-                        "No active LLM profile found. Please load or configure a profile.",
-                    );
-                }
-
-                let _ = app_handle.emit("queue-updated", ());
-            } else {
-                tokio::select! {
-                    _ = state.notify_worker.notified() => {}
-                    _ = sleep(Duration::from_secs(2)) => {}
                 }
+            } else if let Err(error) = pipeline {
+                state.update_queue(|queue| queue.mark_failed(id, error.clone()));
             }
         }
-    });
+        Ok(_) => state.update_queue(|queue| {
+            queue.mark_failed(
+                id,
+                "No active LLM profile found. Please load or configure a profile.",
+            )
+        }),
+    }
+
+    let finished = state
+        .lock_queue()
+        .get(id)
+        .and_then(|job| JobNotification::from_job(&trace_id, job));
+    if let (Some(webhook), Some(notification)) = (webhook, finished) {
+        webhook.notify(&notification).await;
+    }
 }
 
 fn resolve_output_path(input: &std::path::Path) -> PathBuf {
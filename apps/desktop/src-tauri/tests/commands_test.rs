@@ -1,15 +1,27 @@
+use ocr2md_core::health::ConnectionFailure;
+use ocr2md_core::queue::{JobState, PRIORITY_HIGH, Queue};
 use ocr2md_desktop::{
     commands::{
-        ProviderProfilePayload, enqueue_files_inner, load_profiles_inner, save_profiles_inner,
+        ProviderProfilePayload, cancel_job_inner, change_passphrase_inner, clear_completed_inner,
+        enqueue_files_inner, list_jobs_inner, list_stores_inner, load_profiles_inner,
+        output_path_inner, repair_queue_inner, retry_all_failed_inner, save_profiles_inner,
+        set_job_priority_inner, test_profile_inner,
     },
     state::AppState,
 };
 
 #[tokio::test]
 async fn enqueue_command_returns_job_id() {
-    let state = AppState::default();
-    let ids = enqueue_files_inner(&state, vec!["demo.pdf".to_string()]);
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["demo.pdf".to_string()]).ids;
     assert!(!ids.is_empty());
+
+    let restarted = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    assert_eq!(
+        restarted.lock_queue().get(ids[0]).unwrap().input,
+        "demo.pdf"
+    );
 }
 
 #[tokio::test]
@@ -24,10 +36,12 @@ async fn saves_and_loads_profiles_with_passphrase() {
         api_key: "sk-test".to_string(),
         model: "gpt-4.1-mini".to_string(),
         enabled: true,
+        system_prompt: None,
+        extra_headers: Default::default(),
     }];
 
-    save_profiles_inner(&state, passphrase, profiles.clone()).expect("save failed");
-    let loaded = load_profiles_inner(&state, passphrase).expect("load failed");
+    save_profiles_inner(&state, passphrase, profiles.clone(), None).expect("save failed");
+    let loaded = load_profiles_inner(&state, passphrase, None).expect("load failed");
 
     assert_eq!(loaded, profiles);
 }
@@ -37,9 +51,348 @@ async fn rejects_empty_passphrase_for_profile_commands() {
     let temp = tempfile::tempdir().expect("failed to create temp dir");
     let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
 
-    let save_error = save_profiles_inner(&state, "   ", Vec::new()).expect_err("save should fail");
+    let save_error =
+        save_profiles_inner(&state, "   ", Vec::new(), None).expect_err("save should fail");
     assert!(save_error.contains("passphrase"));
 
-    let load_error = load_profiles_inner(&state, "").expect_err("load should fail");
+    let load_error = load_profiles_inner(&state, "", None).expect_err("load should fail");
     assert!(load_error.contains("passphrase"));
 }
+
+#[tokio::test]
+async fn change_passphrase_reports_a_wrong_current_passphrase() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    save_profiles_inner(&state, "old", Vec::new(), None).expect("save failed");
+
+    let error = change_passphrase_inner(&state, "wrong", "new").expect_err("should fail");
+    assert_eq!(error, "current passphrase is incorrect");
+
+    change_passphrase_inner(&state, "old", "new").expect("rotate failed");
+    assert!(
+        load_profiles_inner(&state, "new", None)
+            .expect("load failed")
+            .is_empty()
+    );
+    assert!(load_profiles_inner(&state, "old", None).is_err());
+}
+
+#[tokio::test]
+async fn test_profile_reports_an_unreachable_base_url() {
+    let profile = ProviderProfilePayload {
+        name: "Local relay".to_string(),
+        provider: "openai-compatible".to_string(),
+        base_url: "http://127.0.0.1:9".to_string(),
+        api_key: "sk-test".to_string(),
+        model: "gpt-4.1-mini".to_string(),
+        enabled: true,
+        system_prompt: None,
+        extra_headers: Default::default(),
+    };
+
+    let error = test_profile_inner(profile).await.expect_err("should fail");
+    assert!(matches!(error, ConnectionFailure::Unreachable(_)));
+}
+
+#[tokio::test]
+async fn list_jobs_filters_by_state() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["a.pdf".to_string(), "b.pdf".to_string()]).ids;
+    cancel_job_inner(&state, ids[1]).expect("cancel failed");
+
+    let all = list_jobs_inner(&state, None);
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].job.id, ids[0]);
+
+    let cancelled = list_jobs_inner(&state, Some(JobState::Cancelled));
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(cancelled[0].job.input, "b.pdf");
+    assert!(cancelled[0].duration_ms.is_some());
+}
+
+#[tokio::test]
+async fn clear_completed_prunes_the_persisted_queue() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["a.pdf".to_string(), "b.pdf".to_string()]).ids;
+    cancel_job_inner(&state, ids[0]).expect("cancel failed");
+
+    assert_eq!(clear_completed_inner(&state), 1);
+
+    let restarted = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    assert!(restarted.lock_queue().get(ids[0]).is_none());
+    assert!(restarted.lock_queue().get(ids[1]).is_some());
+}
+
+#[tokio::test]
+async fn shutdown_leaves_pending_jobs_queued() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["a.pdf".to_string(), "b.pdf".to_string()]).ids;
+
+    assert_eq!(state.claim_next_job(), Some(ids[0]));
+    state.shutdown();
+    assert!(state.is_shutting_down());
+    assert_eq!(state.claim_next_job(), None);
+
+    let queue = state.lock_queue();
+    assert_eq!(queue.get(ids[0]).unwrap().state, JobState::Running);
+    assert_eq!(queue.get(ids[1]).unwrap().state, JobState::Queued);
+}
+
+#[tokio::test]
+async fn load_profiles_tells_a_wrong_passphrase_from_a_corrupt_file() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let path = temp.path().join("profiles.enc");
+    let state = AppState::for_profile_path(path.clone());
+    save_profiles_inner(&state, "right", Vec::new(), None).expect("save failed");
+
+    let wrong = load_profiles_inner(&state, "wrong", None).expect_err("load should fail");
+    assert_eq!(wrong, "incorrect passphrase");
+
+    std::fs::write(&path, b"O2MD\x09 not a real envelope, just junk bytes").unwrap();
+    let corrupt = load_profiles_inner(&state, "right", None).expect_err("load should fail");
+    assert!(corrupt.starts_with("profile file is corrupt"), "{corrupt}");
+}
+
+#[tokio::test]
+async fn profiles_with_invalid_extra_headers_are_not_saved() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let mut relay = profile("relay");
+    relay
+        .extra_headers
+        .insert("X-Org Id".to_string(), "acme".to_string());
+
+    let error = save_profiles_inner(&state, "pass", vec![relay.clone()], None)
+        .expect_err("save should fail");
+    assert!(error.contains("invalid extra header name"), "{error}");
+
+    relay.extra_headers.clear();
+    relay
+        .extra_headers
+        .insert("X-Org-Id".to_string(), "acme".to_string());
+    save_profiles_inner(&state, "pass", vec![relay.clone()], None).expect("save failed");
+    assert_eq!(
+        load_profiles_inner(&state, "pass", None).expect("load failed"),
+        vec![relay]
+    );
+}
+
+fn profile(name: &str) -> ProviderProfilePayload {
+    ProviderProfilePayload {
+        name: name.to_string(),
+        provider: "openai".to_string(),
+        base_url: "https://api.openai.com/v1".to_string(),
+        api_key: format!("sk-{name}"),
+        model: "gpt-4.1-mini".to_string(),
+        enabled: true,
+        system_prompt: None,
+        extra_headers: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn named_stores_keep_their_profiles_apart() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let personal = vec![profile("personal")];
+    let shared = vec![profile("shared-a"), profile("shared-b")];
+
+    save_profiles_inner(&state, "mine", personal.clone(), Some("personal")).expect("save failed");
+    save_profiles_inner(&state, "team", shared.clone(), Some("shared")).expect("save failed");
+
+    assert_eq!(
+        load_profiles_inner(&state, "mine", Some("personal")).expect("load failed"),
+        personal
+    );
+    assert_eq!(
+        load_profiles_inner(&state, "team", Some("shared")).expect("load failed"),
+        shared
+    );
+    // The default store was never written.
+    assert!(
+        load_profiles_inner(&state, "mine", None)
+            .expect("load failed")
+            .is_empty()
+    );
+
+    let listed = list_stores_inner(&state);
+    assert_eq!(listed.stores, vec!["personal", "profiles", "shared"]);
+    assert_eq!(listed.active, "profiles");
+}
+
+#[tokio::test]
+async fn the_active_store_serves_calls_without_a_name() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    save_profiles_inner(&state, "pass", vec![profile("default")], None).expect("save failed");
+
+    state
+        .set_active_store(Some("shared"))
+        .expect("select failed");
+    save_profiles_inner(&state, "team", vec![profile("shared")], None).expect("save failed");
+    assert_eq!(list_stores_inner(&state).active, "shared");
+    assert!(temp.path().join("shared.enc").exists());
+
+    state.set_active_store(None).expect("reset failed");
+    assert_eq!(
+        load_profiles_inner(&state, "pass", None).expect("load failed"),
+        vec![profile("default")]
+    );
+
+    for name in ["", "../escape", "a/b", "team.enc"] {
+        assert!(state.set_active_store(Some(name)).is_err(), "{name:?}");
+        assert!(
+            load_profiles_inner(&state, "pass", Some(name)).is_err(),
+            "{name:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn enqueue_reports_files_that_are_already_queued() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let first = enqueue_files_inner(&state, vec!["a.pdf".to_string()]);
+
+    let second = enqueue_files_inner(&state, vec!["a.pdf".to_string(), "b.pdf".to_string()]);
+    assert_eq!(second.deduplicated, first.ids);
+    assert_eq!(second.new.len(), 1);
+    assert_eq!(second.ids, vec![first.ids[0], second.new[0]]);
+    assert_eq!(state.lock_queue().all_jobs().len(), 2);
+}
+
+#[tokio::test]
+async fn enqueue_reports_files_rejected_by_a_full_queue() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    state.lock_queue().set_capacity(2);
+
+    let files = ["a.pdf", "b.pdf", "c.pdf", "a.pdf"]
+        .map(String::from)
+        .to_vec();
+    let report = enqueue_files_inner(&state, files);
+    assert_eq!(report.new.len(), 2);
+    assert_eq!(report.deduplicated, vec![report.new[0]]);
+    assert_eq!(report.rejected, vec!["c.pdf".to_string()]);
+    assert_eq!(report.ids.len(), 3);
+
+    cancel_job_inner(&state, report.new[0]).expect("cancel failed");
+    let retry = enqueue_files_inner(&state, vec!["c.pdf".to_string()]);
+    assert_eq!(retry.new.len(), 1);
+    assert!(retry.rejected.is_empty());
+}
+
+#[tokio::test]
+async fn queue_survives_a_panic_while_locked() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let poisoned = state.clone();
+
+    let result = std::thread::spawn(move || {
+        let _guard = poisoned.queue.lock().unwrap();
+        panic!("simulated worker panic");
+    })
+    .join();
+    assert!(result.is_err());
+    assert!(state.queue.is_poisoned());
+
+    let ids = enqueue_files_inner(&state, vec!["after-panic.pdf".to_string()]).ids;
+    assert_eq!(ids.len(), 1);
+    assert!(state.lock_queue().get(ids[0]).is_some());
+}
+
+#[tokio::test]
+async fn repair_queue_recovers_truncated_file() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    enqueue_files_inner(&state, vec!["a.pdf".to_string(), "b.pdf".to_string()]);
+    state.lock_queue().save_to(state.queue_path()).unwrap();
+
+    let full = std::fs::read_to_string(state.queue_path()).unwrap();
+    let cut = full.find("b.pdf").unwrap();
+    std::fs::write(state.queue_path(), &full[..cut]).unwrap();
+
+    let report = repair_queue_inner(&state).expect("repair failed");
+    assert_eq!(report.recovered, 1);
+    assert_eq!(report.lost, 1);
+    assert_eq!(state.lock_queue().get(1).unwrap().input, "a.pdf");
+    assert!(state.lock_queue().get(2).is_none());
+
+    let (_, clean) = Queue::load_from(state.queue_path()).unwrap();
+    assert_eq!(clean.recovered, 1);
+    assert_eq!(clean.lost, 0);
+}
+
+#[tokio::test]
+async fn cancel_job_stops_queued_work() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["huge-scan.pdf".to_string()]).ids;
+
+    cancel_job_inner(&state, ids[0]).expect("cancel failed");
+
+    assert_eq!(
+        state.lock_queue().get(ids[0]).unwrap().state,
+        JobState::Cancelled
+    );
+    assert_eq!(state.lock_queue().get_next_pending(), None);
+    assert!(cancel_job_inner(&state, ids[0]).is_err());
+}
+
+#[tokio::test]
+async fn set_job_priority_moves_a_queued_job_to_the_front() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(
+        &state,
+        vec!["bulk.pdf".to_string(), "urgent.pdf".to_string()],
+    )
+    .ids;
+
+    set_job_priority_inner(&state, ids[1], PRIORITY_HIGH).expect("bump failed");
+    assert_eq!(state.lock_queue().get_next_pending(), Some(ids[1]));
+
+    cancel_job_inner(&state, ids[0]).expect("cancel failed");
+    assert!(set_job_priority_inner(&state, ids[0], PRIORITY_HIGH).is_err());
+}
+
+#[tokio::test]
+async fn retry_all_failed_requeues_and_persists_failed_jobs() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["a.pdf".to_string(), "b.pdf".to_string()]).ids;
+    state.update_queue(|queue| {
+        queue.claim_next_pending("starting");
+        queue.mark_failed(ids[0], "status 503");
+    });
+
+    assert_eq!(retry_all_failed_inner(&state), vec![ids[0]]);
+
+    let (saved, _) = Queue::load_from(state.queue_path()).unwrap();
+    assert_eq!(saved.get(ids[0]).unwrap().state, JobState::Queued);
+    assert_eq!(saved.get(ids[0]).unwrap().error, None);
+    assert!(retry_all_failed_inner(&state).is_empty());
+}
+
+#[tokio::test]
+async fn output_path_requires_a_successful_job_with_its_file() {
+    let temp = tempfile::tempdir().expect("failed to create temp dir");
+    let state = AppState::for_profile_path(temp.path().join("profiles.enc"));
+    let ids = enqueue_files_inner(&state, vec!["a.pdf".to_string()]).ids;
+    assert!(output_path_inner(&state, ids[0]).is_err());
+
+    let output = temp.path().join("a.md");
+    std::fs::write(&output, "# a\n").unwrap();
+    state.update_queue(|queue| {
+        queue.claim_next_pending("starting");
+        queue.mark_success(ids[0], output.display().to_string());
+    });
+    assert_eq!(output_path_inner(&state, ids[0]).unwrap(), output);
+
+    std::fs::remove_file(&output).unwrap();
+    let error = output_path_inner(&state, ids[0]).unwrap_err();
+    assert!(error.contains("no longer exists"), "{error}");
+}
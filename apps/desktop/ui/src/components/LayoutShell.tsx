@@ -7,6 +7,8 @@ type LocalProfile = {
   apiKey: string;
   model: string;
   enabled: boolean;
+  systemPrompt?: string | null;
+  extraHeaders?: Record<string, string>;
 };
 
 type ProviderProfilePayload = {
@@ -16,6 +18,8 @@ type ProviderProfilePayload = {
   api_key: string;
   model: string;
   enabled: boolean;
+  system_prompt?: string | null;
+  extra_headers?: Record<string, string>;
 };
 
 type StatusTone = "info" | "success" | "error";
@@ -63,7 +67,9 @@ function toLocalProfile(payload: ProviderProfilePayload): LocalProfile {
     baseUrl: payload.base_url,
     apiKey: payload.api_key,
     model: payload.model,
-    enabled: payload.enabled
+    enabled: payload.enabled,
+    systemPrompt: payload.system_prompt ?? null,
+    extraHeaders: payload.extra_headers ?? {}
   };
 }
 
@@ -74,7 +80,9 @@ function toPayload(profile: LocalProfile): ProviderProfilePayload {
     base_url: profile.baseUrl,
     api_key: profile.apiKey,
     model: profile.model,
-    enabled: profile.enabled
+    enabled: profile.enabled,
+    system_prompt: profile.systemPrompt ?? null,
+    extra_headers: profile.extraHeaders ?? {}
   };
 }
 
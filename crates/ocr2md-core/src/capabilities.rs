@@ -0,0 +1,38 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::config::LlmProvider;
+use crate::file_kind::InputKind;
+use crate::ocr::OcrProvider;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Capabilities {
+    pub version: String,
+    pub input_kinds: Vec<String>,
+    pub llm_providers: Vec<String>,
+    pub ocr_backends: Vec<String>,
+    pub features: Vec<String>,
+}
+
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        input_kinds: value_names(InputKind::value_variants()),
+        llm_providers: value_names(LlmProvider::value_variants()),
+        ocr_backends: value_names(OcrProvider::value_variants()),
+        features: enabled_features(),
+    }
+}
+
+fn value_names<T: ValueEnum>(variants: &[T]) -> Vec<String> {
+    variants
+        .iter()
+        .filter_map(ValueEnum::to_possible_value)
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+// Cargo features compiled into this build; extend alongside `[features]`.
+fn enabled_features() -> Vec<String> {
+    Vec::new()
+}
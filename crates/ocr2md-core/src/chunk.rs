@@ -0,0 +1,144 @@
+// Splits OCR text that is too large for one LLM call. Chunks break on blank
+// lines where possible and on whitespace otherwise; each chunk after the first
+// repeats up to `overlap_chars` of trailing paragraphs from the previous one so
+// the model keeps the surrounding heading in view.
+pub fn split_text(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<String> {
+    if max_chars == 0 || text.chars().count() <= max_chars {
+        return vec![text.to_string()];
+    }
+
+    let pieces: Vec<String> = text
+        .split("\n\n")
+        .map(|paragraph| paragraph.trim_matches('\n'))
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .flat_map(|paragraph| split_long(paragraph, max_chars))
+        .collect();
+
+    let mut chunks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut current_len = 0;
+
+    for piece in &pieces {
+        let piece_len = piece.chars().count();
+        if !current.is_empty() && current_len + 2 + piece_len > max_chars {
+            chunks.push(current.join("\n\n"));
+
+            let mut carried = Vec::new();
+            let mut carried_len = 0;
+            for previous in current.iter().rev() {
+                let len = previous.chars().count();
+                if carried_len + len + 2 + piece_len + 2 > max_chars
+                    || carried_len + len > overlap_chars
+                {
+                    break;
+                }
+                carried.insert(0, *previous);
+                carried_len += len + 2;
+            }
+            current = carried;
+            current_len = carried_len.saturating_sub(2);
+        }
+
+        if !current.is_empty() {
+            current_len += 2;
+        }
+        current.push(piece);
+        current_len += piece_len;
+    }
+
+    if !current.is_empty() {
+        chunks.push(current.join("\n\n"));
+    }
+    chunks
+}
+
+// Joins per-chunk Markdown, dropping lines the model repeated because of the
+// overlap and a heading that merely restates the one the previous chunk is in.
+pub fn merge_markdown(parts: &[String]) -> String {
+    let mut merged = String::new();
+    for part in parts {
+        let part = part.trim();
+        if merged.is_empty() {
+            merged.push_str(part);
+            continue;
+        }
+
+        let rest = drop_repeated_lead(&merged, part);
+        if rest.is_empty() {
+            continue;
+        }
+        merged.push_str("\n\n");
+        merged.push_str(rest);
+    }
+    merged
+}
+
+fn split_long(paragraph: &str, max_chars: usize) -> Vec<String> {
+    if paragraph.chars().count() <= max_chars {
+        return vec![paragraph.to_string()];
+    }
+
+    let mut out = Vec::new();
+    let mut buf = String::new();
+    let mut buf_len = 0;
+    for word in paragraph.split_inclusive(char::is_whitespace) {
+        let word_len = word.chars().count();
+        if buf_len + word.trim_end().chars().count() > max_chars && !buf.is_empty() {
+            out.push(buf.trim_end().to_string());
+            buf.clear();
+            buf_len = 0;
+        }
+        if word_len > max_chars {
+            let chars: Vec<char> = word.chars().collect();
+            for slice in chars.chunks(max_chars) {
+                out.push(slice.iter().collect::<String>().trim_end().to_string());
+            }
+            continue;
+        }
+        buf.push_str(word);
+        buf_len += word_len;
+    }
+    if !buf.trim().is_empty() {
+        out.push(buf.trim_end().to_string());
+    }
+    out.retain(|piece| !piece.is_empty());
+    out
+}
+
+fn drop_repeated_lead<'a>(merged: &str, part: &'a str) -> &'a str {
+    let tail: Vec<&str> = merged
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let head: Vec<&str> = part
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let repeated = (1..=tail.len().min(head.len()))
+        .rev()
+        .find(|&k| tail[tail.len() - k..] == head[..k])
+        .unwrap_or(0);
+    let mut rest = skip_lines(part, repeated);
+
+    let last_heading = tail.iter().rev().find(|line| line.starts_with('#'));
+    let next_line = rest.lines().map(str::trim).find(|line| !line.is_empty());
+    if next_line.is_some() && next_line == last_heading.copied() {
+        rest = skip_lines(rest, 1);
+    }
+    rest
+}
+
+fn skip_lines(text: &str, count: usize) -> &str {
+    let mut rest = text;
+    for _ in 0..count {
+        rest = rest.trim_start();
+        rest = match rest.find('\n') {
+            Some(end) => &rest[end + 1..],
+            None => "",
+        };
+    }
+    rest.trim_start()
+}
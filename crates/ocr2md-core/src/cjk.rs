@@ -0,0 +1,109 @@
+const FULLWIDTH_PUNCTUATION: &str = "，。：；！？、）】」』》";
+
+// Pangu-style cleanup for mixed CJK/Latin Markdown: a space between CJK and
+// Latin letters or digits, full-width ASCII letters and digits folded to
+// half-width, and half-width `,:;!?` after CJK text switched to full-width.
+// Fenced code blocks and inline code spans are left untouched.
+pub fn normalize(markdown: &str) -> String {
+    let mut out = String::with_capacity(markdown.len() + markdown.len() / 8);
+    let mut fence: Option<&str> = None;
+
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            out.push_str(line);
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker))
+        {
+            fence = Some(marker);
+            out.push_str(line);
+            continue;
+        }
+
+        for (index, segment) in line.split('`').enumerate() {
+            if index > 0 {
+                out.push('`');
+            }
+            if index % 2 == 1 {
+                out.push_str(segment);
+            } else {
+                out.push_str(&normalize_text(segment));
+            }
+        }
+    }
+    out
+}
+
+fn normalize_text(text: &str) -> String {
+    let chars: Vec<char> = text.chars().map(to_halfwidth_alnum).collect();
+    let mut out = String::with_capacity(text.len());
+
+    for (index, &c) in chars.iter().enumerate() {
+        let prev = index.checked_sub(1).map(|prev| chars[prev]);
+        let next = chars.get(index + 1).copied();
+
+        let c = match to_fullwidth_punctuation(c) {
+            Some(full)
+                if prev.is_some_and(is_cjk)
+                    && next.is_none_or(|next| is_cjk(next) || next.is_whitespace()) =>
+            {
+                full
+            }
+            _ => c,
+        };
+
+        if FULLWIDTH_PUNCTUATION.contains(c) {
+            let mut tail = out.chars().rev();
+            if tail.next() == Some(' ') && tail.next().is_some_and(is_cjk) {
+                out.pop();
+            }
+        } else if let Some(last) = out.chars().next_back()
+            && ((is_cjk(last) && c.is_ascii_alphanumeric())
+                || (last.is_ascii_alphanumeric() && is_cjk(c)))
+        {
+            out.push(' ');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{2E80}'..='\u{2FDF}'
+            | '\u{3040}'..='\u{30FF}'
+            | '\u{3100}'..='\u{312F}'
+            | '\u{3200}'..='\u{32FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+    )
+}
+
+fn to_halfwidth_alnum(c: char) -> char {
+    match c {
+        '\u{FF10}'..='\u{FF19}' | '\u{FF21}'..='\u{FF3A}' | '\u{FF41}'..='\u{FF5A}' => {
+            char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)
+        }
+        _ => c,
+    }
+}
+
+fn to_fullwidth_punctuation(c: char) -> Option<char> {
+    match c {
+        ',' => Some('，'),
+        ':' => Some('：'),
+        ';' => Some('；'),
+        '!' => Some('！'),
+        '?' => Some('？'),
+        _ => None,
+    }
+}
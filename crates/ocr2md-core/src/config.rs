@@ -1,6 +1,8 @@
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use clap::ValueEnum;
+use serde::{Serialize, Serializer};
 
 use crate::error::AppError;
 
@@ -10,6 +12,14 @@ pub enum LlmProvider {
     Anthropic,
     Gemini,
     OpenaiCompatible,
+    Ollama,
+    // Azure OpenAI: OpenAI's request and response shapes behind per-deployment
+    // URLs and an `api-key` header.
+    Azure,
+    // Offline stand-in for tests and demos: wraps the OCR text in a fixed
+    // Markdown template without any HTTP. Only reachable through `--mock`.
+    #[value(skip)]
+    Mock,
 }
 
 impl FromStr for LlmProvider {
@@ -23,39 +33,209 @@ impl FromStr for LlmProvider {
             "openai-compatible" | "openai_compatible" | "relay" | "cc-switch" | "ccswitch" => {
                 Ok(Self::OpenaiCompatible)
             }
+            name if LlmPreset::from_name(name).is_some() => Ok(Self::OpenaiCompatible),
+            "ollama" => Ok(Self::Ollama),
+            "azure" | "azure-openai" | "azure_openai" => Ok(Self::Azure),
             other => Err(AppError::InvalidConfig(format!(
-                "unsupported provider: {other}. use openai|anthropic|gemini|openai-compatible"
+                "unsupported provider: {other}. use openai|anthropic|gemini|openai-compatible|ollama|azure|deepseek|moonshot|kimi"
             ))),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+// OpenAI-compatible vendors that can be named directly. They keep the
+// `OpenaiCompatible` request shape and only seed the base URL and model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmPreset {
+    Deepseek,
+    Moonshot,
+}
+
+impl LlmPreset {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "deepseek" => Some(Self::Deepseek),
+            "moonshot" | "kimi" => Some(Self::Moonshot),
+            _ => None,
+        }
+    }
+
+    pub fn base_url(self) -> &'static str {
+        match self {
+            Self::Deepseek => "https://api.deepseek.com/v1",
+            Self::Moonshot => "https://api.moonshot.cn/v1",
+        }
+    }
+
+    pub fn model(self) -> &'static str {
+        match self {
+            Self::Deepseek => "deepseek-chat",
+            Self::Moonshot => "moonshot-v1-32k",
+        }
+    }
+}
+
+// What `--provider` names: the provider plus, for a vendor shortcut such as
+// `deepseek`, the preset whose defaults apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProviderChoice {
+    pub provider: LlmProvider,
+    pub preset: Option<LlmPreset>,
+}
+
+impl From<LlmProvider> for ProviderChoice {
+    fn from(provider: LlmProvider) -> Self {
+        Self {
+            provider,
+            preset: None,
+        }
+    }
+}
+
+impl FromStr for ProviderChoice {
+    type Err = AppError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            provider: input.parse()?,
+            preset: LlmPreset::from_name(input),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct RuntimeConfig {
     pub request_timeout_ms: u64,
     pub retry_max: u32,
     pub retry_base_ms: u64,
+    // Wall-clock ceiling for one request including every retry; 0 = none.
+    pub total_deadline_ms: u64,
+    // Ceilings for a whole OCR or LLM stage, however many requests the
+    // batching or chunking behind it issues.
+    pub ocr_stage_timeout_ms: u64,
+    pub llm_stage_timeout_ms: u64,
     pub max_ocr_chars: usize,
     pub anthropic_version: String,
     pub anthropic_max_tokens: u32,
+    pub llm_empty_retry_max: u32,
+    pub http_max_concurrency: usize,
+    // Idle connections kept open per host between requests; `None` keeps
+    // reqwest's default (no limit). The cap on open connections overall is
+    // `http_max_concurrency`, since every in-flight request holds one.
+    pub pool_max_idle_per_host: Option<usize>,
+    // HTTP/2 PING interval that keeps idle connections of a long batch from
+    // being dropped by the server or a middlebox; `None` sends none.
+    pub http2_keep_alive_ms: Option<u64>,
+    // Requests per second across one engine, retries included; 0 = unlimited.
+    pub max_rps: f64,
+    pub connect_failure_threshold: u32,
+    pub connect_failure_window_ms: u64,
+    pub host_cooldown_ms: u64,
+    pub llm_chunk_chars: usize,
+    pub llm_input_price_per_mtok: f64,
+    pub llm_output_price_per_mtok: f64,
+    pub ocr_cache_dir: Option<PathBuf>,
+    // Byte-identical inputs OCRed concurrently share one call; see
+    // `SingleFlight`. On unless `OCR2MD_OCR_DEDUP` is false.
+    pub ocr_dedup: bool,
+    #[serde(serialize_with = "serialize_redacted_url")]
+    pub proxy_url: Option<String>,
+    pub no_proxy: String,
+    pub ca_bundle: Option<PathBuf>,
+    pub danger_accept_invalid_certs: bool,
+    // Sent as `User-Agent` on every request; `None` sends none, as before.
+    pub user_agent: Option<String>,
+    // Log request payloads and response bodies at debug level, with
+    // credentials scrubbed. Off by default: bodies hold the document text.
+    pub log_bodies: bool,
 }
 
 impl RuntimeConfig {
     pub fn from_env() -> Self {
+        let request_timeout_ms = env_u64("REQUEST_TIMEOUT_MS", 30_000);
+        let retry_max = env_u32("RETRY_MAX", 2);
+        // Enough for every expected call of the stage to time out on each of
+        // its attempts, so only a runaway stage ever hits the ceiling.
+        let stage_budget = |calls: u64| {
+            request_timeout_ms.saturating_mul(calls.saturating_mul(u64::from(retry_max) + 1))
+        };
         Self {
-            request_timeout_ms: env_u64("REQUEST_TIMEOUT_MS", 30_000),
-            retry_max: env_u32("RETRY_MAX", 2),
+            request_timeout_ms,
+            retry_max,
             retry_base_ms: env_u64("RETRY_BASE_MS", 300),
+            total_deadline_ms: env_u64("OCR2MD_TOTAL_DEADLINE_MS", 0),
+            ocr_stage_timeout_ms: env_u64(
+                "OCR2MD_OCR_STAGE_TIMEOUT_MS",
+                stage_budget(OCR_STAGE_CALLS),
+            ),
+            llm_stage_timeout_ms: env_u64(
+                "OCR2MD_LLM_STAGE_TIMEOUT_MS",
+                stage_budget(LLM_STAGE_CALLS),
+            ),
             max_ocr_chars: env_usize("MAX_OCR_CHARS", 2_000_000),
             anthropic_version: std::env::var("ANTHROPIC_VERSION")
                 .ok()
                 .filter(|value| !value.trim().is_empty())
                 .unwrap_or_else(|| "2023-06-01".to_string()),
             anthropic_max_tokens: env_u32("ANTHROPIC_MAX_TOKENS", 4096),
+            llm_empty_retry_max: env_u32("LLM_EMPTY_RETRY_MAX", 2),
+            http_max_concurrency: env_usize("OCR2MD_HTTP_MAX_CONCURRENCY", 0),
+            // Zero is passed through so `HttpEngine::new` can reject it
+            // instead of silently falling back.
+            pool_max_idle_per_host: env_parse("OCR2MD_POOL_MAX_IDLE_PER_HOST"),
+            http2_keep_alive_ms: env_parse("OCR2MD_HTTP2_KEEPALIVE_MS"),
+            max_rps: env_f64("OCR2MD_MAX_RPS", 0.0),
+            connect_failure_threshold: env_u32("OCR2MD_CONNECT_FAILURE_THRESHOLD", 5),
+            connect_failure_window_ms: env_u64("OCR2MD_CONNECT_FAILURE_WINDOW_MS", 10_000),
+            host_cooldown_ms: env_u64("OCR2MD_HOST_COOLDOWN_MS", 5_000),
+            llm_chunk_chars: env_usize("OCR2MD_LLM_CHUNK_CHARS", 60_000),
+            llm_input_price_per_mtok: env_f64("LLM_INPUT_PRICE_PER_MTOK", 0.0),
+            llm_output_price_per_mtok: env_f64("LLM_OUTPUT_PRICE_PER_MTOK", 0.0),
+            ocr_cache_dir: std::env::var("OCR2MD_CACHE_DIR")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from),
+            ocr_dedup: std::env::var("OCR2MD_OCR_DEDUP").map_or(true, |value| {
+                !matches!(
+                    value.trim().to_ascii_lowercase().as_str(),
+                    "0" | "false" | "no" | "off"
+                )
+            }),
+            proxy_url: ["OCR2MD_PROXY", "HTTPS_PROXY", "https_proxy"]
+                .into_iter()
+                .filter_map(|key| std::env::var(key).ok())
+                .find(|value| !value.trim().is_empty()),
+            no_proxy: ["OCR2MD_NO_PROXY", "NO_PROXY", "no_proxy"]
+                .into_iter()
+                .filter_map(|key| std::env::var(key).ok())
+                .find(|value| !value.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_NO_PROXY.to_string()),
+            ca_bundle: std::env::var("OCR2MD_CA_BUNDLE")
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from),
+            danger_accept_invalid_certs: env_flag("OCR2MD_DANGER_ACCEPT_INVALID_CERTS"),
+            user_agent: std::env::var("OCR2MD_USER_AGENT")
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            log_bodies: env_flag("OCR2MD_LOG_BODIES"),
         }
     }
 }
 
+fn serialize_redacted_url<S: Serializer>(url: &Option<String>, out: S) -> Result<S::Ok, S::Error> {
+    url.as_deref().map(crate::http::redact_url).serialize(out)
+}
+
+// Calls a stage is expected to make at most: a PDF split into page batches
+// on the OCR side, a long document split into chunks on the LLM side.
+const OCR_STAGE_CALLS: u64 = 20;
+const LLM_STAGE_CALLS: u64 = 10;
+
+// Loopback stays direct so a local Ollama keeps working behind a proxy.
+pub const DEFAULT_NO_PROXY: &str = "localhost,127.0.0.1,::1";
+
 pub fn env_u64(key: &str, fallback: u64) -> u64 {
     std::env::var(key)
         .ok()
@@ -72,6 +252,29 @@ pub fn env_u32(key: &str, fallback: u32) -> u32 {
         .unwrap_or(fallback)
 }
 
+pub fn env_f64(key: &str, fallback: f64) -> f64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .unwrap_or(fallback)
+}
+
+fn env_parse<T: FromStr>(key: &str) -> Option<T> {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+pub fn env_flag(key: &str) -> bool {
+    std::env::var(key).is_ok_and(|value| {
+        matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
+}
+
 pub fn env_usize(key: &str, fallback: usize) -> usize {
     std::env::var(key)
         .ok()
@@ -0,0 +1,78 @@
+use std::io::{Cursor, Read};
+
+use anyhow::{Context, Result};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+
+const DOCUMENT_PART: &str = "word/document.xml";
+
+// The media type `[Content_Types].xml` gives the main Word part, for files
+// that keep it somewhere other than `word/document.xml`.
+const DOCUMENT_CONTENT_TYPE: &str = "wordprocessingml.document.main+xml";
+
+// Zip is also the container of .xlsx, .pptx, .odt, .epub and plain
+// archives; only one holding a Word body is a docx.
+pub fn is_word_container(bytes: &[u8]) -> bool {
+    let Ok(mut archive) = zip::ZipArchive::new(Cursor::new(bytes)) else {
+        return false;
+    };
+    if archive.index_for_name(DOCUMENT_PART).is_some() {
+        return true;
+    }
+    let mut types = String::new();
+    archive
+        .by_name("[Content_Types].xml")
+        .is_ok_and(|mut file| file.read_to_string(&mut types).is_ok())
+        && types.contains(DOCUMENT_CONTENT_TYPE)
+}
+
+// Reads the body text of a .docx without a network round trip: text runs
+// (`<w:t>`) in document order, one line per paragraph. Tabs and manual line
+// breaks inside a paragraph are kept; formatting is not.
+pub fn extract_text(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).context("docx is not a zip")?;
+    let mut xml = String::new();
+    archive
+        .by_name(DOCUMENT_PART)
+        .with_context(|| format!("docx has no {DOCUMENT_PART}"))?
+        .read_to_string(&mut xml)
+        .with_context(|| format!("failed to read {DOCUMENT_PART}"))?;
+    document_text(&xml)
+}
+
+fn document_text(xml: &str) -> Result<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut paragraphs = Vec::new();
+    let mut paragraph = String::new();
+    let mut in_text = false;
+
+    loop {
+        match reader.read_event().context("malformed document.xml")? {
+            Event::Start(tag) if tag.name().as_ref() == b"w:t" => in_text = true,
+            Event::End(tag) => match tag.name().as_ref() {
+                b"w:t" => in_text = false,
+                b"w:p" => paragraphs.push(std::mem::take(&mut paragraph)),
+                _ => {}
+            },
+            Event::Empty(tag) => match tag.name().as_ref() {
+                b"w:tab" => paragraph.push('\t'),
+                b"w:br" | b"w:cr" => paragraph.push('\n'),
+                b"w:p" => paragraphs.push(String::new()),
+                _ => {}
+            },
+            Event::Text(text) if in_text => {
+                paragraph.push_str(&text.unescape().context("malformed text run")?);
+            }
+            Event::CData(text) if in_text => {
+                paragraph.push_str(&String::from_utf8_lossy(&text));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !paragraph.is_empty() {
+        paragraphs.push(paragraph);
+    }
+
+    Ok(paragraphs.join("\n").trim().to_string())
+}
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::http::redact_secrets;
+
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("unsupported input file type: {0}")]
@@ -8,9 +10,56 @@ pub enum AppError {
     #[error("invalid configuration: {0}")]
     InvalidConfig(String),
 
-    #[error("API call failed with status {status}: {message}")]
-    ApiStatus { status: u16, message: String },
+    // `service` is the label passed to `HttpEngine` (`glm_ocr`,
+    // `llm_openai_compatible`, ...), so a failed batch names the failing call.
+    // `message` comes from the provider, so it is scrubbed of keys again on
+    // the way out in case it was built from an unredacted body.
+    #[error(
+        "{service} API call failed with status {status}: {}",
+        redact_secrets(message)
+    )]
+    ApiStatus {
+        service: String,
+        status: u16,
+        message: String,
+    },
 
     #[error("API response parse error: {0}")]
     ApiResponse(String),
+
+    #[error("job cancelled")]
+    Cancelled,
+
+    #[error("wrong passphrase for the profile store")]
+    WrongPassphrase,
+
+    #[error("profile store is corrupt: {0}")]
+    CorruptProfileStore(String),
+
+    #[error(
+        "input is {size} bytes, {} over the {limit}-byte upload limit (OCR2MD_MAX_INPUT_BYTES)",
+        size.saturating_sub(*limit)
+    )]
+    InputTooLarge { size: u64, limit: u64 },
+
+    #[error("gave up after {attempts} attempt(s): total deadline of {deadline_ms} ms exceeded")]
+    DeadlineExceeded { deadline_ms: u64, attempts: u32 },
+
+    // A whole OCR or LLM stage overran `OCR2MD_{OCR,LLM}_STAGE_TIMEOUT_MS`.
+    #[error("{stage} stage did not finish within {timeout_ms} ms")]
+    StageTimeout { stage: String, timeout_ms: u64 },
+
+    #[error("input is empty: {0}")]
+    EmptyInput(String),
+
+    #[error("OCR found no text in the input (--on-empty-ocr fail)")]
+    EmptyOcr,
+
+    // The provider's moderation refused the input. Sending the same bytes
+    // again gets the same answer, so this is never retried.
+    #[error("OCR provider blocked the content: {0}")]
+    ContentBlocked(String),
+
+    #[error("queue is full: {capacity} job(s) already waiting or running (OCR2MD_QUEUE_CAPACITY)")]
+    QueueFull { capacity: usize },
 }
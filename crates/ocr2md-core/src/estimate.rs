@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::file_kind::{InputKind, detect_input_kind_from_bytes};
+use crate::pdf;
+
+// Word files have no page count that is cheap to read, so every started
+// block of this many bytes counts as a page.
+pub const WORD_BYTES_PER_PAGE: u64 = 8 * 1024;
+
+// Entry every lookup falls back to when neither the model nor the provider
+// has rates of its own.
+pub const DEFAULT_RATE_KEY: &str = "default";
+
+// Placeholder rates so an estimate always comes out; they are not anyone's
+// price list. Put real ones in a rates file (`OCR2MD_RATE_TABLE`).
+const DEFAULT_OCR_RATE: OcrRate = OcrRate {
+    per_page: 0.01,
+    chars_per_page: 2000,
+};
+const DEFAULT_LLM_RATE: LlmRate = LlmRate {
+    input_per_million_tokens: 1.0,
+    output_per_million_tokens: 4.0,
+    chars_per_token: 2.0,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OcrRate {
+    // Cost of one page sent to OCR or file parsing.
+    pub per_page: f64,
+    // Text one page typically yields, which is what the LLM then reads.
+    pub chars_per_page: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LlmRate {
+    pub input_per_million_tokens: f64,
+    pub output_per_million_tokens: f64,
+    // About 4 for English, closer to 1.5 for Chinese.
+    pub chars_per_token: f64,
+}
+
+// Rates by model name or provider name (`glm`, `openai`, ...), in whatever
+// currency the table says. A rates file only needs the entries it changes:
+//
+//     {"currency": "CNY", "ocr": {"glm": {"per_page": 0.05, "chars_per_page": 1500}}}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateTable {
+    pub currency: String,
+    pub ocr: BTreeMap<String, OcrRate>,
+    pub llm: BTreeMap<String, LlmRate>,
+}
+
+impl Default for RateTable {
+    fn default() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            ocr: BTreeMap::from([(DEFAULT_RATE_KEY.to_string(), DEFAULT_OCR_RATE)]),
+            llm: BTreeMap::from([(DEFAULT_RATE_KEY.to_string(), DEFAULT_LLM_RATE)]),
+        }
+    }
+}
+
+impl RateTable {
+    // The built-in table with the file's entries laid over it.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read rate table: {}", path.display()))?;
+        let mut table: Self = serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse rate table: {}", path.display()))?;
+        let builtin = Self::default();
+        for (key, rate) in builtin.ocr {
+            table.ocr.entry(key).or_insert(rate);
+        }
+        for (key, rate) in builtin.llm {
+            table.llm.entry(key).or_insert(rate);
+        }
+        if let Some(key) = table
+            .llm
+            .iter()
+            .find_map(|(key, rate)| (rate.chars_per_token <= 0.0).then_some(key))
+        {
+            return Err(AppError::InvalidConfig(format!(
+                "rate table {}: llm.{key}.chars_per_token must be positive",
+                path.display()
+            ))
+            .into());
+        }
+        Ok(table)
+    }
+
+    // The model's own rates win over its provider's, which win over
+    // `default`.
+    pub fn pricing(
+        &self,
+        ocr_provider: &str,
+        ocr_model: Option<&str>,
+        llm_provider: &str,
+        llm_model: Option<&str>,
+    ) -> Pricing {
+        Pricing {
+            currency: self.currency.clone(),
+            ocr: lookup(&self.ocr, ocr_provider, ocr_model).unwrap_or(DEFAULT_OCR_RATE),
+            llm: lookup(&self.llm, llm_provider, llm_model).unwrap_or(DEFAULT_LLM_RATE),
+        }
+    }
+}
+
+fn lookup<T: Copy>(rates: &BTreeMap<String, T>, provider: &str, model: Option<&str>) -> Option<T> {
+    model
+        .into_iter()
+        .chain([provider, DEFAULT_RATE_KEY])
+        .find_map(|key| rates.get(key).copied())
+}
+
+// The rates one estimate was computed with.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Pricing {
+    pub currency: String,
+    pub ocr: OcrRate,
+    pub llm: LlmRate,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileEstimate {
+    pub path: PathBuf,
+    pub kind: InputKind,
+    pub bytes: u64,
+    pub pages: u64,
+    pub chars: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub ocr_cost: f64,
+    pub llm_cost: f64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EstimateTotal {
+    pub files: usize,
+    pub pages: u64,
+    pub chars: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchEstimate {
+    pub pricing: Pricing,
+    pub files: Vec<FileEstimate>,
+    pub total: EstimateTotal,
+}
+
+// What `ocr2md estimate` prints. Nothing is sent anywhere: pages come from
+// the file itself (PDF page count, one per image, size for Word files), and
+// the LLM is assumed to write about as much Markdown as it reads.
+pub fn estimate_batch(files: &[PathBuf], pricing: &Pricing) -> Result<BatchEstimate> {
+    let files = files
+        .iter()
+        .map(|path| estimate_file(path, pricing))
+        .collect::<Result<Vec<_>>>()?;
+    let mut total = EstimateTotal {
+        files: files.len(),
+        ..EstimateTotal::default()
+    };
+    for file in &files {
+        total.pages += file.pages;
+        total.chars += file.chars;
+        total.input_tokens += file.input_tokens;
+        total.output_tokens += file.output_tokens;
+        total.cost += file.cost;
+    }
+    Ok(BatchEstimate {
+        pricing: pricing.clone(),
+        files,
+        total,
+    })
+}
+
+fn estimate_file(path: &Path, pricing: &Pricing) -> Result<FileEstimate> {
+    let bytes =
+        fs::read(path).with_context(|| format!("failed to read input file: {}", path.display()))?;
+    let kind = detect_input_kind_from_bytes(path, &bytes)?;
+    let pages = match kind {
+        InputKind::Pdf => pdf::page_count(&bytes)
+            .with_context(|| format!("failed to count pages of {}", path.display()))?
+            as u64,
+        InputKind::Doc | InputKind::Docx => (bytes.len() as u64).div_ceil(WORD_BYTES_PER_PAGE),
+        InputKind::Png | InputKind::Jpeg | InputKind::Webp => 1,
+    };
+    Ok(estimate_pages(
+        path,
+        kind,
+        bytes.len() as u64,
+        pages,
+        pricing,
+    ))
+}
+
+pub fn estimate_pages(
+    path: &Path,
+    kind: InputKind,
+    bytes: u64,
+    pages: u64,
+    pricing: &Pricing,
+) -> FileEstimate {
+    let chars = pages * pricing.ocr.chars_per_page;
+    let input_tokens = (chars as f64 / pricing.llm.chars_per_token).ceil() as u64;
+    let output_tokens = input_tokens;
+    let ocr_cost = pages as f64 * pricing.ocr.per_page;
+    let llm_cost = (input_tokens as f64 * pricing.llm.input_per_million_tokens
+        + output_tokens as f64 * pricing.llm.output_per_million_tokens)
+        / 1_000_000.0;
+    FileEstimate {
+        path: path.to_path_buf(),
+        kind,
+        bytes,
+        pages,
+        chars,
+        input_tokens,
+        output_tokens,
+        ocr_cost,
+        llm_cost,
+        cost: ocr_cost + llm_cost,
+    }
+}
@@ -1,12 +1,138 @@
 use std::path::Path;
 
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::docx;
 use crate::error::AppError;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// An input path of `-` means standard input.
+pub const STDIN_PATH: &str = "-";
+
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN_PATH)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum InputKind {
     Pdf,
     Doc,
     Docx,
+    Png,
+    #[value(alias = "jpg")]
+    Jpeg,
+    Webp,
+}
+
+impl InputKind {
+    pub fn is_image(self) -> bool {
+        matches!(self, Self::Png | Self::Jpeg | Self::Webp)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Doc => "doc",
+            Self::Docx => "docx",
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+
+    pub fn default_mime(self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Doc => "application/msword",
+            Self::Docx => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+}
+
+// Content wins over the name: the leading magic bytes decide when they are
+// recognised, and the extension is only consulted when they are not. Empty
+// files and plain text are refused here, before they cost an OCR call.
+pub fn detect_input_kind_from_bytes(path: &Path, bytes: &[u8]) -> Result<InputKind, AppError> {
+    let name = path.display().to_string();
+    if bytes.is_empty() {
+        return Err(AppError::EmptyInput(name));
+    }
+    match sniff(bytes) {
+        Some(kind) => Ok(kind),
+        None => {
+            let kind = detect_input_kind(path)?;
+            reject_text(&name, kind, bytes)?;
+            Ok(kind)
+        }
+    }
+}
+
+// Stdin has no name to fall back on, so the declared kind (`--stdin-kind`)
+// stands in for the extension behind the magic bytes.
+pub fn detect_stdin_kind(bytes: &[u8], declared: Option<InputKind>) -> Result<InputKind, AppError> {
+    if bytes.is_empty() {
+        return Err(AppError::EmptyInput("stdin".to_string()));
+    }
+    if let Some(kind) = sniff(bytes) {
+        return Ok(kind);
+    }
+    let kind = declared.ok_or_else(|| {
+        AppError::UnsupportedInputType(
+            "stdin (unrecognised content, pass --stdin-kind)".to_string(),
+        )
+    })?;
+    reject_text("stdin", kind, bytes)?;
+    Ok(kind)
+}
+
+// Every supported kind is binary, so content with unrecognised magic bytes
+// that reads as text (a `.txt` renamed to `.pdf`, an HTML error page saved
+// as `.docx`) cannot be what its name claims. A PDF may put a few bytes of
+// junk before its header, so `%PDF-` anywhere in the head still passes.
+fn reject_text(name: &str, kind: InputKind, bytes: &[u8]) -> Result<(), AppError> {
+    let head = &bytes[..bytes.len().min(1024)];
+    if kind == InputKind::Pdf && head.windows(5).any(|window| window == b"%PDF-") {
+        return Ok(());
+    }
+    if looks_like_text(head) {
+        return Err(AppError::UnsupportedInputType(format!(
+            "{name} (content is plain text, not {})",
+            kind.extension()
+        )));
+    }
+    Ok(())
+}
+
+// No NUL bytes and valid UTF-8, allowing a character cut off at the end.
+fn looks_like_text(head: &[u8]) -> bool {
+    !head.contains(&0)
+        && match std::str::from_utf8(head) {
+            Ok(_) => true,
+            Err(err) => err.error_len().is_none(),
+        }
+}
+
+fn sniff(bytes: &[u8]) -> Option<InputKind> {
+    if bytes.starts_with(b"%PDF-") {
+        Some(InputKind::Pdf)
+    } else if bytes.starts_with(b"PK\x03\x04") {
+        // Any other zip falls back to its extension, which rejects it.
+        docx::is_word_container(bytes).then_some(InputKind::Docx)
+    } else if bytes.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1") {
+        Some(InputKind::Doc)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(InputKind::Png)
+    } else if bytes.starts_with(b"\xFF\xD8\xFF") {
+        Some(InputKind::Jpeg)
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some(InputKind::Webp)
+    } else {
+        None
+    }
 }
 
 pub fn detect_input_kind(path: &Path) -> Result<InputKind, AppError> {
@@ -20,6 +146,9 @@ pub fn detect_input_kind(path: &Path) -> Result<InputKind, AppError> {
         "pdf" => Ok(InputKind::Pdf),
         "doc" => Ok(InputKind::Doc),
         "docx" => Ok(InputKind::Docx),
+        "png" => Ok(InputKind::Png),
+        "jpg" | "jpeg" => Ok(InputKind::Jpeg),
+        "webp" => Ok(InputKind::Webp),
         _ => Err(AppError::UnsupportedInputType(path.display().to_string())),
     }
 }
@@ -0,0 +1,149 @@
+use clap::ValueEnum;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, html};
+
+// What the LLM's Markdown is turned into before it is written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Md,
+    Html,
+    Txt,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Md => "md",
+            Self::Html => "html",
+            Self::Txt => "txt",
+        }
+    }
+
+    pub fn render(self, markdown: &str) -> String {
+        match self {
+            Self::Md => markdown.to_string(),
+            Self::Html => to_html(markdown),
+            Self::Txt => to_plain_text(markdown),
+        }
+    }
+}
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    )
+}
+
+// An HTML fragment, not a full page: callers embedding it bring their own
+// `<html>` shell and styles.
+pub fn to_html(markdown: &str) -> String {
+    let mut out = String::new();
+    html::push_html(&mut out, parser(markdown));
+    out
+}
+
+// Keeps the words and the block layout: one paragraph per line group, list
+// items as `- ` / `1. ` lines, table cells separated by tabs. Raw HTML
+// (including comments) is dropped.
+pub fn to_plain_text(markdown: &str) -> String {
+    let mut out = String::new();
+    // `Some(n)` for an ordered list at item number n, `None` for bullets.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::List(start)) => {
+                end_line(&mut out);
+                lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    out.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                end_line(&mut out);
+                out.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        out.push_str(&format!("{number}. "));
+                        *number += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) => end_line(&mut out),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                end_line(&mut out);
+                if lists.is_empty() {
+                    out.push('\n');
+                }
+            }
+            Event::End(TagEnd::Table) => out.push('\n'),
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => {
+                if out.ends_with('\t') {
+                    out.pop();
+                }
+                out.push('\n');
+            }
+            Event::End(TagEnd::TableCell) => out.push('\t'),
+            Event::Text(text) | Event::Code(text) => out.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Rule => end_line(&mut out),
+            _ => {}
+        }
+    }
+
+    out.trim().to_string() + "\n"
+}
+
+fn end_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{OutputFormat, to_html, to_plain_text};
+
+    const SAMPLE: &str = "# Report\n\nSome **bold** text.\n\n\
+        - one\n- two\n  1. nested\n\n\
+        | Item | Qty |\n| --- | --- |\n| Pen | 2 |\n";
+
+    #[test]
+    fn html_renders_headings_lists_and_tables() {
+        let html = to_html(SAMPLE);
+        assert!(html.contains("<h1>Report</h1>"), "{html}");
+        assert!(html.contains("<strong>bold</strong>"), "{html}");
+        assert!(html.contains("<li>one</li>"), "{html}");
+        assert!(html.contains("<ol>"), "{html}");
+        assert!(html.contains("<th>Item</th>"), "{html}");
+        assert!(html.contains("<td>Pen</td>"), "{html}");
+    }
+
+    #[test]
+    fn plain_text_strips_markup_but_keeps_layout() {
+        assert_eq!(
+            to_plain_text(SAMPLE),
+            "Report\n\nSome bold text.\n\n- one\n- two\n  1. nested\n\nItem\tQty\nPen\t2\n"
+        );
+    }
+
+    #[test]
+    fn plain_text_drops_html_comments() {
+        assert_eq!(to_plain_text("Body\n\n<!-- note -->\n"), "Body\n");
+    }
+
+    #[test]
+    fn markdown_is_passed_through_and_extensions_match() {
+        assert_eq!(OutputFormat::Md.render(SAMPLE), SAMPLE);
+        assert_eq!(OutputFormat::Md.extension(), "md");
+        assert_eq!(OutputFormat::Html.extension(), "html");
+        assert_eq!(OutputFormat::Txt.extension(), "txt");
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// What produced a Markdown file, written as YAML frontmatter when asked for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversionMetadata {
+    pub source: String,
+    pub converted_at: String,
+    pub ocr_model: Option<String>,
+    pub llm_model: String,
+    pub trace_id: String,
+}
+
+impl ConversionMetadata {
+    // `source` is the input's file name: the part a static-site generator
+    // can use without leaking the local directory layout.
+    pub fn new(
+        source: &Path,
+        ocr_model: Option<&str>,
+        llm_model: &str,
+        trace_id: &str,
+        converted_at: SystemTime,
+    ) -> Self {
+        let source = source.file_name().map_or_else(
+            || source.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        Self {
+            source,
+            converted_at: rfc3339_utc(converted_at),
+            ocr_model: ocr_model.map(str::to_string),
+            llm_model: llm_model.to_string(),
+            trace_id: trace_id.to_string(),
+        }
+    }
+}
+
+// Values are emitted as double-quoted scalars with JSON escaping, which YAML
+// reads back verbatim, so quotes, colons or newlines in names stay inert.
+pub fn build_frontmatter(metadata: &ConversionMetadata) -> String {
+    let mut out = String::from("---\n");
+    let mut field = |key: &str, value: &str| {
+        out.push_str(key);
+        out.push_str(": ");
+        out.push_str(&serde_json::to_string(value).unwrap_or_else(|_| "\"\"".to_string()));
+        out.push('\n');
+    };
+    field("source", &metadata.source);
+    field("converted_at", &metadata.converted_at);
+    if let Some(ocr_model) = &metadata.ocr_model {
+        field("ocr_model", ocr_model);
+    }
+    field("llm_model", &metadata.llm_model);
+    field("trace_id", &metadata.trace_id);
+    out.push_str("---\n\n");
+    out
+}
+
+// `YYYY-MM-DDTHH:MM:SSZ`, via the days-to-civil conversion from Howard
+// Hinnant's date algorithms.
+fn rfc3339_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pretty_assertions::assert_eq;
+
+    use super::{ConversionMetadata, build_frontmatter, rfc3339_utc};
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(rfc3339_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(
+            rfc3339_utc(UNIX_EPOCH + Duration::from_secs(1_709_210_096)),
+            "2024-02-29T12:34:56Z"
+        );
+    }
+
+    #[test]
+    fn frontmatter_lists_every_field() {
+        let metadata = ConversionMetadata::new(
+            Path::new("/scans/report.pdf"),
+            Some("glm-4.1v"),
+            "gpt-4o-mini",
+            "trace-1",
+            UNIX_EPOCH,
+        );
+
+        assert_eq!(
+            build_frontmatter(&metadata),
+            "---\n\
+             source: \"report.pdf\"\n\
+             converted_at: \"1970-01-01T00:00:00Z\"\n\
+             ocr_model: \"glm-4.1v\"\n\
+             llm_model: \"gpt-4o-mini\"\n\
+             trace_id: \"trace-1\"\n\
+             ---\n\n"
+        );
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        let metadata = ConversionMetadata::new(
+            Path::new("say \"hi\": a\\b\n#1.pdf"),
+            None,
+            "model: x",
+            "t",
+            UNIX_EPOCH,
+        );
+        let frontmatter = build_frontmatter(&metadata);
+
+        assert!(frontmatter.contains("source: \"say \\\"hi\\\": a\\\\b\\n#1.pdf\"\n"));
+        assert!(frontmatter.contains("llm_model: \"model: x\"\n"));
+        assert!(!frontmatter.contains("ocr_model"));
+        // Each field stays on its own line.
+        assert_eq!(frontmatter.lines().count(), 7);
+    }
+}
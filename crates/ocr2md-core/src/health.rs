@@ -0,0 +1,124 @@
+use std::future::Future;
+use std::time::Instant;
+
+use anyhow::Result;
+use futures::future::join_all;
+use serde::Serialize;
+
+use crate::config::RuntimeConfig;
+use crate::error::AppError;
+use crate::http::HttpEngine;
+use crate::llm::LlmConfig;
+use crate::ocr::GlmConfig;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ProbeStatus {
+    pub name: String,
+    pub ready: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub probes: Vec<ProbeStatus>,
+}
+
+pub const CONNECTION_TEST_TIMEOUT_MS: u64 = 15_000;
+
+// Why a credential check failed, so the UI can point at the right field.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ConnectionFailure {
+    Auth(String),
+    Unreachable(String),
+    ModelNotFound(String),
+    Other(String),
+}
+
+impl ConnectionFailure {
+    pub fn classify(err: &anyhow::Error) -> Self {
+        let message = format!("{err:#}");
+        if let Some(AppError::ApiStatus { status, .. }) = err.downcast_ref::<AppError>() {
+            return match status {
+                401 | 403 => Self::Auth(message),
+                404 => Self::ModelNotFound(message),
+                _ => Self::Other(message),
+            };
+        }
+        let unreachable = err
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+            .any(|cause| cause.is_connect() || cause.is_timeout() || cause.is_builder());
+        if unreachable {
+            Self::Unreachable(message)
+        } else {
+            Self::Other(message)
+        }
+    }
+}
+
+// Single-shot settings for connection tests: a short timeout independent of
+// the job timeout, no retries, and a tiny Anthropic token budget.
+pub fn connection_test_runtime(mut runtime: RuntimeConfig) -> RuntimeConfig {
+    runtime.request_timeout_ms = CONNECTION_TEST_TIMEOUT_MS;
+    runtime.retry_max = 0;
+    runtime.llm_empty_retry_max = 0;
+    runtime.anthropic_max_tokens = 16;
+    runtime
+}
+
+// `/healthz`: the process is up and able to answer.
+pub fn liveness() -> bool {
+    true
+}
+
+// `/readyz`: every configured provider endpoint is reachable. Any HTTP answer
+// counts as reachable; credentials are not checked here.
+pub async fn readiness(
+    http: &HttpEngine,
+    glm_cfg: Option<&GlmConfig>,
+    llm_cfgs: &[LlmConfig],
+) -> ReadinessReport {
+    let mut targets = Vec::new();
+    if let Some(cfg) = glm_cfg {
+        targets.push(("glm".to_string(), cfg.base_url.clone()));
+    }
+    for cfg in llm_cfgs {
+        targets.push((
+            format!("llm:{:?}:{}", cfg.provider, cfg.model),
+            cfg.base_url.clone(),
+        ));
+    }
+
+    aggregate(
+        targets
+            .into_iter()
+            .map(|(name, url)| (name, async move { http.probe(&url).await.map(|_| ()) }))
+            .collect(),
+    )
+    .await
+}
+
+pub async fn aggregate<F>(probes: Vec<(String, F)>) -> ReadinessReport
+where
+    F: Future<Output = Result<()>>,
+{
+    let statuses = join_all(probes.into_iter().map(|(name, probe)| async move {
+        let started = Instant::now();
+        let result = probe.await;
+        ProbeStatus {
+            name,
+            ready: result.is_ok(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            error: result.err().map(|err| err.to_string()),
+        }
+    }))
+    .await;
+
+    ReadinessReport {
+        ready: statuses.iter().all(|status| status.ready),
+        probes: statuses,
+    }
+}
@@ -0,0 +1,78 @@
+use std::io::Cursor;
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, GenericImageView, ImageDecoder, ImageFormat, ImageReader};
+use tracing::info;
+
+pub const DEFAULT_MAX_DIMENSION: u32 = 3000;
+
+// Runs the enabled preparation steps, returning the original bytes untouched
+// when nothing needed to change.
+pub fn prepare(bytes: &[u8], auto_rotate_enabled: bool, max_dimension: u32) -> Result<Vec<u8>> {
+    let mut current = bytes.to_vec();
+    if auto_rotate_enabled && let Some(rotated) = auto_rotate(&current)? {
+        current = rotated;
+    }
+    if let Some(scaled) = downscale(&current, max_dimension)? {
+        current = scaled;
+    }
+    Ok(current)
+}
+
+// Applies the EXIF orientation tag so the image is upright before OCR.
+// Returns `None` when the image is already upright.
+pub fn auto_rotate(bytes: &[u8]) -> Result<Option<Vec<u8>>> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .context("failed to read image")?;
+    let format = reader.format().context("unrecognized image format")?;
+    let mut decoder = reader.into_decoder().context("failed to decode image")?;
+
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    if orientation == Orientation::NoTransforms {
+        return Ok(None);
+    }
+
+    let mut image = DynamicImage::from_decoder(decoder).context("failed to decode image")?;
+    image.apply_orientation(orientation);
+    encode(&image, format).map(Some)
+}
+
+// Shrinks the image so its longest side is at most `max_dimension` pixels.
+// Zero disables the cap.
+pub fn downscale(bytes: &[u8], max_dimension: u32) -> Result<Option<Vec<u8>>> {
+    if max_dimension == 0 {
+        return Ok(None);
+    }
+
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .context("failed to read image")?;
+    let format = reader.format().context("unrecognized image format")?;
+    let (width, height) = reader
+        .into_dimensions()
+        .context("failed to read image dimensions")?;
+    if width.max(height) <= max_dimension {
+        return Ok(None);
+    }
+
+    let image =
+        image::load_from_memory_with_format(bytes, format).context("failed to decode image")?;
+    let scaled = image.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+    let (scaled_width, scaled_height) = scaled.dimensions();
+    info!(
+        width,
+        height, scaled_width, scaled_height, "image_downscaled"
+    );
+    encode(&scaled, format).map(Some)
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+    let mut out = Cursor::new(Vec::new());
+    image
+        .write_to(&mut out, format)
+        .with_context(|| format!("failed to encode {format:?} image"))?;
+    Ok(out.into_inner())
+}
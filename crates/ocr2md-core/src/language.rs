@@ -0,0 +1,12 @@
+// Detection only needs a sample; long documents are not scanned in full.
+const DETECT_SAMPLE_CHARS: usize = 20_000;
+
+// Dominant language of the OCR text as an English name ("English",
+// "Mandarin", "Japanese"), or `None` when the text is too short or too mixed
+// to call with confidence. It is only a hint for the structuring prompt.
+pub fn detect_language(text: &str) -> Option<String> {
+    let sample: String = text.chars().take(DETECT_SAMPLE_CHARS).collect();
+    let info = whatlang::detect(&sample)?;
+    info.is_reliable()
+        .then(|| info.lang().eng_name().to_string())
+}
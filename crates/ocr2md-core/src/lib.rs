@@ -1,10 +1,36 @@
+pub mod capabilities;
+pub mod chunk;
+pub mod cjk;
 pub mod config;
+pub mod docx;
 pub mod error;
+pub mod estimate;
 pub mod file_kind;
+pub mod format;
+pub mod frontmatter;
+pub mod health;
 pub mod http;
+pub mod image_prep;
+pub mod info;
+pub mod language;
 pub mod llm;
+pub mod manifest;
 pub mod ocr;
+pub mod ocr_cache;
+pub mod output;
+pub mod pdf;
 pub mod pipeline;
+pub mod postprocess;
 pub mod profile_store;
+pub mod progress;
 pub mod queue;
+pub mod redact;
+pub mod running_headers;
+pub mod schema;
+pub mod secret_backend;
+pub mod sections;
 pub mod secure_config;
+pub mod single_flight;
+pub mod tables;
+pub mod toc;
+pub mod webhook;
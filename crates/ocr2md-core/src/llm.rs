@@ -1,15 +1,47 @@
+use std::future::Future;
+
 use anyhow::{Context, Result};
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use futures::StreamExt;
+use futures::future::join_all;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
 use serde_json::{Value, json};
+use tracing::{info, warn};
 
-use crate::config::{LlmProvider, RuntimeConfig};
+use crate::chunk;
+use crate::config::{LlmProvider, ProviderChoice, RuntimeConfig};
 use crate::error::AppError;
-use crate::http::HttpEngine;
-use crate::ocr::extract_openai_content;
+use crate::http::{HttpEngine, SseDecoder, gemini_headers, looks_like_sse, secret_header};
+use crate::language::detect_language;
+use crate::ocr::{TRUNCATION_MARKER, extract_openai_content, parse_page_marker};
+use crate::schema::{
+    self, ANTHROPIC_MESSAGES_RULES, FieldRule, GEMINI_GENERATE_RULES, OLLAMA_CHAT_RULES,
+    OPENAI_CHAT_RULES,
+};
 
-const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const CHUNK_OVERLAP_CHARS: usize = 400;
+pub const PING_PROMPT: &str = "Reply with the single word OK.";
+pub const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
 const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
-const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+pub const DEFAULT_GEMINI_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434";
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+const DEFAULT_TEMPERATURE: f64 = 0.1;
+const MAX_TEMPERATURE: f64 = 2.0;
+const STRICT_MARKDOWN_REMINDER: &str = "\n\n再次强调：只输出整理后的 Markdown 正文本身，不要任何解释、开场白或总结，也不要用代码块包裹。";
+const PROSE_PREAMBLES: &[&str] = &[
+    "here is",
+    "here's",
+    "below is",
+    "sure,",
+    "sure!",
+    "certainly",
+    "以下是",
+    "下面是",
+    "好的，",
+    "好的！",
+    "当然，",
+];
 
 #[derive(Debug, Clone)]
 pub struct LlmConfig {
@@ -18,6 +50,22 @@ pub struct LlmConfig {
     pub base_url: String,
     pub model: String,
     pub system_prompt: String,
+    pub stop: Vec<String>,
+    pub require_streaming: bool,
+    // `None` keeps each provider's default: 0.1, and for Anthropic's
+    // mandatory ceiling `ANTHROPIC_MAX_TOKENS`.
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    // Dominant document language for the prompt (`--lang`); `None` detects
+    // it from the OCR text.
+    pub document_language: Option<String>,
+    // Azure only: the deployment in the URL (`None` uses `model`) and the
+    // `api-version` query (`None` uses `DEFAULT_AZURE_API_VERSION`).
+    pub deployment: Option<String>,
+    pub api_version: Option<String>,
+    // Sent with every request on top of the provider's own headers; see
+    // `with_extra_headers`.
+    pub extra_headers: HeaderMap,
 }
 
 impl LlmConfig {
@@ -28,18 +76,44 @@ impl LlmConfig {
         model: Option<String>,
         system_prompt: Option<String>,
     ) -> Result<Self> {
+        Self::from_choice(provider.into(), api_key, base_url, model, system_prompt)
+    }
+
+    // A vendor preset only fills in the base URL and model the caller (and
+    // `LLM_BASE_URL` / `LLM_MODEL`) left unset.
+    pub fn from_choice(
+        choice: ProviderChoice,
+        api_key: Option<String>,
+        base_url: Option<String>,
+        model: Option<String>,
+        system_prompt: Option<String>,
+    ) -> Result<Self> {
+        let ProviderChoice { provider, preset } = choice;
+        // A local Ollama server needs no key; one is only sent when provided.
         let api_key = api_key
             .or_else(|| std::env::var("LLM_API_KEY").ok())
-            .filter(|value| !value.trim().is_empty())
-            .ok_or_else(|| AppError::InvalidConfig("LLM_API_KEY is required".to_string()))?;
+            .filter(|value| !value.trim().is_empty());
+        let api_key = match (api_key, provider) {
+            (Some(key), _) => key,
+            (None, LlmProvider::Ollama | LlmProvider::Mock) => String::new(),
+            (None, _) => {
+                return Err(AppError::InvalidConfig("LLM_API_KEY is required".to_string()).into());
+            }
+        };
 
         let base_url = base_url
             .or_else(|| std::env::var("LLM_BASE_URL").ok())
+            // A blank `LLM_BASE_URL=` line in `.env` must not hide the preset.
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| preset.map(|preset| preset.base_url().to_string()))
             .unwrap_or_else(|| match provider {
                 LlmProvider::Openai => DEFAULT_OPENAI_BASE_URL.to_string(),
                 LlmProvider::Anthropic => DEFAULT_ANTHROPIC_BASE_URL.to_string(),
                 LlmProvider::Gemini => DEFAULT_GEMINI_BASE_URL.to_string(),
-                LlmProvider::OpenaiCompatible => String::new(),
+                LlmProvider::OpenaiCompatible | LlmProvider::Azure | LlmProvider::Mock => {
+                    String::new()
+                }
+                LlmProvider::Ollama => DEFAULT_OLLAMA_BASE_URL.to_string(),
             });
 
         if provider == LlmProvider::OpenaiCompatible && base_url.trim().is_empty() {
@@ -49,17 +123,34 @@ impl LlmConfig {
             )
             .into());
         }
+        if provider == LlmProvider::Azure && base_url.trim().is_empty() {
+            return Err(AppError::InvalidConfig(
+                "LLM_BASE_URL is required for azure (https://<resource>.openai.azure.com)"
+                    .to_string(),
+            )
+            .into());
+        }
 
         let model = model
             .or_else(|| std::env::var("LLM_MODEL").ok())
+            // Same for a blank `LLM_MODEL=`.
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| preset.map(|preset| preset.model().to_string()))
             .unwrap_or_else(|| match provider {
                 LlmProvider::Openai => "gpt-4o-mini".to_string(),
                 LlmProvider::Anthropic => "claude-sonnet-4-5".to_string(),
                 LlmProvider::Gemini => "gemini-2.0-flash".to_string(),
-                LlmProvider::OpenaiCompatible => "gpt-4o-mini".to_string(),
+                LlmProvider::OpenaiCompatible | LlmProvider::Azure => "gpt-4o-mini".to_string(),
+                LlmProvider::Ollama => "llama3.1".to_string(),
+                LlmProvider::Mock => "mock".to_string(),
             });
 
-        let system_prompt = system_prompt.unwrap_or_else(default_system_prompt);
+        let system_prompt = resolve_system_prompt(system_prompt);
+        let azure_env = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| provider == LlmProvider::Azure && !value.trim().is_empty())
+        };
 
         Ok(Self {
             provider,
@@ -67,8 +158,120 @@ impl LlmConfig {
             base_url: base_url.trim_end_matches('/').to_string(),
             model,
             system_prompt,
+            stop: Vec::new(),
+            require_streaming: false,
+            temperature: None,
+            max_tokens: None,
+            document_language: None,
+            deployment: azure_env("AZURE_OPENAI_DEPLOYMENT"),
+            api_version: azure_env("AZURE_OPENAI_API_VERSION"),
+            extra_headers: HeaderMap::new(),
         })
     }
+
+    pub fn with_sampling(
+        mut self,
+        temperature: Option<f64>,
+        max_tokens: Option<u32>,
+    ) -> Result<Self> {
+        if let Some(temperature) = temperature
+            && !(0.0..=MAX_TEMPERATURE).contains(&temperature)
+        {
+            return Err(AppError::InvalidConfig(format!(
+                "temperature must be between 0 and {MAX_TEMPERATURE}, got {temperature}"
+            ))
+            .into());
+        }
+        if max_tokens == Some(0) {
+            return Err(
+                AppError::InvalidConfig("max tokens must be greater than 0".to_string()).into(),
+            );
+        }
+
+        self.temperature = temperature;
+        self.max_tokens = max_tokens;
+        Ok(self)
+    }
+
+    pub fn with_stop(mut self, stop: Vec<String>) -> Result<Self> {
+        let limit = max_stop_sequences(self.provider);
+        if stop.len() > limit {
+            return Err(AppError::InvalidConfig(format!(
+                "{:?} accepts at most {limit} stop sequences, got {}",
+                self.provider,
+                stop.len()
+            ))
+            .into());
+        }
+        if stop.iter().any(|sequence| sequence.is_empty()) {
+            return Err(
+                AppError::InvalidConfig("stop sequences must not be empty".to_string()).into(),
+            );
+        }
+
+        self.stop = stop;
+        Ok(self)
+    }
+
+    // Headers a relay or gateway wants on every request (an `X-Org-Id`
+    // routing header, say). They never replace a header the provider call
+    // sets itself, so `Authorization`, `x-api-key` and `Content-Type` always
+    // come from the config.
+    pub fn with_extra_headers<'a>(
+        mut self,
+        headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self> {
+        for (name, value) in headers {
+            let header = HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| {
+                AppError::InvalidConfig(format!("invalid extra header name {name:?}"))
+            })?;
+            let value = HeaderValue::from_str(value.trim()).map_err(|_| {
+                AppError::InvalidConfig(format!("invalid value for extra header {name:?}"))
+            })?;
+            self.extra_headers.insert(header, value);
+        }
+        Ok(self)
+    }
+}
+
+fn max_stop_sequences(provider: LlmProvider) -> usize {
+    match provider {
+        LlmProvider::Openai | LlmProvider::OpenaiCompatible | LlmProvider::Azure => 4,
+        LlmProvider::Anthropic => 16,
+        LlmProvider::Gemini => 5,
+        LlmProvider::Ollama | LlmProvider::Mock => 16,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    pub fn estimated_cost(&self, input_per_mtok: f64, output_per_mtok: f64) -> f64 {
+        (self.input_tokens as f64 * input_per_mtok + self.output_tokens as f64 * output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownResult {
+    pub markdown: String,
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Debug)]
+pub struct CandidateResult {
+    pub index: usize,
+    pub provider: LlmProvider,
+    pub model: String,
+    pub result: Result<String>,
 }
 
 pub struct LlmClient {
@@ -82,132 +285,789 @@ impl LlmClient {
         Self { http, cfg, runtime }
     }
 
-    pub async fn to_markdown(&self, ocr_text: &str, trace_id: &str) -> Result<String> {
-        let user_prompt = build_user_prompt(ocr_text);
+    // Runs every candidate concurrently (bounded by the shared `HttpEngine`
+    // limiter) and returns results in candidate order, not completion order.
+    pub async fn compare(
+        candidates: &[LlmClient],
+        ocr_text: &str,
+        trace_id: &str,
+    ) -> Vec<CandidateResult> {
+        let runs = candidates
+            .iter()
+            .enumerate()
+            .map(|(index, client)| async move {
+                let candidate_trace = format!("{trace_id}-c{index}");
+                CandidateResult {
+                    index,
+                    provider: client.cfg.provider,
+                    model: client.cfg.model.clone(),
+                    result: client
+                        .to_markdown(ocr_text, &candidate_trace)
+                        .await
+                        .map(|result| result.markdown),
+                }
+            });
+        join_all(runs).await
+    }
+
+    pub fn model(&self) -> &str {
+        &self.cfg.model
+    }
 
-        match self.cfg.provider {
-            LlmProvider::Openai | LlmProvider::OpenaiCompatible => {
-                self.call_openai_compatible(&user_prompt, trace_id).await
+    pub fn stage_timeout_ms(&self) -> u64 {
+        self.runtime.llm_stage_timeout_ms
+    }
+
+    // Uses the runtime's per-million-token prices; `None` when none are set.
+    pub fn estimated_cost(&self, usage: &TokenUsage) -> Option<f64> {
+        let (input, output) = (
+            self.runtime.llm_input_price_per_mtok,
+            self.runtime.llm_output_price_per_mtok,
+        );
+        (input > 0.0 || output > 0.0).then(|| usage.estimated_cost(input, output))
+    }
+
+    // Sends `PING_PROMPT` once to confirm the key, base URL and model work,
+    // returning whatever the model answered.
+    pub async fn ping(&self, trace_id: &str) -> Result<String> {
+        let reply = self
+            .call_provider(PING_PROMPT, trace_id)
+            .await?
+            .ok_or_else(|| AppError::ApiResponse(self.missing_content_message().to_string()))?;
+        Ok(reply.markdown.trim().to_string())
+    }
+
+    pub async fn to_markdown(&self, ocr_text: &str, trace_id: &str) -> Result<MarkdownResult> {
+        let (ocr_text, truncated) = split_truncation_marker(ocr_text);
+        let chunks = chunk::split_text(ocr_text, self.runtime.llm_chunk_chars, CHUNK_OVERLAP_CHARS);
+        if chunks.len() > 1 {
+            info!(chunks = chunks.len(), trace_id, "llm_chunked_input");
+        }
+
+        let language = self.document_language(ocr_text, trace_id);
+        let mut parts = Vec::with_capacity(chunks.len());
+        for (index, text) in chunks.iter().enumerate() {
+            let is_last = index + 1 == chunks.len();
+            parts.push(
+                self.markdown_for_chunk(text, truncated && is_last, language.as_deref(), trace_id)
+                    .await?,
+            );
+        }
+
+        Ok(merge_results(parts))
+    }
+
+    // `--lang` wins; otherwise the language is detected once on the whole
+    // document so every chunk gets the same hint.
+    fn document_language(&self, ocr_text: &str, trace_id: &str) -> Option<String> {
+        if let Some(language) = &self.cfg.document_language {
+            return Some(language.clone());
+        }
+        let detected = detect_language(ocr_text);
+        info!(
+            language = detected.as_deref().unwrap_or("unknown"),
+            trace_id, "document_language_detected"
+        );
+        detected
+    }
+
+    async fn markdown_for_chunk(
+        &self,
+        ocr_text: &str,
+        truncated: bool,
+        language: Option<&str>,
+        trace_id: &str,
+    ) -> Result<MarkdownResult> {
+        let user_prompt = build_user_prompt(ocr_text, truncated, language);
+
+        let mut result = self.call_with_empty_retry(&user_prompt, trace_id).await?;
+        let mut markdown = unwrap_code_fence(&result.markdown);
+        // One stricter attempt; if the model still chats, its answer is kept.
+        if looks_like_prose_preamble(&markdown) {
+            warn!(trace_id, "llm_prose_preamble_retry");
+            let strict_prompt = format!("{user_prompt}{STRICT_MARKDOWN_REMINDER}");
+            let retry = self.call_with_empty_retry(&strict_prompt, trace_id).await?;
+            markdown = unwrap_code_fence(&retry.markdown);
+            result.usage = add_usage(result.usage, retry.usage);
+        }
+
+        Ok(MarkdownResult {
+            markdown: strip_truncation_marker(&markdown),
+            usage: result.usage,
+        })
+    }
+
+    async fn call_with_empty_retry(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+    ) -> Result<MarkdownResult> {
+        retry_on_empty(
+            self.runtime.llm_empty_retry_max,
+            self.missing_content_message(),
+            trace_id,
+            || self.call_provider(user_prompt, trace_id),
+        )
+        .await
+    }
+
+    // OpenAI-style and Anthropic providers stream natively: `on_chunk` sees
+    // each text delta as it arrives. Gemini and Ollama still degrade to one
+    // final chunk unless `require_streaming` asks for a hard error instead.
+    pub async fn to_markdown_streaming<F>(
+        &self,
+        ocr_text: &str,
+        trace_id: &str,
+        mut on_chunk: F,
+    ) -> Result<MarkdownResult>
+    where
+        F: FnMut(&str),
+    {
+        if matches!(
+            self.cfg.provider,
+            LlmProvider::Gemini | LlmProvider::Ollama | LlmProvider::Mock
+        ) {
+            if self.cfg.require_streaming {
+                return Err(AppError::InvalidConfig(format!(
+                    "streaming is not available for {:?}",
+                    self.cfg.provider
+                ))
+                .into());
             }
-            LlmProvider::Anthropic => self.call_anthropic(&user_prompt, trace_id).await,
-            LlmProvider::Gemini => self.call_gemini(&user_prompt, trace_id).await,
+
+            warn!(
+                provider = ?self.cfg.provider,
+                trace_id,
+                "llm_streaming_unavailable"
+            );
+            let result = self.to_markdown(ocr_text, trace_id).await?;
+            on_chunk(&result.markdown);
+            return Ok(result);
         }
+
+        let (ocr_text, truncated) = split_truncation_marker(ocr_text);
+        let chunks = chunk::split_text(ocr_text, self.runtime.llm_chunk_chars, CHUNK_OVERLAP_CHARS);
+        if chunks.len() > 1 {
+            info!(chunks = chunks.len(), trace_id, "llm_chunked_input");
+        }
+
+        let language = self.document_language(ocr_text, trace_id);
+        let mut parts = Vec::with_capacity(chunks.len());
+        for (index, text) in chunks.iter().enumerate() {
+            if index > 0 {
+                on_chunk("\n\n");
+            }
+            let user_prompt = build_user_prompt(
+                text,
+                truncated && index + 1 == chunks.len(),
+                language.as_deref(),
+            );
+            parts.push(
+                self.stream_with_retry(&user_prompt, trace_id, &mut on_chunk)
+                    .await?,
+            );
+        }
+
+        Ok(merge_results(parts))
     }
 
-    async fn call_openai_compatible(&self, user_prompt: &str, trace_id: &str) -> Result<String> {
-        let url = format!("{}/chat/completions", self.cfg.base_url);
+    // Mirrors `retry_on_empty`: a stream that produced no text emitted nothing
+    // through `on_chunk`, so asking again cannot duplicate output.
+    async fn stream_with_retry<F>(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+        on_chunk: &mut F,
+    ) -> Result<MarkdownResult>
+    where
+        F: FnMut(&str),
+    {
+        for attempt in 0..=self.runtime.llm_empty_retry_max {
+            let result = self
+                .stream_provider(user_prompt, trace_id, on_chunk)
+                .await?;
+            if !result.markdown.trim().is_empty() {
+                return Ok(MarkdownResult {
+                    markdown: strip_truncation_marker(&result.markdown),
+                    usage: result.usage,
+                });
+            }
+            warn!(attempt, trace_id, "llm_empty_response");
+        }
 
-        let payload = json!({
-            "model": self.cfg.model,
-            "temperature": 0.1,
-            "messages": [
-                {
-                    "role": "system",
-                    "content": self.cfg.system_prompt
-                },
-                {
-                    "role": "user",
-                    "content": user_prompt
+        Err(AppError::ApiResponse(self.missing_content_message().to_string()).into())
+    }
+
+    async fn stream_provider<F>(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+        on_chunk: &mut F,
+    ) -> Result<MarkdownResult>
+    where
+        F: FnMut(&str),
+    {
+        let (service, url, headers, mut payload) = match self.cfg.provider {
+            LlmProvider::Anthropic => (
+                "llm_anthropic",
+                format!("{}/messages", self.cfg.base_url),
+                self.headers(anthropic_headers(
+                    &self.cfg.api_key,
+                    &self.runtime.anthropic_version,
+                )?),
+                build_anthropic_payload(&self.cfg, &self.runtime, user_prompt),
+            ),
+            _ => (
+                "llm_openai_compatible",
+                chat_completions_url(&self.cfg),
+                self.headers(openai_headers(&self.cfg)?),
+                build_openai_payload(&self.cfg, user_prompt),
+            ),
+        };
+        payload["stream"] = json!(true);
+        if self.cfg.provider == LlmProvider::Openai {
+            payload["stream_options"] = json!({"include_usage": true});
+        }
+
+        let mut body = self
+            .http
+            .post_json_stream(service, &url, headers, &payload, trace_id)
+            .await?;
+        let mut decoder = SseDecoder::default();
+        let mut raw = Vec::new();
+        let mut markdown = String::new();
+        let mut usage = None;
+
+        while let Some(bytes) = body.next().await {
+            let bytes = bytes?;
+            raw.extend_from_slice(&bytes);
+            for event in decoder.push(&bytes) {
+                let event: Value = serde_json::from_str(&event)
+                    .with_context(|| format!("invalid stream event from {service}"))?;
+                usage = max_usage(usage, parse_usage(&event));
+                if let Some(delta) = parse_stream_delta(service, &event)? {
+                    on_chunk(&delta);
+                    markdown.push_str(&delta);
                 }
-            ]
-        });
+            }
+        }
+        for event in decoder.finish() {
+            let event: Value = serde_json::from_str(&event)
+                .with_context(|| format!("invalid stream event from {service}"))?;
+            usage = max_usage(usage, parse_usage(&event));
+            if let Some(delta) = parse_stream_delta(service, &event)? {
+                on_chunk(&delta);
+                markdown.push_str(&delta);
+            }
+        }
+
+        // Some relays ignore `stream` and answer with a plain JSON body.
+        if markdown.is_empty() && !looks_like_sse(&String::from_utf8_lossy(&raw)) {
+            warn!(service, trace_id, "llm_stream_ignored");
+            let value: Value = serde_json::from_slice(&raw)
+                .with_context(|| format!("invalid JSON from {service}"))?;
+            let content = match self.cfg.provider {
+                LlmProvider::Anthropic => parse_anthropic_content(&value),
+                _ => extract_openai_content(&value),
+            };
+            if let Some(content) = content.filter(|text| !text.trim().is_empty()) {
+                on_chunk(&content);
+                markdown = content;
+            }
+            usage = parse_usage(&value);
+        }
+
+        Ok(MarkdownResult { markdown, usage })
+    }
+
+    // `Ok(None)` means the provider answered successfully but with blank content.
+    async fn call_provider(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+    ) -> Result<Option<MarkdownResult>> {
+        match self.cfg.provider {
+            LlmProvider::Openai | LlmProvider::OpenaiCompatible | LlmProvider::Azure => {
+                self.call_openai_compatible(user_prompt, trace_id).await
+            }
+            LlmProvider::Anthropic => self.call_anthropic(user_prompt, trace_id).await,
+            LlmProvider::Gemini => self.call_gemini(user_prompt, trace_id).await,
+            LlmProvider::Ollama => self.call_ollama(user_prompt, trace_id).await,
+            LlmProvider::Mock => Ok(Some(MarkdownResult {
+                markdown: mock_markdown(user_prompt),
+                usage: None,
+            })),
+        }
+    }
+
+    fn headers(&self, provider_headers: HeaderMap) -> HeaderMap {
+        merge_extra_headers(provider_headers, &self.cfg.extra_headers)
+    }
+
+    fn missing_content_message(&self) -> &'static str {
+        match self.cfg.provider {
+            LlmProvider::Openai | LlmProvider::OpenaiCompatible | LlmProvider::Azure => {
+                "missing OpenAI content"
+            }
+            LlmProvider::Anthropic => "missing Anthropic content",
+            LlmProvider::Gemini => "missing Gemini content",
+            LlmProvider::Ollama => "missing Ollama content",
+            LlmProvider::Mock => "missing mock content",
+        }
+    }
+
+    async fn call_openai_compatible(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+    ) -> Result<Option<MarkdownResult>> {
+        let url = chat_completions_url(&self.cfg);
+
+        let payload = build_openai_payload(&self.cfg, user_prompt);
 
         let response = self
             .http
             .post_json(
                 "llm_openai_compatible",
                 &url,
-                bearer_headers(&self.cfg.api_key)?,
+                self.headers(openai_headers(&self.cfg)?),
                 &payload,
                 trace_id,
             )
             .await?;
 
-        extract_openai_content(&response)
-            .ok_or_else(|| AppError::ApiResponse("missing OpenAI content".to_string()).into())
+        content_or_empty(
+            "llm_openai_compatible",
+            &response,
+            extract_openai_content(&response),
+            OPENAI_CHAT_RULES,
+        )
     }
 
-    async fn call_anthropic(&self, user_prompt: &str, trace_id: &str) -> Result<String> {
+    async fn call_anthropic(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+    ) -> Result<Option<MarkdownResult>> {
         let url = format!("{}/messages", self.cfg.base_url);
 
-        let payload = json!({
-            "model": self.cfg.model,
-            "max_tokens": self.runtime.anthropic_max_tokens,
-            "system": self.cfg.system_prompt,
-            "messages": [
-                {
-                    "role": "user",
-                    "content": user_prompt
-                }
-            ]
-        });
+        let payload = build_anthropic_payload(&self.cfg, &self.runtime, user_prompt);
 
         let response = self
             .http
             .post_json(
                 "llm_anthropic",
                 &url,
-                anthropic_headers(&self.cfg.api_key, &self.runtime.anthropic_version)?,
+                self.headers(anthropic_headers(
+                    &self.cfg.api_key,
+                    &self.runtime.anthropic_version,
+                )?),
                 &payload,
                 trace_id,
             )
             .await?;
 
-        parse_anthropic_content(&response)
-            .ok_or_else(|| AppError::ApiResponse("missing Anthropic content".to_string()).into())
+        content_or_empty(
+            "llm_anthropic",
+            &response,
+            parse_anthropic_content(&response),
+            ANTHROPIC_MESSAGES_RULES,
+        )
     }
 
-    async fn call_gemini(&self, user_prompt: &str, trace_id: &str) -> Result<String> {
+    async fn call_gemini(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+    ) -> Result<Option<MarkdownResult>> {
         let url = format!(
-            "{}/models/{}:generateContent?key={}",
-            self.cfg.base_url, self.cfg.model, self.cfg.api_key
+            "{}/models/{}:generateContent",
+            self.cfg.base_url, self.cfg.model
         );
 
-        let merged_prompt = format!("{}\n\n{}", self.cfg.system_prompt, user_prompt);
-        let payload = json!({
-            "contents": [
-                {
-                    "role": "user",
-                    "parts": [
-                        {
-                            "text": merged_prompt
-                        }
-                    ]
-                }
-            ],
-            "generationConfig": {
-                "temperature": 0.1
-            }
+        let payload = build_gemini_payload(&self.cfg, user_prompt);
+
+        let response = self
+            .http
+            .post_json(
+                "llm_gemini",
+                &url,
+                self.headers(gemini_headers(
+                    &self.cfg.api_key,
+                    "LLM_API_KEY for gemini header",
+                )?),
+                &payload,
+                trace_id,
+            )
+            .await?;
+
+        content_or_empty(
+            "llm_gemini",
+            &response,
+            parse_gemini_content(&response),
+            GEMINI_GENERATE_RULES,
+        )
+    }
+
+    async fn call_ollama(
+        &self,
+        user_prompt: &str,
+        trace_id: &str,
+    ) -> Result<Option<MarkdownResult>> {
+        let url = format!("{}/api/chat", self.cfg.base_url);
+
+        let payload = build_ollama_payload(&self.cfg, user_prompt);
+        let headers = self.headers(if self.cfg.api_key.is_empty() {
+            json_headers()?
+        } else {
+            bearer_headers(&self.cfg.api_key)?
         });
 
         let response = self
             .http
-            .post_json("llm_gemini", &url, json_headers()?, &payload, trace_id)
+            .post_json("llm_ollama", &url, headers, &payload, trace_id)
             .await?;
 
-        parse_gemini_content(&response)
-            .ok_or_else(|| AppError::ApiResponse("missing Gemini content".to_string()).into())
+        content_or_empty(
+            "llm_ollama",
+            &response,
+            parse_ollama_content(&response),
+            OLLAMA_CHAT_RULES,
+        )
     }
 }
 
+fn build_openai_payload(cfg: &LlmConfig, user_prompt: &str) -> Value {
+    let mut payload = json!({
+        "model": cfg.model,
+        "temperature": cfg.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+        "messages": [
+            {
+                "role": "system",
+                "content": cfg.system_prompt
+            },
+            {
+                "role": "user",
+                "content": user_prompt
+            }
+        ]
+    });
+    if !cfg.stop.is_empty() {
+        payload["stop"] = json!(cfg.stop);
+    }
+    if let Some(max_tokens) = cfg.max_tokens {
+        payload["max_tokens"] = json!(max_tokens);
+    }
+    payload
+}
+
+fn build_anthropic_payload(cfg: &LlmConfig, runtime: &RuntimeConfig, user_prompt: &str) -> Value {
+    let mut payload = json!({
+        "model": cfg.model,
+        "max_tokens": cfg.max_tokens.unwrap_or(runtime.anthropic_max_tokens),
+        "system": cfg.system_prompt,
+        "messages": [
+            {
+                "role": "user",
+                "content": user_prompt
+            }
+        ]
+    });
+    if !cfg.stop.is_empty() {
+        payload["stop_sequences"] = json!(cfg.stop);
+    }
+    if let Some(temperature) = cfg.temperature {
+        payload["temperature"] = json!(temperature);
+    }
+    payload
+}
+
+fn build_ollama_payload(cfg: &LlmConfig, user_prompt: &str) -> Value {
+    let mut payload = json!({
+        "model": cfg.model,
+        "stream": false,
+        "messages": [
+            {
+                "role": "system",
+                "content": cfg.system_prompt
+            },
+            {
+                "role": "user",
+                "content": user_prompt
+            }
+        ],
+        "options": {
+            "temperature": cfg.temperature.unwrap_or(DEFAULT_TEMPERATURE)
+        }
+    });
+    if !cfg.stop.is_empty() {
+        payload["options"]["stop"] = json!(cfg.stop);
+    }
+    if let Some(max_tokens) = cfg.max_tokens {
+        payload["options"]["num_predict"] = json!(max_tokens);
+    }
+    payload
+}
+
+fn build_gemini_payload(cfg: &LlmConfig, user_prompt: &str) -> Value {
+    let merged_prompt = format!("{}\n\n{}", cfg.system_prompt, user_prompt);
+    let mut payload = json!({
+        "contents": [
+            {
+                "role": "user",
+                "parts": [
+                    {
+                        "text": merged_prompt
+                    }
+                ]
+            }
+        ],
+        "generationConfig": {
+            "temperature": cfg.temperature.unwrap_or(DEFAULT_TEMPERATURE)
+        }
+    });
+    if !cfg.stop.is_empty() {
+        payload["generationConfig"]["stopSequences"] = json!(cfg.stop);
+    }
+    if let Some(max_tokens) = cfg.max_tokens {
+        payload["generationConfig"]["maxOutputTokens"] = json!(max_tokens);
+    }
+    payload
+}
+
+// A well-shaped response without text is "empty" and may be retried; a
+// malformed one fails immediately with the offending field named.
+fn content_or_empty(
+    service: &str,
+    response: &Value,
+    parsed: Option<String>,
+    rules: &[FieldRule],
+) -> Result<Option<MarkdownResult>> {
+    match parsed {
+        Some(markdown) => Ok(Some(MarkdownResult {
+            markdown,
+            usage: parse_usage(response),
+        })),
+        None => {
+            schema::validate(service, response, rules)?;
+            Ok(None)
+        }
+    }
+}
+
+async fn retry_on_empty<T, F, Fut>(
+    max_retries: u32,
+    missing: &str,
+    trace_id: &str,
+    mut call: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>>>,
+{
+    for attempt in 0..=max_retries {
+        if let Some(text) = call().await? {
+            return Ok(text);
+        }
+        warn!(attempt, trace_id, "llm_empty_response");
+    }
+
+    Err(AppError::ApiResponse(missing.to_string()).into())
+}
+
+fn merge_results(mut parts: Vec<MarkdownResult>) -> MarkdownResult {
+    if parts.len() == 1 {
+        return parts.remove(0);
+    }
+
+    let usage = parts
+        .iter()
+        .map(|part| part.usage)
+        .reduce(add_usage)
+        .flatten();
+    let markdown: Vec<String> = parts.into_iter().map(|part| part.markdown).collect();
+    MarkdownResult {
+        markdown: chunk::merge_markdown(&markdown),
+        usage,
+    }
+}
+
+fn add_usage(total: Option<TokenUsage>, usage: Option<TokenUsage>) -> Option<TokenUsage> {
+    match (total, usage) {
+        (Some(total), Some(usage)) => Some(TokenUsage {
+            input_tokens: total.input_tokens + usage.input_tokens,
+            output_tokens: total.output_tokens + usage.output_tokens,
+        }),
+        (total, usage) => total.or(usage),
+    }
+}
+
+// Streamed counters are cumulative, and Anthropic reports input and output
+// tokens in different events, so keep the largest value seen for each.
+fn max_usage(seen: Option<TokenUsage>, event: Option<TokenUsage>) -> Option<TokenUsage> {
+    match (seen, event) {
+        (Some(seen), Some(event)) => Some(TokenUsage {
+            input_tokens: seen.input_tokens.max(event.input_tokens),
+            output_tokens: seen.output_tokens.max(event.output_tokens),
+        }),
+        (seen, event) => seen.or(event),
+    }
+}
+
+// An explicit prompt wins, then `SYSTEM_PROMPT`, then the built-in default.
+// Blank values count as unset.
+pub fn resolve_system_prompt(explicit: Option<String>) -> String {
+    let set = |value: &String| !value.trim().is_empty();
+    explicit
+        .filter(set)
+        .or_else(|| std::env::var("SYSTEM_PROMPT").ok().filter(set))
+        .unwrap_or_else(default_system_prompt)
+}
+
 fn default_system_prompt() -> String {
     "你是一个严谨的文档结构化助手。将输入文本整理为高质量 Markdown，要求：\n1) 只输出 Markdown，不输出解释。\n2) 保留原文信息，不杜撰。\n3) 自动识别并组织标题层级、段落、列表、表格。\n4) 对明显噪声进行最小清洗（如重复页眉页脚）。\n5) 对公式、代码块、表格尽量保持可读性。"
         .to_string()
 }
 
-fn build_user_prompt(ocr_text: &str) -> String {
+// Mixed documents (English headings over a Chinese body) only report their
+// dominant language, so the prompt always asks to keep every language as is.
+fn build_user_prompt(ocr_text: &str, truncated: bool, language: Option<&str>) -> String {
+    let notice = if truncated {
+        "注意：输入文本因长度限制已被截断，请只整理已有内容，不要在输出中提及截断。\n\n"
+    } else {
+        ""
+    };
+    let language = match language {
+        Some(language) => format!("文档主要语言为 {language}。"),
+        None => String::new(),
+    };
+    // Page markers only help if the model knows to read past them.
+    let pages = if ocr_text
+        .lines()
+        .any(|line| parse_page_marker(line).is_some())
+    {
+        "文本中的 `--- page N ---` 行标出原文分页：请据此理解跨页内容（如跨页续接的表格或段落应合并为一处），但输出中不要保留这些分页标记。\n\n"
+    } else {
+        ""
+    };
     format!(
-        "请将下面 OCR 文本整理成结构化 Markdown。\n\n--- OCR START ---\n{}\n--- OCR END ---",
+        "请将下面 OCR 文本整理成结构化 Markdown。\n\n{language}请保留原文的语言（包括混排的其他语言），不要翻译。\n\n{pages}{notice}--- OCR START ---\n{}\n--- OCR END ---",
         ocr_text
     )
 }
 
+// The mock provider's answer: whatever sits between the OCR markers of the
+// prompt under a fixed heading, or the whole prompt (e.g. a ping) otherwise.
+fn mock_markdown(user_prompt: &str) -> String {
+    let body = user_prompt
+        .split_once("--- OCR START ---\n")
+        .and_then(|(_, rest)| rest.rsplit_once("\n--- OCR END ---"))
+        .map_or(user_prompt, |(body, _)| body);
+    format!("# Mock document\n\n{}\n", body.trim())
+}
+
+// Removes a fence wrapping the whole answer (```markdown ... ``` or a bare
+// ```). Fences around only part of the document are real code blocks.
+pub fn unwrap_code_fence(markdown: &str) -> String {
+    let trimmed = markdown.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return markdown.to_string();
+    };
+    let Some((info, body)) = rest.split_once('\n') else {
+        return markdown.to_string();
+    };
+    let info = info.trim().to_ascii_lowercase();
+    if !matches!(info.as_str(), "" | "markdown" | "md") {
+        return markdown.to_string();
+    }
+    let Some(body) = body.trim_end().strip_suffix("```") else {
+        return markdown.to_string();
+    };
+    if body
+        .lines()
+        .any(|line| line.trim_start().starts_with("```"))
+    {
+        return markdown.to_string();
+    }
+    body.trim_end().to_string()
+}
+
+// The model talking about the document instead of returning it, e.g.
+// "Here is the Markdown:" or "以下是整理后的内容". Only the first line counts.
+pub fn looks_like_prose_preamble(markdown: &str) -> bool {
+    let first_line = markdown
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+    PROSE_PREAMBLES
+        .iter()
+        .any(|preamble| first_line.starts_with(preamble))
+}
+
+fn split_truncation_marker(ocr_text: &str) -> (&str, bool) {
+    match ocr_text.trim_end().strip_suffix(TRUNCATION_MARKER) {
+        Some(body) => (body.trim_end(), true),
+        None => (ocr_text, false),
+    }
+}
+
+fn strip_truncation_marker(markdown: &str) -> String {
+    if !markdown.contains(TRUNCATION_MARKER) {
+        return markdown.to_string();
+    }
+
+    let mut out = markdown
+        .lines()
+        .filter(|line| line.trim() != TRUNCATION_MARKER)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .replace(TRUNCATION_MARKER, "");
+    let trimmed_len = out.trim_end().len();
+    out.truncate(trimmed_len);
+    if markdown.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+// Azure addresses a deployment rather than a model and versions its API
+// through the query string; everyone else takes the OpenAI path as is.
+fn chat_completions_url(cfg: &LlmConfig) -> String {
+    if cfg.provider != LlmProvider::Azure {
+        return format!("{}/chat/completions", cfg.base_url);
+    }
+    format!(
+        "{}/openai/deployments/{}/chat/completions?api-version={}",
+        cfg.base_url,
+        cfg.deployment.as_deref().unwrap_or(&cfg.model),
+        cfg.api_version
+            .as_deref()
+            .unwrap_or(DEFAULT_AZURE_API_VERSION)
+    )
+}
+
+fn openai_headers(cfg: &LlmConfig) -> Result<HeaderMap> {
+    if cfg.provider != LlmProvider::Azure {
+        return bearer_headers(&cfg.api_key);
+    }
+    let mut headers = json_headers()?;
+    headers.insert(
+        "api-key",
+        HeaderValue::from_str(&cfg.api_key).context("invalid LLM_API_KEY for azure header")?,
+    );
+    Ok(headers)
+}
+
 fn bearer_headers(api_key: &str) -> Result<HeaderMap> {
     let mut headers = json_headers()?;
     headers.insert(
         AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {api_key}"))
-            .context("invalid LLM_API_KEY for bearer header")?,
+        secret_header(
+            &format!("Bearer {api_key}"),
+            "LLM_API_KEY for bearer header",
+        )?,
     );
     Ok(headers)
 }
@@ -216,7 +1076,7 @@ fn anthropic_headers(api_key: &str, version: &str) -> Result<HeaderMap> {
     let mut headers = json_headers()?;
     headers.insert(
         "x-api-key",
-        HeaderValue::from_str(api_key).context("invalid LLM_API_KEY for anthropic header")?,
+        secret_header(api_key, "LLM_API_KEY for anthropic header")?,
     );
     headers.insert(
         "anthropic-version",
@@ -225,12 +1085,74 @@ fn anthropic_headers(api_key: &str, version: &str) -> Result<HeaderMap> {
     Ok(headers)
 }
 
+fn merge_extra_headers(mut headers: HeaderMap, extra: &HeaderMap) -> HeaderMap {
+    for (name, value) in extra {
+        if !headers.contains_key(name) {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+    headers
+}
+
 fn json_headers() -> Result<HeaderMap> {
     let mut headers = HeaderMap::new();
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
     Ok(headers)
 }
 
+// Text carried by one streamed event, for either the OpenAI chunk shape or
+// Anthropic's `content_block_delta`. Error events abort the stream.
+pub fn parse_stream_delta(service: &str, value: &Value) -> Result<Option<String>> {
+    if let Some(error) = value.get("error").filter(|error| !error.is_null()) {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Err(AppError::ApiResponse(format!("{service} stream error: {message}")).into());
+    }
+
+    let delta = value
+        .pointer("/choices/0/delta/content")
+        .or_else(|| {
+            (value.get("type").and_then(Value::as_str) == Some("content_block_delta"))
+                .then(|| value.pointer("/delta/text"))
+                .flatten()
+        })
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty());
+    Ok(delta.map(str::to_string))
+}
+
+// Token counts in the shape each provider reports them: OpenAI
+// `prompt_tokens`/`completion_tokens`, Anthropic `input_tokens`/
+// `output_tokens` (top level or inside `message_start`), Gemini
+// `usageMetadata`, Ollama `prompt_eval_count`/`eval_count`.
+pub fn parse_usage(value: &Value) -> Option<TokenUsage> {
+    const SHAPES: [(&str, &str); 5] = [
+        ("/usage/prompt_tokens", "/usage/completion_tokens"),
+        ("/usage/input_tokens", "/usage/output_tokens"),
+        (
+            "/message/usage/input_tokens",
+            "/message/usage/output_tokens",
+        ),
+        (
+            "/usageMetadata/promptTokenCount",
+            "/usageMetadata/candidatesTokenCount",
+        ),
+        ("/prompt_eval_count", "/eval_count"),
+    ];
+    let count = |pointer: &str| value.pointer(pointer).and_then(Value::as_u64);
+
+    SHAPES.iter().find_map(|(input, output)| {
+        let (input, output) = (count(input), count(output));
+        (input.is_some() || output.is_some()).then(|| TokenUsage {
+            input_tokens: input.unwrap_or(0),
+            output_tokens: output.unwrap_or(0),
+        })
+    })
+}
+
 pub fn parse_anthropic_content(value: &Value) -> Option<String> {
     let content = value.pointer("/content")?.as_array()?;
     let mut out = String::new();
@@ -251,6 +1173,15 @@ pub fn parse_anthropic_content(value: &Value) -> Option<String> {
     }
 }
 
+pub fn parse_ollama_content(value: &Value) -> Option<String> {
+    value
+        .pointer("/message/content")
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
 pub fn parse_gemini_content(value: &Value) -> Option<String> {
     let parts = value.pointer("/candidates/0/content/parts")?.as_array()?;
     let mut out = String::new();
@@ -276,7 +1207,19 @@ mod tests {
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
-    use super::{parse_anthropic_content, parse_gemini_content};
+    use std::cell::Cell;
+
+    use super::{
+        DEFAULT_AZURE_API_VERSION, LlmConfig, TokenUsage, bearer_headers, build_anthropic_payload,
+        build_gemini_payload, build_ollama_payload, build_openai_payload, build_user_prompt,
+        chat_completions_url, looks_like_prose_preamble, merge_extra_headers, openai_headers,
+        parse_anthropic_content, parse_gemini_content, parse_ollama_content, parse_usage,
+        resolve_system_prompt, retry_on_empty, split_truncation_marker, strip_truncation_marker,
+        unwrap_code_fence,
+    };
+    use crate::config::{LlmProvider, RuntimeConfig};
+    use crate::error::AppError;
+    use crate::ocr::TRUNCATION_MARKER;
 
     #[test]
     fn parse_anthropic_response() {
@@ -313,4 +1256,411 @@ mod tests {
             Some("# Title\nbody")
         );
     }
+
+    #[test]
+    fn parse_usage_for_each_provider_shape() {
+        let usage = |input_tokens, output_tokens| {
+            Some(TokenUsage {
+                input_tokens,
+                output_tokens,
+            })
+        };
+
+        let openai =
+            json!({"usage": {"prompt_tokens": 10, "completion_tokens": 4, "total_tokens": 14}});
+        assert_eq!(parse_usage(&openai), usage(10, 4));
+
+        let anthropic = json!({"usage": {"input_tokens": 7, "output_tokens": 3}});
+        assert_eq!(parse_usage(&anthropic), usage(7, 3));
+
+        let gemini = json!({"usageMetadata": {"promptTokenCount": 5, "candidatesTokenCount": 2}});
+        assert_eq!(parse_usage(&gemini), usage(5, 2));
+
+        let ollama = json!({"prompt_eval_count": 9, "eval_count": 6});
+        assert_eq!(parse_usage(&ollama), usage(9, 6));
+
+        assert_eq!(parse_usage(&json!({"choices": []})), None);
+        assert_eq!(parse_usage(&json!({"usage": null})), None);
+        assert_eq!(usage(10, 4).unwrap().total(), 14);
+    }
+
+    #[test]
+    fn parse_ollama_response() {
+        let value = json!({
+            "model": "llama3.1",
+            "message": {"role": "assistant", "content": "# Title\n"},
+            "done": true
+        });
+
+        assert_eq!(parse_ollama_content(&value).as_deref(), Some("# Title"));
+        assert_eq!(parse_ollama_content(&json!({"done": true})), None);
+    }
+
+    fn preset_config(name: &str, base_url: Option<&str>, model: Option<&str>) -> LlmConfig {
+        LlmConfig::from_choice(
+            name.parse().unwrap(),
+            Some("key".to_string()),
+            base_url.map(str::to_string),
+            model.map(str::to_string),
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn vendor_aliases_seed_their_base_url_and_model() {
+        for (name, base_url, model) in [
+            ("deepseek", "https://api.deepseek.com/v1", "deepseek-chat"),
+            ("moonshot", "https://api.moonshot.cn/v1", "moonshot-v1-32k"),
+            ("Kimi", "https://api.moonshot.cn/v1", "moonshot-v1-32k"),
+        ] {
+            let cfg = preset_config(name, None, None);
+            assert_eq!(cfg.provider, LlmProvider::OpenaiCompatible, "{name}");
+            assert_eq!(cfg.base_url, base_url, "{name}");
+            assert_eq!(cfg.model, model, "{name}");
+        }
+    }
+
+    #[test]
+    fn explicit_base_url_and_model_override_the_preset() {
+        let cfg = preset_config(
+            "deepseek",
+            Some("https://relay.example/v1/"),
+            Some("deepseek-reasoner"),
+        );
+        assert_eq!(cfg.base_url, "https://relay.example/v1");
+        assert_eq!(cfg.model, "deepseek-reasoner");
+
+        let cfg = preset_config("kimi", Some(""), Some("kimi-k2"));
+        assert_eq!(cfg.base_url, "https://api.moonshot.cn/v1");
+        assert_eq!(cfg.model, "kimi-k2");
+    }
+
+    #[test]
+    fn ollama_needs_no_api_key() {
+        let cfg = LlmConfig::from_sources(
+            "ollama".parse().unwrap(),
+            Some(String::new()),
+            None,
+            Some("llama3.1".to_string()),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(cfg.provider, LlmProvider::Ollama);
+        assert_eq!(cfg.base_url, "http://localhost:11434");
+
+        let payload = build_ollama_payload(&cfg.with_stop(vec!["END".to_string()]).unwrap(), "u");
+        assert_eq!(payload["stream"], json!(false));
+        assert_eq!(payload["messages"][1]["content"], json!("u"));
+        assert_eq!(payload["options"]["stop"], json!(["END"]));
+    }
+
+    #[test]
+    fn azure_addresses_a_deployment_with_an_api_key_header() {
+        let mut cfg = LlmConfig::from_sources(
+            "azure".parse().unwrap(),
+            Some("azure-key".to_string()),
+            Some("https://contoso.openai.azure.com/".to_string()),
+            Some("gpt-4o".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(cfg.provider, LlmProvider::Azure);
+        assert_eq!(
+            chat_completions_url(&cfg),
+            format!(
+                "https://contoso.openai.azure.com/openai/deployments/gpt-4o/chat/completions?api-version={DEFAULT_AZURE_API_VERSION}"
+            )
+        );
+
+        cfg.deployment = Some("prod-4o".to_string());
+        cfg.api_version = Some("2025-01-01-preview".to_string());
+        assert_eq!(
+            chat_completions_url(&cfg),
+            "https://contoso.openai.azure.com/openai/deployments/prod-4o/chat/completions?api-version=2025-01-01-preview"
+        );
+
+        let headers = openai_headers(&cfg).unwrap();
+        assert_eq!(headers["api-key"], "azure-key");
+        assert!(headers.get("authorization").is_none());
+    }
+
+    #[test]
+    fn other_openai_shapes_keep_the_bearer_header() {
+        let cfg = config(LlmProvider::Openai, &[]);
+        assert_eq!(
+            chat_completions_url(&cfg),
+            format!("{}/chat/completions", cfg.base_url)
+        );
+        let headers = openai_headers(&cfg).unwrap();
+        assert_eq!(headers["authorization"], format!("Bearer {}", cfg.api_key));
+        assert!(headers.get("api-key").is_none());
+    }
+
+    #[test]
+    fn extra_headers_never_replace_the_provider_headers() {
+        let cfg = config(LlmProvider::Openai, &[])
+            .with_extra_headers([
+                ("X-Org-Id", " acme "),
+                ("Authorization", "Bearer relay-token"),
+                ("content-type", "text/plain"),
+            ])
+            .unwrap();
+
+        let headers =
+            merge_extra_headers(bearer_headers(&cfg.api_key).unwrap(), &cfg.extra_headers);
+
+        assert_eq!(headers["x-org-id"], "acme");
+        assert_eq!(headers["authorization"], format!("Bearer {}", cfg.api_key));
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers.len(), 3);
+    }
+
+    #[test]
+    fn invalid_extra_headers_are_config_errors() {
+        for (name, value, expected) in [
+            ("X-Org Id", "acme", "invalid extra header name"),
+            ("", "acme", "invalid extra header name"),
+            ("X-Org-Id", "line\nbreak", "invalid value for extra header"),
+        ] {
+            let err = config(LlmProvider::Openai, &[])
+                .with_extra_headers([(name, value)])
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<AppError>(),
+                    Some(AppError::InvalidConfig(message)) if message.contains(expected)
+                ),
+                "{err:#}"
+            );
+        }
+    }
+
+    #[test]
+    fn azure_requires_its_resource_endpoint() {
+        let err = LlmConfig::from_sources(
+            "azure-openai".parse().unwrap(),
+            Some("azure-key".to_string()),
+            Some(" ".to_string()),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("azure"), "{err:#}");
+    }
+
+    #[test]
+    fn explicit_system_prompt_wins_and_blank_falls_back() {
+        assert_eq!(
+            resolve_system_prompt(Some("Summarise tables only.".to_string())),
+            "Summarise tables only."
+        );
+        assert!(
+            !resolve_system_prompt(Some("  ".to_string()))
+                .trim()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn truncation_marker_becomes_instruction_and_is_stripped() {
+        let ocr_text = format!("page one\n\n{TRUNCATION_MARKER}");
+        let (body, truncated) = split_truncation_marker(&ocr_text);
+        assert!(truncated);
+        assert_eq!(body, "page one");
+
+        let prompt = build_user_prompt(body, truncated, None);
+        assert!(!prompt.contains(TRUNCATION_MARKER));
+        assert!(prompt.contains("截断"));
+
+        let echoed = format!("# Title\n\nbody\n\n{TRUNCATION_MARKER}\n");
+        let cleaned = strip_truncation_marker(&echoed);
+        assert!(!cleaned.contains(TRUNCATION_MARKER));
+        assert_eq!(cleaned, "# Title\n\nbody\n");
+
+        assert_eq!(split_truncation_marker("plain"), ("plain", false));
+        assert_eq!(strip_truncation_marker("# Title\n"), "# Title\n");
+    }
+
+    #[test]
+    fn user_prompt_names_the_language_and_asks_not_to_translate() {
+        let prompt = build_user_prompt("Quarterly report", false, Some("English"));
+        assert!(prompt.contains("文档主要语言为 English"));
+        assert!(prompt.contains("不要翻译"));
+
+        let unknown = build_user_prompt("??", false, None);
+        assert!(!unknown.contains("文档主要语言"));
+        assert!(unknown.contains("不要翻译"));
+    }
+
+    #[test]
+    fn user_prompt_explains_page_markers_only_when_present() {
+        let paged = build_user_prompt("--- page 1 ---\n\nA\n\n--- page 2 ---\n\nB", false, None);
+        assert!(paged.contains("不要保留这些分页标记"));
+
+        let plain = build_user_prompt("A\n\nB", false, None);
+        assert!(!plain.contains("分页标记"));
+    }
+
+    #[tokio::test]
+    async fn empty_response_is_retried_until_content_arrives() {
+        let calls = Cell::new(0);
+        let markdown = retry_on_empty(2, "missing OpenAI content", "trace", || {
+            calls.set(calls.get() + 1);
+            let reply = (calls.get() > 1).then(|| "# Title".to_string());
+            async move { Ok(reply) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(markdown, "# Title");
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[tokio::test]
+    async fn empty_response_fails_after_retry_cap() {
+        let calls = Cell::new(0);
+        let err = retry_on_empty(1, "missing OpenAI content", "trace", || {
+            calls.set(calls.get() + 1);
+            async { Ok(None::<String>) }
+        })
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("missing OpenAI content"));
+        assert_eq!(calls.get(), 2);
+    }
+
+    fn config(provider: LlmProvider, stop: &[&str]) -> LlmConfig {
+        LlmConfig::from_sources(
+            provider,
+            Some("key".to_string()),
+            Some("https://example.test/v1".to_string()),
+            Some("model".to_string()),
+            Some("system".to_string()),
+        )
+        .unwrap()
+        .with_stop(stop.iter().map(|value| value.to_string()).collect())
+        .unwrap()
+    }
+
+    #[test]
+    fn stop_sequences_land_in_each_provider_payload() {
+        let runtime = RuntimeConfig::from_env();
+
+        let openai = build_openai_payload(&config(LlmProvider::Openai, &["END"]), "u");
+        assert_eq!(openai["stop"], json!(["END"]));
+
+        let anthropic =
+            build_anthropic_payload(&config(LlmProvider::Anthropic, &["END"]), &runtime, "u");
+        assert_eq!(anthropic["stop_sequences"], json!(["END"]));
+
+        let gemini = build_gemini_payload(&config(LlmProvider::Gemini, &["END"]), "u");
+        assert_eq!(gemini["generationConfig"]["stopSequences"], json!(["END"]));
+
+        let plain = build_openai_payload(&config(LlmProvider::Openai, &[]), "u");
+        assert!(plain.get("stop").is_none());
+    }
+
+    #[test]
+    fn sampling_overrides_land_in_each_provider_payload() {
+        let runtime = RuntimeConfig::from_env();
+        let tuned = |provider| {
+            config(provider, &[])
+                .with_sampling(Some(0.0), Some(512))
+                .unwrap()
+        };
+
+        let openai = build_openai_payload(&tuned(LlmProvider::Openai), "u");
+        assert_eq!(openai["temperature"], json!(0.0));
+        assert_eq!(openai["max_tokens"], json!(512));
+
+        let anthropic = build_anthropic_payload(&tuned(LlmProvider::Anthropic), &runtime, "u");
+        assert_eq!(anthropic["temperature"], json!(0.0));
+        assert_eq!(anthropic["max_tokens"], json!(512));
+
+        let gemini = build_gemini_payload(&tuned(LlmProvider::Gemini), "u");
+        assert_eq!(gemini["generationConfig"]["temperature"], json!(0.0));
+        assert_eq!(gemini["generationConfig"]["maxOutputTokens"], json!(512));
+
+        let ollama = build_ollama_payload(&tuned(LlmProvider::Ollama), "u");
+        assert_eq!(ollama["options"]["num_predict"], json!(512));
+    }
+
+    #[test]
+    fn unset_sampling_keeps_the_defaults() {
+        let runtime = RuntimeConfig::from_env();
+
+        let openai = build_openai_payload(&config(LlmProvider::Openai, &[]), "u");
+        assert_eq!(openai["temperature"], json!(0.1));
+        assert!(openai.get("max_tokens").is_none());
+
+        let anthropic =
+            build_anthropic_payload(&config(LlmProvider::Anthropic, &[]), &runtime, "u");
+        assert!(anthropic.get("temperature").is_none());
+        assert_eq!(anthropic["max_tokens"], json!(runtime.anthropic_max_tokens));
+
+        let gemini = build_gemini_payload(&config(LlmProvider::Gemini, &[]), "u");
+        assert_eq!(gemini["generationConfig"], json!({"temperature": 0.1}));
+    }
+
+    #[test]
+    fn out_of_range_sampling_is_a_config_error() {
+        let cfg = config(LlmProvider::Openai, &[]);
+        for (temperature, max_tokens) in [(Some(2.5), None), (Some(-0.1), None), (None, Some(0))] {
+            let err = cfg
+                .clone()
+                .with_sampling(temperature, max_tokens)
+                .unwrap_err();
+            assert!(
+                matches!(err.downcast_ref(), Some(AppError::InvalidConfig(_))),
+                "{err:#}"
+            );
+        }
+        assert!(cfg.with_sampling(Some(2.0), Some(1)).is_ok());
+    }
+
+    #[test]
+    fn too_many_stop_sequences_are_rejected() {
+        let cfg = config(LlmProvider::Openai, &[]);
+        let stop = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+        assert!(cfg.with_stop(stop).is_err());
+    }
+
+    #[test]
+    fn unwraps_a_fence_around_the_whole_answer() {
+        assert_eq!(
+            unwrap_code_fence("```markdown\n# Title\n\nBody\n```"),
+            "# Title\n\nBody"
+        );
+        assert_eq!(unwrap_code_fence("  ```\n- a\n- b\n```\n"), "- a\n- b");
+        assert_eq!(unwrap_code_fence("```MD\n# T\n```"), "# T");
+    }
+
+    #[test]
+    fn leaves_real_code_blocks_alone() {
+        let partial = "# Title\n\n```\ncode\n```";
+        assert_eq!(unwrap_code_fence(partial), partial);
+        let rust = "```rust\nfn main() {}\n```";
+        assert_eq!(unwrap_code_fence(rust), rust);
+        let two = "```\na\n```\n\n```\nb\n```";
+        assert_eq!(unwrap_code_fence(two), two);
+    }
+
+    #[test]
+    fn detects_prose_preambles_on_the_first_line() {
+        assert!(looks_like_prose_preamble(
+            "Here is the Markdown:\n\n# Title"
+        ));
+        assert!(looks_like_prose_preamble(
+            "\n  以下是整理后的 Markdown：\n# 标题"
+        ));
+        assert!(looks_like_prose_preamble("Sure! Here you go."));
+        assert!(!looks_like_prose_preamble("# Here is a heading"));
+        assert!(!looks_like_prose_preamble(
+            "Quarterly report\n\nHere is the data."
+        ));
+        assert!(!looks_like_prose_preamble(""));
+    }
 }
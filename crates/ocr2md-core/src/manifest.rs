@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const MANIFEST_VERSION: u8 = 1;
+
+// What happened to one input of a batch run. Nothing about the run's own
+// timing goes in, so manifests of two runs over the same inputs diff cleanly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobOutcome {
+    pub input: String,
+    // Only set when the output was written.
+    pub output_path: Option<String>,
+    pub success: bool,
+    pub error: Option<String>,
+    // `None` when the file failed before OCR finished.
+    pub ocr_chars: Option<usize>,
+    // Nothing was written because OCR found no text under `--on-empty-ocr
+    // skip`, or the output was already up to date under `--skip-existing`;
+    // still a success.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    // The input as it was when the entry was recorded; `None` for stdin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceStamp>,
+}
+
+// Size and modification time of an input file. A `--resume` run redoes a
+// successful entry whose file no longer matches its stamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceStamp {
+    pub size: u64,
+    pub modified_ms: u64,
+}
+
+impl SourceStamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified_ms: modified.as_millis() as u64,
+        })
+    }
+}
+
+impl JobOutcome {
+    pub fn from_result(
+        input: &Path,
+        output: &Path,
+        ocr_chars: Option<usize>,
+        result: &Result<()>,
+    ) -> Self {
+        let (output_path, error) = match result {
+            Ok(()) => (Some(output.display().to_string()), None),
+            Err(err) => (None, Some(format!("{err:#}"))),
+        };
+        Self {
+            input: input.display().to_string(),
+            output_path,
+            success: result.is_ok(),
+            error,
+            ocr_chars,
+            skipped: false,
+            source: SourceStamp::of(input),
+        }
+    }
+
+    pub fn skipped(self) -> Self {
+        Self {
+            output_path: None,
+            skipped: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u8,
+    pub succeeded: usize,
+    pub failed: usize,
+    // In input order.
+    pub jobs: Vec<JobOutcome>,
+}
+
+impl Manifest {
+    pub fn new(jobs: Vec<JobOutcome>) -> Self {
+        let succeeded = jobs.iter().filter(|job| job.success).count();
+        Self {
+            version: MANIFEST_VERSION,
+            succeeded,
+            failed: jobs.len() - succeeded,
+            jobs,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let body = fs::read(path)
+            .with_context(|| format!("failed to read manifest: {}", path.display()))?;
+        serde_json::from_slice(&body)
+            .with_context(|| format!("failed to parse manifest: {}", path.display()))
+    }
+
+    // Whether an earlier run converted `input` and the file is still the one
+    // it converted; entries without a stamp never count.
+    pub fn is_done(&self, input: &Path) -> bool {
+        let name = input.display().to_string();
+        self.jobs.iter().any(|job| {
+            job.input == name
+                && job.success
+                && job.source.is_some()
+                && job.source == SourceStamp::of(input)
+        })
+    }
+
+    // Replaces the entry for the same input, or adds one at the end.
+    pub fn record(&mut self, outcome: JobOutcome) {
+        let mut jobs = std::mem::take(&mut self.jobs);
+        match jobs.iter_mut().find(|job| job.input == outcome.input) {
+            Some(job) => *job = outcome,
+            None => jobs.push(outcome),
+        }
+        *self = Self::new(jobs);
+    }
+
+    // Puts entries back in `inputs` order after `record`s that came in as
+    // files finished; entries for other inputs go after them.
+    pub fn sort_by_inputs(&mut self, inputs: &[PathBuf]) {
+        let names: Vec<String> = inputs
+            .iter()
+            .map(|input| input.display().to_string())
+            .collect();
+        self.jobs.sort_by_key(|job| {
+            names
+                .iter()
+                .position(|name| *name == job.input)
+                .unwrap_or(names.len())
+        });
+    }
+
+    // Same temp-file-and-rename dance as the queue file, so an interrupted
+    // write never leaves half a manifest behind.
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let mut body = serde_json::to_vec_pretty(self).context("failed to serialize manifest")?;
+        body.push(b'\n');
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("failed to create manifest directory")?;
+        }
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, body)
+            .with_context(|| format!("failed to write manifest: {}", temp.display()))?;
+        fs::rename(&temp, path)
+            .with_context(|| format!("failed to replace manifest: {}", path.display()))?;
+        Ok(())
+    }
+}
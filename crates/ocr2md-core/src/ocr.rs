@@ -1,16 +1,219 @@
-use std::path::Path;
+use std::sync::LazyLock;
 
 use anyhow::{Context, Result};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
+use clap::ValueEnum;
+use futures::{StreamExt, TryStreamExt, stream};
+use regex::Regex;
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
 use serde_json::{Value, json};
+use tracing::{info, warn};
 
+use crate::config::env_usize;
+use crate::docx;
 use crate::error::AppError;
-use crate::file_kind::{InputKind, detect_input_kind};
-use crate::http::HttpEngine;
+use crate::file_kind::InputKind;
+use crate::http::{HttpEngine, gemini_headers};
+use crate::image_prep::{self, DEFAULT_MAX_DIMENSION};
+use crate::llm::{
+    DEFAULT_GEMINI_BASE_URL, DEFAULT_OPENAI_BASE_URL, PING_PROMPT, parse_gemini_content,
+};
+use crate::ocr_cache::OcrCache;
+use crate::pdf::{self, PageRanges, PdfBatch};
+use crate::schema::{self, GEMINI_GENERATE_RULES, OPENAI_CHAT_RULES};
+use crate::single_flight::SingleFlight;
 
 const DEFAULT_GLM_BASE_URL: &str = "https://open.bigmodel.cn/api/paas/v4";
 const DEFAULT_GLM_OCR_MODEL: &str = "glm-4.1v-thinking-flashx";
+const DEFAULT_GEMINI_OCR_MODEL: &str = "gemini-2.0-flash";
+const DEFAULT_OPENAI_OCR_MODEL: &str = "gpt-4o-mini";
+pub const TRUNCATION_MARKER: &str = "[TRUNCATED: OCR output exceeded MAX_OCR_CHARS]";
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 50 * 1024 * 1024;
+pub const DEFAULT_OCR_PROMPT: &str =
+    "请提取文档完整内容，尽量保留标题、段落和表格结构，输出纯文本。";
+pub const DEFAULT_FILE_PARSE_PROMPT: &str = "提取文档全部正文与结构信息，保留标题层级和表格文本。";
+const PAGE_CAP_NOTICE_PREFIX: &str = "<!-- ocr2md: stopped after ";
+pub const MOCK_OCR_TEXT: &str =
+    "Mock OCR text\n\nThis text was produced offline by the mock OCR provider.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OcrFallback {
+    #[default]
+    None,
+    FileParse,
+}
+
+// Which vision API reads images and PDFs. Word files always go through the
+// local .docx reader or GLM file parsing. OpenAI only reads images: PDFs are
+// rejected (or handed to GLM file parsing with `--ocr-fallback file-parse`)
+// since chat completions take no PDF input and nothing here rasterizes pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OcrProvider {
+    #[default]
+    Glm,
+    Gemini,
+    Openai,
+    // Offline stand-in: every input reads as `MOCK_OCR_TEXT`, no HTTP.
+    // Only reachable through `--mock`.
+    #[value(skip)]
+    Mock,
+}
+
+impl OcrProvider {
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("OCR_PROVIDER") {
+            Ok(value) if !value.trim().is_empty() => {
+                <Self as ValueEnum>::from_str(value.trim(), true).map_err(|_| {
+                    AppError::InvalidConfig(format!(
+                        "unsupported OCR provider: {value}. use glm|gemini|openai"
+                    ))
+                    .into()
+                })
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeminiOcrConfig {
+    pub api_key: String,
+    pub base_url: String,
+    pub model: String,
+}
+
+impl GeminiOcrConfig {
+    // GEMINI_API_KEY, or LLM_API_KEY when Gemini is also the LLM provider.
+    pub fn from_env() -> Result<Self> {
+        let llm_is_gemini = std::env::var("LLM_PROVIDER")
+            .is_ok_and(|provider| provider.trim().eq_ignore_ascii_case("gemini"));
+        let api_key = std::env::var("GEMINI_API_KEY")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| {
+                llm_is_gemini
+                    .then(|| std::env::var("LLM_API_KEY").ok())
+                    .flatten()
+                    .filter(|value| !value.trim().is_empty())
+            })
+            .ok_or_else(|| {
+                AppError::InvalidConfig(
+                    "GEMINI_API_KEY is required for OCR_PROVIDER=gemini".to_string(),
+                )
+            })?;
+        let base_url = std::env::var("GEMINI_BASE_URL")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_GEMINI_BASE_URL.to_string());
+        let model = std::env::var("GEMINI_OCR_MODEL")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_GEMINI_OCR_MODEL.to_string());
+        Ok(Self {
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenAiOcrConfig {
+    pub api_key: String,
+    pub base_url: String,
+    pub model: String,
+}
+
+impl OpenAiOcrConfig {
+    // OPENAI_API_KEY, or LLM_API_KEY when OpenAI is also the LLM provider.
+    pub fn from_env() -> Result<Self> {
+        let llm_is_openai = std::env::var("LLM_PROVIDER")
+            .is_ok_and(|provider| provider.trim().eq_ignore_ascii_case("openai"));
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .or_else(|| {
+                llm_is_openai
+                    .then(|| std::env::var("LLM_API_KEY").ok())
+                    .flatten()
+                    .filter(|value| !value.trim().is_empty())
+            })
+            .ok_or_else(|| {
+                AppError::InvalidConfig(
+                    "OPENAI_API_KEY is required for OCR_PROVIDER=openai".to_string(),
+                )
+            })?;
+        let base_url = std::env::var("OPENAI_BASE_URL")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_OPENAI_BASE_URL.to_string());
+        let model = std::env::var("OPENAI_OCR_MODEL")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_OPENAI_OCR_MODEL.to_string());
+        Ok(Self {
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model,
+        })
+    }
+}
+
+// Parsed from a known keyword, anything else is passed through verbatim.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocType {
+    Receipt,
+    Invoice,
+    Form,
+    Handwritten,
+    Table,
+    Academic,
+    Other(String),
+}
+
+impl std::str::FromStr for DocType {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "" => {
+                return Err(AppError::InvalidConfig(
+                    "doc type must not be empty".to_string(),
+                ));
+            }
+            "receipt" => Self::Receipt,
+            "invoice" => Self::Invoice,
+            "form" => Self::Form,
+            "handwritten" => Self::Handwritten,
+            "table" => Self::Table,
+            "academic" | "paper" => Self::Academic,
+            _ => Self::Other(value.to_string()),
+        })
+    }
+}
+
+impl DocType {
+    fn prompt_hint(&self) -> String {
+        match self {
+            Self::Receipt => "这是一张收据或小票，请逐行保留商品、数量、金额与合计。".to_string(),
+            Self::Invoice => {
+                "这是一张发票，请完整保留发票号码、日期、买卖双方信息、明细表格与金额。".to_string()
+            }
+            Self::Form => {
+                "这是一份表单，请以“字段: 值”的形式保留每个填写项，包括空白项。".to_string()
+            }
+            Self::Handwritten => {
+                "这是一份手写文档，请尽量辨认字迹，无法辨认处用 [?] 标注。".to_string()
+            }
+            Self::Table => "这是以表格为主的文档，请完整保留表格的行列结构。".to_string(),
+            Self::Academic => {
+                "这是一篇学术文献，请保留章节标题、公式、图表标题与参考文献。".to_string()
+            }
+            Self::Other(kind) => format!("文档类型：{kind}。"),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct GlmConfig {
@@ -20,6 +223,28 @@ pub struct GlmConfig {
     pub ocr_url: String,
     pub file_parse_url: String,
     pub max_ocr_chars: usize,
+    pub fallback: OcrFallback,
+    pub auto_rotate: bool,
+    pub max_dimension: u32,
+    pub normalize_page_breaks: bool,
+    pub continue_on_partial: bool,
+    pub doc_type: Option<DocType>,
+    pub language_hint: Option<String>,
+    // Replace the built-in extraction requests; the doc type, language and
+    // rotation hints are still appended to `ocr_prompt`.
+    pub ocr_prompt: Option<String>,
+    pub file_parse_prompt: Option<String>,
+    pub max_pages: usize,
+    pub pages: Option<PageRanges>,
+    // PDFs longer than this are OCR'd in batches of this many pages; zero
+    // sends the whole document in one request.
+    pub pdf_batch_pages: usize,
+    pub pdf_batch_concurrency: usize,
+    pub local_docx: bool,
+    pub max_input_bytes: usize,
+    pub provider: OcrProvider,
+    pub gemini: Option<GeminiOcrConfig>,
+    pub openai: Option<OpenAiOcrConfig>,
 }
 
 impl GlmConfig {
@@ -30,20 +255,59 @@ impl GlmConfig {
         ocr_url: Option<String>,
         file_parse_url: Option<String>,
         max_ocr_chars: usize,
+    ) -> Result<Self> {
+        Self::from_sources_for(
+            OcrProvider::from_env()?,
+            api_key,
+            base_url,
+            ocr_model,
+            ocr_url,
+            file_parse_url,
+            max_ocr_chars,
+        )
+    }
+
+    // With another provider reading images the GLM key is only needed for
+    // Word files, so its absence is reported when one is parsed.
+    pub fn from_sources_for(
+        provider: OcrProvider,
+        api_key: Option<String>,
+        base_url: Option<String>,
+        ocr_model: Option<String>,
+        ocr_url: Option<String>,
+        file_parse_url: Option<String>,
+        max_ocr_chars: usize,
     ) -> Result<Self> {
         let api_key = api_key
             .or_else(|| std::env::var("GLM_API_KEY").ok())
-            .filter(|value| !value.trim().is_empty())
-            .ok_or_else(|| AppError::InvalidConfig("GLM_API_KEY is required".to_string()))?;
+            .filter(|value| !value.trim().is_empty());
+        let api_key = match (api_key, provider) {
+            (Some(key), _) => key,
+            (None, OcrProvider::Gemini | OcrProvider::Openai | OcrProvider::Mock) => String::new(),
+            (None, OcrProvider::Glm) => {
+                return Err(AppError::InvalidConfig("GLM_API_KEY is required".to_string()).into());
+            }
+        };
+        let gemini = match provider {
+            OcrProvider::Gemini => Some(GeminiOcrConfig::from_env()?),
+            _ => None,
+        };
+        let openai = match provider {
+            OcrProvider::Openai => Some(OpenAiOcrConfig::from_env()?),
+            _ => None,
+        };
 
         let base_url = base_url
             .or_else(|| std::env::var("GLM_BASE_URL").ok())
             .unwrap_or_else(|| DEFAULT_GLM_BASE_URL.to_string());
         let base_url = base_url.trim_end_matches('/').to_string();
 
-        let ocr_model = ocr_model
-            .or_else(|| std::env::var("GLM_OCR_MODEL").ok())
-            .unwrap_or_else(|| DEFAULT_GLM_OCR_MODEL.to_string());
+        let ocr_model = match provider {
+            OcrProvider::Mock => "mock".to_string(),
+            _ => ocr_model
+                .or_else(|| std::env::var("GLM_OCR_MODEL").ok())
+                .unwrap_or_else(|| DEFAULT_GLM_OCR_MODEL.to_string()),
+        };
 
         let ocr_url = ocr_url
             .or_else(|| std::env::var("GLM_OCR_URL").ok())
@@ -60,38 +324,451 @@ impl GlmConfig {
             ocr_url,
             file_parse_url,
             max_ocr_chars,
+            fallback: OcrFallback::None,
+            auto_rotate: false,
+            max_dimension: DEFAULT_MAX_DIMENSION,
+            normalize_page_breaks: true,
+            continue_on_partial: false,
+            doc_type: None,
+            language_hint: None,
+            ocr_prompt: env_prompt("GLM_OCR_PROMPT"),
+            file_parse_prompt: env_prompt("GLM_FILE_PARSE_PROMPT"),
+            max_pages: env_usize("OCR2MD_MAX_PAGES", 0),
+            pages: None,
+            pdf_batch_pages: env_usize("OCR2MD_PDF_BATCH_PAGES", 0),
+            pdf_batch_concurrency: env_usize("OCR2MD_PDF_BATCH_CONCURRENCY", 2),
+            max_input_bytes: env_usize("OCR2MD_MAX_INPUT_BYTES", DEFAULT_MAX_INPUT_BYTES),
+            local_docx: !matches!(
+                std::env::var("OCR2MD_LOCAL_DOCX")
+                    .unwrap_or_default()
+                    .trim()
+                    .to_ascii_lowercase()
+                    .as_str(),
+                "0" | "false" | "off" | "no"
+            ),
+            provider,
+            gemini,
+            openai,
         })
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedPage {
+    pub number: usize,
+    pub content: String,
+    pub headings: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ParsedDocument {
+    pub pages: Vec<ParsedPage>,
+}
+
+impl ParsedDocument {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for page in &self.pages {
+            if !out.is_empty() {
+                out.push_str("\n\n");
+            }
+            out.push_str(&page_marker(page.number));
+            out.push_str("\n\n");
+            out.push_str(page.content.trim());
+        }
+        out
+    }
+}
+
+pub fn page_marker(number: usize) -> String {
+    format!("--- page {number} ---")
+}
+
+pub fn parse_page_marker(line: &str) -> Option<usize> {
+    let number = line
+        .trim()
+        .strip_prefix("--- page ")?
+        .strip_suffix(" ---")?
+        .parse()
+        .ok()?;
+    (line.trim() == page_marker(number)).then_some(number)
+}
+
+pub fn page_cap_notice(max_pages: usize) -> String {
+    format!("{PAGE_CAP_NOTICE_PREFIX}{max_pages} pages -->")
+}
+
+// Splits a trailing page-cap notice off OCR text so it can bypass the LLM and
+// be appended to the final Markdown verbatim.
+pub fn split_page_cap_notice(text: &str) -> (&str, Option<&str>) {
+    let trimmed = text.trim_end();
+    match trimmed.rfind(PAGE_CAP_NOTICE_PREFIX) {
+        Some(start) if !trimmed[start..].contains('\n') => {
+            (trimmed[..start].trim_end(), Some(&trimmed[start..]))
+        }
+        _ => (text, None),
+    }
+}
+
+static PAGE_LINE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^\s*(?:page\s+\d+(?:\s*(?:of|/)\s*\d+)?|第\s*\d+\s*页(?:\s*[,，/]?\s*共\s*\d+\s*页)?)\s*$",
+    )
+    .expect("valid page line regex")
+});
+
+// Turns form feeds and "Page N of M" header/footer lines into canonical
+// `--- page N ---` markers. A page line seen before any content on its page
+// is treated as a header, otherwise as a footer closing the current page.
+pub fn normalize_page_breaks(text: &str) -> String {
+    let has_breaks = text.contains('\x0c') || text.lines().any(|line| PAGE_LINE.is_match(line));
+    if !has_breaks || text.contains("--- page ") {
+        return text.to_string();
+    }
+
+    let mut pages: Vec<Vec<&str>> = Vec::new();
+    for segment in text.split('\x0c') {
+        let mut current: Vec<&str> = Vec::new();
+        let mut closed_by_footer = false;
+        for line in segment.lines() {
+            if PAGE_LINE.is_match(line) {
+                if current.iter().any(|line| !line.trim().is_empty()) {
+                    pages.push(std::mem::take(&mut current));
+                    closed_by_footer = true;
+                }
+                continue;
+            }
+            current.push(line);
+        }
+        if !closed_by_footer || current.iter().any(|line| !line.trim().is_empty()) {
+            pages.push(current);
+        }
+    }
+
+    let pages = pages
+        .into_iter()
+        .map(|lines| lines.join("\n"))
+        .enumerate()
+        .filter(|(_, content)| !content.trim().is_empty())
+        .map(|(index, content)| ParsedPage {
+            number: index + 1,
+            content,
+            headings: Vec::new(),
+        })
+        .collect();
+
+    ParsedDocument { pages }.to_text()
+}
+
+#[derive(Clone)]
 pub struct GlmOcrClient {
     http: HttpEngine,
     cfg: GlmConfig,
+    cache: Option<OcrCache>,
+    single_flight: Option<SingleFlight>,
 }
 
 impl GlmOcrClient {
     pub fn new(http: HttpEngine, cfg: GlmConfig) -> Self {
-        Self { http, cfg }
+        Self {
+            http,
+            cfg,
+            cache: None,
+            single_flight: None,
+        }
     }
 
+    pub fn with_cache(mut self, cache: Option<OcrCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    // Identical inputs OCRed at the same time by clones of this client share
+    // one call; see `SingleFlight`.
+    pub fn with_single_flight(mut self, single_flight: Option<SingleFlight>) -> Self {
+        self.single_flight = single_flight;
+        self
+    }
+
+    // The caller says what `bytes` are (see `detect_input_kind_from_bytes`
+    // for files), so in-memory input needs no path.
     pub async fn extract_text(
         &self,
-        input_path: &Path,
+        kind: InputKind,
         bytes: &[u8],
         trace_id: &str,
     ) -> Result<String> {
-        match detect_input_kind(input_path)? {
-            InputKind::Pdf => self.extract_pdf(input_path, bytes, trace_id).await,
-            InputKind::Doc | InputKind::Docx => self.parse_word(input_path, bytes, trace_id).await,
+        // Same key as the on-disk cache: the bytes, the model and every
+        // setting that changes the text.
+        let key = OcrCache::key(bytes, self.ocr_model(), &self.cache_settings(kind));
+        let extract = self.extract_keyed(kind, bytes, &key, trace_id);
+        match &self.single_flight {
+            Some(single_flight) => single_flight.run(&key, trace_id, extract).await,
+            None => extract.await,
         }
     }
 
-    async fn extract_pdf(&self, input_path: &Path, bytes: &[u8], trace_id: &str) -> Result<String> {
-        let mime = mime_guess::from_path(input_path)
-            .first_raw()
-            .unwrap_or("application/pdf");
+    async fn extract_keyed(
+        &self,
+        kind: InputKind,
+        bytes: &[u8],
+        key: &str,
+        trace_id: &str,
+    ) -> Result<String> {
+        let Some(cache) = &self.cache else {
+            return self.extract_uncached(kind, bytes, trace_id).await;
+        };
+
+        if let Some(text) = cache.get(key) {
+            info!(trace_id, key, "ocr_cache_hit");
+            return Ok(text);
+        }
+
+        let text = self.extract_uncached(kind, bytes, trace_id).await?;
+        if let Err(err) = cache.put(key, &text) {
+            warn!(trace_id, error = %err, "ocr_cache_write_failed");
+        }
+        Ok(text)
+    }
+
+    fn cache_settings(&self, kind: InputKind) -> String {
+        format!(
+            "{kind:?}|{:?}|{:?}|{}|{}|{:?}|{}|{}|{}|{}|{}|{}|{}",
+            self.cfg.provider,
+            self.cfg.fallback,
+            self.cfg.continue_on_partial,
+            self.cfg.max_pages,
+            self.cfg.pages.as_ref().map(ToString::to_string),
+            self.cfg.pdf_batch_pages,
+            self.cfg.max_ocr_chars,
+            self.cfg.normalize_page_breaks,
+            self.cfg.auto_rotate,
+            self.cfg.max_dimension,
+            self.ocr_prompt(),
+            self.cfg.local_docx
+        )
+    }
+
+    async fn extract_uncached(
+        &self,
+        kind: InputKind,
+        bytes: &[u8],
+        trace_id: &str,
+    ) -> Result<String> {
+        if self.cfg.provider == OcrProvider::Mock {
+            info!(trace_id, ?kind, "ocr_mock");
+            return Ok(self.finish_text(MOCK_OCR_TEXT.to_string()));
+        }
+        match kind {
+            InputKind::Pdf => {
+                let selected = self.select_pdf_pages(bytes, trace_id)?;
+                let bytes = selected.as_deref().unwrap_or(bytes);
+                let capped = self.cap_pdf_pages(bytes, trace_id);
+                let bytes = capped.as_deref().unwrap_or(bytes);
+                let text = match self.extract_pdf(bytes, trace_id).await {
+                    Err(err) if self.cfg.fallback == OcrFallback::FileParse => {
+                        warn!(trace_id, error = %err, "ocr_fallback_file_parse");
+                        self.parse_word(bytes, trace_id).await
+                    }
+                    result => result,
+                }?;
+                Ok(match capped {
+                    Some(_) => format!("{text}\n\n{}", page_cap_notice(self.cfg.max_pages)),
+                    None => text,
+                })
+            }
+            InputKind::Docx if self.cfg.local_docx => match docx::extract_text(bytes) {
+                Ok(text) if !text.trim().is_empty() => {
+                    info!(trace_id, chars = text.chars().count(), "docx_local_extract");
+                    Ok(self.finish_text(text))
+                }
+                Ok(_) => {
+                    warn!(trace_id, "docx_local_empty_fallback_api");
+                    self.parse_word(bytes, trace_id).await
+                }
+                Err(err) => {
+                    warn!(trace_id, error = %err, "docx_local_failed_fallback_api");
+                    self.parse_word(bytes, trace_id).await
+                }
+            },
+            InputKind::Doc | InputKind::Docx => self.parse_word(bytes, trace_id).await,
+            kind @ (InputKind::Png | InputKind::Jpeg | InputKind::Webp) => {
+                self.extract_image(kind, bytes, trace_id).await
+            }
+        }
+    }
+
+    async fn extract_image(&self, kind: InputKind, bytes: &[u8], trace_id: &str) -> Result<String> {
+        // The endpoint may still read what the local decoder cannot, so a
+        // failed preparation falls back to uploading the original bytes.
+        let prepared =
+            match image_prep::prepare(bytes, self.cfg.auto_rotate, self.cfg.max_dimension) {
+                Ok(prepared) => prepared,
+                Err(err) => {
+                    warn!(trace_id, error = %err, "image_prep_skipped");
+                    bytes.to_vec()
+                }
+            };
+
+        let mime = kind.default_mime();
+        self.check_upload_size(&prepared)?;
+        if let Some(gemini) = self.gemini() {
+            let text = self.gemini_ocr(gemini, mime, &prepared, trace_id).await?;
+            return Ok(self.finish_text(text));
+        }
+        let data_url = format!("data:{mime};base64,{}", STANDARD.encode(&prepared));
+        if let Some(openai) = self.openai() {
+            let text = self.openai_ocr(openai, &data_url, trace_id).await?;
+            return Ok(self.finish_text(text));
+        }
+
+        let payload = json!({
+            "model": self.cfg.ocr_model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": [
+                        {
+                            "type": "image_url",
+                            "image_url": {
+                                "url": data_url
+                            }
+                        },
+                        {
+                            "type": "text",
+                            "text": self.ocr_prompt()
+                        }
+                    ]
+                }
+            ]
+        });
+
+        let response = self
+            .http
+            .post_json(
+                "glm_ocr",
+                &self.cfg.ocr_url,
+                self.auth_headers()?,
+                &payload,
+                trace_id,
+            )
+            .await?;
+
+        let text = parse_glm_ocr_text(&response)?;
+        Ok(self.finish_text(text))
+    }
+
+    // Text-only request against the OCR model to check the credentials.
+    pub async fn ping(&self, trace_id: &str) -> Result<String> {
+        if self.cfg.provider == OcrProvider::Mock {
+            return Ok("mock".to_string());
+        }
+        if let Some(gemini) = self.gemini() {
+            let payload = json!({
+                "contents": [{"role": "user", "parts": [{"text": PING_PROMPT}]}]
+            });
+            let response = self.post_gemini(gemini, &payload, trace_id).await?;
+            return Ok(parse_gemini_ocr_text(&response)?.trim().to_string());
+        }
+        if let Some(openai) = self.openai() {
+            let payload = json!({
+                "model": openai.model,
+                "messages": [{"role": "user", "content": PING_PROMPT}]
+            });
+            let response = self.post_openai(openai, &payload, trace_id).await?;
+            return Ok(parse_openai_ocr_text(&response)?.trim().to_string());
+        }
+        let payload = json!({
+            "model": self.cfg.ocr_model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": PING_PROMPT
+                }
+            ]
+        });
+
+        let response = self
+            .http
+            .post_json(
+                "glm_ocr",
+                &self.cfg.ocr_url,
+                self.auth_headers()?,
+                &payload,
+                trace_id,
+            )
+            .await?;
+
+        Ok(parse_glm_ocr_text(&response)?.trim().to_string())
+    }
+
+    async fn extract_pdf(&self, bytes: &[u8], trace_id: &str) -> Result<String> {
+        match self.split_pdf_batches(bytes, trace_id) {
+            Some(batches) => self.extract_pdf_batches(batches, trace_id).await,
+            None => {
+                let text = self.ocr_pdf(bytes, trace_id).await?;
+                Ok(self.finish_text(text))
+            }
+        }
+    }
+
+    // Batches run with bounded concurrency but are joined in page order. Each
+    // batch's page markers are shifted to document page numbers before the
+    // combined text is length-capped once.
+    async fn extract_pdf_batches(&self, batches: Vec<PdfBatch>, trace_id: &str) -> Result<String> {
+        let texts: Vec<String> = stream::iter(batches.into_iter().enumerate())
+            .map(|(index, batch)| async move {
+                let batch_trace = format!("{trace_id}-b{index}");
+                let text = self.ocr_pdf(&batch.bytes, &batch_trace).await?;
+                Ok::<_, anyhow::Error>(self.number_batch_pages(&text, batch.first_page))
+            })
+            .buffered(self.cfg.pdf_batch_concurrency.max(1))
+            .try_collect()
+            .await?;
+        Ok(limit_text(texts.join("\n\n"), self.cfg.max_ocr_chars))
+    }
+
+    // A batch whose text carries no page breaks is marked by its first page
+    // only, so the whole batch reads as that page.
+    fn number_batch_pages(&self, text: &str, first_page: usize) -> String {
+        let text = if self.cfg.normalize_page_breaks {
+            normalize_page_breaks(text)
+        } else {
+            text.to_string()
+        };
+        let text = text.trim();
+        if !text.lines().any(|line| parse_page_marker(line).is_some()) {
+            return format!("{}\n\n{text}", page_marker(first_page));
+        }
+        text.lines()
+            .map(|line| match parse_page_marker(line) {
+                Some(number) => page_marker(number + first_page - 1),
+                None => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Raw OCR text of one PDF upload, before page-break normalization and the
+    // length cap.
+    async fn ocr_pdf(&self, bytes: &[u8], trace_id: &str) -> Result<String> {
+        // The content was sniffed as PDF, so the name's MIME type is irrelevant.
+        let mime = InputKind::Pdf.default_mime();
+        self.check_upload_size(bytes)?;
+        if let Some(gemini) = self.gemini() {
+            return self.gemini_ocr(gemini, mime, bytes, trace_id).await;
+        }
+        if self.openai().is_some() {
+            return Err(AppError::UnsupportedInputType(
+                "PDF with OCR_PROVIDER=openai; use glm or gemini, or convert the pages to images"
+                    .to_string(),
+            )
+            .into());
+        }
         let data_url = format!("data:{mime};base64,{}", STANDARD.encode(bytes));
 
+        let prompt = self.ocr_prompt();
+
         let payload = json!({
             "model": self.cfg.ocr_model,
             "messages": [
@@ -106,7 +783,7 @@ impl GlmOcrClient {
                         },
                         {
                             "type": "text",
-                            "text": "请提取文档完整内容，尽量保留标题、段落和表格结构，输出纯文本。"
+                            "text": prompt
                         }
                     ]
                 }
@@ -124,15 +801,19 @@ impl GlmOcrClient {
             )
             .await?;
 
-        let text = parse_glm_ocr_text(&response)?;
-        Ok(limit_text(text, self.cfg.max_ocr_chars))
+        parse_glm_ocr_text(&response)
     }
 
-    async fn parse_word(&self, _input_path: &Path, bytes: &[u8], trace_id: &str) -> Result<String> {
+    async fn parse_word(&self, bytes: &[u8], trace_id: &str) -> Result<String> {
+        self.check_upload_size(bytes)?;
         let payload = json!({
             "file": format!("base64://{}", STANDARD.encode(bytes)),
             "purpose": "file-extract",
-            "prompt": "提取文档全部正文与结构信息，保留标题层级和表格文本。"
+            "prompt": self
+                .cfg
+                .file_parse_prompt
+                .as_deref()
+                .unwrap_or(DEFAULT_FILE_PARSE_PROMPT)
         });
 
         let response = self
@@ -146,11 +827,254 @@ impl GlmOcrClient {
             )
             .await?;
 
-        let text = parse_glm_file_parse_text(&response)?;
-        Ok(limit_text(text, self.cfg.max_ocr_chars))
+        let text = match parse_glm_file_parse_text(&response) {
+            Err(err) if self.cfg.continue_on_partial => {
+                let partial = parse_glm_partial_text(&response).ok_or(err)?;
+                warn!(
+                    trace_id,
+                    chars = partial.chars().count(),
+                    "file_parse_partial_content"
+                );
+                partial
+            }
+            result => result?,
+        };
+        Ok(self.finish_text(text))
+    }
+
+    fn gemini(&self) -> Option<&GeminiOcrConfig> {
+        match self.cfg.provider {
+            OcrProvider::Gemini => self.cfg.gemini.as_ref(),
+            _ => None,
+        }
+    }
+
+    fn openai(&self) -> Option<&OpenAiOcrConfig> {
+        match self.cfg.provider {
+            OcrProvider::Openai => self.cfg.openai.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn stage_timeout_ms(&self) -> u64 {
+        self.http.runtime().ocr_stage_timeout_ms
+    }
+
+    pub fn ocr_model(&self) -> &str {
+        if let Some(gemini) = self.gemini() {
+            return &gemini.model;
+        }
+        self.openai()
+            .map_or(self.cfg.ocr_model.as_str(), |openai| openai.model.as_str())
+    }
+
+    // Sends the file inline next to the prompt, the same way a PDF or image
+    // is handed to the GLM vision model.
+    async fn gemini_ocr(
+        &self,
+        gemini: &GeminiOcrConfig,
+        mime: &str,
+        bytes: &[u8],
+        trace_id: &str,
+    ) -> Result<String> {
+        let payload = json!({
+            "contents": [
+                {
+                    "role": "user",
+                    "parts": [
+                        {
+                            "inline_data": {
+                                "mime_type": mime,
+                                "data": STANDARD.encode(bytes)
+                            }
+                        },
+                        {
+                            "text": self.ocr_prompt()
+                        }
+                    ]
+                }
+            ]
+        });
+
+        let response = self.post_gemini(gemini, &payload, trace_id).await?;
+        parse_gemini_ocr_text(&response)
+    }
+
+    async fn post_gemini(
+        &self,
+        gemini: &GeminiOcrConfig,
+        payload: &Value,
+        trace_id: &str,
+    ) -> Result<Value> {
+        let url = format!(
+            "{}/models/{}:generateContent",
+            gemini.base_url, gemini.model
+        );
+        let headers = gemini_headers(&gemini.api_key, "Gemini OCR API key header")?;
+        self.http
+            .post_json("gemini_ocr", &url, headers, payload, trace_id)
+            .await
+    }
+
+    // Same chat-completions shape as the GLM image request, sent to OpenAI
+    // with its own key and model.
+    async fn openai_ocr(
+        &self,
+        openai: &OpenAiOcrConfig,
+        data_url: &str,
+        trace_id: &str,
+    ) -> Result<String> {
+        let payload = json!({
+            "model": openai.model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": [
+                        {
+                            "type": "image_url",
+                            "image_url": {
+                                "url": data_url
+                            }
+                        },
+                        {
+                            "type": "text",
+                            "text": self.ocr_prompt()
+                        }
+                    ]
+                }
+            ]
+        });
+
+        let response = self.post_openai(openai, &payload, trace_id).await?;
+        parse_openai_ocr_text(&response)
+    }
+
+    async fn post_openai(
+        &self,
+        openai: &OpenAiOcrConfig,
+        payload: &Value,
+        trace_id: &str,
+    ) -> Result<Value> {
+        let url = format!("{}/chat/completions", openai.base_url);
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", openai.api_key))
+                .context("invalid OPENAI_API_KEY for header")?,
+        );
+        self.http
+            .post_json("openai_ocr", &url, headers, payload, trace_id)
+            .await
+    }
+
+    // Unlike the page cap, a selection the document cannot satisfy is an
+    // error: the caller asked for specific pages.
+    fn select_pdf_pages(&self, bytes: &[u8], trace_id: &str) -> Result<Option<Vec<u8>>> {
+        let Some(ranges) = &self.cfg.pages else {
+            return Ok(None);
+        };
+        let selected = pdf::select_pages(bytes, ranges)?;
+        if selected.is_some() {
+            info!(trace_id, pages = %ranges, "ocr_page_selection_applied");
+        }
+        Ok(selected)
+    }
+
+    // Like the page cap, a PDF that cannot be split is sent whole.
+    fn split_pdf_batches(&self, bytes: &[u8], trace_id: &str) -> Option<Vec<PdfBatch>> {
+        if self.cfg.pdf_batch_pages == 0 {
+            return None;
+        }
+        match pdf::split_into_batches(bytes, self.cfg.pdf_batch_pages) {
+            Ok(Some(batches)) => {
+                info!(
+                    trace_id,
+                    batches = batches.len(),
+                    batch_pages = self.cfg.pdf_batch_pages,
+                    "ocr_pdf_batched"
+                );
+                Some(batches)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!(trace_id, error = %err, "ocr_pdf_batching_skipped");
+                None
+            }
+        }
+    }
+
+    fn cap_pdf_pages(&self, bytes: &[u8], trace_id: &str) -> Option<Vec<u8>> {
+        if self.cfg.max_pages == 0 {
+            return None;
+        }
+        match pdf::keep_first_pages(bytes, self.cfg.max_pages) {
+            Ok(Some(capped)) => {
+                warn!(
+                    trace_id,
+                    max_pages = self.cfg.max_pages,
+                    total_pages = pdf::page_count(bytes).unwrap_or_default(),
+                    "ocr_page_cap_applied"
+                );
+                Some(capped)
+            }
+            Ok(None) => None,
+            Err(err) => {
+                warn!(trace_id, error = %err, "ocr_page_cap_skipped");
+                None
+            }
+        }
+    }
+
+    // Document type, language and layout hints are appended in that order so
+    // they read as one instruction after the base extraction request.
+    fn ocr_prompt(&self) -> String {
+        let mut prompt = self
+            .cfg
+            .ocr_prompt
+            .as_deref()
+            .unwrap_or(DEFAULT_OCR_PROMPT)
+            .to_string();
+        if let Some(doc_type) = &self.cfg.doc_type {
+            prompt.push_str(&doc_type.prompt_hint());
+        }
+        if let Some(language) = &self.cfg.language_hint {
+            prompt.push_str(&format!("文档主要语言为{language}，请按该语言识别文字。"));
+        }
+        if self.cfg.auto_rotate {
+            prompt.push_str("如页面为旋转或横向扫描，请先按正确方向阅读后再提取。");
+        }
+        prompt
+    }
+
+    // Refuses to base64 a body the API would most likely time out on.
+    fn check_upload_size(&self, bytes: &[u8]) -> Result<()> {
+        if bytes.len() > self.cfg.max_input_bytes {
+            return Err(AppError::InputTooLarge {
+                size: bytes.len() as u64,
+                limit: self.cfg.max_input_bytes as u64,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn finish_text(&self, text: String) -> String {
+        let text = if self.cfg.normalize_page_breaks {
+            normalize_page_breaks(&text)
+        } else {
+            text
+        };
+        limit_text(text, self.cfg.max_ocr_chars)
     }
 
     fn auth_headers(&self) -> Result<HeaderMap> {
+        if self.cfg.api_key.is_empty() {
+            return Err(AppError::InvalidConfig(
+                "GLM_API_KEY is required for GLM file parsing".to_string(),
+            )
+            .into());
+        }
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert(
@@ -162,24 +1086,173 @@ impl GlmOcrClient {
     }
 }
 
-fn limit_text(mut text: String, max_chars: usize) -> String {
-    if text.chars().count() <= max_chars {
+// A blank override means "use the default", not "send an empty prompt".
+fn env_prompt(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+}
+
+// Keeps at most `max_chars` characters, cut at a clean boundary so the LLM
+// never sees half a table row or half a sentence.
+fn limit_text(text: String, max_chars: usize) -> String {
+    let Some((cap, _)) = text.char_indices().nth(max_chars) else {
         return text;
-    }
+    };
+    let head = &text[..cap];
+    let mut out = head[..clean_cut(head)].trim_end().to_string();
+    out.push_str("\n\n");
+    out.push_str(TRUNCATION_MARKER);
+    out
+}
 
-    text = text.chars().take(max_chars).collect();
-    text.push_str("\n\n[TRUNCATED: OCR output exceeded MAX_OCR_CHARS]");
-    text
+// Byte offset to cut `head` at: its last paragraph break, else its last line
+// break, else the end of its last word or CJK sentence. A boundary that would
+// drop more than half of `head` is skipped for the next kind; one unbroken run
+// is cut at the cap.
+fn clean_cut(head: &str) -> usize {
+    let floor = head.len() / 2;
+    let paragraph = head.rfind("\n\n");
+    let line = head.rfind('\n');
+    let word = head
+        .char_indices()
+        .rev()
+        .find(|(_, ch)| ch.is_whitespace() || "。！？；，、".contains(*ch))
+        .map(|(index, ch)| index + ch.len_utf8());
+    [paragraph, line, word]
+        .into_iter()
+        .flatten()
+        .find(|index| *index >= floor && *index > 0)
+        .unwrap_or(head.len())
 }
 
 fn parse_glm_ocr_text(value: &Value) -> Result<String> {
-    extract_openai_content(value).ok_or_else(|| {
-        AppError::ApiResponse("missing choices[0].message.content in GLM OCR response".to_string())
+    if let Some(reason) = glm_moderation(value) {
+        return Err(AppError::ContentBlocked(reason).into());
+    }
+    schema::validate("glm_ocr", value, OPENAI_CHAT_RULES)?;
+    extract_glm_pages(value)
+        .or_else(|| extract_openai_content(value))
+        .ok_or_else(|| {
+            AppError::ApiResponse(
+                "missing choices[0].message.content in GLM OCR response".to_string(),
+            )
             .into()
+        })
+}
+
+// Keys GLM may tag a content part with to say which page it came from.
+const PAGE_KEYS: [&str; 3] = ["page", "page_number", "page_index"];
+
+// Content parts tagged with a page come back as `--- page N ---` sections,
+// so the LLM still sees where a table or paragraph crosses a page. Pages are
+// numbered 1, 2, ... in the order they first appear, whatever the tags count
+// from; untagged parts stay on the page before them. `None` when no part is
+// tagged, leaving the text to `extract_openai_content`.
+fn extract_glm_pages(value: &Value) -> Option<String> {
+    let parts = value.pointer("/choices/0/message/content")?.as_array()?;
+    let mut pages: Vec<(&Value, String)> = Vec::new();
+    for part in parts.iter().filter(|part| is_answer_part(part)) {
+        let Some(text) = part.get("text").and_then(Value::as_str) else {
+            continue;
+        };
+        let page = PAGE_KEYS.iter().find_map(|key| part.get(*key));
+        match (page, pages.last_mut()) {
+            (Some(page), Some((last, body))) if *last == page => push_line(body, text),
+            (None, Some((_, body))) => push_line(body, text),
+            (Some(page), _) => pages.push((page, text.to_string())),
+            (None, None) => pages.push((&Value::Null, text.to_string())),
+        }
+    }
+    if pages.iter().all(|(page, _)| page.is_null()) {
+        return None;
+    }
+
+    let document = ParsedDocument {
+        pages: pages
+            .into_iter()
+            .enumerate()
+            .map(|(index, (_, content))| ParsedPage {
+                number: index + 1,
+                content,
+                headings: Vec::new(),
+            })
+            .collect(),
+    };
+    Some(document.to_text())
+}
+
+fn push_line(buf: &mut String, text: &str) {
+    if !buf.is_empty() {
+        buf.push('\n');
+    }
+    buf.push_str(text);
+}
+
+// Text parts carry the answer; reasoning and other typed parts do not.
+fn is_answer_part(part: &Value) -> bool {
+    part.get("type")
+        .and_then(Value::as_str)
+        .is_none_or(|kind| matches!(kind, "text" | "output_text"))
+}
+
+// GLM answers moderated input with a 200 whose choice stops on `sensitive`
+// (`content_filter` behind OpenAI-style relays), or with an error object in
+// place of the choices carrying code 1301. Other error bodies are left to the
+// schema check.
+fn glm_moderation(value: &Value) -> Option<String> {
+    const MARKERS: [&str; 2] = ["sensitive", "content_filter"];
+
+    if let Some(reason) = value
+        .pointer("/choices/0/finish_reason")
+        .and_then(Value::as_str)
+        .filter(|reason| MARKERS.contains(reason))
+    {
+        return Some(format!("finish_reason {reason}"));
+    }
+
+    let error = value.get("error")?;
+    let code = match error.get("code") {
+        Some(Value::String(code)) => code.clone(),
+        Some(Value::Number(code)) => code.to_string(),
+        _ => String::new(),
+    };
+    let message = error
+        .get("message")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let blocked = code == "1301"
+        || MARKERS
+            .iter()
+            .any(|marker| code.contains(marker) || message.to_lowercase().contains(marker));
+    blocked.then(|| format!("error {code}: {message}"))
+}
+
+fn parse_openai_ocr_text(value: &Value) -> Result<String> {
+    schema::validate("openai_ocr", value, OPENAI_CHAT_RULES)?;
+    extract_openai_content(value).ok_or_else(|| {
+        AppError::ApiResponse(
+            "missing choices[0].message.content in OpenAI OCR response".to_string(),
+        )
+        .into()
+    })
+}
+
+fn parse_gemini_ocr_text(value: &Value) -> Result<String> {
+    schema::validate("gemini_ocr", value, GEMINI_GENERATE_RULES)?;
+    parse_gemini_content(value).ok_or_else(|| {
+        AppError::ApiResponse(
+            "missing candidates[0].content.parts in Gemini OCR response".to_string(),
+        )
+        .into()
     })
 }
 
 fn parse_glm_file_parse_text(value: &Value) -> Result<String> {
+    if let Some(document) = parse_glm_structured_pages(value) {
+        return Ok(document.to_text());
+    }
+
     for pointer in [
         "/content",
         "/data/content",
@@ -200,6 +1273,72 @@ fn parse_glm_file_parse_text(value: &Value) -> Result<String> {
     )
 }
 
+fn parse_glm_partial_text(value: &Value) -> Option<String> {
+    [
+        "/partial_content",
+        "/data/partial_content",
+        "/error/partial_content",
+    ]
+    .iter()
+    .find_map(|pointer| value.pointer(pointer).and_then(Value::as_str))
+    .filter(|text| !text.trim().is_empty())
+    .map(str::to_string)
+}
+
+pub fn parse_glm_structured_pages(value: &Value) -> Option<ParsedDocument> {
+    let items = ["/pages", "/data/pages", "/result/pages", "/data/content"]
+        .iter()
+        .find_map(|pointer| value.pointer(pointer).and_then(Value::as_array))?;
+
+    let mut pages = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let Some(content) = ["content", "text", "markdown"]
+            .iter()
+            .find_map(|key| item.get(*key).and_then(Value::as_str))
+        else {
+            continue;
+        };
+
+        let number = ["page", "page_num", "page_number", "page_index"]
+            .iter()
+            .find_map(|key| item.get(*key).and_then(Value::as_u64))
+            .map(|number| number as usize)
+            .unwrap_or(index + 1);
+
+        let headings = item
+            .get("headings")
+            .and_then(Value::as_array)
+            .map(|headings| {
+                headings
+                    .iter()
+                    .filter_map(|heading| {
+                        heading
+                            .as_str()
+                            .or_else(|| heading.get("text").and_then(Value::as_str))
+                            .map(str::to_string)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        pages.push(ParsedPage {
+            number,
+            content: content.to_string(),
+            headings,
+        });
+    }
+
+    if pages.iter().all(|page| page.content.trim().is_empty()) {
+        return None;
+    }
+
+    pages.sort_by_key(|page| page.number);
+    Some(ParsedDocument { pages })
+}
+
+// Content parts without a type or typed `text`/`output_text` are the answer;
+// thinking models such as GLM-4.1V-Thinking may interleave `thinking` or
+// `reasoning` parts that must not reach the OCR text.
 pub fn extract_openai_content(value: &Value) -> Option<String> {
     let content = value.pointer("/choices/0/message/content")?;
     if let Some(text) = content.as_str() {
@@ -208,12 +1347,9 @@ pub fn extract_openai_content(value: &Value) -> Option<String> {
 
     let mut buf = String::new();
     if let Some(parts) = content.as_array() {
-        for part in parts {
+        for part in parts.iter().filter(|part| is_answer_part(part)) {
             if let Some(text) = part.get("text").and_then(Value::as_str) {
-                if !buf.is_empty() {
-                    buf.push('\n');
-                }
-                buf.push_str(text);
+                push_line(&mut buf, text);
             }
         }
     }
@@ -230,7 +1366,54 @@ mod tests {
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
-    use super::extract_openai_content;
+    use super::{
+        ParsedPage, TRUNCATION_MARKER, extract_openai_content, limit_text, normalize_page_breaks,
+        parse_glm_file_parse_text, parse_glm_ocr_text, parse_glm_structured_pages,
+    };
+    use crate::error::AppError;
+
+    #[test]
+    fn truncation_never_cuts_a_table_row() {
+        let text = "Intro paragraph.\n\n| Item | Qty |\n|---|---|\n| Pen | 2 |\n| Ink | 1 |\n";
+        // The cap lands inside the `| Ink | 1 |` row.
+        let cap = text.find("Ink").unwrap();
+
+        let limited = limit_text(text.to_string(), cap);
+
+        assert_eq!(
+            limited,
+            format!(
+                "Intro paragraph.\n\n| Item | Qty |\n|---|---|\n| Pen | 2 |\n\n{TRUNCATION_MARKER}"
+            )
+        );
+    }
+
+    #[test]
+    fn truncation_ends_multibyte_text_at_a_sentence() {
+        let text = "第一句话。第二句话很长很长。第三句";
+        // A plain cut at 16 characters would end in "第三".
+        let limited = limit_text(text.to_string(), 16);
+
+        assert_eq!(
+            limited,
+            format!("第一句话。第二句话很长很长。\n\n{TRUNCATION_MARKER}")
+        );
+        assert!(limited.chars().count() <= 16 + 2 + TRUNCATION_MARKER.chars().count());
+    }
+
+    #[test]
+    fn truncation_prefers_paragraphs_and_keeps_short_text() {
+        let text = "first paragraph\nstill first\n\nsecond paragraph goes on";
+        assert_eq!(
+            limit_text(text.to_string(), 40),
+            format!("first paragraph\nstill first\n\n{TRUNCATION_MARKER}")
+        );
+        assert_eq!(limit_text("short".to_string(), 5), "short");
+        assert_eq!(
+            limit_text("abcdefgh".to_string(), 3),
+            format!("abc\n\n{TRUNCATION_MARKER}")
+        );
+    }
 
     #[test]
     fn parse_openai_content_string() {
@@ -267,4 +1450,139 @@ mod tests {
             Some("line1\nline2")
         );
     }
+
+    #[test]
+    fn parse_openai_content_skips_thinking_parts() {
+        let value = json!({
+            "choices": [
+                {
+                    "message": {
+                        "content": [
+                            {"type": "thinking", "text": "Let me read the header first."},
+                            {"type": "text", "text": "Invoice 42"},
+                            {"type": "reasoning", "text": "The table has two columns."},
+                            {"text": "Total: 10.00"}
+                        ]
+                    }
+                }
+            ]
+        });
+
+        assert_eq!(
+            extract_openai_content(&value).as_deref(),
+            Some("Invoice 42\nTotal: 10.00")
+        );
+    }
+
+    #[test]
+    fn glm_page_segments_are_joined_under_page_markers() {
+        let segmented = json!({
+            "choices": [{"message": {"content": [
+                {"type": "text", "page_index": 0, "text": "| 品名 | 数量 |\n|---|---|\n| 笔 | 2 |"},
+                {"type": "text", "page_index": 1, "text": "| 墨水 | 1 |"},
+                {"type": "text", "text": "合计 3"}
+            ]}}]
+        });
+        assert_eq!(
+            parse_glm_ocr_text(&segmented).unwrap(),
+            "--- page 1 ---\n\n| 品名 | 数量 |\n|---|---|\n| 笔 | 2 |\n\n--- page 2 ---\n\n| 墨水 | 1 |\n合计 3"
+        );
+
+        let untagged = json!({
+            "choices": [{"message": {"content": [{"type": "text", "text": "one"}, {"text": "blob"}]}}]
+        });
+        assert_eq!(parse_glm_ocr_text(&untagged).unwrap(), "one\nblob");
+    }
+
+    #[test]
+    fn glm_moderation_is_a_content_blocked_error() {
+        let filtered = json!({
+            "choices": [{
+                "finish_reason": "sensitive",
+                "message": {"role": "assistant", "content": ""}
+            }]
+        });
+        let error_shaped = json!({
+            "error": {"code": "1301", "message": "系统检测到输入或生成内容可能包含不安全或敏感内容"}
+        });
+
+        for response in [filtered, error_shaped] {
+            let err = parse_glm_ocr_text(&response).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref(), Some(AppError::ContentBlocked(_))),
+                "{err:#}"
+            );
+        }
+    }
+
+    #[test]
+    fn glm_normal_and_other_error_responses_parse_as_before() {
+        let normal = json!({
+            "choices": [{"finish_reason": "stop", "message": {"content": "第一页\n正文"}}]
+        });
+        assert_eq!(parse_glm_ocr_text(&normal).unwrap(), "第一页\n正文");
+
+        let other = json!({"error": {"code": "1113", "message": "余额不足"}});
+        let err = parse_glm_ocr_text(&other).unwrap_err();
+        assert!(!matches!(
+            err.downcast_ref(),
+            Some(AppError::ContentBlocked(_))
+        ));
+    }
+
+    #[test]
+    fn parse_structured_file_parse_pages() {
+        let value = json!({
+            "data": {
+                "pages": [
+                    {"page_num": 2, "content": "second page", "headings": []},
+                    {
+                        "page_num": 1,
+                        "content": "# Report\nfirst page",
+                        "headings": [{"level": 1, "text": "Report"}]
+                    }
+                ]
+            }
+        });
+
+        let document = parse_glm_structured_pages(&value).unwrap();
+        assert_eq!(
+            document.pages[0],
+            ParsedPage {
+                number: 1,
+                content: "# Report\nfirst page".to_string(),
+                headings: vec!["Report".to_string()],
+            }
+        );
+        assert_eq!(
+            parse_glm_file_parse_text(&value).unwrap(),
+            "--- page 1 ---\n\n# Report\nfirst page\n\n--- page 2 ---\n\nsecond page"
+        );
+    }
+
+    #[test]
+    fn parse_flat_file_parse_text_without_pages() {
+        let value = json!({"data": {"content": "flat text"}});
+        assert!(parse_glm_structured_pages(&value).is_none());
+        assert_eq!(parse_glm_file_parse_text(&value).unwrap(), "flat text");
+    }
+
+    #[test]
+    fn normalize_form_feeds_and_page_footers() {
+        let text = "Intro\nPage 1 of 5\n\x0cBody\nPage 2 of 5\n\x0c\x0cEnd\n第 4 页 共 5 页";
+        assert_eq!(
+            normalize_page_breaks(text),
+            "--- page 1 ---\n\nIntro\n\n--- page 2 ---\n\nBody\n\n--- page 4 ---\n\nEnd"
+        );
+    }
+
+    #[test]
+    fn normalize_page_headers_without_form_feeds() {
+        let text = "Page 1 of 2\nFirst\nPage 2 of 2\nSecond";
+        assert_eq!(
+            normalize_page_breaks(text),
+            "--- page 1 ---\n\nFirst\n\n--- page 2 ---\n\nSecond"
+        );
+        assert_eq!(normalize_page_breaks("no breaks here"), "no breaks here");
+    }
 }
@@ -0,0 +1,63 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::config::RuntimeConfig;
+
+// OCR text stored on disk so re-running a document (e.g. after editing the
+// LLM prompt) does not pay for OCR again. Only OCR is cached; the LLM step
+// always runs.
+#[derive(Debug, Clone)]
+pub struct OcrCache {
+    dir: PathBuf,
+}
+
+impl OcrCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn from_runtime(runtime: &RuntimeConfig) -> Option<Self> {
+        runtime.ocr_cache_dir.as_ref().map(Self::new)
+    }
+
+    // The file hash plus everything that shapes the OCR request, so changing
+    // the model or OCR settings never serves a stale entry.
+    pub fn key(bytes: &[u8], model: &str, settings: &str) -> String {
+        let request = format!("{model}\n{settings}");
+        format!(
+            "{}-{}",
+            content_hash(bytes),
+            &content_hash(request.as_bytes())[..16]
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        fs::read_to_string(self.entry_path(key)).ok()
+    }
+
+    pub fn put(&self, key: &str, text: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create OCR cache dir: {}", self.dir.display()))?;
+        let path = self.entry_path(key);
+        let tmp = path.with_extension("txt.tmp");
+        fs::write(&tmp, text)
+            .with_context(|| format!("failed to write OCR cache entry: {}", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("failed to write OCR cache entry: {}", path.display()))?;
+        Ok(())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.txt"))
+    }
+}
+
+fn content_hash(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
@@ -0,0 +1,177 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result};
+
+// An output path of `-` means standard output.
+pub const STDOUT_PATH: &str = "-";
+
+// Goes between conversions appended to the same file.
+pub const APPEND_SEPARATOR: &str = "\n\n---\n\n";
+
+// Held while a finished output is copied onto its append target, so jobs
+// finishing together never interleave their text.
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+// Keeps temp names unique when several jobs in this process target the same
+// output, as they do when appending.
+static TEMP_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new(STDOUT_PATH)
+}
+
+enum Sink {
+    File(BufWriter<File>),
+    Stdout(BufWriter<Stdout>),
+}
+
+impl Sink {
+    fn as_write(&mut self) -> &mut dyn Write {
+        match self {
+            Self::File(writer) => writer,
+            Self::Stdout(writer) => writer,
+        }
+    }
+}
+
+pub struct StreamingWriter {
+    final_path: PathBuf,
+    temp_path: PathBuf,
+    writer: Option<Sink>,
+    bytes: usize,
+    append: bool,
+    // Set once the temp file has been renamed into place; until then `Drop`
+    // removes it, whichever step failed.
+    committed: bool,
+}
+
+impl StreamingWriter {
+    pub fn create(final_path: &Path) -> Result<Self> {
+        if is_stdout(final_path) {
+            return Ok(Self {
+                final_path: final_path.to_path_buf(),
+                temp_path: final_path.to_path_buf(),
+                writer: Some(Sink::Stdout(BufWriter::new(io::stdout()))),
+                bytes: 0,
+                append: false,
+                committed: false,
+            });
+        }
+
+        let temp_path = temp_path_for(final_path);
+        let file = File::create(&temp_path)
+            .with_context(|| format!("failed to create temp output: {}", temp_path.display()))?;
+
+        Ok(Self {
+            final_path: final_path.to_path_buf(),
+            temp_path,
+            writer: Some(Sink::File(BufWriter::new(file))),
+            bytes: 0,
+            append: false,
+            committed: false,
+        })
+    }
+
+    // Like `create`, but `finish` adds the output to the end of the final
+    // file (creating it if needed) behind `APPEND_SEPARATOR` instead of
+    // replacing it. The text is still staged in a temp file, so a failed job
+    // appends nothing.
+    pub fn append(final_path: &Path) -> Result<Self> {
+        let mut writer = Self::create(final_path)?;
+        writer.append = !is_stdout(final_path);
+        Ok(writer)
+    }
+
+    pub fn write_chunk(&mut self, chunk: &str) -> Result<()> {
+        let writer = self
+            .writer
+            .as_mut()
+            .context("output writer already finished")?;
+        writer
+            .as_write()
+            .write_all(chunk.as_bytes())
+            .with_context(|| format!("failed to write output: {}", self.temp_path.display()))?;
+        self.bytes += chunk.len();
+        Ok(())
+    }
+
+    pub fn bytes_written(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn finish(mut self) -> Result<usize> {
+        let writer = match self.writer.take() {
+            Some(Sink::File(writer)) => writer,
+            Some(Sink::Stdout(mut writer)) => {
+                writer.flush().context("failed to flush stdout")?;
+                return Ok(self.bytes);
+            }
+            None => anyhow::bail!("output writer already finished"),
+        };
+        let file = writer
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .with_context(|| format!("failed to flush output: {}", self.temp_path.display()))?;
+        file.sync_all()
+            .with_context(|| format!("failed to sync output: {}", self.temp_path.display()))?;
+        drop(file);
+
+        if self.append {
+            let result = self.append_temp();
+            let _ = fs::remove_file(&self.temp_path);
+            return result.map(|()| self.bytes);
+        }
+        fs::rename(&self.temp_path, &self.final_path)
+            .with_context(|| format!("failed to write output: {}", self.final_path.display()))?;
+        self.committed = true;
+        Ok(self.bytes)
+    }
+
+    fn append_temp(&self) -> Result<()> {
+        let _guard = APPEND_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        let mut target = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.final_path)
+            .with_context(|| format!("failed to open output: {}", self.final_path.display()))?;
+        let existing = target
+            .metadata()
+            .with_context(|| format!("failed to stat output: {}", self.final_path.display()))?
+            .len();
+        let mut staged = File::open(&self.temp_path)
+            .with_context(|| format!("failed to read temp output: {}", self.temp_path.display()))?;
+        if existing > 0 {
+            target
+                .write_all(APPEND_SEPARATOR.as_bytes())
+                .with_context(|| {
+                    format!("failed to append output: {}", self.final_path.display())
+                })?;
+        }
+        io::copy(&mut staged, &mut target)
+            .with_context(|| format!("failed to append output: {}", self.final_path.display()))?;
+        target
+            .sync_all()
+            .with_context(|| format!("failed to sync output: {}", self.final_path.display()))
+    }
+}
+
+impl Drop for StreamingWriter {
+    fn drop(&mut self) {
+        if !self.committed && !is_stdout(&self.final_path) {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|value| value.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    let seq = TEMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.{}.{seq}.tmp", std::process::id()))
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use lopdf::Document;
+
+use crate::error::AppError;
+
+// 1-based page selection such as `5-12,20,30-`. Segments may overlap or
+// come in any order; the selected pages are always kept in document order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRanges(Vec<(usize, Option<usize>)>);
+
+impl FromStr for PageRanges {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| {
+            AppError::InvalidConfig(format!("invalid page range {value:?}: {reason}"))
+        };
+        let number = |text: &str| -> Result<usize, AppError> {
+            match text.trim().parse::<usize>() {
+                Ok(0) => Err(invalid("pages are numbered from 1")),
+                Ok(page) => Ok(page),
+                Err(_) => Err(invalid(&format!("{:?} is not a page number", text.trim()))),
+            }
+        };
+
+        let mut segments = Vec::new();
+        for segment in value.split(',').map(str::trim) {
+            if segment.is_empty() {
+                return Err(invalid("empty segment"));
+            }
+            let (start, end) = match segment.split_once('-') {
+                None => {
+                    let page = number(segment)?;
+                    (page, Some(page))
+                }
+                Some((start, end)) if end.trim().is_empty() => (number(start)?, None),
+                Some((start, end)) => (number(start)?, Some(number(end)?)),
+            };
+            if end.is_some_and(|end| end < start) {
+                return Err(invalid(&format!("{segment} runs backwards")));
+            }
+            segments.push((start, end));
+        }
+        Ok(Self(segments))
+    }
+}
+
+impl fmt::Display for PageRanges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (start, end)) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+            match end {
+                Some(end) if end == start => write!(f, "{start}")?,
+                Some(end) => write!(f, "{start}-{end}")?,
+                None => write!(f, "{start}-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PageRanges {
+    // Sorted, de-duplicated page numbers for a document of `total` pages.
+    pub fn resolve(&self, total: usize) -> Result<Vec<usize>, AppError> {
+        let mut pages = Vec::new();
+        for &(start, end) in &self.0 {
+            let end = end.unwrap_or(total);
+            if start > total || end > total {
+                return Err(AppError::InvalidConfig(format!(
+                    "page range {self} is out of bounds: the PDF has {total} page(s)"
+                )));
+            }
+            pages.extend(start..=end);
+        }
+        pages.sort_unstable();
+        pages.dedup();
+        Ok(pages)
+    }
+}
+
+pub fn page_count(bytes: &[u8]) -> Result<usize> {
+    let document = Document::load_mem(bytes).context("failed to read PDF structure")?;
+    Ok(document.get_pages().len())
+}
+
+// Returns a copy of the PDF keeping only the first `max_pages` pages, or
+// `None` when the document already fits.
+pub fn keep_first_pages(bytes: &[u8], max_pages: usize) -> Result<Option<Vec<u8>>> {
+    let total = page_count(bytes)?;
+    if total <= max_pages {
+        return Ok(None);
+    }
+    let pages: Vec<usize> = (1..=max_pages).collect();
+    keep_pages(bytes, &pages).map(Some)
+}
+
+// A run of consecutive pages cut out of a larger PDF.
+#[derive(Debug, Clone)]
+pub struct PdfBatch {
+    // 1-based number of the batch's first page in the source document.
+    pub first_page: usize,
+    pub bytes: Vec<u8>,
+}
+
+// Splits the PDF into consecutive batches of at most `pages_per_batch`
+// pages. `None` when the document fits in a single batch.
+pub fn split_into_batches(bytes: &[u8], pages_per_batch: usize) -> Result<Option<Vec<PdfBatch>>> {
+    let total = page_count(bytes)?;
+    if pages_per_batch == 0 || total <= pages_per_batch {
+        return Ok(None);
+    }
+    let pages: Vec<usize> = (1..=total).collect();
+    pages
+        .chunks(pages_per_batch)
+        .map(|batch| {
+            keep_pages(bytes, batch).map(|batch_bytes| PdfBatch {
+                first_page: batch[0],
+                bytes: batch_bytes,
+            })
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(Some)
+}
+
+// Returns a copy of the PDF with only the selected pages, or `None` when the
+// selection covers the whole document.
+pub fn select_pages(bytes: &[u8], ranges: &PageRanges) -> Result<Option<Vec<u8>>> {
+    let total = page_count(bytes)?;
+    let pages = ranges.resolve(total)?;
+    if pages.len() == total {
+        return Ok(None);
+    }
+    keep_pages(bytes, &pages).map(Some)
+}
+
+// Returns a copy of the PDF with only the given 1-based pages, in their
+// original order.
+pub fn keep_pages(bytes: &[u8], pages: &[usize]) -> Result<Vec<u8>> {
+    let mut document = Document::load_mem(bytes).context("failed to read PDF structure")?;
+    let drop: Vec<u32> = document
+        .get_pages()
+        .into_keys()
+        .filter(|number| !pages.contains(&(*number as usize)))
+        .collect();
+    document.delete_pages(&drop);
+    document.prune_objects();
+
+    let mut out = Vec::new();
+    document
+        .save_to(&mut out)
+        .context("failed to write truncated PDF")?;
+    Ok(out)
+}
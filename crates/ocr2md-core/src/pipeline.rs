@@ -1,14 +1,312 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
+use crate::cjk;
 use crate::config::RuntimeConfig;
+use crate::error::AppError;
+use crate::file_kind::{InputKind, detect_input_kind_from_bytes, detect_stdin_kind, is_stdin};
+use crate::format::OutputFormat;
+use crate::frontmatter::{ConversionMetadata, build_frontmatter};
 use crate::http::HttpEngine;
-use crate::llm::{LlmClient, LlmConfig};
-use crate::ocr::{GlmConfig, GlmOcrClient};
+use crate::llm::{LlmClient, LlmConfig, TokenUsage};
+use crate::ocr::{GlmConfig, GlmOcrClient, split_page_cap_notice};
+use crate::ocr_cache::OcrCache;
+use crate::output::{StreamingWriter, is_stdout};
+use crate::pdf;
+use crate::postprocess::{self, Postprocess};
+use crate::progress::{ProgressEvent, ProgressSink};
+use crate::redact::Redactor;
+use crate::running_headers;
+use crate::sections::{Section, SectionedDocument};
+use crate::single_flight::SingleFlight;
+use crate::tables;
+use crate::toc::{self, TocTarget};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    Ocr,
+    Markdown,
+}
+
+// What to do when OCR comes back with nothing but whitespace: carry on to
+// the LLM with a warning, stop without writing anything, or fail the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum EmptyOcrPolicy {
+    #[default]
+    Warn,
+    Skip,
+    Fail,
+}
+
+impl EmptyOcrPolicy {
+    // `OCR2MD_ON_EMPTY_OCR`, for callers without the CLI flag.
+    pub fn from_env() -> Self {
+        std::env::var("OCR2MD_ON_EMPTY_OCR")
+            .ok()
+            .and_then(|value| Self::from_str(value.trim(), true).ok())
+            .unwrap_or_default()
+    }
+
+    // Whether the file goes on to the LLM stage. Text with anything besides
+    // whitespace always does.
+    pub fn proceeds(self, ocr_text: &str) -> Result<bool, AppError> {
+        if !ocr_text.trim().is_empty() {
+            return Ok(true);
+        }
+        match self {
+            Self::Warn => Ok(true),
+            Self::Skip => Ok(false),
+            Self::Fail => Err(AppError::EmptyOcr),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    pub emit: Vec<Emit>,
+    pub stream: bool,
+    pub redact: Option<Redactor>,
+    pub cjk_normalize: bool,
+    // Cleanups run over the LLM's Markdown, in order; see `postprocess`.
+    pub postprocess: Vec<Postprocess>,
+    // Drop running headers and footers from the text sent to the LLM; see
+    // `running_headers::strip`.
+    pub strip_running_headers: bool,
+    // Prepend YAML frontmatter describing the conversion to the Markdown.
+    pub frontmatter: bool,
+    // HTML and plain text are rendered from the finished Markdown; they get
+    // no frontmatter.
+    pub format: OutputFormat,
+    // Add each conversion to the end of its output file instead of
+    // replacing it; see `StreamingWriter::append`.
+    pub append: bool,
+    // Also write each pipe table of the Markdown as a CSV into this directory.
+    pub extract_tables: Option<PathBuf>,
+    // Outline of the Markdown's headings, as a JSON file or a linked TOC at
+    // the top of a Markdown output; see `toc::headings`.
+    pub toc: Option<TocTarget>,
+    // Kind of an input read from stdin (path `-`) whose magic bytes are not
+    // recognised.
+    pub stdin_kind: Option<InputKind>,
+    pub on_empty_ocr: EmptyOcrPolicy,
+    // Skip a file whose output already exists and is newer than the input;
+    // see `is_up_to_date`. Ignored when appending or for stdin and stdout.
+    pub skip_if_exists: bool,
+    pub cancel: CancellationToken,
+    pub progress: Option<ProgressSink>,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            emit: vec![Emit::Markdown],
+            stream: false,
+            redact: None,
+            cjk_normalize: false,
+            postprocess: Vec::new(),
+            strip_running_headers: false,
+            frontmatter: false,
+            format: OutputFormat::default(),
+            append: false,
+            extract_tables: None,
+            toc: None,
+            stdin_kind: None,
+            on_empty_ocr: EmptyOcrPolicy::default(),
+            skip_if_exists: false,
+            cancel: CancellationToken::new(),
+            progress: None,
+        }
+    }
+}
+
+impl ProcessOptions {
+    fn writer(&self, path: &Path) -> Result<StreamingWriter> {
+        if self.append {
+            StreamingWriter::append(path)
+        } else {
+            StreamingWriter::create(path)
+        }
+    }
+
+    // The file this conversion would produce (the OCR sidecar when only that
+    // is emitted), if `skip_if_exists` applies and it is already up to date.
+    fn up_to_date_output(&self, input_path: &Path, output_path: &Path) -> Option<PathBuf> {
+        if !self.skip_if_exists || self.append || is_stdin(input_path) || is_stdout(output_path) {
+            return None;
+        }
+        let produced = if self.emit.contains(&Emit::Markdown) {
+            output_path.to_path_buf()
+        } else {
+            ocr_sidecar_path(output_path)
+        };
+        is_up_to_date(input_path, &produced).then_some(produced)
+    }
+
+    fn report(&self, event: ProgressEvent) {
+        if let Some(progress) = &self.progress {
+            progress.report(event);
+        }
+    }
+
+    fn frontmatter_for(
+        &self,
+        source: &Path,
+        ocr_model: Option<&str>,
+        llm_client: &LlmClient,
+        trace_id: &str,
+    ) -> Option<String> {
+        (self.frontmatter && self.format == OutputFormat::Md).then(|| {
+            build_frontmatter(&ConversionMetadata::new(
+                source,
+                ocr_model,
+                llm_client.model(),
+                trace_id,
+                SystemTime::now(),
+            ))
+        })
+    }
+}
+
+// Clients for converting many files: one `HttpEngine` (connection pool,
+// limiter and host cooldowns) and OCR client shared by every conversion,
+// with an `LlmClient` built per conversion from the LLM config chain.
+// Cloning is cheap and keeps the shared engine.
+#[derive(Clone)]
+pub struct Pipeline {
+    http: HttpEngine,
+    runtime: RuntimeConfig,
+    ocr_client: GlmOcrClient,
+    llm_cfgs: Vec<LlmConfig>,
+}
+
+impl Pipeline {
+    pub fn new(glm_cfg: GlmConfig, runtime: RuntimeConfig) -> Result<Self> {
+        let http = HttpEngine::new(runtime.clone())?;
+        let ocr_client = GlmOcrClient::new(http.clone(), glm_cfg)
+            .with_cache(OcrCache::from_runtime(&runtime))
+            .with_single_flight(runtime.ocr_dedup.then(SingleFlight::default));
+        Ok(Self {
+            http,
+            runtime,
+            ocr_client,
+            llm_cfgs: Vec::new(),
+        })
+    }
+
+    // The LLM configs to structure with, in fallback order.
+    pub fn with_llm_configs(mut self, llm_cfgs: Vec<LlmConfig>) -> Self {
+        self.llm_cfgs = llm_cfgs;
+        self
+    }
+
+    pub fn ocr_client(&self) -> &GlmOcrClient {
+        &self.ocr_client
+    }
+
+    pub fn llm_client(&self, llm_cfg: &LlmConfig) -> LlmClient {
+        LlmClient::new(self.http.clone(), llm_cfg.clone(), self.runtime.clone())
+    }
+
+    pub async fn convert(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        trace_id: &str,
+    ) -> Result<usize> {
+        self.convert_with(
+            input_path,
+            output_path,
+            &ProcessOptions::default(),
+            trace_id,
+        )
+        .await
+    }
+
+    // OCRs once, then tries each LLM config in order. Only provider-side
+    // failures (see `is_provider_failure`) move on to the next config; the
+    // returned index says which one produced the Markdown.
+    pub async fn convert_with(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+        options: &ProcessOptions,
+        trace_id: &str,
+    ) -> Result<usize> {
+        if self.llm_cfgs.is_empty() {
+            return Err(AppError::InvalidConfig("no LLM config to try".to_string()).into());
+        }
+
+        let OcrOutcome::Text(ocr_text) =
+            ocr_stage(input_path, output_path, &self.ocr_client, options, trace_id).await?
+        else {
+            return Ok(0);
+        };
+
+        let mut tried = Vec::new();
+        for (index, llm_cfg) in self.llm_cfgs.iter().enumerate() {
+            tried.push(format!("{:?}/{}", llm_cfg.provider, llm_cfg.model));
+            let llm_client = self.llm_client(llm_cfg);
+            let header = options.frontmatter_for(
+                input_path,
+                Some(self.ocr_client.ocr_model()),
+                &llm_client,
+                trace_id,
+            );
+            match markdown_stage(
+                &llm_client,
+                ocr_text.clone(),
+                header.as_deref(),
+                output_path,
+                options,
+                trace_id,
+            )
+            .await
+            {
+                Ok(()) => {
+                    if index > 0 {
+                        info!(llm = %tried[index], trace_id, "llm_fallback_succeeded");
+                    }
+                    return Ok(index);
+                }
+                Err(err) if index + 1 < self.llm_cfgs.len() && is_provider_failure(&err) => {
+                    warn!(llm = %tried[index], error = %err, trace_id, "llm_fallback_next");
+                }
+                Err(err) if tried.len() > 1 => {
+                    return Err(err.context(format!("LLM profiles tried: {}", tried.join(", "))));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        unreachable!("the last LLM config always returns")
+    }
+}
+
+pub fn ocr_sidecar_path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("ocr.txt")
+}
+
+// An output strictly newer than its input means the input was already
+// converted. A missing output, or an mtime that cannot be read on either
+// side, counts as out of date so the file is converted again.
+pub fn is_up_to_date(input: &Path, output: &Path) -> bool {
+    let modified =
+        |path: &Path| -> Option<SystemTime> { std::fs::metadata(path).ok()?.modified().ok() };
+    match (modified(input), modified(output)) {
+        (Some(input), Some(output)) => output > input,
+        _ => false,
+    }
+}
+
+// Reads the file, converts it with `convert_bytes` and writes the Markdown.
+// `process_file_with` takes the same route through `ProcessOptions` for
+// everything else (sidecars, streaming, frontmatter, ...).
 pub async fn process_file(
     input_path: &Path,
     output_path: &Path,
@@ -16,50 +314,628 @@ pub async fn process_file(
     llm_cfg: LlmConfig,
     runtime: RuntimeConfig,
     trace_id: &str,
+) -> Result<()> {
+    let bytes = read_file(input_path).await?;
+    let kind = detect_input_kind_from_bytes(input_path, &bytes)?;
+    let markdown = convert_bytes(kind, &bytes, glm_cfg, llm_cfg, runtime, trace_id).await?;
+
+    let mut writer = StreamingWriter::create(output_path)?;
+    writer.write_chunk(&markdown)?;
+    let bytes = writer.finish()?;
+    info!(output = %output_path.display(), bytes, trace_id, "pipeline_done");
+    Ok(())
+}
+
+// OCR and structuring of a document already in memory, for library callers
+// such as a web service; nothing is read from or written to disk (the OCR
+// cache aside, when `runtime` enables it). Returns the Markdown.
+pub async fn convert_bytes(
+    kind: InputKind,
+    bytes: &[u8],
+    glm_cfg: GlmConfig,
+    llm_cfg: LlmConfig,
+    runtime: RuntimeConfig,
+    trace_id: &str,
+) -> Result<String> {
+    let pipeline = Pipeline::new(glm_cfg, runtime)?;
+    let ocr_client = pipeline.ocr_client();
+    let ocr_text = with_stage_timeout(
+        "ocr",
+        ocr_client.stage_timeout_ms(),
+        ocr_client.extract_text(kind, bytes, trace_id),
+    )
+    .await?;
+    if ocr_text.trim().is_empty() {
+        warn!(trace_id, "ocr_output_empty");
+    }
+
+    let llm_client = pipeline.llm_client(&llm_cfg);
+    let (ocr_text, page_cap_notice) = split_page_cap_notice(&ocr_text);
+    let result = with_stage_timeout(
+        "llm",
+        llm_client.stage_timeout_ms(),
+        llm_client.to_markdown(ocr_text, trace_id),
+    )
+    .await?;
+    log_usage(&llm_client, result.usage, trace_id);
+
+    let mut markdown = result.markdown;
+    if let Some(notice) = page_cap_notice {
+        markdown.push_str(&format!("\n\n{notice}\n"));
+    }
+    Ok(markdown)
+}
+
+pub async fn process_file_with(
+    input_path: &Path,
+    output_path: &Path,
+    glm_cfg: GlmConfig,
+    llm_cfg: LlmConfig,
+    runtime: RuntimeConfig,
+    options: &ProcessOptions,
+    trace_id: &str,
 ) -> Result<()> {
     info!(
-        input = %input_path.display(),
-        output = %output_path.display(),
         provider = ?llm_cfg.provider,
+        glm_base_url = %glm_cfg.base_url,
+        glm_ocr_url = %glm_cfg.ocr_url,
         trace_id,
-        "pipeline_start"
+        "ocr_config_loaded"
     );
 
-    let file_bytes = fs::read(input_path)
+    Pipeline::new(glm_cfg, runtime)?
+        .with_llm_configs(vec![llm_cfg])
+        .convert_with(input_path, output_path, options, trace_id)
         .await
-        .with_context(|| format!("failed to read input file: {}", input_path.display()))?;
+        .map(|_| ())
+}
 
-    let http = HttpEngine::new(runtime.clone())?;
+// Same as `process_file_with`, but with caller-owned clients so a batch run
+// shares one `HttpEngine` (and its limiter and host cooldowns) across files.
+pub async fn process_file_using(
+    input_path: &Path,
+    output_path: &Path,
+    ocr_client: &GlmOcrClient,
+    llm_client: &LlmClient,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<()> {
+    let OcrOutcome::Text(ocr_text) =
+        ocr_stage(input_path, output_path, ocr_client, options, trace_id).await?
+    else {
+        return Ok(());
+    };
+    let header = options.frontmatter_for(
+        input_path,
+        Some(ocr_client.ocr_model()),
+        llm_client,
+        trace_id,
+    );
+    markdown_stage(
+        llm_client,
+        ocr_text,
+        header.as_deref(),
+        output_path,
+        options,
+        trace_id,
+    )
+    .await
+}
+
+// Dry run: OCR only, with the (already length-capped) text written to the
+// `.ocr.txt` sidecar of `output_path`, whose path is returned; `None` when
+// the input was skipped. No LLM client or credentials needed.
+pub async fn ocr_only(
+    input_path: &Path,
+    output_path: &Path,
+    ocr_client: &GlmOcrClient,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<Option<PathBuf>> {
+    let options = ProcessOptions {
+        emit: vec![Emit::Ocr],
+        ..options.clone()
+    };
+    match ocr_stage(input_path, output_path, ocr_client, &options, trace_id).await? {
+        OcrOutcome::Skipped => Ok(None),
+        _ => Ok(Some(ocr_sidecar_path(output_path))),
+    }
+}
+
+// OCRs once, then tries each LLM config in order; see `Pipeline::convert_with`.
+pub async fn process_file_with_fallback(
+    input_path: &Path,
+    output_path: &Path,
+    glm_cfg: GlmConfig,
+    llm_cfgs: &[LlmConfig],
+    runtime: RuntimeConfig,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<usize> {
+    Pipeline::new(glm_cfg, runtime)?
+        .with_llm_configs(llm_cfgs.to_vec())
+        .convert_with(input_path, output_path, options, trace_id)
+        .await
+}
+
+// Failures another provider might not share: server errors, rate limits,
+// rejected credentials and unreachable or stalled endpoints.
+pub fn is_provider_failure(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<AppError>() {
+        Some(AppError::ApiStatus { status, .. }) => {
+            return *status >= 500 || matches!(status, 401 | 403 | 429);
+        }
+        Some(AppError::DeadlineExceeded { .. } | AppError::StageTimeout { .. }) => return true,
+        _ => {}
+    }
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|cause| cause.is_connect() || cause.is_timeout())
+}
+
+enum OcrOutcome {
+    // Text for the LLM stage.
+    Text(String),
+    // Only the OCR sidecar was requested, and it is written.
+    SidecarOnly,
+    // Empty OCR output under `EmptyOcrPolicy::Skip`, or an up-to-date output
+    // under `skip_if_exists`; nothing is written.
+    Skipped,
+}
 
+async fn ocr_stage(
+    input_path: &Path,
+    output_path: &Path,
+    ocr_client: &GlmOcrClient,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<OcrOutcome> {
     info!(
-        glm_base_url = %glm_cfg.base_url,
-        glm_ocr_url = %glm_cfg.ocr_url,
+        input = %input_path.display(),
+        output = %output_path.display(),
         trace_id,
-        "ocr_config_loaded"
+        "pipeline_start"
     );
 
-    let ocr_client = GlmOcrClient::new(http.clone(), glm_cfg);
-    let ocr_text = ocr_client
-        .extract_text(input_path, &file_bytes, trace_id)
-        .await?;
+    if options.cancel.is_cancelled() {
+        return Err(AppError::Cancelled.into());
+    }
+
+    if let Some(existing) = options.up_to_date_output(input_path, output_path) {
+        info!(output = %existing.display(), trace_id, "output_up_to_date_skipped");
+        options.report(ProgressEvent::Skipped {
+            reason: "output is up to date".to_string(),
+        });
+        return Ok(OcrOutcome::Skipped);
+    }
+
+    options.report(ProgressEvent::Reading);
+    let (kind, file_bytes) = read_input(input_path, options).await?;
 
+    options.report(ProgressEvent::OcrStarted);
+    let ocr_text = cancellable(
+        &options.cancel,
+        with_stage_timeout(
+            "ocr",
+            ocr_client.stage_timeout_ms(),
+            ocr_client.extract_text(kind, &file_bytes, trace_id),
+        ),
+    )
+    .await?;
+    options.report(ProgressEvent::OcrDone {
+        chars: ocr_text.chars().count(),
+    });
+
+    if !options.on_empty_ocr.proceeds(&ocr_text)? {
+        warn!(trace_id, "ocr_output_empty_skipped");
+        options.report(ProgressEvent::Skipped {
+            reason: "OCR found no text".to_string(),
+        });
+        return Ok(OcrOutcome::Skipped);
+    }
     if ocr_text.trim().is_empty() {
         warn!(trace_id, "ocr_output_empty");
     }
 
-    let llm_client = LlmClient::new(http, llm_cfg, runtime);
-    let markdown = llm_client.to_markdown(&ocr_text, trace_id).await?;
+    if options.emit.contains(&Emit::Ocr) {
+        let ocr_path = ocr_sidecar_path(output_path);
+        let mut writer = options.writer(&ocr_path)?;
+        writer.write_chunk(&ocr_text)?;
+        let bytes = writer.finish()?;
+        info!(output = %ocr_path.display(), bytes, trace_id, "ocr_text_written");
+        if !options.emit.contains(&Emit::Markdown) {
+            options.report(ProgressEvent::Written { bytes });
+        }
+    }
+
+    if !options.emit.contains(&Emit::Markdown) {
+        info!(trace_id, "llm_stage_skipped");
+        return Ok(OcrOutcome::SidecarOnly);
+    }
+    Ok(OcrOutcome::Text(ocr_text))
+}
+
+// Stdin is read whole and takes the sniffed or declared kind, since `-` has
+// no extension to go by.
+async fn read_input(input_path: &Path, options: &ProcessOptions) -> Result<(InputKind, Vec<u8>)> {
+    if !is_stdin(input_path) {
+        let bytes = read_file(input_path).await?;
+        return Ok((detect_input_kind_from_bytes(input_path, &bytes)?, bytes));
+    }
+    let mut bytes = Vec::new();
+    tokio::io::stdin()
+        .read_to_end(&mut bytes)
+        .await
+        .context("failed to read input from stdin")?;
+    Ok((detect_stdin_kind(&bytes, options.stdin_kind)?, bytes))
+}
 
-    fs::write(output_path, markdown.as_bytes())
+async fn read_file(input_path: &Path) -> Result<Vec<u8>> {
+    fs::read(input_path)
         .await
-        .with_context(|| format!("failed to write output: {}", output_path.display()))?;
+        .with_context(|| format!("failed to read input file: {}", input_path.display()))
+}
+
+async fn markdown_stage(
+    llm_client: &LlmClient,
+    ocr_text: String,
+    header: Option<&str>,
+    output_path: &Path,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<()> {
+    options.report(ProgressEvent::LlmStarted);
+    let bytes = cancellable(
+        &options.cancel,
+        with_stage_timeout(
+            "llm",
+            llm_client.stage_timeout_ms(),
+            write_markdown(llm_client, ocr_text, header, output_path, options, trace_id),
+        ),
+    )
+    .await?;
+    options.report(ProgressEvent::Written { bytes });
 
     info!(
         output = %output_path.display(),
-        bytes = markdown.len(),
+        bytes,
         trace_id,
         "pipeline_done"
     );
 
     Ok(())
 }
+
+#[derive(Debug, Default)]
+pub struct RestructureReport {
+    pub written: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+    pub missing_sidecars: Vec<PathBuf>,
+}
+
+// Regenerates Markdown from saved `.ocr.txt` sidecars without touching OCR.
+// `.md` files in `ocr_dir` without a sidecar are reported, not fatal.
+pub async fn restructure_dir(
+    ocr_dir: &Path,
+    output_dir: &Path,
+    llm_cfg: LlmConfig,
+    runtime: RuntimeConfig,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<RestructureReport> {
+    let mut sidecars = Vec::new();
+    let mut markdown_files = Vec::new();
+    let mut entries = fs::read_dir(ocr_dir)
+        .await
+        .with_context(|| format!("failed to read OCR directory: {}", ocr_dir.display()))?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(stem) = name.strip_suffix(".ocr.txt") {
+            sidecars.push((stem.to_string(), path.clone()));
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            markdown_files.push(path.clone());
+        }
+    }
+    sidecars.sort();
+    markdown_files.sort();
+
+    let mut report = RestructureReport::default();
+    for markdown in markdown_files {
+        if !ocr_sidecar_path(&markdown).exists() {
+            warn!(markdown = %markdown.display(), trace_id, "ocr_sidecar_missing");
+            report.missing_sidecars.push(markdown);
+        }
+    }
+
+    fs::create_dir_all(output_dir)
+        .await
+        .with_context(|| format!("failed to create output dir: {}", output_dir.display()))?;
+
+    let http = HttpEngine::new(runtime.clone())?;
+    let llm_client = LlmClient::new(http, llm_cfg, runtime);
+    for (index, (stem, sidecar)) in sidecars.into_iter().enumerate() {
+        let output_path = output_dir.join(format!("{stem}.{}", options.format.extension()));
+        let file_trace = format!("{trace_id}-{index}");
+        let result = async {
+            let ocr_text = fs::read_to_string(&sidecar)
+                .await
+                .with_context(|| format!("failed to read sidecar: {}", sidecar.display()))?;
+            let header = options.frontmatter_for(&sidecar, None, &llm_client, &file_trace);
+            with_stage_timeout(
+                "llm",
+                llm_client.stage_timeout_ms(),
+                write_markdown(
+                    &llm_client,
+                    ocr_text,
+                    header.as_deref(),
+                    &output_path,
+                    options,
+                    &file_trace,
+                ),
+            )
+            .await
+        }
+        .await;
+
+        match result {
+            Ok(bytes) => {
+                info!(output = %output_path.display(), bytes, trace_id = %file_trace, "restructure_done");
+                report.written.push(output_path);
+            }
+            Err(err) => {
+                warn!(sidecar = %sidecar.display(), error = %err, trace_id = %file_trace, "restructure_failed");
+                report.failed.push((sidecar, format!("{err:#}")));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// OCRs the whole input once, then structures each page section separately so
+// every section carries its own confidence score.
+pub async fn process_sections(
+    input_path: &Path,
+    glm_cfg: GlmConfig,
+    llm_cfg: LlmConfig,
+    runtime: RuntimeConfig,
+    trace_id: &str,
+) -> Result<SectionedDocument> {
+    let file_bytes = read_file(input_path).await?;
+    let kind = detect_input_kind_from_bytes(input_path, &file_bytes)?;
+
+    let http = HttpEngine::new(runtime.clone())?;
+    let ocr_client = GlmOcrClient::new(http.clone(), glm_cfg);
+    let ocr_text = with_stage_timeout(
+        "ocr",
+        ocr_client.stage_timeout_ms(),
+        ocr_client.extract_text(kind, &file_bytes, trace_id),
+    )
+    .await?;
+    let llm_client = LlmClient::new(http, llm_cfg, runtime);
+
+    let mut document = SectionedDocument::from_ocr_text(&ocr_text);
+    for (index, section) in document.sections.iter_mut().enumerate() {
+        let section_trace = format!("{trace_id}-s{index}");
+        let markdown = with_stage_timeout(
+            "llm",
+            llm_client.stage_timeout_ms(),
+            llm_client.to_markdown(&section.ocr_text, &section_trace),
+        )
+        .await?
+        .markdown;
+        *section = section.clone().with_markdown(markdown);
+    }
+    Ok(document)
+}
+
+// Re-OCRs only the pages behind `indices` and merges the restructured
+// sections back in place; untouched sections keep their Markdown.
+pub async fn rerun_sections(
+    input_path: &Path,
+    document: &mut SectionedDocument,
+    indices: &[usize],
+    glm_cfg: GlmConfig,
+    llm_cfg: LlmConfig,
+    runtime: RuntimeConfig,
+    trace_id: &str,
+) -> Result<()> {
+    let file_bytes = read_file(input_path).await?;
+    let kind = detect_input_kind_from_bytes(input_path, &file_bytes)?;
+
+    let http = HttpEngine::new(runtime.clone())?;
+    let ocr_client = GlmOcrClient::new(http.clone(), glm_cfg);
+    let llm_client = LlmClient::new(http, llm_cfg, runtime);
+
+    for &index in indices {
+        let pages = document
+            .sections
+            .get(index)
+            .map(|section| section.pages.clone())
+            .ok_or_else(|| AppError::InvalidConfig(format!("section {index} out of range")))?;
+
+        let bytes = match (pages.is_empty(), kind == InputKind::Pdf) {
+            (true, _) => file_bytes.clone(),
+            (false, true) => pdf::keep_pages(&file_bytes, &pages)?,
+            (false, false) => {
+                return Err(AppError::InvalidConfig(
+                    "re-running a single page section requires a PDF input".to_string(),
+                )
+                .into());
+            }
+        };
+
+        let section_trace = format!("{trace_id}-s{index}");
+        let ocr_text = with_stage_timeout(
+            "ocr",
+            ocr_client.stage_timeout_ms(),
+            ocr_client.extract_text(kind, &bytes, &section_trace),
+        )
+        .await?;
+        let section = Section::new(pages, ocr_text);
+        let markdown = with_stage_timeout(
+            "llm",
+            llm_client.stage_timeout_ms(),
+            llm_client.to_markdown(&section.ocr_text, &section_trace),
+        )
+        .await?
+        .markdown;
+        let section = section.with_markdown(markdown);
+        info!(
+            index,
+            confidence = section.confidence,
+            trace_id = %section_trace,
+            "section_rerun_done"
+        );
+        document.merge(index, section)?;
+    }
+    Ok(())
+}
+
+// Dropping the stage future aborts any in-flight HTTP request, and a
+// half-written output is discarded by `StreamingWriter`'s drop.
+async fn cancellable<T>(
+    cancel: &CancellationToken,
+    stage: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        _ = cancel.cancelled() => Err(AppError::Cancelled.into()),
+        result = stage => result,
+    }
+}
+
+// Bounds a whole stage, however many requests it makes; the per-request
+// timeout and retries of `HttpEngine` still apply inside it.
+async fn with_stage_timeout<T>(
+    stage: &str,
+    timeout_ms: u64,
+    future: impl std::future::Future<Output = Result<T>>,
+) -> Result<T> {
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), future).await {
+        Ok(result) => result,
+        Err(_) => {
+            warn!(stage, timeout_ms, "stage_timeout");
+            Err(AppError::StageTimeout {
+                stage: stage.to_string(),
+                timeout_ms,
+            }
+            .into())
+        }
+    }
+}
+
+fn log_usage(llm_client: &LlmClient, usage: Option<TokenUsage>, trace_id: &str) {
+    let Some(usage) = usage else {
+        info!(trace_id, "llm_usage_unreported");
+        return;
+    };
+    info!(
+        trace_id,
+        input_tokens = usage.input_tokens,
+        output_tokens = usage.output_tokens,
+        total_tokens = usage.total(),
+        estimated_cost_usd = llm_client.estimated_cost(&usage),
+        "llm_token_usage"
+    );
+}
+
+async fn write_markdown(
+    llm_client: &LlmClient,
+    ocr_text: String,
+    header: Option<&str>,
+    output_path: &Path,
+    options: &ProcessOptions,
+    trace_id: &str,
+) -> Result<usize> {
+    let (ocr_text, page_cap_notice) = split_page_cap_notice(&ocr_text);
+
+    // Only the copy sent to the remote LLM is cleaned up and redacted; the
+    // OCR sidecar keeps the original text.
+    let stripped;
+    let ocr_text = if options.strip_running_headers {
+        stripped = running_headers::strip(ocr_text);
+        info!(
+            trace_id,
+            removed_chars = ocr_text.len() - stripped.len(),
+            "running_headers_stripped"
+        );
+        stripped.as_str()
+    } else {
+        ocr_text
+    };
+    let llm_input = match &options.redact {
+        Some(redactor) => {
+            let redacted = redactor.redact(ocr_text);
+            info!(trace_id, changed = redacted != ocr_text, "pii_redacted");
+            redacted
+        }
+        None => ocr_text.to_string(),
+    };
+
+    // Post-passes need the whole document, so they turn streaming off.
+    let mut writer = options.writer(output_path)?;
+    if let Some(header) = header {
+        writer.write_chunk(header)?;
+    }
+    // Streamed chunks are only kept when the tables or the outline need the
+    // whole text.
+    let keep_streamed = options.extract_tables.is_some() || options.toc.is_some();
+    let inline_toc = options.toc == Some(TocTarget::Inline) && options.format == OutputFormat::Md;
+    let mut markdown = String::new();
+    if options.stream
+        && !options.cjk_normalize
+        && options.postprocess.is_empty()
+        && !inline_toc
+        && options.format == OutputFormat::Md
+    {
+        let mut write_error = None;
+        let result = llm_client
+            .to_markdown_streaming(&llm_input, trace_id, |chunk| {
+                if keep_streamed {
+                    markdown.push_str(chunk);
+                }
+                if write_error.is_none() {
+                    write_error = writer.write_chunk(chunk).err();
+                }
+            })
+            .await?;
+        if let Some(err) = write_error {
+            return Err(err);
+        }
+        log_usage(llm_client, result.usage, trace_id);
+    } else {
+        let result = llm_client.to_markdown(&llm_input, trace_id).await?;
+        log_usage(llm_client, result.usage, trace_id);
+        markdown = result.markdown;
+        if options.cjk_normalize {
+            markdown = cjk::normalize(&markdown);
+        }
+        markdown = postprocess::apply(&options.postprocess, markdown);
+        if inline_toc {
+            writer.write_chunk(&toc::to_markdown(&toc::headings(&markdown)))?;
+        }
+        writer.write_chunk(&options.format.render(&markdown))?;
+    }
+    if let Some(notice) = page_cap_notice {
+        writer.write_chunk(&format!("\n\n{notice}\n"))?;
+    }
+    let bytes = writer.finish()?;
+
+    if let Some(dir) = &options.extract_tables {
+        let stem = output_path
+            .file_stem()
+            .filter(|_| !is_stdout(output_path))
+            .map_or_else(|| "output".into(), |stem| stem.to_string_lossy());
+        let written = tables::write_csvs(&markdown, dir, &stem)?;
+        info!(dir = %dir.display(), tables = written.len(), trace_id, "tables_extracted");
+    }
+    if let Some(TocTarget::Json(path)) = &options.toc {
+        let headings = toc::headings(&markdown);
+        toc::write_json(&headings, path)?;
+        info!(path = %path.display(), headings = headings.len(), trace_id, "toc_written");
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,154 @@
+use clap::ValueEnum;
+
+// One cleanup pass over the finished Markdown. Each is pure, so passes
+// compose in any order and can be tested on their own.
+pub type MarkdownTransform = fn(String) -> String;
+
+// Built-in passes, picked by name with `--postprocess` and run in the order
+// given. None run by default. Fenced code blocks are left as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Postprocess {
+    TrimTrailing,
+    CollapseBlanks,
+    NormalizeHeadings,
+    HalfwidthPunctuation,
+}
+
+impl Postprocess {
+    pub fn transform(self) -> MarkdownTransform {
+        match self {
+            Self::TrimTrailing => trim_trailing,
+            Self::CollapseBlanks => collapse_blanks,
+            Self::NormalizeHeadings => normalize_headings,
+            Self::HalfwidthPunctuation => halfwidth_punctuation,
+        }
+    }
+}
+
+pub fn apply(steps: &[Postprocess], markdown: String) -> String {
+    steps
+        .iter()
+        .fold(markdown, |markdown, step| step.transform()(markdown))
+}
+
+// Drops spaces and tabs at line ends, which also drops Markdown's
+// two-space hard line breaks.
+pub fn trim_trailing(markdown: String) -> String {
+    map_lines(&markdown, |lines| {
+        lines
+            .into_iter()
+            .map(|(line, code)| if code { line } else { line.trim_end() })
+            .collect()
+    })
+}
+
+// At most one blank line between blocks.
+pub fn collapse_blanks(markdown: String) -> String {
+    map_lines(&markdown, |lines| {
+        let mut out: Vec<&str> = Vec::with_capacity(lines.len());
+        let mut blank = false;
+        for (line, code) in lines {
+            let is_blank = !code && line.trim().is_empty();
+            if !(is_blank && blank) {
+                out.push(line);
+            }
+            blank = is_blank;
+        }
+        out
+    })
+}
+
+// `##Title` and `##   Title` become `## Title`, with a blank line before
+// and after each heading.
+pub fn normalize_headings(markdown: String) -> String {
+    map_lines(&markdown, |lines| {
+        let fixed: Vec<(String, bool)> = lines
+            .into_iter()
+            .map(|(line, code)| match heading(line).filter(|_| !code) {
+                Some((hashes, text)) => (format!("{hashes} {text}"), true),
+                None => (line.to_string(), false),
+            })
+            .collect();
+        let mut out: Vec<String> = Vec::with_capacity(fixed.len());
+        for (index, (line, is_heading)) in fixed.iter().enumerate() {
+            if *is_heading && out.last().is_some_and(|last| !last.trim().is_empty()) {
+                out.push(String::new());
+            }
+            out.push(line.clone());
+            let next = fixed.get(index + 1);
+            if *is_heading && next.is_some_and(|(next, _)| !next.trim().is_empty()) {
+                out.push(String::new());
+            }
+        }
+        out
+    })
+}
+
+fn heading(line: &str) -> Option<(&str, &str)> {
+    let level = line.chars().take_while(|&ch| ch == '#').count();
+    let text = line[level..].trim();
+    ((1..=6).contains(&level) && !text.is_empty()).then(|| (&line[..level], text))
+}
+
+// Full-width ASCII forms (`，：（Ａ１）`) and the ideographic space folded to
+// their ASCII counterparts, for documents that should read as plain ASCII.
+pub fn halfwidth_punctuation(markdown: String) -> String {
+    map_lines(&markdown, |lines| {
+        lines
+            .into_iter()
+            .map(|(line, code)| {
+                if code {
+                    line.to_string()
+                } else {
+                    line.chars().map(to_halfwidth).collect()
+                }
+            })
+            .collect::<Vec<String>>()
+    })
+}
+
+fn to_halfwidth(ch: char) -> char {
+    match ch {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+        _ => ch,
+    }
+}
+
+// Runs `f` over the lines of `markdown`, each paired with whether it belongs
+// to a fenced code block (fences included), and joins the result back with
+// the original trailing newline.
+fn map_lines<'a, L: AsRef<str>>(
+    markdown: &'a str,
+    f: impl FnOnce(Vec<(&'a str, bool)>) -> Vec<L>,
+) -> String {
+    let mut fence: Option<&str> = None;
+    let mut lines = Vec::new();
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        let code = match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                true
+            }
+            None => {
+                fence = ["```", "~~~"]
+                    .into_iter()
+                    .find(|marker| trimmed.starts_with(marker));
+                fence.is_some()
+            }
+        };
+        lines.push((line, code));
+    }
+    let mut out = f(lines)
+        .iter()
+        .map(AsRef::as_ref)
+        .collect::<Vec<&str>>()
+        .join("\n");
+    if markdown.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
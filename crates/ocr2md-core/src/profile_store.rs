@@ -1,11 +1,21 @@
-use crate::secure_config::{decrypt_blob, encrypt_blob};
+use crate::config::LlmProvider;
+use crate::error::AppError;
+use crate::secret_backend::{FileSecrets, SecretBackend};
+use crate::secure_config::{DecryptError, decrypt_blob, encrypt_blob};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
 
 const STORE_VERSION: u8 = 1;
 
+pub const EXPORT_WARNING: &str =
+    "This file contains API keys in cleartext. Keep it private and delete it after importing.";
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ProviderProfile {
     pub name: String,
@@ -15,6 +25,12 @@ pub struct ProviderProfile {
     pub model: String,
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    // Extra request headers for relays that route on them; see
+    // `LlmConfig::with_extra_headers`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_headers: BTreeMap<String, String>,
 }
 
 fn default_enabled() -> bool {
@@ -30,6 +46,8 @@ impl ProviderProfile {
             api_key: api_key.to_string(),
             model: model.to_string(),
             enabled: true,
+            system_prompt: None,
+            extra_headers: BTreeMap::new(),
         }
     }
 }
@@ -37,38 +55,176 @@ impl ProviderProfile {
 #[derive(Debug, Clone)]
 pub struct ProfileStore {
     path: PathBuf,
+    secrets: Arc<dyn SecretBackend>,
 }
 
 impl ProfileStore {
     pub fn new(path: impl Into<PathBuf>) -> Self {
-        Self { path: path.into() }
+        Self {
+            path: path.into(),
+            secrets: Arc::new(FileSecrets),
+        }
+    }
+
+    pub fn with_secrets(mut self, secrets: Arc<dyn SecretBackend>) -> Self {
+        self.secrets = secrets;
+        self
     }
 
     pub fn save_all(&self, passphrase: &str, profiles: &[ProviderProfile]) -> Result<()> {
+        // Names the backend holds keys for now; unreadable under this
+        // passphrase (a passphrase change) means there is nothing to forget.
+        let previous = match self.read_envelope(passphrase) {
+            Ok(Some(envelope)) if envelope.secret_backend == self.secrets.name() => {
+                envelope.profiles
+            }
+            _ => Vec::new(),
+        };
+
+        // Keys reach the backend before the file stops holding them.
+        let mut stored = profiles.to_vec();
+        self.secrets.stash(&mut stored)?;
         let payload = StoreEnvelope {
             version: STORE_VERSION,
-            profiles: profiles.to_vec(),
+            secret_backend: self.secrets.name().to_string(),
+            profiles: stored,
         };
         let plain = serde_json::to_vec(&payload).context("failed to serialize profiles")?;
         let ciphertext = encrypt_blob(&plain, passphrase).context("failed to encrypt profiles")?;
 
+        // Write-then-rename so a crash mid-save leaves the previous store intact.
+        let tmp = self.write_temp(&ciphertext)?;
+        replace_file(&tmp, &self.path).context("failed to replace encrypted profile store")?;
+        sync_parent_dir(&self.path);
+
+        let removed: Vec<String> = previous
+            .into_iter()
+            .map(|profile| profile.name)
+            .filter(|name| !profiles.iter().any(|profile| &profile.name == name))
+            .collect();
+        self.secrets.forget(&removed)
+    }
+
+    // The new store is on disk, fsynced, next to the old one before the
+    // rename makes it visible.
+    fn write_temp(&self, ciphertext: &[u8]) -> Result<PathBuf> {
         if let Some(parent) = self.path.parent() {
             fs::create_dir_all(parent).context("failed to create profile directory")?;
         }
-        fs::write(&self.path, ciphertext).context("failed to write encrypted profile store")?;
-        Ok(())
+        let tmp = self.path.with_extension("enc.tmp");
+        let mut file = File::create(&tmp).context("failed to write encrypted profile store")?;
+        file.write_all(ciphertext)
+            .context("failed to write encrypted profile store")?;
+        file.sync_all()
+            .context("failed to sync encrypted profile store")?;
+        Ok(tmp)
     }
 
-    pub fn load_all(&self, passphrase: &str) -> Result<Vec<ProviderProfile>> {
+    // Re-encrypts the stored profiles under `new` with a fresh salt and nonce.
+    // A wrong `old` surfaces as `AppError::WrongPassphrase`.
+    pub fn change_passphrase(&self, old: &str, new: &str) -> Result<()> {
         if !self.path.exists() {
+            return Err(AppError::InvalidConfig(format!(
+                "no profile store at {}",
+                self.path.display()
+            ))
+            .into());
+        }
+        let profiles = self.load_all(old)?;
+        self.save_all(new, &profiles)
+    }
+
+    // Plain JSON copy of the profiles for backup or editing. API keys are in
+    // cleartext; the document carries `EXPORT_WARNING` to say so.
+    pub fn export_json(&self, passphrase: &str) -> Result<String> {
+        let export = ProfileExport {
+            warning: Some(EXPORT_WARNING.to_string()),
+            version: STORE_VERSION,
+            profiles: self.load_all(passphrase)?,
+        };
+        serde_json::to_string_pretty(&export).context("failed to serialize profiles")
+    }
+
+    // Replaces the stored profiles with those in `json` (an export document or
+    // a bare array), encrypted under `passphrase`. Nothing is written unless
+    // every entry names a known provider.
+    pub fn import_json(&self, passphrase: &str, json: &str) -> Result<Vec<ProviderProfile>> {
+        let profiles = match serde_json::from_str(json).context("failed to parse profile JSON")? {
+            ImportDocument::Export(export) => export.profiles,
+            ImportDocument::Profiles(profiles) => profiles,
+        };
+        for (index, profile) in profiles.iter().enumerate() {
+            if profile.provider.trim().is_empty() {
+                return Err(AppError::InvalidConfig(format!(
+                    "profile {} ({}) has no provider",
+                    index + 1,
+                    profile.name
+                ))
+                .into());
+            }
+            LlmProvider::from_str(profile.provider.trim())
+                .with_context(|| format!("profile {} ({}) is invalid", index + 1, profile.name))?;
+        }
+        self.save_all(passphrase, &profiles)?;
+        Ok(profiles)
+    }
+
+    pub fn load_all(&self, passphrase: &str) -> Result<Vec<ProviderProfile>> {
+        let Some(envelope) = self.read_envelope(passphrase)? else {
             return Ok(Vec::new());
+        };
+        if envelope.secret_backend != self.secrets.name() {
+            return Err(AppError::InvalidConfig(format!(
+                "{} keeps its API keys in the {} secret backend; set OCR2MD_SECRET_BACKEND={}",
+                self.path.display(),
+                envelope.secret_backend,
+                envelope.secret_backend
+            ))
+            .into());
+        }
+        let mut profiles = envelope.profiles;
+        self.secrets.fill(&mut profiles)?;
+        Ok(profiles)
+    }
+
+    fn read_envelope(&self, passphrase: &str) -> Result<Option<StoreEnvelope>> {
+        if !self.path.exists() {
+            return Ok(None);
         }
 
         let ciphertext = fs::read(&self.path).context("failed to read encrypted profile store")?;
-        let plain = decrypt_blob(&ciphertext, passphrase).context("failed to decrypt profiles")?;
-        let payload: StoreEnvelope =
-            serde_json::from_slice(&plain).context("failed to deserialize profiles")?;
-        Ok(payload.profiles)
+        let plain = decrypt_blob(&ciphertext, passphrase).map_err(|err| match err {
+            DecryptError::WrongPassphraseOrTampered => AppError::WrongPassphrase,
+            other => AppError::CorruptProfileStore(other.to_string()),
+        })?;
+        // The tag verified, so undecodable JSON was written that way.
+        let payload: StoreEnvelope = serde_json::from_slice(&plain)
+            .map_err(|err| AppError::CorruptProfileStore(format!("invalid profile JSON: {err}")))?;
+        Ok(Some(payload))
+    }
+}
+
+// `rename` replaces an existing file atomically on Unix. Windows may refuse
+// to rename over an existing file, so there the old one is removed first;
+// a crash in between leaves only the fsynced temp file to recover from.
+fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if cfg!(windows) && to.exists() => {
+            fs::remove_file(to).map_err(|_| err)?;
+            fs::rename(from, to)
+        }
+        result => result,
+    }
+}
+
+// Persists the rename itself. Best effort: not every platform can open or
+// sync a directory.
+fn sync_parent_dir(path: &Path) {
+    if cfg!(unix)
+        && let Some(parent) = path.parent()
+        && let Ok(dir) = File::open(parent)
+    {
+        let _ = dir.sync_all();
     }
 }
 
@@ -76,10 +232,61 @@ impl ProfileStore {
 struct StoreEnvelope {
     #[serde(default = "default_store_version")]
     version: u8,
+    // `SecretBackend::name` of the backend holding the keys; stores written
+    // before backends existed kept them inline.
+    #[serde(default = "default_secret_backend")]
+    secret_backend: String,
     #[serde(default)]
     profiles: Vec<ProviderProfile>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfileExport {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    warning: Option<String>,
+    #[serde(default = "default_store_version")]
+    version: u8,
+    profiles: Vec<ProviderProfile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ImportDocument {
+    Export(ProfileExport),
+    Profiles(Vec<ProviderProfile>),
+}
+
 fn default_store_version() -> u8 {
     STORE_VERSION
 }
+
+fn default_secret_backend() -> String {
+    FileSecrets.name().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProfileStore, ProviderProfile};
+    use crate::secure_config::encrypt_blob;
+
+    #[test]
+    fn crash_before_rename_leaves_the_old_store_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProfileStore::new(dir.path().join("profiles.enc"));
+        let old = vec![ProviderProfile::openai("old", "https://a", "k1", "m")];
+        store.save_all("pass", &old).unwrap();
+
+        // The next save gets as far as the temp file, then the process dies.
+        let tmp = store
+            .write_temp(&encrypt_blob(b"{}", "pass").unwrap())
+            .unwrap();
+        assert!(tmp.exists());
+
+        assert_eq!(store.load_all("pass").unwrap(), old);
+
+        let new = vec![ProviderProfile::openai("new", "https://b", "k2", "m")];
+        store.save_all("pass", &new).unwrap();
+        assert_eq!(store.load_all("pass").unwrap(), new);
+        assert!(!tmp.exists());
+    }
+}
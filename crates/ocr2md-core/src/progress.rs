@@ -0,0 +1,183 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::{IsTerminal, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::info;
+
+pub const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+// Milestones of one file going through the pipeline, in the order they are
+// reported. Falling back to another LLM profile repeats `LlmStarted`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Reading,
+    OcrStarted,
+    OcrDone { chars: usize },
+    LlmStarted,
+    Written { bytes: usize },
+    // Ends the file instead of `Written`; nothing was written.
+    Skipped { reason: String },
+}
+
+impl ProgressEvent {
+    // Rough share of a file's wall time spent before this point; OCR and the
+    // LLM call dominate, so each gets about half.
+    pub fn percent(&self) -> u8 {
+        match self {
+            Self::Reading => 0,
+            Self::OcrStarted => 5,
+            Self::OcrDone { .. } => 50,
+            Self::LlmStarted => 55,
+            Self::Written { .. } | Self::Skipped { .. } => 100,
+        }
+    }
+}
+
+// Callback handed to the pipeline through `ProcessOptions`.
+#[derive(Clone)]
+pub struct ProgressSink(Arc<dyn Fn(ProgressEvent) + Send + Sync>);
+
+impl ProgressSink {
+    pub fn new(callback: impl Fn(ProgressEvent) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub fn report(&self, event: ProgressEvent) {
+        (self.0)(event);
+    }
+}
+
+impl fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressSink")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchEvent {
+    Started { file: String },
+    Succeeded { file: String },
+    Failed { file: String, error: String },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchProgress {
+    pub total: usize,
+    pub succeeded: usize,
+    pub running: BTreeSet<String>,
+    pub failures: Vec<(String, String)>,
+}
+
+impl BatchProgress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            total,
+            ..Self::default()
+        }
+    }
+
+    pub fn apply(&mut self, event: BatchEvent) {
+        match event {
+            BatchEvent::Started { file } => {
+                self.running.insert(file);
+            }
+            BatchEvent::Succeeded { file } => {
+                self.running.remove(&file);
+                self.succeeded += 1;
+            }
+            BatchEvent::Failed { file, error } => {
+                self.running.remove(&file);
+                self.failures.push((file, error));
+            }
+        }
+    }
+
+    pub fn completed(&self) -> usize {
+        self.succeeded + self.failures.len()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.completed() >= self.total
+    }
+
+    pub fn summary(&self) -> String {
+        let mut line = format!("{}/{} done", self.completed(), self.total);
+        if !self.failures.is_empty() {
+            line.push_str(&format!(", {} failed", self.failures.len()));
+        }
+        if !self.running.is_empty() {
+            let running: Vec<&str> = self.running.iter().map(String::as_str).collect();
+            line.push_str(&format!("; running: {}", running.join(", ")));
+        }
+        line
+    }
+}
+
+// Renders batch progress either as one self-overwriting stderr line (TTY) or
+// as plain log lines at most once per `interval`, so concurrent jobs do not
+// interleave their per-file logs into noise.
+#[derive(Debug)]
+pub struct ProgressDisplay {
+    progress: BatchProgress,
+    live: bool,
+    interval: Duration,
+    last_report: Option<Instant>,
+}
+
+impl ProgressDisplay {
+    pub fn new(total: usize, quiet: bool) -> Self {
+        Self {
+            progress: BatchProgress::new(total),
+            live: !quiet && std::io::stderr().is_terminal(),
+            interval: DEFAULT_PROGRESS_INTERVAL,
+            last_report: None,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn progress(&self) -> &BatchProgress {
+        &self.progress
+    }
+
+    pub fn handle(&mut self, event: BatchEvent) {
+        if let BatchEvent::Failed { file, error } = &event {
+            self.clear_line();
+            info!(file, error, "batch_file_failed");
+        }
+        self.progress.apply(event);
+
+        if self.live {
+            let mut stderr = std::io::stderr();
+            let _ = write!(stderr, "\r\x1b[2K{}", self.progress.summary());
+            let _ = stderr.flush();
+        } else if self
+            .last_report
+            .is_none_or(|last| last.elapsed() >= self.interval)
+        {
+            self.last_report = Some(Instant::now());
+            info!(progress = %self.progress.summary(), "batch_progress");
+        }
+    }
+
+    pub fn finish(self) -> BatchProgress {
+        if self.live {
+            eprintln!();
+        }
+        info!(progress = %self.progress.summary(), "batch_done");
+        self.progress
+    }
+
+    fn clear_line(&self) {
+        if self.live {
+            let _ = write!(std::io::stderr(), "\r\x1b[2K");
+        }
+    }
+}
@@ -1,17 +1,73 @@
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::AppError;
 
 pub type JobId = u64;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+pub const PRIORITY_LOW: u8 = 0;
+pub const PRIORITY_NORMAL: u8 = 50;
+pub const PRIORITY_HIGH: u8 = 100;
+pub const MAX_ATTEMPT_HISTORY: usize = 20;
+const QUEUE_FILE_VERSION: u8 = 1;
+// Longest a retried job waits, however many times it has failed.
+const MAX_RETRY_BACKOFF_MS: u64 = 5 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum JobState {
     Queued,
     Running,
     Retrying,
     Failed,
     Success,
+    Cancelled,
+}
+
+impl JobState {
+    // Final states: the worker will not pick the job up again on its own.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Success | Self::Failed | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enqueued {
+    New(JobId),
+    Existing(JobId),
+}
+
+impl Enqueued {
+    pub fn id(self) -> JobId {
+        match self {
+            Self::New(id) | Self::Existing(id) => id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AttemptOutcome {
+    InProgress,
+    Success,
+    Failed { error: String },
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttemptRecord {
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub outcome: AttemptOutcome,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobRecord {
     pub id: JobId,
     pub input: String,
@@ -19,16 +75,155 @@ pub struct JobRecord {
     pub stage: String,
     pub retries: u8,
     pub error: Option<String>,
+    pub priority: u8,
+    #[serde(alias = "enqueued_at")]
+    pub created_at: u64,
+    // Set when the job reaches a final state; cleared again if it is retried.
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    #[serde(default)]
+    pub attempts: Vec<AttemptRecord>,
+    // Where the last successful run wrote its Markdown.
+    #[serde(default)]
+    pub output: Option<String>,
+    // Why a successful run wrote nothing, see `Queue::mark_skipped`.
+    #[serde(default)]
+    pub note: Option<String>,
+    // A retrying job is not served again before this time (ms since the
+    // epoch); see `Queue::set_retry_backoff`.
+    #[serde(default)]
+    pub retry_after: Option<u64>,
+}
+
+impl JobRecord {
+    // Wall time from enqueue to the final state, `None` while unfinished.
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.finished_at
+            .map(|finished| finished.saturating_sub(self.created_at))
+    }
+
+    fn begin_attempt(&mut self) {
+        if self
+            .attempts
+            .last()
+            .is_some_and(|attempt| attempt.outcome == AttemptOutcome::InProgress)
+        {
+            return;
+        }
+
+        if self.attempts.len() >= MAX_ATTEMPT_HISTORY {
+            self.attempts.remove(0);
+        }
+        self.attempts.push(AttemptRecord {
+            started_at: now_ms(),
+            finished_at: None,
+            outcome: AttemptOutcome::InProgress,
+        });
+    }
+
+    fn finish_attempt(&mut self, outcome: AttemptOutcome) {
+        if let Some(attempt) = self
+            .attempts
+            .last_mut()
+            .filter(|attempt| attempt.outcome == AttemptOutcome::InProgress)
+        {
+            attempt.finished_at = Some(now_ms());
+            attempt.outcome = outcome;
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct QueueFile {
+    version: u8,
+    next_id: JobId,
+    jobs: Vec<JobRecord>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QueueLoadReport {
+    pub recovered: usize,
+    pub lost: usize,
+    pub backup: Option<PathBuf>,
 }
 
 #[derive(Debug, Default)]
 pub struct Queue {
     next_id: JobId,
     jobs: HashMap<JobId, JobRecord>,
+    aging_secs_per_point: u64,
+    capacity: usize,
+    retry_max: u8,
+    retry_backoff_ms: u64,
 }
 
 impl Queue {
-    pub fn enqueue(&mut self, input: impl Into<String>) -> JobId {
+    // A waiting job gains one priority point every `secs_per_point` seconds so
+    // low-priority work is not starved; zero disables aging.
+    pub fn set_priority_aging(&mut self, secs_per_point: u64) {
+        self.aging_secs_per_point = secs_per_point;
+    }
+
+    // Caps how many jobs may be waiting or running at once; finished jobs do
+    // not count. Zero means no limit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    // How many times a failed run is put back in the queue before the job
+    // fails for good. These are whole-job reruns, separate from the HTTP
+    // retries `HttpEngine` makes inside one run (`RuntimeConfig::retry_max`).
+    pub fn set_retry_max(&mut self, retry_max: u8) {
+        self.retry_max = retry_max;
+    }
+
+    // Delay before the first rerun of a failed job, doubling with every
+    // further failure up to five minutes, so a rate-limited job does not hit
+    // the provider again straight away. Zero reruns immediately.
+    pub fn set_retry_backoff(&mut self, base_ms: u64) {
+        self.retry_backoff_ms = base_ms;
+    }
+
+    pub fn active_len(&self) -> usize {
+        self.jobs
+            .values()
+            .filter(|job| !job.state.is_terminal())
+            .count()
+    }
+
+    pub fn enqueue(&mut self, input: impl Into<String>) -> Result<JobId, AppError> {
+        self.enqueue_with_priority(input, PRIORITY_NORMAL)
+    }
+
+    // Like `enqueue`, but hands back the existing job when the same input is
+    // still waiting or running. Finished jobs do not count, so a file can be
+    // converted again on purpose. A duplicate is reported even when the queue
+    // is full, since it adds no work.
+    pub fn enqueue_dedup(&mut self, input: impl Into<String>) -> Result<Enqueued, AppError> {
+        let input = input.into();
+        let existing = self
+            .jobs
+            .values()
+            .filter(|job| job.input == input && !job.state.is_terminal())
+            .map(|job| job.id)
+            .min();
+        match existing {
+            Some(id) => Ok(Enqueued::Existing(id)),
+            None => self.enqueue(input).map(Enqueued::New),
+        }
+    }
+
+    pub fn enqueue_with_priority(
+        &mut self,
+        input: impl Into<String>,
+        priority: u8,
+    ) -> Result<JobId, AppError> {
+        if self.capacity > 0 && self.active_len() >= self.capacity {
+            return Err(AppError::QueueFull {
+                capacity: self.capacity,
+            });
+        }
+
         self.next_id += 1;
         let id = self.next_id;
         self.jobs.insert(
@@ -40,54 +235,398 @@ impl Queue {
                 stage: "queued".to_string(),
                 retries: 0,
                 error: None,
+                priority,
+                created_at: now_ms(),
+                finished_at: None,
+                attempts: Vec::new(),
+                output: None,
+                note: None,
+                retry_after: None,
             },
         );
-        id
+        Ok(id)
     }
 
     pub fn mark_running(&mut self, id: JobId, stage: impl Into<String>) {
-        if let Some(job) = self.jobs.get_mut(&id) {
+        if let Some(job) = self.active_job(id) {
             job.state = JobState::Running;
             job.stage = stage.into();
             job.error = None;
+            job.finished_at = None;
+            job.output = None;
+            job.note = None;
+            job.retry_after = None;
+            job.begin_attempt();
         }
     }
 
     pub fn mark_retrying(&mut self, id: JobId, stage: impl Into<String>, error: impl Into<String>) {
-        if let Some(job) = self.jobs.get_mut(&id) {
+        let base_ms = self.retry_backoff_ms;
+        if let Some(job) = self.active_job(id) {
             job.state = JobState::Retrying;
             job.stage = stage.into();
             job.retries = job.retries.saturating_add(1);
-            job.error = Some(error.into());
+            job.retry_after = Some(now_ms() + retry_backoff_ms(base_ms, job.retries));
+            let error = error.into();
+            job.finish_attempt(AttemptOutcome::Failed {
+                error: error.clone(),
+            });
+            job.error = Some(error);
+        }
+    }
+
+    // Retries the job while it is under the `set_retry_max` ceiling, then
+    // fails it. Returns the state the job ended up in.
+    pub fn mark_run_failed(&mut self, id: JobId, error: impl Into<String>) -> Option<JobState> {
+        let retries = self.active_job(id)?.retries;
+        if retries < self.retry_max {
+            self.mark_retrying(id, "failed_retry", error);
+        } else {
+            self.mark_failed(id, error);
         }
+        self.get(id).map(|job| job.state.clone())
     }
 
     pub fn mark_failed(&mut self, id: JobId, error: impl Into<String>) {
-        if let Some(job) = self.jobs.get_mut(&id) {
+        if let Some(job) = self.active_job(id) {
             job.state = JobState::Failed;
-            job.error = Some(error.into());
+            job.finished_at = Some(now_ms());
+            let error = error.into();
+            job.finish_attempt(AttemptOutcome::Failed {
+                error: error.clone(),
+            });
+            job.error = Some(error);
         }
     }
 
-    pub fn mark_success(&mut self, id: JobId) {
-        if let Some(job) = self.jobs.get_mut(&id) {
+    pub fn mark_success(&mut self, id: JobId, output: impl Into<String>) {
+        if let Some(job) = self.active_job(id) {
             job.state = JobState::Success;
             job.stage = "done".to_string();
             job.error = None;
+            job.output = Some(output.into());
+            job.finished_at = Some(now_ms());
+            job.finish_attempt(AttemptOutcome::Success);
+        }
+    }
+
+    // A run that finished without anything to write, such as an input OCR
+    // found no text in. It counts as a success, with `note` saying why there
+    // is no output.
+    pub fn mark_skipped(&mut self, id: JobId, note: impl Into<String>) {
+        if let Some(job) = self.active_job(id) {
+            job.state = JobState::Success;
+            job.stage = "skipped".to_string();
+            job.error = None;
+            job.output = None;
+            job.note = Some(note.into());
+            job.finished_at = Some(now_ms());
+            job.finish_attempt(AttemptOutcome::Success);
+        }
+    }
+
+    // Returns whether the priority changed; finished jobs keep theirs since
+    // they will not be scheduled again.
+    pub fn set_priority(&mut self, id: JobId, priority: u8) -> bool {
+        match self.jobs.get_mut(&id) {
+            Some(job) if !job.state.is_terminal() => {
+                job.priority = priority;
+                true
+            }
+            _ => false,
         }
     }
 
+    // Returns whether the job was stopped; finished jobs cannot be cancelled.
+    pub fn mark_cancelled(&mut self, id: JobId) -> bool {
+        let Some(job) = self.jobs.get_mut(&id) else {
+            return false;
+        };
+        if job.state.is_terminal() {
+            return false;
+        }
+
+        job.state = JobState::Cancelled;
+        job.stage = "cancelled".to_string();
+        job.finished_at = Some(now_ms());
+        job.finish_attempt(AttemptOutcome::Cancelled);
+        true
+    }
+
+    // Cancellation is final: late updates from a worker that was still
+    // unwinding must not revive the job.
+    fn active_job(&mut self, id: JobId) -> Option<&mut JobRecord> {
+        self.jobs
+            .get_mut(&id)
+            .filter(|job| job.state != JobState::Cancelled)
+    }
+
+    // Carries over the jobs `live` is still running, so swapping this queue
+    // in (after a repair) does not hand them to the worker a second time.
+    pub fn keep_running_from(&mut self, live: &Queue) {
+        for job in live
+            .jobs
+            .values()
+            .filter(|job| job.state == JobState::Running)
+        {
+            self.next_id = self.next_id.max(job.id);
+            self.jobs.insert(job.id, job.clone());
+        }
+    }
+
+    // Drops finished jobs and returns how many were removed. `next_id` is
+    // untouched so ids are never reused.
+    pub fn clear_completed(&mut self) -> usize {
+        let before = self.jobs.len();
+        self.jobs.retain(|_, job| !job.state.is_terminal());
+        before - self.jobs.len()
+    }
+
+    // Puts every failed or cancelled job back in the queue with a fresh retry
+    // budget; returns their ids in order. Attempt history is kept.
+    pub fn requeue_failed(&mut self) -> Vec<JobId> {
+        let mut ids: Vec<JobId> = self
+            .jobs
+            .values_mut()
+            .filter(|job| matches!(job.state, JobState::Failed | JobState::Cancelled))
+            .map(|job| {
+                job.state = JobState::Queued;
+                job.stage = "queued".to_string();
+                job.error = None;
+                job.finished_at = None;
+                job.retries = 0;
+                job.retry_after = None;
+                job.id
+            })
+            .collect();
+        ids.sort_unstable();
+        ids
+    }
+
     pub fn get(&self, id: JobId) -> Option<&JobRecord> {
         self.jobs.get(&id)
     }
 
+    // Every job, finished ones included, in id (i.e. enqueue) order.
+    pub fn all_jobs(&self) -> Vec<JobRecord> {
+        let mut jobs: Vec<JobRecord> = self.jobs.values().cloned().collect();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
     pub fn get_next_pending(&self) -> Option<JobId> {
+        self.get_next_pending_at(now_ms())
+    }
+
+    // Picks the next pending job and marks it running in one step, so two
+    // workers sharing the queue lock can never claim the same job.
+    pub fn claim_next_pending(&mut self, stage: impl Into<String>) -> Option<JobId> {
+        let id = self.get_next_pending()?;
+        self.mark_running(id, stage);
+        Some(id)
+    }
+
+    pub fn get_next_pending_at(&self, now_ms: u64) -> Option<JobId> {
         let mut pending: Vec<&JobRecord> = self
             .jobs
             .values()
             .filter(|job| job.state == JobState::Queued || job.state == JobState::Retrying)
+            .filter(|job| job.retry_after.is_none_or(|at| at <= now_ms))
             .collect();
-        pending.sort_by_key(|job| job.id);
+        pending.sort_by_key(|job| (Reverse(self.effective_priority(job, now_ms)), job.id));
         pending.first().map(|job| job.id)
     }
+
+    // Time until the earliest retrying job becomes due, zero when one already
+    // is; `None` without retrying jobs. The worker sleeps at most this long.
+    pub fn next_retry_in(&self) -> Option<Duration> {
+        let now = now_ms();
+        self.jobs
+            .values()
+            .filter(|job| job.state == JobState::Retrying)
+            .filter_map(|job| job.retry_after)
+            .min()
+            .map(|at| Duration::from_millis(at.saturating_sub(now)))
+    }
+
+    // Writes via a temp file and rename so a crash mid-write leaves the
+    // previous snapshot intact.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let file = QueueFile {
+            version: QUEUE_FILE_VERSION,
+            next_id: self.next_id,
+            jobs: self.all_jobs(),
+        };
+        let body = serde_json::to_vec_pretty(&file).context("failed to serialize queue")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("failed to create queue directory")?;
+        }
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, body).context("failed to write queue file")?;
+        fs::rename(&temp, path).context("failed to replace queue file")?;
+        Ok(())
+    }
+
+    // A missing file is an empty queue. A damaged one is salvaged job by job
+    // from the start of its `jobs` array; if nothing is salvageable the file is
+    // moved aside and an empty queue is returned instead of an error.
+    pub fn load_from(path: &Path) -> Result<(Self, QueueLoadReport)> {
+        if !path.exists() {
+            return Ok((Self::default(), QueueLoadReport::default()));
+        }
+
+        let raw = fs::read(path).context("failed to read queue file")?;
+        let text = String::from_utf8_lossy(&raw);
+        if let Ok(file) = serde_json::from_str::<QueueFile>(&text) {
+            let recovered = file.jobs.len();
+            let queue = Self::from_jobs(file.jobs, file.next_id);
+            return Ok((
+                queue,
+                QueueLoadReport {
+                    recovered,
+                    ..QueueLoadReport::default()
+                },
+            ));
+        }
+
+        let (jobs, lost) = recover_jobs(&text);
+        if jobs.is_empty() {
+            let backup = path.with_extension(format!("json.corrupt-{}", now_ms()));
+            fs::rename(path, &backup).context("failed to back up unreadable queue file")?;
+            warn!(backup = %backup.display(), "queue_file_unreadable");
+            return Ok((
+                Self::default(),
+                QueueLoadReport {
+                    recovered: 0,
+                    lost,
+                    backup: Some(backup),
+                },
+            ));
+        }
+
+        warn!(recovered = jobs.len(), lost, "queue_file_recovered");
+        let recovered = jobs.len();
+        Ok((
+            Self::from_jobs(jobs, 0),
+            QueueLoadReport {
+                recovered,
+                lost,
+                backup: None,
+            },
+        ))
+    }
+
+    // Jobs persisted mid-run were interrupted, so they go back to the queue.
+    fn from_jobs(mut jobs: Vec<JobRecord>, next_id: JobId) -> Self {
+        for job in jobs.iter_mut().filter(|job| job.state == JobState::Running) {
+            job.state = JobState::Queued;
+            job.stage = "queued".to_string();
+            job.finish_attempt(AttemptOutcome::Failed {
+                error: "interrupted".to_string(),
+            });
+        }
+        let max_id = jobs.iter().map(|job| job.id).max().unwrap_or(0);
+        Self {
+            next_id: next_id.max(max_id),
+            jobs: jobs.into_iter().map(|job| (job.id, job)).collect(),
+            aging_secs_per_point: 0,
+            capacity: 0,
+            retry_max: 0,
+            retry_backoff_ms: 0,
+        }
+    }
+
+    fn effective_priority(&self, job: &JobRecord, now_ms: u64) -> u64 {
+        let base = u64::from(job.priority);
+        if self.aging_secs_per_point == 0 {
+            return base;
+        }
+
+        let waited_secs = now_ms.saturating_sub(job.created_at) / 1000;
+        base.saturating_add(waited_secs / self.aging_secs_per_point)
+    }
+}
+
+// Parses complete job objects from the front of a possibly truncated `jobs`
+// array. Whatever follows the last good object is counted as lost by its
+// `"id"` keys, which is an estimate when the tail is cut mid-record.
+fn recover_jobs(text: &str) -> (Vec<JobRecord>, usize) {
+    let Some(start) = text
+        .find("\"jobs\"")
+        .and_then(|key| text[key..].find('[').map(|bracket| key + bracket + 1))
+    else {
+        return (Vec::new(), text.matches("\"id\"").count());
+    };
+
+    let mut jobs = Vec::new();
+    let mut rest = &text[start..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() || rest.starts_with(']') {
+            return (jobs, 0);
+        }
+
+        let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<JobRecord>();
+        match stream.next() {
+            Some(Ok(job)) => {
+                let consumed = stream.byte_offset();
+                jobs.push(job);
+                rest = &rest[consumed..];
+            }
+            _ => return (jobs, rest.matches("\"id\"").count().max(1)),
+        }
+    }
+}
+
+// `base_ms` for the first retry, doubled for each one after it.
+fn retry_backoff_ms(base_ms: u64, retries: u8) -> u64 {
+    let doublings = u32::from(retries.saturating_sub(1)).min(20);
+    base_ms
+        .saturating_mul(1 << doublings)
+        .min(MAX_RETRY_BACKOFF_MS)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_RETRY_BACKOFF_MS, PRIORITY_LOW, PRIORITY_NORMAL, Queue, retry_backoff_ms};
+
+    #[test]
+    fn aged_low_priority_job_overtakes_fresh_normal_job() {
+        let mut queue = Queue::default();
+        queue.set_priority_aging(10);
+
+        let old = queue
+            .enqueue_with_priority("old.pdf", PRIORITY_LOW)
+            .unwrap();
+        let fresh = queue
+            .enqueue_with_priority("fresh.pdf", PRIORITY_NORMAL)
+            .unwrap();
+
+        let now = 1_000_000_000;
+        queue.jobs.get_mut(&fresh).unwrap().created_at = now;
+        queue.jobs.get_mut(&old).unwrap().created_at = now - 1_000_000;
+
+        assert_eq!(queue.get_next_pending_at(now), Some(old));
+
+        queue.set_priority_aging(0);
+        assert_eq!(queue.get_next_pending_at(now), Some(fresh));
+    }
+
+    #[test]
+    fn retry_backoff_doubles_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=4)
+            .map(|retries| retry_backoff_ms(1_000, retries))
+            .collect();
+        assert_eq!(delays, [1_000, 2_000, 4_000, 8_000]);
+        assert_eq!(retry_backoff_ms(1_000, u8::MAX), MAX_RETRY_BACKOFF_MS);
+        assert_eq!(retry_backoff_ms(0, 3), 0);
+    }
 }
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::error::AppError;
+
+// `(?-u:\b)` is an ASCII word boundary, so digits glued to CJK text
+// ("电话13812345678") still match while "ID1234..." style tokens do not.
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    (
+        "[EMAIL]",
+        r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}",
+    ),
+    (
+        "[ID]",
+        r"(?-u:\b)[1-9]\d{5}(?:19|20)\d{2}(?:0[1-9]|1[0-2])(?:0[1-9]|[12]\d|3[01])\d{3}[\dXx](?-u:\b)",
+    ),
+    (
+        "[PHONE]",
+        r"\+\d{1,3}[ -]?(?:\(\d{1,4}\)[ -]?)?\d{2,4}(?:[ -]\d{2,4}){1,3}(?-u:\b)",
+    ),
+    ("[PHONE]", r"(?-u:\b)1[3-9]\d(?:[ -]?\d{4}){2}(?-u:\b)"),
+    (
+        "[PHONE]",
+        r"(?:\(\d{3}\)\s?|(?-u:\b)\d{3}[.-])\d{3}[.-]\d{4}(?-u:\b)",
+    ),
+];
+
+// Best-effort scrubbing of obvious PII before text is sent to a remote LLM.
+// Regexes cannot catch names, addresses or unusual formats; treat this as a
+// safety net, not an anonymization guarantee.
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    rules: Vec<(Regex, String)>,
+}
+
+impl Redactor {
+    pub fn builtin() -> Self {
+        let rules = BUILTIN_RULES
+            .iter()
+            .map(|(placeholder, pattern)| {
+                (
+                    Regex::new(pattern).expect("valid builtin PII pattern"),
+                    placeholder.to_string(),
+                )
+            })
+            .collect();
+        Self { rules }
+    }
+
+    // Parses a `LABEL=REGEX` spec; matches are replaced with `[LABEL]`.
+    pub fn with_pattern(mut self, spec: &str) -> Result<Self> {
+        let (label, pattern) = spec.split_once('=').ok_or_else(|| {
+            AppError::InvalidConfig(format!("redact pattern must be LABEL=REGEX, got {spec}"))
+        })?;
+        let label = label.trim();
+        if label.is_empty() {
+            return Err(
+                AppError::InvalidConfig(format!("redact pattern label is empty: {spec}")).into(),
+            );
+        }
+
+        let regex =
+            Regex::new(pattern).with_context(|| format!("invalid redact pattern for {label}"))?;
+        self.rules
+            .push((regex, format!("[{}]", label.to_uppercase())));
+        Ok(self)
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for (regex, placeholder) in &self.rules {
+            if let std::borrow::Cow::Owned(replaced) =
+                regex.replace_all(&out, regex::NoExpand(placeholder))
+            {
+                out = replaced;
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ocr::parse_page_marker;
+
+// A line must recur at least this often to count as a running header or
+// footer; with page markers it must also sit on at least half the pages.
+const MIN_REPEATS: usize = 3;
+// Non-blank lines at the top and at the bottom of a page that may hold one.
+const EDGE_LINES: usize = 2;
+// Longer lines are body text even when they repeat.
+const MAX_LINE_CHARS: usize = 80;
+// Without page markers, repeats closer than this are lists or tables rather
+// than pages.
+const MIN_PAGE_LINES: usize = 5;
+
+// Drops running headers and footers (a document title on every page,
+// "第 N 页" lines, ...) from OCR text. Lines match when they are equal once
+// whitespace and case are ignored and every digit run counts the same, so
+// incrementing page numbers and dates still match. With `--- page N ---`
+// markers only the edges of each page are looked at; otherwise a line must
+// recur at roughly regular intervals. Page markers are always kept.
+pub fn strip(text: &str) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let pages = split_pages(&lines);
+    let remove = if pages.len() > 1 {
+        repeated_edges(&lines, &pages)
+    } else {
+        regular_repeats(&lines)
+    };
+    if remove.is_empty() {
+        return text.to_string();
+    }
+
+    let mut out = lines
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !remove.contains(index))
+        .map(|(_, line)| *line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+// Line ranges between page markers; text before the first marker is not a
+// page of its own.
+fn split_pages(lines: &[&str]) -> Vec<std::ops::Range<usize>> {
+    let markers: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| parse_page_marker(line).is_some())
+        .map(|(index, _)| index)
+        .collect();
+    markers
+        .iter()
+        .enumerate()
+        .map(|(nth, &start)| start + 1..markers.get(nth + 1).copied().unwrap_or(lines.len()))
+        .collect()
+}
+
+fn repeated_edges(lines: &[&str], pages: &[std::ops::Range<usize>]) -> HashSet<usize> {
+    let mut seen: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+    for (page, range) in pages.iter().enumerate() {
+        let content: Vec<usize> = range
+            .clone()
+            .filter(|&index| !lines[index].trim().is_empty())
+            .collect();
+        let edges = content
+            .iter()
+            .take(EDGE_LINES)
+            .chain(content.iter().rev().take(EDGE_LINES));
+        for &index in edges {
+            if let Some(key) = line_key(lines[index]) {
+                let hits = seen.entry(key).or_default();
+                if !hits.contains(&(page, index)) {
+                    hits.push((page, index));
+                }
+            }
+        }
+    }
+
+    let threshold = MIN_REPEATS.max(pages.len() / 2);
+    seen.into_values()
+        .filter(|hits| {
+            let pages: HashSet<usize> = hits.iter().map(|(page, _)| *page).collect();
+            pages.len() >= threshold
+        })
+        .flatten()
+        .map(|(_, index)| index)
+        .collect()
+}
+
+fn regular_repeats(lines: &[&str]) -> HashSet<usize> {
+    let mut seen: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(key) = line_key(line) {
+            seen.entry(key).or_default().push(index);
+        }
+    }
+
+    seen.into_values()
+        .filter(|positions| {
+            if positions.len() < MIN_REPEATS {
+                return false;
+            }
+            let gaps: Vec<usize> = positions.windows(2).map(|pair| pair[1] - pair[0]).collect();
+            let shortest = gaps.iter().copied().min().unwrap_or(0);
+            let longest = gaps.iter().copied().max().unwrap_or(0);
+            shortest >= MIN_PAGE_LINES && longest <= shortest * 2
+        })
+        .flatten()
+        .collect()
+}
+
+// `None` for lines that are never headers: blank, long, page markers and
+// table rows.
+fn line_key(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty()
+        || line.chars().count() > MAX_LINE_CHARS
+        || line.starts_with('|')
+        || parse_page_marker(line).is_some()
+    {
+        return None;
+    }
+
+    let mut key = String::with_capacity(line.len());
+    for ch in line.chars().filter(|ch| !ch.is_whitespace()) {
+        if ch.is_ascii_digit() {
+            if !key.ends_with('#') {
+                key.push('#');
+            }
+        } else {
+            key.extend(ch.to_lowercase());
+        }
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::strip;
+
+    fn paged(pages: &[&str]) -> String {
+        pages
+            .iter()
+            .enumerate()
+            .map(|(index, body)| format!("--- page {} ---\n\n{body}", index + 1))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    #[test]
+    fn drops_a_header_and_page_numbers_repeated_on_every_page() {
+        let text = paged(&[
+            "ACME 年度报告 2024\n\n# 概述\n正文一\n\n第 1 页",
+            "ACME 年度报告 2024\n\n正文二\n\n第 2 页",
+            "ACME  年度报告 2024\n\n正文三\n\n第 3 页",
+            "Acme 年度报告 2024\n\n正文四\n\n第 4 页",
+        ]);
+
+        assert_eq!(
+            strip(&text),
+            paged(&[
+                "\n# 概述\n正文一\n",
+                "\n正文二\n",
+                "\n正文三\n",
+                "\n正文四\n",
+            ])
+        );
+    }
+
+    #[test]
+    fn lines_on_too_few_pages_or_mid_page_stay() {
+        let text = paged(&[
+            "Chapter 1\n\nIntro text\n\nSummary",
+            "Chapter 1\n\nMore text\n\nNotes\n\nSummary\n\nfinal line",
+            "Appendix\n\nEven more\n\nSummary\nthe end\nreally",
+            "Index\n\nLast page",
+            "Index\n\nAfter",
+            "Glossary\n\nWords",
+        ]);
+
+        assert_eq!(strip(&text), text);
+    }
+
+    #[test]
+    fn unmarked_text_needs_regular_spacing() {
+        let bodies = [
+            [
+                "Revenue grew",
+                "across regions",
+                "in the year",
+                "under review",
+            ],
+            ["Costs fell", "thanks to", "lower freight", "and energy"],
+            ["Outlook stays", "cautious for", "next year", "overall"],
+        ];
+        let text: String = bodies
+            .iter()
+            .enumerate()
+            .map(|(index, body)| {
+                format!(
+                    "Contoso Ltd. — Confidential\n{}\nPage {} of 3\n",
+                    body.join("\n"),
+                    index + 1
+                )
+            })
+            .collect();
+
+        let expected: String = bodies.iter().map(|body| body.join("\n") + "\n").collect();
+        assert_eq!(strip(&text), expected);
+    }
+
+    #[test]
+    fn short_repeats_and_table_rows_are_not_headers() {
+        let text = "- 是\n- 否\n- 是\n- 否\n- 是\n\n| 1 | 2 |\n|---|---|\n| 3 | 4 |\n";
+        assert_eq!(strip(text), text);
+    }
+}
@@ -0,0 +1,119 @@
+use serde_json::Value;
+
+use crate::error::AppError;
+
+const SNIPPET_MAX: usize = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    String,
+    Array,
+    NonEmptyArray,
+    Object,
+    StringOrArray,
+}
+
+impl Expected {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Array => "array",
+            Self::NonEmptyArray => "non-empty array",
+            Self::Object => "object",
+            Self::StringOrArray => "string or array",
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Self::String => value.is_string(),
+            Self::Array => value.is_array(),
+            Self::NonEmptyArray => value.as_array().is_some_and(|items| !items.is_empty()),
+            Self::Object => value.is_object(),
+            Self::StringOrArray => value.is_string() || value.is_array(),
+        }
+    }
+}
+
+pub type FieldRule = (&'static str, Expected);
+
+pub const OPENAI_CHAT_RULES: &[FieldRule] = &[
+    ("/choices", Expected::NonEmptyArray),
+    ("/choices/0/message", Expected::Object),
+    ("/choices/0/message/content", Expected::StringOrArray),
+];
+
+pub const ANTHROPIC_MESSAGES_RULES: &[FieldRule] = &[("/content", Expected::Array)];
+
+pub const OLLAMA_CHAT_RULES: &[FieldRule] = &[
+    ("/message", Expected::Object),
+    ("/message/content", Expected::String),
+];
+
+pub const GEMINI_GENERATE_RULES: &[FieldRule] = &[
+    ("/candidates", Expected::NonEmptyArray),
+    ("/candidates/0/content", Expected::Object),
+    ("/candidates/0/content/parts", Expected::Array),
+];
+
+// Checks each rule in order and reports the first field that is missing or
+// has the wrong type, e.g. "expected string or array at
+// /choices/0/message/content, found null".
+pub fn validate(service: &str, value: &Value, rules: &[FieldRule]) -> Result<(), AppError> {
+    for (pointer, expected) in rules {
+        let found = value.pointer(pointer);
+        if found.is_some_and(|found| expected.matches(found)) {
+            continue;
+        }
+
+        let found = match found {
+            Some(found) => describe(found),
+            None => "nothing".to_string(),
+        };
+        return Err(AppError::ApiResponse(format!(
+            "{service}: expected {} at {pointer}, found {found}; body: {}",
+            expected.describe(),
+            redacted_snippet(value)
+        )));
+    }
+    Ok(())
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "boolean".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(text) => format!("string of {} chars", text.chars().count()),
+        Value::Array(items) => format!("array of {} items", items.len()),
+        Value::Object(map) => {
+            let keys: Vec<&str> = map.keys().map(String::as_str).collect();
+            format!("object with keys [{}]", keys.join(", "))
+        }
+    }
+}
+
+// Keeps the shape of the body but hides string contents, which may carry
+// document text or echoed credentials.
+pub fn redacted_snippet(value: &Value) -> String {
+    let snippet = redact_strings(value).to_string();
+    if snippet.chars().count() <= SNIPPET_MAX {
+        return snippet;
+    }
+    let mut out: String = snippet.chars().take(SNIPPET_MAX).collect();
+    out.push_str("...");
+    out
+}
+
+fn redact_strings(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(format!("<{} chars>", text.chars().count())),
+        Value::Array(items) => Value::Array(items.iter().map(redact_strings).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), redact_strings(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
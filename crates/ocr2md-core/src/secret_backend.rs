@@ -0,0 +1,159 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use tracing::warn;
+
+use crate::profile_store::ProviderProfile;
+
+// Where a profile store keeps API keys. The encrypted profile file always
+// holds everything else; a backend decides whether `api_key` goes there too.
+pub trait SecretBackend: fmt::Debug + Send + Sync {
+    // Recorded in the store so a file saved with one backend is never read
+    // back with another and silently loses its keys.
+    fn name(&self) -> &'static str;
+
+    // Called before `profiles` are written to the file; takes the keys it
+    // keeps elsewhere out of them.
+    fn stash(&self, profiles: &mut [ProviderProfile]) -> Result<()>;
+
+    // Puts the keys back into profiles just read from the file.
+    fn fill(&self, profiles: &mut [ProviderProfile]) -> Result<()>;
+
+    // Drops the keys of profiles that are no longer in the store.
+    fn forget(&self, names: &[String]) -> Result<()>;
+}
+
+// Keys stay inside the encrypted profile file, as they always have.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSecrets;
+
+impl SecretBackend for FileSecrets {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn stash(&self, _: &mut [ProviderProfile]) -> Result<()> {
+        Ok(())
+    }
+
+    fn fill(&self, _: &mut [ProviderProfile]) -> Result<()> {
+        Ok(())
+    }
+
+    fn forget(&self, _: &[String]) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Keys live in the OS keychain (Keychain, Credential Manager, the kernel
+// keyring on Linux), one entry per profile name under `service`; the file
+// only ever sees empty keys.
+#[derive(Debug, Clone)]
+pub struct KeyringSecrets {
+    service: String,
+}
+
+impl KeyringSecrets {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+        }
+    }
+
+    fn entry(&self, profile: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&self.service, profile)
+            .with_context(|| format!("failed to open keychain entry for profile {profile}"))
+    }
+}
+
+impl SecretBackend for KeyringSecrets {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn stash(&self, profiles: &mut [ProviderProfile]) -> Result<()> {
+        for profile in profiles {
+            let entry = self.entry(&profile.name)?;
+            if profile.api_key.is_empty() {
+                delete_entry(&entry, &profile.name)?;
+            } else {
+                entry.set_password(&profile.api_key).with_context(|| {
+                    format!("failed to store the key of profile {}", profile.name)
+                })?;
+            }
+            profile.api_key.clear();
+        }
+        Ok(())
+    }
+
+    // A missing entry leaves the key empty, so one lost key does not lock
+    // the user out of every other profile.
+    fn fill(&self, profiles: &mut [ProviderProfile]) -> Result<()> {
+        for profile in profiles {
+            match self.entry(&profile.name)?.get_password() {
+                Ok(key) => profile.api_key = key,
+                Err(keyring::Error::NoEntry) => {
+                    warn!(profile = %profile.name, "no API key in the keychain");
+                    profile.api_key.clear();
+                }
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("failed to read the key of profile {}", profile.name)
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn forget(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            delete_entry(&self.entry(name)?, name)?;
+        }
+        Ok(())
+    }
+}
+
+fn delete_entry(entry: &keyring::Entry, profile: &str) -> Result<()> {
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => {
+            Err(err).with_context(|| format!("failed to delete the key of profile {profile}"))
+        }
+    }
+}
+
+// `OCR2MD_SECRET_BACKEND`: `file` (default) or `keyring`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SecretBackendKind {
+    #[default]
+    File,
+    Keyring,
+}
+
+impl SecretBackendKind {
+    pub fn from_env() -> Self {
+        std::env::var("OCR2MD_SECRET_BACKEND")
+            .ok()
+            .and_then(|value| Self::from_str(value.trim(), true).ok())
+            .unwrap_or_default()
+    }
+
+    // Keychain entries are namespaced by the store's file stem, so profiles
+    // with the same name in two stores keep separate keys.
+    pub fn backend_for(self, store_path: &Path) -> Arc<dyn SecretBackend> {
+        match self {
+            Self::File => Arc::new(FileSecrets),
+            Self::Keyring => {
+                let stem = store_path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                Arc::new(KeyringSecrets::new(format!("ocr2md:{stem}")))
+            }
+        }
+    }
+}
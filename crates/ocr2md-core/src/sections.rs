@@ -0,0 +1,135 @@
+use crate::error::AppError;
+use crate::ocr::parse_page_marker;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    // 1-based source pages; empty when the OCR text carried no page markers.
+    pub pages: Vec<usize>,
+    pub ocr_text: String,
+    pub markdown: String,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SectionedDocument {
+    pub sections: Vec<Section>,
+}
+
+impl SectionedDocument {
+    // One section per `--- page N ---` block, or a single section for
+    // unmarked text. Text ahead of the first marker, such as words the model
+    // put before `--- page 1 ---`, opens the first section; a page-less
+    // section of its own would make a rerun re-OCR the whole file. Markdown
+    // is filled in by the LLM stage.
+    pub fn from_ocr_text(text: &str) -> Self {
+        let mut sections = Vec::new();
+        let mut current: Option<(usize, Vec<&str>)> = None;
+        let mut preamble = Vec::new();
+
+        for line in text.lines() {
+            if let Some(number) = parse_page_marker(line) {
+                let lines = match current.take() {
+                    Some((page, lines)) => {
+                        sections.push(Section::new(vec![page], lines.join("\n")));
+                        Vec::new()
+                    }
+                    None => std::mem::take(&mut preamble),
+                };
+                current = Some((number, lines));
+                continue;
+            }
+            match current.as_mut() {
+                Some((_, lines)) => lines.push(line),
+                None => preamble.push(line),
+            }
+        }
+
+        match current {
+            Some((page, lines)) => sections.push(Section::new(vec![page], lines.join("\n"))),
+            None => sections.push(Section::new(Vec::new(), preamble.join("\n"))),
+        }
+        Self { sections }
+    }
+
+    pub fn low_confidence(&self, threshold: f32) -> Vec<usize> {
+        self.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, section)| section.confidence < threshold)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn merge(&mut self, index: usize, section: Section) -> Result<(), AppError> {
+        let len = self.sections.len();
+        let slot = self.sections.get_mut(index).ok_or_else(|| {
+            AppError::InvalidConfig(format!("section {index} out of range (0..{len})"))
+        })?;
+        *slot = section;
+        Ok(())
+    }
+
+    pub fn to_markdown(&self) -> String {
+        self.sections
+            .iter()
+            .map(|section| section.markdown.trim())
+            .filter(|markdown| !markdown.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+impl Section {
+    pub fn new(pages: Vec<usize>, ocr_text: String) -> Self {
+        let confidence = ocr_confidence(&ocr_text);
+        Self {
+            pages,
+            ocr_text: ocr_text.trim().to_string(),
+            markdown: String::new(),
+            confidence,
+        }
+    }
+
+    // Folds in how much of the OCR text survived structuring: a reply far
+    // shorter than its input usually means the LLM gave up on the section.
+    pub fn with_markdown(mut self, markdown: String) -> Self {
+        let input = self.ocr_text.chars().count();
+        if input > 0 {
+            let kept = markdown.chars().count() as f32 / input as f32;
+            if kept < 0.3 {
+                self.confidence *= kept / 0.3;
+            }
+        }
+        self.markdown = markdown;
+        self
+    }
+}
+
+// Heuristic OCR quality in `0.0..=1.0`. GLM returns no per-token confidence,
+// so this scores the share of characters that look like recognition noise:
+// replacement characters, `[?]` placeholders and stray symbol runs.
+pub fn ocr_confidence(text: &str) -> f32 {
+    let total = text.chars().filter(|c| !c.is_whitespace()).count();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let unreadable = text.matches("[?]").count() * 3;
+    let noise = text
+        .chars()
+        .filter(|c| *c == '\u{FFFD}' || (c.is_control() && !c.is_whitespace()))
+        .count();
+    let symbol_runs = text
+        .split_whitespace()
+        .filter(|word| {
+            word.chars().count() >= 4
+                && word
+                    .chars()
+                    .all(|c| !c.is_alphanumeric() && !matches!(c, '-' | '=' | '|' | '*' | '#'))
+        })
+        .map(|word| word.chars().count())
+        .sum::<usize>();
+
+    let bad = (unreadable + noise + symbol_runs).min(total);
+    1.0 - bad as f32 / total as f32
+}
@@ -3,20 +3,97 @@ use argon2::{Algorithm, Argon2, Params, Version};
 use chacha20poly1305::aead::{Aead, KeyInit};
 use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
 use rand::RngCore;
+use thiserror::Error;
+
+use crate::config::env_u32;
 
 const MAGIC: [u8; 4] = *b"O2MD";
-const VERSION: u8 = 1;
+// Version 1 blobs carry no KDF parameters and always used `KdfParams::V1`.
+const VERSION_V1: u8 = 1;
+const VERSION: u8 = 2;
+const PARAMS_LEN: usize = 12;
 const SALT_LEN: usize = 16;
 const NONCE_LEN: usize = 12;
 const KEY_LEN: usize = 32;
 const TAG_LEN: usize = 16;
+// Upper bounds for parameters read from a blob, so a tampered file cannot
+// make decryption allocate or spin without limit.
+const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_LANES: u32 = 64;
+
+// Argon2id work factor. Each blob records the parameters it was written
+// with, so raising them only affects files saved afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub lanes: u32,
+}
+
+impl KdfParams {
+    pub const V1: Self = Self {
+        memory_kib: 19_456,
+        iterations: 2,
+        lanes: 1,
+    };
+
+    pub fn from_env() -> Self {
+        Self {
+            memory_kib: env_u32("OCR2MD_KDF_MEMORY_KIB", Self::V1.memory_kib),
+            iterations: env_u32("OCR2MD_KDF_ITERATIONS", Self::V1.iterations),
+            lanes: env_u32("OCR2MD_KDF_LANES", Self::V1.lanes),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; PARAMS_LEN] {
+        let mut out = [0_u8; PARAMS_LEN];
+        out[0..4].copy_from_slice(&self.memory_kib.to_le_bytes());
+        out[4..8].copy_from_slice(&self.iterations.to_le_bytes());
+        out[8..12].copy_from_slice(&self.lanes.to_le_bytes());
+        out
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"));
+        let params = Self {
+            memory_kib: word(0),
+            iterations: word(4),
+            lanes: word(8),
+        };
+        if params.memory_kib > MAX_MEMORY_KIB
+            || params.iterations > MAX_ITERATIONS
+            || params.lanes > MAX_LANES
+        {
+            bail!("unsupported key derivation parameters: {params:?}");
+        }
+        Ok(params)
+    }
+}
+
+// Why `decrypt_blob` failed. Only `WrongPassphraseOrTampered` can be the
+// user's fault; the rest mean the file itself is damaged or from elsewhere.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DecryptError {
+    #[error("wrong passphrase, or the ciphertext was modified")]
+    WrongPassphraseOrTampered,
+
+    #[error("malformed ciphertext envelope: {0}")]
+    BadEnvelope(String),
+
+    #[error("unsupported ciphertext version: {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("failed to derive decryption key: {0}")]
+    KeyDerivation(String),
+}
 
-fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
+fn derive_key(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<[u8; KEY_LEN]> {
     if passphrase.is_empty() {
         bail!("passphrase cannot be empty");
     }
 
-    let params = Params::new(19_456, 2, 1, Some(KEY_LEN))
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.lanes, Some(KEY_LEN))
         .map_err(|err| anyhow!("failed to initialize argon2 params: {err}"))?;
     let mut key = [0_u8; KEY_LEN];
     Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
@@ -25,52 +102,126 @@ fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN]> {
     Ok(key)
 }
 
+// Uses the work factor from `OCR2MD_KDF_*` (default `KdfParams::V1`).
 pub fn encrypt_blob(plain: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    encrypt_blob_with(plain, passphrase, KdfParams::from_env())
+}
+
+pub fn encrypt_blob_with(plain: &[u8], passphrase: &str, kdf: KdfParams) -> Result<Vec<u8>> {
     let mut salt = [0_u8; SALT_LEN];
     let mut nonce = [0_u8; NONCE_LEN];
     rand::rngs::OsRng.fill_bytes(&mut salt);
     rand::rngs::OsRng.fill_bytes(&mut nonce);
 
-    let key = derive_key(passphrase, &salt)?;
+    let key = derive_key(passphrase, &salt, kdf)?;
     let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
     let ciphertext = cipher
         .encrypt(Nonce::from_slice(&nonce), plain)
         .map_err(|_| anyhow!("failed to encrypt blob"))?;
 
-    let mut out = Vec::with_capacity(MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+    let mut out =
+        Vec::with_capacity(MAGIC.len() + 1 + PARAMS_LEN + SALT_LEN + NONCE_LEN + ciphertext.len());
     out.extend_from_slice(&MAGIC);
     out.push(VERSION);
+    out.extend_from_slice(&kdf.to_bytes());
     out.extend_from_slice(&salt);
     out.extend_from_slice(&nonce);
     out.extend_from_slice(&ciphertext);
     Ok(out)
 }
 
-pub fn decrypt_blob(blob: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+pub fn decrypt_blob(blob: &[u8], passphrase: &str) -> Result<Vec<u8>, DecryptError> {
+    let bad = |reason: &str| DecryptError::BadEnvelope(reason.to_string());
     let min_len = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + TAG_LEN;
     if blob.len() < min_len {
-        bail!("ciphertext envelope is too short");
+        return Err(bad("too short"));
     }
 
     let (magic, rest) = blob.split_at(MAGIC.len());
     if magic != MAGIC {
-        bail!("unsupported ciphertext envelope");
+        return Err(bad("unknown magic bytes"));
     }
 
-    let (&version, rest) = rest
-        .split_first()
-        .ok_or_else(|| anyhow!("missing ciphertext version"))?;
-    if version != VERSION {
-        bail!("unsupported ciphertext version: {version}");
-    }
+    let (&version, rest) = rest.split_first().ok_or_else(|| bad("missing version"))?;
+    let (kdf, rest) = match version {
+        VERSION_V1 => (KdfParams::V1, rest),
+        VERSION => {
+            if rest.len() < PARAMS_LEN + SALT_LEN + NONCE_LEN + TAG_LEN {
+                return Err(bad("too short"));
+            }
+            let (params, rest) = rest.split_at(PARAMS_LEN);
+            let params = KdfParams::from_bytes(params)
+                .map_err(|err| DecryptError::BadEnvelope(err.to_string()))?;
+            (params, rest)
+        }
+        other => return Err(DecryptError::UnsupportedVersion(other)),
+    };
 
     let (salt, rest) = rest.split_at(SALT_LEN);
     let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
 
-    let key = derive_key(passphrase, salt)?;
+    let key = derive_key(passphrase, salt, kdf)
+        .map_err(|err| DecryptError::KeyDerivation(err.to_string()))?;
     let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
-    let plain = cipher
+    // The AEAD tag fails to verify for a different key or any flipped byte;
+    // the two cannot be told apart, but both differ from a broken envelope.
+    cipher
         .decrypt(Nonce::from_slice(nonce), ciphertext)
-        .map_err(|_| anyhow!("failed to decrypt blob"))?;
-    Ok(plain)
+        .map_err(|_| DecryptError::WrongPassphraseOrTampered)
+}
+
+#[cfg(test)]
+mod tests {
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+    use super::{
+        DecryptError, KdfParams, MAGIC, VERSION_V1, decrypt_blob, derive_key, encrypt_blob_with,
+    };
+
+    // What `encrypt_blob` produced before the parameters were stored.
+    fn v1_blob(plain: &[u8], passphrase: &str) -> Vec<u8> {
+        let salt = [7_u8; 16];
+        let nonce = [9_u8; 12];
+        let key = derive_key(passphrase, &salt, KdfParams::V1).unwrap();
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(Nonce::from_slice(&nonce), plain)
+            .unwrap();
+        [&MAGIC[..], &[VERSION_V1], &salt, &nonce, &ciphertext].concat()
+    }
+
+    #[test]
+    fn version_1_blobs_still_decrypt() {
+        let blob = v1_blob(b"legacy profiles", "passphrase");
+        assert_eq!(
+            decrypt_blob(&blob, "passphrase").unwrap(),
+            b"legacy profiles"
+        );
+    }
+
+    #[test]
+    fn version_2_blobs_carry_their_own_parameters() {
+        let kdf = KdfParams {
+            memory_kib: 8_192,
+            iterations: 3,
+            lanes: 2,
+        };
+        let blob = encrypt_blob_with(b"profiles", "passphrase", kdf).unwrap();
+
+        assert_eq!(blob[4], 2);
+        assert_eq!(&blob[5..9], &8_192_u32.to_le_bytes());
+        assert_eq!(decrypt_blob(&blob, "passphrase").unwrap(), b"profiles");
+    }
+
+    #[test]
+    fn absurd_stored_parameters_are_rejected() {
+        let mut blob = encrypt_blob_with(b"profiles", "passphrase", KdfParams::V1).unwrap();
+        blob[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let err = decrypt_blob(&blob, "passphrase").unwrap_err();
+        assert!(
+            matches!(&err, DecryptError::BadEnvelope(reason) if reason.contains("key derivation parameters")),
+            "{err}"
+        );
+    }
 }
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use anyhow::Result;
+use futures::FutureExt;
+use futures::future::{BoxFuture, Shared};
+use tokio::sync::oneshot;
+use tracing::info;
+
+type Flight = Shared<BoxFuture<'static, Option<String>>>;
+
+// Concurrent OCR calls for the same key share one request: the first caller
+// runs it and the others await its text. An entry lives only while its call
+// is in flight; repeats after that are the on-disk cache's job. Clones share
+// the table, so every job of a batch joins the same flights.
+//
+// The lock is never held across an await, and a leader dropped mid-call
+// (cancelled, timed out) clears its entry from `Drop`, which an async mutex
+// could not do.
+#[derive(Clone, Default)]
+pub struct SingleFlight {
+    inflight: Arc<Mutex<HashMap<String, Flight>>>,
+}
+
+impl SingleFlight {
+    // When the shared call fails, or its leader goes away, each waiter makes
+    // its own call instead of inheriting an error it might not have hit.
+    pub async fn run<F>(&self, key: &str, trace_id: &str, work: F) -> Result<String>
+    where
+        F: Future<Output = Result<String>>,
+    {
+        let flight = match self.join(key) {
+            Role::Lead(tx) => return self.lead(key, tx, work).await,
+            Role::Wait(flight) => flight,
+        };
+        if let Some(text) = flight.await {
+            info!(trace_id, key, "ocr_deduplicated");
+            return Ok(text);
+        }
+        info!(trace_id, key, "ocr_dedup_leader_failed");
+        work.await
+    }
+
+    async fn lead<F>(
+        &self,
+        key: &str,
+        tx: oneshot::Sender<Option<String>>,
+        work: F,
+    ) -> Result<String>
+    where
+        F: Future<Output = Result<String>>,
+    {
+        let guard = Leader { flight: self, key };
+        let result = work.await;
+        drop(guard);
+        let _ = tx.send(result.as_ref().ok().cloned());
+        result
+    }
+
+    fn join(&self, key: &str) -> Role {
+        let mut inflight = self.lock();
+        if let Some(flight) = inflight.get(key) {
+            return Role::Wait(flight.clone());
+        }
+        let (tx, rx) = oneshot::channel();
+        let flight = rx.map(|text| text.ok().flatten()).boxed().shared();
+        inflight.insert(key.to_string(), flight);
+        Role::Lead(tx)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Flight>> {
+        self.inflight.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+enum Role {
+    Lead(oneshot::Sender<Option<String>>),
+    Wait(Flight),
+}
+
+// Removes the leader's entry however `lead` ends; waiters already holding
+// the flight still get the result, or `None` if the sender was dropped.
+struct Leader<'a> {
+    flight: &'a SingleFlight,
+    key: &'a str,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.flight.lock().remove(self.key);
+    }
+}
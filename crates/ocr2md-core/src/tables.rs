@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+// Excel only reads a UTF-8 CSV as UTF-8 when it starts with a BOM; without
+// it Chinese cells come out garbled.
+const CSV_BOM: &str = "\u{feff}";
+
+// One pipe table, header row first. Every row has the same number of cells:
+// short rows are padded with empty cells rather than dropping the long ones.
+pub type Table = Vec<Vec<String>>;
+
+// GitHub-style pipe tables in document order. A table is a row line followed
+// by a delimiter row (`---|:--:`) with as many cells, then every following
+// line with a `|` up to the first blank one. Fenced code is skipped.
+pub fn extract_tables(markdown: &str) -> Vec<Table> {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut tables = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index].trim();
+        if let Some(marker) = fence {
+            if line.starts_with(marker) {
+                fence = None;
+            }
+            index += 1;
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| line.starts_with(m)) {
+            fence = Some(marker);
+            index += 1;
+            continue;
+        }
+
+        let header = split_row(line);
+        let is_table = line.contains('|')
+            && lines
+                .get(index + 1)
+                .map(|next| split_row(next.trim()))
+                .is_some_and(|delimiter| {
+                    delimiter.len() == header.len() && delimiter.iter().all(|c| is_delimiter(c))
+                });
+        if !is_table {
+            index += 1;
+            continue;
+        }
+
+        let mut rows = vec![header];
+        index += 2;
+        while let Some(line) = lines.get(index).map(|line| line.trim()) {
+            if line.is_empty() || !line.contains('|') {
+                break;
+            }
+            rows.push(split_row(line));
+            index += 1;
+        }
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        for row in &mut rows {
+            row.resize(width, String::new());
+        }
+        tables.push(rows);
+    }
+    tables
+}
+
+// RFC 4180 quoting: fields with a comma, quote or line break are quoted and
+// inner quotes doubled.
+pub fn to_csv(table: &Table) -> String {
+    let mut out = String::new();
+    for row in table {
+        let fields: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+// Writes `<stem>.table1.csv`, `<stem>.table2.csv`, ... into `dir` and returns
+// their paths; nothing is written when the Markdown has no table.
+pub fn write_csvs(markdown: &str, dir: &Path, stem: &str) -> Result<Vec<PathBuf>> {
+    let tables = extract_tables(markdown);
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+    fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create tables dir: {}", dir.display()))?;
+
+    let mut written = Vec::with_capacity(tables.len());
+    for (index, table) in tables.iter().enumerate() {
+        let path = dir.join(format!("{stem}.table{}.csv", index + 1));
+        fs::write(&path, format!("{CSV_BOM}{}", to_csv(table)))
+            .with_context(|| format!("failed to write table: {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+// Splits on unescaped pipes; one leading and one trailing pipe are optional
+// row borders, and `\|` stands for a literal pipe inside a cell.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some('|') => cell.push('|'),
+                Some(other) => {
+                    cell.push('\\');
+                    cell.push(other);
+                }
+                None => cell.push('\\'),
+            },
+            '|' => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(ch),
+        }
+    }
+    // Text after the last pipe is a cell unless the pipe was the right border.
+    if !cell.trim().is_empty() || cells.is_empty() {
+        cells.push(cell);
+    }
+    cells
+        .into_iter()
+        .map(|cell| cell.trim().to_string())
+        .collect()
+}
+
+fn is_delimiter(cell: &str) -> bool {
+    let dashes = cell.strip_prefix(':').unwrap_or(cell);
+    let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+    !dashes.is_empty() && dashes.chars().all(|ch| ch == '-')
+}
+
+fn csv_field(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{extract_tables, to_csv, write_csvs};
+
+    fn row(cells: &[&str]) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_every_table_in_a_document() {
+        let markdown = "# Invoice\n\n\
+            | Item | Qty | Price |\n\
+            |:-----|:---:|------:|\n\
+            | Pen | 2 | 1.50 |\n\
+            | Ink | 1 |\n\
+            \n\
+            Some text | with a pipe.\n\n\
+            ```\n| a | b |\n|---|---|\n```\n\n\
+            Total | Amount\n\
+            --- | ---\n\
+            Net | 3.00\n";
+
+        assert_eq!(
+            extract_tables(markdown),
+            vec![
+                vec![
+                    row(&["Item", "Qty", "Price"]),
+                    row(&["Pen", "2", "1.50"]),
+                    row(&["Ink", "1", ""]),
+                ],
+                vec![row(&["Total", "Amount"]), row(&["Net", "3.00"])],
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_pipes_stay_inside_their_cell() {
+        let markdown = "| Expr | Note |\n| --- | --- |\n| a \\| b | x, \"y\" |\n";
+        let tables = extract_tables(markdown);
+
+        assert_eq!(tables[0][1], row(&["a | b", "x, \"y\""]));
+        assert_eq!(
+            to_csv(&tables[0]),
+            "Expr,Note\r\na | b,\"x, \"\"y\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn long_rows_widen_the_table() {
+        let tables = extract_tables("| A | B |\n|---|---|\n| 1 | 2 | 3 |\n");
+        assert_eq!(tables[0], vec![row(&["A", "B", ""]), row(&["1", "2", "3"])]);
+    }
+
+    #[test]
+    fn text_without_a_delimiter_row_is_not_a_table() {
+        assert!(extract_tables("a | b\nc | d\n").is_empty());
+        assert!(extract_tables("| a | b |\n| --- |\n| 1 | 2 |\n").is_empty());
+    }
+
+    #[test]
+    fn writes_one_numbered_csv_per_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let markdown = "| A |\n|---|\n| 1 |\n\n| B |\n|---|\n| 2 |\n";
+
+        let written = write_csvs(markdown, dir.path(), "invoice").unwrap();
+
+        assert_eq!(
+            written,
+            vec![
+                dir.path().join("invoice.table1.csv"),
+                dir.path().join("invoice.table2.csv"),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(&written[1]).unwrap(),
+            "\u{feff}B\r\n2\r\n"
+        );
+        assert!(
+            write_csvs("no tables", dir.path(), "empty")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+// Where `--toc` puts the outline: `inline` prepends a linked Markdown TOC to
+// the output, anything else is a path for a JSON outline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TocTarget {
+    Inline,
+    Json(PathBuf),
+}
+
+impl FromStr for TocTarget {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "" => Err(AppError::InvalidConfig(
+                "--toc needs a JSON path or `inline`".to_string(),
+            )),
+            "inline" => Ok(Self::Inline),
+            path => Ok(Self::Json(PathBuf::from(path))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    // 1 for `#` through 6 for `######`.
+    pub level: u8,
+    pub text: String,
+    // Anchor GitHub gives the heading; unique within the document.
+    pub slug: String,
+    // 1-based line of the heading in the scanned Markdown.
+    pub line: usize,
+}
+
+// ATX headings (`# Title`, `## Title ##`) in document order. Lines inside
+// fenced code are skipped; setext headings (underlined with `===`) are not
+// recognised. Repeated heading text gets `-1`, `-2`, ... slugs the way
+// GitHub numbers them.
+pub fn headings(markdown: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut used = HashSet::new();
+    let mut fence: Option<&str> = None;
+
+    for (index, raw) in markdown.lines().enumerate() {
+        let line = raw.trim();
+        if let Some(marker) = fence {
+            if line.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| line.starts_with(m)) {
+            fence = Some(marker);
+            continue;
+        }
+
+        // Four or more spaces of indent make an indented code block.
+        if raw.len() - raw.trim_start().len() > 3 {
+            continue;
+        }
+        let Some((level, text)) = parse_atx(line) else {
+            continue;
+        };
+        let slug = unique_slug(&slugify(&text), &mut used);
+        headings.push(Heading {
+            level,
+            text,
+            slug,
+            line: index + 1,
+        });
+    }
+    headings
+}
+
+// A nested bullet list of links, indented relative to the shallowest
+// heading, followed by a blank line; empty when there are no headings.
+pub fn to_markdown(headings: &[Heading]) -> String {
+    let top = headings
+        .iter()
+        .map(|heading| heading.level)
+        .min()
+        .unwrap_or(1);
+    let mut out = String::new();
+    for heading in headings {
+        let indent = "  ".repeat(usize::from(heading.level - top));
+        out.push_str(&format!(
+            "{indent}- [{}](#{})\n",
+            heading.text, heading.slug
+        ));
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_json(headings: &[Heading], path: &Path) -> Result<()> {
+    let mut body = serde_json::to_vec_pretty(headings).context("failed to serialize outline")?;
+    body.push(b'\n');
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent).context("failed to create outline directory")?;
+    }
+    fs::write(path, body).with_context(|| format!("failed to write outline: {}", path.display()))
+}
+
+fn parse_atx(line: &str) -> Option<(u8, String)> {
+    let level = line.chars().take_while(|&ch| ch == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    // A closing run of `#` only counts when whitespace separates it.
+    let rest = rest.trim();
+    let text = match rest.trim_end_matches('#') {
+        stripped if stripped.len() == rest.len() => rest,
+        stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+        _ => rest,
+    };
+    (!text.is_empty()).then(|| (level as u8, text.to_string()))
+}
+
+// GitHub's rules: lowercase, drop punctuation other than `-` and `_`, and
+// turn spaces into hyphens. Letters of any script are kept.
+fn slugify(text: &str) -> String {
+    text.chars()
+        .filter_map(|ch| match ch {
+            ' ' => Some('-'),
+            '-' | '_' => Some(ch),
+            ch if ch.is_alphanumeric() => Some(ch),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn unique_slug(base: &str, used: &mut HashSet<String>) -> String {
+    let mut slug = base.to_string();
+    let mut n = 0;
+    while !used.insert(slug.clone()) {
+        n += 1;
+        slug = format!("{base}-{n}");
+    }
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{Heading, TocTarget, headings, to_markdown};
+
+    fn heading(level: u8, text: &str, slug: &str, line: usize) -> Heading {
+        Heading {
+            level,
+            text: text.to_string(),
+            slug: slug.to_string(),
+            line,
+        }
+    }
+
+    #[test]
+    fn collects_atx_headings_with_level_and_line() {
+        let markdown = "# 年度报告\n\nIntro\n\n## Revenue & Costs ##\n  ### Q1 (draft)\n#NoSpace\n####### too deep\n";
+
+        assert_eq!(
+            headings(markdown),
+            vec![
+                heading(1, "年度报告", "年度报告", 1),
+                heading(2, "Revenue & Costs", "revenue--costs", 5),
+                heading(3, "Q1 (draft)", "q1-draft", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn headings_inside_code_fences_are_skipped() {
+        let markdown = "# Setup\n```bash\n# install\n~~~\n```\n~~~\n## not a heading\n~~~\n    # indented code\n## Usage\n";
+
+        assert_eq!(
+            headings(markdown),
+            vec![
+                heading(1, "Setup", "setup", 1),
+                heading(2, "Usage", "usage", 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_headings_get_unique_slugs() {
+        let markdown = "## Notes\n## Notes\n## Notes-1\n## Notes\n";
+        let slugs: Vec<String> = headings(markdown)
+            .into_iter()
+            .map(|heading| heading.slug)
+            .collect();
+
+        assert_eq!(slugs, ["notes", "notes-1", "notes-1-1", "notes-2"]);
+    }
+
+    #[test]
+    fn markdown_toc_nests_under_the_shallowest_heading() {
+        let toc = to_markdown(&headings("## A\n### B\n## A\n"));
+
+        assert_eq!(toc, "- [A](#a)\n  - [B](#b)\n- [A](#a-1)\n\n");
+        assert_eq!(to_markdown(&[]), "");
+    }
+
+    #[test]
+    fn toc_target_is_inline_or_a_path() {
+        assert_eq!("inline".parse::<TocTarget>().unwrap(), TocTarget::Inline);
+        assert_eq!(
+            "out/report.toc.json".parse::<TocTarget>().unwrap(),
+            TocTarget::Json("out/report.toc.json".into())
+        );
+        assert!(" ".parse::<TocTarget>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::path::Path;
+
+use anyhow::Result;
+use reqwest::Url;
+use reqwest::header::{CONTENT_TYPE, HeaderMap, HeaderValue};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::config::RuntimeConfig;
+use crate::http::{HttpEngine, redact_url};
+use crate::queue::{JobRecord, JobState};
+
+pub const WEBHOOK_TIMEOUT_MS: u64 = 5_000;
+
+// Body POSTed once a job reaches a final state. `job_id` is the run's trace
+// id (`job-7` in the desktop app), so it matches the logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JobNotification {
+    pub job_id: String,
+    pub input: String,
+    pub state: JobState,
+    pub output_path: Option<String>,
+    pub error: Option<String>,
+}
+
+impl JobNotification {
+    // For a one-shot run: the output path on success, the error otherwise.
+    pub fn from_outcome(
+        job_id: impl Into<String>,
+        input: &Path,
+        result: std::result::Result<&Path, String>,
+    ) -> Self {
+        let (state, output_path, error) = match result {
+            Ok(output) => (JobState::Success, Some(output.display().to_string()), None),
+            Err(error) => (JobState::Failed, None, Some(error)),
+        };
+        Self {
+            job_id: job_id.into(),
+            input: input.display().to_string(),
+            state,
+            output_path,
+            error,
+        }
+    }
+
+    // For a queued job; `None` while the job may still run again.
+    pub fn from_job(job_id: impl Into<String>, job: &JobRecord) -> Option<Self> {
+        job.state.is_terminal().then(|| Self {
+            job_id: job_id.into(),
+            input: job.input.clone(),
+            state: job.state.clone(),
+            output_path: job.output.clone(),
+            error: job.error.clone(),
+        })
+    }
+}
+
+// Fire-and-forget completion callback. It has its own engine with a short
+// timeout and no retries, so a slow receiver cannot hold up the queue, and
+// `notify` never fails: the job's outcome is already decided.
+#[derive(Clone)]
+pub struct Webhook {
+    http: HttpEngine,
+    url: String,
+    // Scheme and host only. Webhook URLs often carry a token in the path or
+    // query (Slack, Discord, signed endpoints), so the full URL is never
+    // logged.
+    logged_url: String,
+}
+
+impl Webhook {
+    pub fn new(url: impl Into<String>, runtime: &RuntimeConfig) -> Result<Self> {
+        let mut runtime = runtime.clone();
+        runtime.request_timeout_ms = WEBHOOK_TIMEOUT_MS;
+        runtime.retry_max = 0;
+        runtime.total_deadline_ms = 0;
+        runtime.max_rps = 0.0;
+        runtime.http_max_concurrency = 0;
+        let url = url.into();
+        Ok(Self {
+            http: HttpEngine::new(runtime)?,
+            logged_url: origin(&url),
+            url,
+        })
+    }
+
+    // `OCR2MD_WEBHOOK_URL`, when set and non-empty.
+    pub fn from_env(runtime: &RuntimeConfig) -> Result<Option<Self>> {
+        std::env::var("OCR2MD_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| Self::new(url.trim(), runtime))
+            .transpose()
+    }
+
+    pub async fn notify(&self, notification: &JobNotification) {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let payload = json!(notification);
+        // Receivers often answer 204 or plain text, so only the status
+        // counts; the body is never read.
+        match self
+            .http
+            .post_json_stream(
+                "webhook",
+                &self.url,
+                headers,
+                &payload,
+                &notification.job_id,
+            )
+            .await
+        {
+            Ok(_) => {
+                info!(trace_id = %notification.job_id, state = ?notification.state, "webhook_sent")
+            }
+            Err(err) => warn!(
+                trace_id = %notification.job_id,
+                url = %self.logged_url,
+                error = %self.scrub(&format!("{err:#}")),
+                "webhook_failed"
+            ),
+        }
+    }
+
+    // reqwest errors quote the request URL; swap in the logged form.
+    fn scrub(&self, message: &str) -> String {
+        [redact_url(&self.url), self.url.clone()]
+            .iter()
+            .fold(message.to_string(), |message, url| {
+                message.replace(url.as_str(), &self.logged_url)
+            })
+    }
+}
+
+fn origin(url: &str) -> String {
+    Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| "<invalid url>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::origin;
+
+    #[test]
+    fn only_the_origin_of_a_webhook_url_is_logged() {
+        assert_eq!(
+            origin("https://hooks.slack.com/services/T000/B000/XXXXsecret?token=abc"),
+            "https://hooks.slack.com"
+        );
+        assert_eq!(origin("not a url"), "<invalid url>");
+    }
+}
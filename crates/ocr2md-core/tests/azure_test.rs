@@ -0,0 +1,76 @@
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{header, header_exists, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer, stream: bool) -> LlmClient {
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.retry_max = 0;
+    let mut cfg = LlmConfig::from_sources(
+        LlmProvider::Azure,
+        Some("azure-key".to_string()),
+        Some(server.uri()),
+        Some("gpt-4o".to_string()),
+        None,
+    )
+    .unwrap();
+    cfg.deployment = Some("prod-4o".to_string());
+    cfg.api_version = Some("2024-10-21".to_string());
+    cfg.require_streaming = stream;
+    LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime)
+}
+
+#[tokio::test]
+async fn azure_calls_the_deployment_with_an_api_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/prod-4o/chat/completions"))
+        .and(query_param("api-version", "2024-10-21"))
+        .and(header("api-key", "azure-key"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Title"}}]})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(header_exists("authorization"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let result = client(&server, false)
+        .to_markdown("ocr text", "trace")
+        .await
+        .unwrap();
+
+    assert_eq!(result.markdown, "# Title");
+}
+
+#[tokio::test]
+async fn azure_streams_from_the_same_deployment_url() {
+    let server = MockServer::start().await;
+    let body = "data: {\"choices\":[{\"delta\":{\"content\":\"# Ti\"}}]}\n\n\
+                data: {\"choices\":[{\"delta\":{\"content\":\"tle\"}}]}\n\n\
+                data: [DONE]\n\n";
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/prod-4o/chat/completions"))
+        .and(query_param("api-version", "2024-10-21"))
+        .and(header("api-key", "azure-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut chunks = Vec::new();
+    let result = client(&server, true)
+        .to_markdown_streaming("ocr text", "trace", |chunk| chunks.push(chunk.to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(result.markdown, "# Title");
+    assert_eq!(chunks, ["# Ti", "tle"]);
+}
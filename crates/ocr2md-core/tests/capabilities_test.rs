@@ -0,0 +1,26 @@
+use ocr2md_core::capabilities::capabilities;
+
+#[test]
+fn capabilities_json_lists_core_formats_and_providers() {
+    let json = serde_json::to_value(capabilities()).unwrap();
+
+    let kinds = json["input_kinds"].as_array().unwrap();
+    for kind in ["pdf", "doc", "docx"] {
+        assert!(kinds.iter().any(|value| value == kind), "missing {kind}");
+    }
+
+    let providers = json["llm_providers"].as_array().unwrap();
+    for provider in ["openai", "anthropic", "gemini", "openai-compatible"] {
+        assert!(
+            providers.iter().any(|value| value == provider),
+            "missing {provider}"
+        );
+    }
+
+    assert_eq!(
+        json["ocr_backends"],
+        serde_json::json!(["glm", "gemini", "openai"])
+    );
+
+    assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+}
@@ -0,0 +1,85 @@
+use ocr2md_core::chunk::{merge_markdown, split_text};
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn short_text_is_a_single_chunk() {
+    let text = "# Title\n\nbody";
+    assert_eq!(split_text(text, 100, 20), vec![text.to_string()]);
+}
+
+#[test]
+fn splits_on_paragraphs_and_carries_overlap() {
+    let text = "# Intro\n\nalpha alpha\n\nbeta beta\n\ngamma gamma";
+    let chunks = split_text(text, 30, 12);
+
+    assert_eq!(
+        chunks,
+        vec![
+            "# Intro\n\nalpha alpha".to_string(),
+            "alpha alpha\n\nbeta beta".to_string(),
+            "beta beta\n\ngamma gamma".to_string(),
+        ]
+    );
+    assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 30));
+}
+
+#[test]
+fn long_paragraph_breaks_between_words() {
+    let chunks = split_text("one two three four five six", 10, 0);
+    assert_eq!(chunks, vec!["one two", "three four", "five six"]);
+}
+
+#[test]
+fn merge_drops_overlap_and_repeated_heading() {
+    let parts = vec![
+        "# Intro\n\nalpha alpha".to_string(),
+        "alpha alpha\n\nbeta beta".to_string(),
+        "# Intro\n\ngamma gamma".to_string(),
+    ];
+    assert_eq!(
+        merge_markdown(&parts),
+        "# Intro\n\nalpha alpha\n\nbeta beta\n\ngamma gamma"
+    );
+}
+
+#[tokio::test]
+async fn oversized_input_is_sent_in_chunks_and_merged() {
+    let server = MockServer::start().await;
+    for (needle, reply) in [
+        ("first part", "# Report\n\nfirst part"),
+        ("second part", "# Report\n\nsecond part"),
+    ] {
+        Mock::given(method("POST"))
+            .and(body_string_contains(needle))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "choices": [{"message": {"content": reply}}]
+            })))
+            .mount(&server)
+            .await;
+    }
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.llm_chunk_chars = 20;
+    let cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("key".to_string()),
+        Some(server.uri()),
+        None,
+        None,
+    )
+    .unwrap();
+    let client = LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime);
+
+    let markdown = client
+        .to_markdown("first part\n\nsecond part", "trace")
+        .await
+        .unwrap();
+
+    assert_eq!(markdown.markdown, "# Report\n\nfirst part\n\nsecond part");
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
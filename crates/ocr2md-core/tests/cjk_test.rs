@@ -0,0 +1,29 @@
+use ocr2md_core::cjk::normalize;
+
+#[test]
+fn inserts_spaces_between_cjk_and_latin() {
+    assert_eq!(
+        normalize("使用Rust编写的OCR工具,支持PDF和DOCX格式。"),
+        "使用 Rust 编写的 OCR 工具，支持 PDF 和 DOCX 格式。"
+    );
+    assert_eq!(normalize("共３页 ，第2页"), "共 3 页，第 2 页");
+    assert_eq!(normalize("已经 spaced 的 text"), "已经 spaced 的 text");
+}
+
+#[test]
+fn leaves_urls_and_ascii_punctuation_alone() {
+    assert_eq!(
+        normalize("见http://example.com:8080/a,b 文档"),
+        "见 http://example.com:8080/a,b 文档"
+    );
+}
+
+#[test]
+fn code_blocks_and_inline_code_are_untouched() {
+    let markdown =
+        "运行`cargo test`命令:\n\n```rust\nlet 名字=\"值\";println!(\"{名字}\");\n```\n\n结束End";
+    assert_eq!(
+        normalize(markdown),
+        "运行`cargo test`命令：\n\n```rust\nlet 名字=\"值\";println!(\"{名字}\");\n```\n\n结束 End"
+    );
+}
@@ -0,0 +1,60 @@
+// Each test crate uses only some of these.
+#![allow(dead_code)]
+
+use lopdf::{Document, Object, dictionary};
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::ocr::GlmConfig;
+
+// GLM OCR against a mock server: a dummy key, the default model, and the OCR
+// and file-parse endpoints under `base_url`. Tests set the fields they
+// exercise on the result.
+pub fn glm_config(base_url: impl Into<String>) -> GlmConfig {
+    GlmConfig::from_sources(
+        Some("glm-key".to_string()),
+        Some(base_url.into()),
+        None,
+        None,
+        None,
+        RuntimeConfig::from_env().max_ocr_chars,
+    )
+    .unwrap()
+}
+
+pub fn pdf_with_pages(pages: usize) -> Vec<u8> {
+    pdf_with_page_widths(&vec![595; pages])
+}
+
+// One page per width, so tests can tell pages apart after a split.
+pub fn pdf_with_page_widths(widths: &[i64]) -> Vec<u8> {
+    let mut document = Document::with_version("1.7");
+    let pages_id = document.new_object_id();
+    let kids: Vec<Object> = widths
+        .iter()
+        .map(|&width| {
+            document
+                .add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "MediaBox" => vec![0.into(), 0.into(), width.into(), 842.into()],
+                })
+                .into()
+        })
+        .collect();
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => widths.len() as i64,
+        }),
+    );
+    let catalog_id = document.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    document.trailer.set("Root", catalog_id);
+
+    let mut out = Vec::new();
+    document.save_to(&mut out).unwrap();
+    out
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use std::io::{Cursor, Write};
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::docx::extract_text;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{GlmConfig, GlmOcrClient};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn docx_with_body(body: &str) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+    writer.start_file("word/document.xml", options).unwrap();
+    write!(
+        writer,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{body}</w:body></w:document>"#
+    )
+    .unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+fn glm_config(server: &MockServer, local_docx: bool) -> GlmConfig {
+    let mut cfg = common::glm_config(server.uri());
+    cfg.local_docx = local_docx;
+    cfg
+}
+
+#[test]
+fn extracts_runs_in_order_one_line_per_paragraph() {
+    let docx = docx_with_body(
+        "<w:p><w:r><w:t>Quarterly </w:t></w:r><w:r><w:t>report</w:t></w:r></w:p>\
+         <w:p/>\
+         <w:p><w:r><w:t>A</w:t><w:tab/><w:t xml:space=\"preserve\">B &amp; C</w:t></w:r></w:p>",
+    );
+
+    assert_eq!(extract_text(&docx).unwrap(), "Quarterly report\n\nA\tB & C");
+}
+
+#[test]
+fn rejects_files_that_are_not_docx() {
+    assert!(extract_text(b"\xd0\xcf\x11\xe0 legacy doc").is_err());
+}
+
+#[tokio::test]
+async fn docx_is_read_locally_without_calling_the_api() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/parse"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"content": "remote"})))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let client = GlmOcrClient::new(http, glm_config(&server, true));
+    let docx = docx_with_body("<w:p><w:r><w:t>local text</w:t></w:r></w:p>");
+    let text = client
+        .extract_text(InputKind::Docx, &docx, "trace-test")
+        .await
+        .unwrap();
+
+    assert_eq!(text, "local text");
+}
+
+#[tokio::test]
+async fn empty_or_disabled_local_extraction_uses_the_api() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/parse"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"content": "remote"})))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let empty = docx_with_body("<w:p/>");
+    let client = GlmOcrClient::new(http.clone(), glm_config(&server, true));
+    let text = client
+        .extract_text(InputKind::Docx, &empty, "trace-test")
+        .await
+        .unwrap();
+    assert_eq!(text, "remote");
+
+    let docx = docx_with_body("<w:p><w:r><w:t>local text</w:t></w:r></w:p>");
+    let client = GlmOcrClient::new(http, glm_config(&server, false));
+    let text = client
+        .extract_text(InputKind::Docx, &docx, "trace-test")
+        .await
+        .unwrap();
+    assert_eq!(text, "remote");
+}
+
+#[tokio::test]
+async fn file_parse_sends_the_configured_prompt() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/files/parse"))
+        .and(body_partial_json(json!({"prompt": "Keep tables as-is."})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"content": "remote"})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let mut cfg = glm_config(&server, false);
+    cfg.file_parse_prompt = Some("Keep tables as-is.".to_string());
+    let docx = docx_with_body("<w:p><w:r><w:t>local text</w:t></w:r></w:p>");
+    let text = GlmOcrClient::new(http, cfg)
+        .extract_text(InputKind::Docx, &docx, "trace-test")
+        .await
+        .unwrap();
+
+    assert_eq!(text, "remote");
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::error::AppError;
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::{EmptyOcrPolicy, ProcessOptions, process_file_with};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+fn empty_and_blank_text_follow_the_policy() {
+    for text in ["", " \n\t\u{3000}\n"] {
+        assert!(EmptyOcrPolicy::Warn.proceeds(text).unwrap());
+        assert!(!EmptyOcrPolicy::Skip.proceeds(text).unwrap());
+        assert!(matches!(
+            EmptyOcrPolicy::Fail.proceeds(text),
+            Err(AppError::EmptyOcr)
+        ));
+    }
+}
+
+#[test]
+fn real_text_always_proceeds() {
+    for policy in [
+        EmptyOcrPolicy::Warn,
+        EmptyOcrPolicy::Skip,
+        EmptyOcrPolicy::Fail,
+    ] {
+        assert!(policy.proceeds("  Invoice 42\n").unwrap(), "{policy:?}");
+    }
+}
+
+async fn run_blank_scan(policy: EmptyOcrPolicy) -> (tempfile::TempDir, anyhow::Result<()>) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "  \n"}}]})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("blank.pdf");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+    let options = ProcessOptions {
+        on_empty_ocr: policy,
+        ..ProcessOptions::default()
+    };
+
+    let result = process_file_with(
+        &input,
+        &dir.path().join("blank.md"),
+        glm_cfg,
+        llm_cfg,
+        runtime,
+        &options,
+        "trace",
+    )
+    .await;
+    (dir, result)
+}
+
+#[tokio::test]
+async fn skip_writes_nothing_and_never_calls_the_llm() {
+    let (dir, result) = run_blank_scan(EmptyOcrPolicy::Skip).await;
+
+    result.unwrap();
+    assert!(!dir.path().join("blank.md").exists());
+}
+
+#[tokio::test]
+async fn fail_reports_empty_ocr() {
+    let (dir, result) = run_blank_scan(EmptyOcrPolicy::Fail).await;
+
+    let err = result.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref::<AppError>(), Some(AppError::EmptyOcr)),
+        "{err:#}"
+    );
+    assert!(!dir.path().join("blank.md").exists());
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use std::path::Path;
+
+use ocr2md_core::estimate::{
+    LlmRate, OcrRate, Pricing, RateTable, WORD_BYTES_PER_PAGE, estimate_batch, estimate_pages,
+};
+use ocr2md_core::file_kind::InputKind;
+use pretty_assertions::assert_eq;
+
+use common::pdf_with_pages;
+
+fn pricing() -> Pricing {
+    Pricing {
+        currency: "CNY".to_string(),
+        ocr: OcrRate {
+            per_page: 0.5,
+            chars_per_page: 1000,
+        },
+        llm: LlmRate {
+            input_per_million_tokens: 2.0,
+            output_per_million_tokens: 8.0,
+            chars_per_token: 4.0,
+        },
+    }
+}
+
+#[test]
+fn page_count_drives_chars_tokens_and_cost() {
+    let estimate = estimate_pages(Path::new("a.pdf"), InputKind::Pdf, 1234, 4, &pricing());
+
+    assert_eq!(estimate.chars, 4000);
+    assert_eq!(estimate.input_tokens, 1000);
+    assert_eq!(estimate.output_tokens, 1000);
+    assert_eq!(estimate.ocr_cost, 2.0);
+    // 1000 tokens in at 2 and out at 8 per million.
+    assert_eq!(estimate.llm_cost, 0.01);
+    assert_eq!(estimate.cost, 2.01);
+}
+
+#[test]
+fn batch_sums_pdf_pages_images_and_word_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let pdf = dir.path().join("report.pdf");
+    std::fs::write(&pdf, pdf_with_pages(3)).unwrap();
+    let image = dir.path().join("scan.png");
+    std::fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+    let word = dir.path().join("memo.docx");
+    let mut docx = b"PK\x03\x04".to_vec();
+    docx.resize(WORD_BYTES_PER_PAGE as usize + 1, 0);
+    std::fs::write(&word, docx).unwrap();
+
+    let estimate = estimate_batch(&[pdf, image, word], &pricing()).unwrap();
+    let pages: Vec<u64> = estimate.files.iter().map(|file| file.pages).collect();
+
+    assert_eq!(pages, [3, 1, 2]);
+    assert_eq!(estimate.total.files, 3);
+    assert_eq!(estimate.total.pages, 6);
+    assert_eq!(estimate.total.input_tokens, 1500);
+    assert!((estimate.total.cost - (6.0 * 0.5 + 1500.0 * 10.0 / 1e6)).abs() < 1e-9);
+}
+
+#[test]
+fn rate_file_overrides_by_model_then_provider() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("rates.json");
+    std::fs::write(
+        &path,
+        r#"{"currency": "CNY",
+            "ocr": {"glm": {"per_page": 0.05, "chars_per_page": 1500}},
+            "llm": {"deepseek-chat": {"input_per_million_tokens": 2, "output_per_million_tokens": 8, "chars_per_token": 1.5}}}"#,
+    )
+    .unwrap();
+    let table = RateTable::load(&path).unwrap();
+    let builtin = RateTable::default().pricing("glm", None, "openai", None);
+
+    let pricing = table.pricing(
+        "glm",
+        Some("glm-4.1v"),
+        "openai-compatible",
+        Some("deepseek-chat"),
+    );
+    assert_eq!(pricing.currency, "CNY");
+    assert_eq!(pricing.ocr.per_page, 0.05);
+    assert_eq!(pricing.llm.chars_per_token, 1.5);
+
+    let fallback = table.pricing("gemini", None, "openai", Some("gpt-4o"));
+    assert_eq!(fallback.ocr, builtin.ocr);
+    assert_eq!(fallback.llm, builtin.llm);
+
+    std::fs::write(
+        &path,
+        r#"{"llm": {"x": {"input_per_million_tokens": 1, "output_per_million_tokens": 1, "chars_per_token": 0}}}"#,
+    )
+    .unwrap();
+    assert!(RateTable::load(&path).is_err());
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::file_kind::{
+    InputKind, detect_input_kind, detect_input_kind_from_bytes, detect_stdin_kind,
+};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::GlmOcrClient;
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn zip_with(entry: &str) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file(entry, zip::write::SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(b"<xml/>").unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn magic_bytes_override_the_extension() {
+    let docx = zip_with("word/document.xml");
+    let cases: [(&str, &[u8], InputKind); 6] = [
+        ("scan.txt", b"%PDF-1.7\n", InputKind::Pdf),
+        ("photo.pdf", b"\xFF\xD8\xFF\xE0\0\x10JFIF", InputKind::Jpeg),
+        ("memo.bin", &docx, InputKind::Docx),
+        (
+            "old.docx",
+            b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1\0\0",
+            InputKind::Doc,
+        ),
+        ("shot.jpg", b"\x89PNG\r\n\x1a\n\0\0", InputKind::Png),
+        ("page", b"RIFF\x24\0\0\0WEBPVP8 ", InputKind::Webp),
+    ];
+    for (name, bytes, expected) in cases {
+        assert_eq!(
+            detect_input_kind_from_bytes(Path::new(name), bytes).unwrap(),
+            expected,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn other_zip_containers_are_not_taken_for_docx() {
+    let workbook = zip_with("xl/workbook.xml");
+    let err = detect_input_kind_from_bytes(Path::new("sheet.xlsx"), &workbook).unwrap_err();
+    assert!(matches!(err, AppError::UnsupportedInputType(_)), "{err}");
+    assert!(detect_stdin_kind(&workbook, None).is_err());
+
+    // Without a Word body the extension decides, as for any unknown content.
+    assert_eq!(
+        detect_input_kind_from_bytes(Path::new("memo.docx"), &workbook).unwrap(),
+        InputKind::Docx
+    );
+}
+
+#[test]
+fn stdin_kind_is_sniffed_before_the_declared_one() {
+    assert_eq!(
+        detect_stdin_kind(b"%PDF-1.7\n", Some(InputKind::Png)).unwrap(),
+        InputKind::Pdf
+    );
+    assert_eq!(
+        detect_stdin_kind(b"\0\x01garbage", Some(InputKind::Doc)).unwrap(),
+        InputKind::Doc
+    );
+    assert!(detect_stdin_kind(b"\0\x01garbage", None).is_err());
+}
+
+#[test]
+fn unknown_content_falls_back_to_the_extension() {
+    assert_eq!(
+        detect_input_kind_from_bytes(Path::new("report.docx"), b"\0\x01garbage").unwrap(),
+        InputKind::Docx
+    );
+    assert!(detect_input_kind_from_bytes(Path::new("notes.txt"), b"plain text").is_err());
+    assert!(detect_input_kind(Path::new("scan.txt")).is_err());
+}
+
+#[test]
+fn empty_and_plain_text_inputs_are_rejected_before_ocr() {
+    let dir = tempfile::tempdir().unwrap();
+    let empty = dir.path().join("blank.pdf");
+    std::fs::write(&empty, b"").unwrap();
+    let renamed = dir.path().join("notes.pdf");
+    std::fs::write(&renamed, "会议纪要\nminutes of the meeting\n").unwrap();
+
+    let err = detect_input_kind_from_bytes(&empty, &std::fs::read(&empty).unwrap()).unwrap_err();
+    assert!(matches!(err, AppError::EmptyInput(_)), "{err}");
+    let err =
+        detect_input_kind_from_bytes(&renamed, &std::fs::read(&renamed).unwrap()).unwrap_err();
+    assert!(matches!(err, AppError::UnsupportedInputType(_)), "{err}");
+    assert!(err.to_string().contains("plain text"), "{err}");
+
+    assert!(matches!(
+        detect_stdin_kind(b"", Some(InputKind::Pdf)),
+        Err(AppError::EmptyInput(_))
+    ));
+    assert!(detect_stdin_kind(b"just text", Some(InputKind::Png)).is_err());
+    assert_eq!(
+        detect_input_kind_from_bytes(Path::new("scan.pdf"), b"\r\n%PDF-1.4\n").unwrap(),
+        InputKind::Pdf
+    );
+}
+
+#[tokio::test]
+async fn mislabeled_pdf_is_sent_to_vision_ocr_as_pdf() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("data:application/pdf;base64,"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "pdf text"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let cfg = common::glm_config(server.uri());
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let kind = detect_input_kind_from_bytes(Path::new("scan.txt"), b"%PDF-1.7").unwrap();
+    let text = GlmOcrClient::new(http, cfg)
+        .extract_text(kind, b"%PDF-1.7", "trace-test")
+        .await
+        .unwrap();
+
+    assert_eq!(text, "pdf text");
+}
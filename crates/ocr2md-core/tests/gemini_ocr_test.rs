@@ -0,0 +1,86 @@
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{GeminiOcrConfig, GlmConfig, GlmOcrClient, OcrProvider};
+use serde_json::{Value, json};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn gemini_config(server: &MockServer) -> GlmConfig {
+    let mut cfg = GlmConfig::from_sources_for(
+        OcrProvider::Glm,
+        Some("glm-key".to_string()),
+        Some(server.uri()),
+        None,
+        None,
+        None,
+        10_000,
+    )
+    .unwrap();
+    cfg.provider = OcrProvider::Gemini;
+    cfg.gemini = Some(GeminiOcrConfig {
+        api_key: "gemini-key".to_string(),
+        base_url: server.uri(),
+        model: "gemini-test".to_string(),
+    });
+    cfg
+}
+
+#[tokio::test]
+async fn pdf_is_sent_inline_to_gemini_instead_of_glm() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/models/gemini-test:generateContent"))
+        .and(header("x-goog-api-key", "gemini-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{"content": {"parts": [{"text": "gemini text"}]}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let text = GlmOcrClient::new(http, gemini_config(&server))
+        .extract_text(InputKind::Pdf, b"%PDF-1.7", "trace-test")
+        .await
+        .unwrap();
+    assert_eq!(text, "gemini text");
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let parts = &body["contents"][0]["parts"];
+    assert_eq!(parts[0]["inline_data"]["mime_type"], "application/pdf");
+    assert_eq!(parts[0]["inline_data"]["data"], "JVBERi0xLjc=");
+    assert!(
+        parts[1]["text"]
+            .as_str()
+            .unwrap()
+            .contains("请提取文档完整内容")
+    );
+}
+
+#[tokio::test]
+async fn word_files_without_a_glm_key_report_the_missing_key() {
+    let server = MockServer::start().await;
+    let mut cfg = gemini_config(&server);
+    cfg.api_key = String::new();
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let err = GlmOcrClient::new(http, cfg)
+        .extract_text(
+            InputKind::Doc,
+            b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1",
+            "trace-test",
+        )
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("GLM_API_KEY"), "{err:#}");
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
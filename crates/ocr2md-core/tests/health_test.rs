@@ -0,0 +1,89 @@
+use futures::future::{BoxFuture, FutureExt};
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::health::{ConnectionFailure, aggregate, connection_test_runtime, liveness};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn probe(ok: bool) -> BoxFuture<'static, anyhow::Result<()>> {
+    async move {
+        if ok {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("connection refused"))
+        }
+    }
+    .boxed()
+}
+
+#[test]
+fn liveness_is_always_true() {
+    assert!(liveness());
+}
+
+#[tokio::test]
+async fn readiness_requires_every_probe() {
+    let report = aggregate(vec![
+        ("glm".to_string(), probe(true)),
+        ("llm".to_string(), probe(false)),
+    ])
+    .await;
+
+    assert!(!report.ready);
+    assert_eq!(report.probes.len(), 2);
+    assert!(report.probes[0].ready);
+    assert_eq!(report.probes[1].name, "llm");
+    assert_eq!(
+        report.probes[1].error.as_deref(),
+        Some("connection refused")
+    );
+
+    let report = aggregate(vec![("glm".to_string(), probe(true))]).await;
+    assert!(report.ready);
+}
+
+async fn ping_with_status(status: u16) -> Result<String, ConnectionFailure> {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(status).set_body_json(json!({
+            "choices": [{"message": {"content": " OK "}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let runtime = connection_test_runtime(RuntimeConfig::from_env());
+    let http = HttpEngine::new(runtime.clone()).unwrap();
+    let cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("sk-test".to_string()),
+        Some(server.uri()),
+        Some("gpt-test".to_string()),
+        None::<String>,
+    )
+    .unwrap();
+    LlmClient::new(http, cfg, runtime)
+        .ping("trace-test")
+        .await
+        .map_err(|err| ConnectionFailure::classify(&err))
+}
+
+#[tokio::test]
+async fn connection_test_classifies_failures() {
+    assert_eq!(ping_with_status(200).await, Ok("OK".to_string()));
+    assert!(matches!(
+        ping_with_status(401).await,
+        Err(ConnectionFailure::Auth(_))
+    ));
+    assert!(matches!(
+        ping_with_status(403).await,
+        Err(ConnectionFailure::Auth(_))
+    ));
+    assert!(matches!(
+        ping_with_status(404).await,
+        Err(ConnectionFailure::ModelNotFound(_))
+    ));
+}
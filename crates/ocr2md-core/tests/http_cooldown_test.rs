@@ -0,0 +1,48 @@
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::http::HttpEngine;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+
+fn refused_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    format!("http://127.0.0.1:{port}/v1/chat/completions")
+}
+
+#[tokio::test]
+async fn repeated_connect_failures_trigger_host_cooldown() {
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.retry_max = 0;
+    runtime.connect_failure_threshold = 2;
+    runtime.connect_failure_window_ms = 10_000;
+    runtime.host_cooldown_ms = 400;
+    let http = HttpEngine::new(runtime).unwrap();
+    let url = refused_url();
+    let payload = json!({});
+
+    let post = || http.post_json("test", &url, HeaderMap::new(), &payload, "trace");
+
+    assert!(post().await.is_err());
+    assert!(http.host_cooldown_remaining(&url).is_none());
+
+    assert!(post().await.is_err());
+    let remaining = http
+        .host_cooldown_remaining(&url)
+        .expect("host cooling down");
+    assert!(remaining <= Duration::from_millis(400));
+
+    // A clone shares the cooldown and waits it out before connecting again.
+    let clone = http.clone();
+    let started = Instant::now();
+    assert!(
+        clone
+            .post_json("test", &url, HeaderMap::new(), &payload, "trace")
+            .await
+            .is_err()
+    );
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::http::HttpEngine;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn stalled_server_gives_up_at_the_total_deadline() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.request_timeout_ms = 300;
+    runtime.retry_max = 10;
+    runtime.retry_base_ms = 20;
+    runtime.total_deadline_ms = 700;
+    let http = HttpEngine::new(runtime).unwrap();
+
+    let started = Instant::now();
+    let err = http
+        .post_json("test", &server.uri(), HeaderMap::new(), &json!({}), "trace")
+        .await
+        .unwrap_err();
+    let elapsed = started.elapsed();
+
+    assert!(
+        matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::DeadlineExceeded {
+                deadline_ms: 700,
+                ..
+            })
+        ),
+        "{err:#}"
+    );
+    // Eleven 300 ms attempts would take over three seconds.
+    assert!(elapsed >= Duration::from_millis(600), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(1_200), "{elapsed:?}");
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::http::HttpEngine;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn concurrent_calls_are_spaced_to_the_configured_rate() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+        .expect(5)
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.max_rps = 10.0;
+    let http = HttpEngine::new(runtime).unwrap();
+
+    let started = Instant::now();
+    let calls = (0..5).map(|i| {
+        let http = http.clone();
+        let url = server.uri();
+        async move {
+            http.post_json("test", &url, HeaderMap::new(), &json!({}), &format!("t{i}"))
+                .await
+                .unwrap()
+        }
+    });
+    futures::future::join_all(calls).await;
+    let elapsed = started.elapsed();
+
+    // The first call goes straight out; the other four wait 100 ms apiece.
+    assert!(elapsed >= Duration::from_millis(390), "{elapsed:?}");
+    assert!(elapsed < Duration::from_millis(1_500), "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn retries_wait_for_the_rate_limiter_too() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.max_rps = 5.0;
+    runtime.retry_max = 2;
+    runtime.retry_base_ms = 1;
+    let http = HttpEngine::new(runtime).unwrap();
+
+    let started = Instant::now();
+    http.post_json("test", &server.uri(), HeaderMap::new(), &json!({}), "trace")
+        .await
+        .unwrap_err();
+    let elapsed = started.elapsed();
+
+    assert!(elapsed >= Duration::from_millis(390), "{elapsed:?}");
+}
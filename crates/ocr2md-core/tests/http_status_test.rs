@@ -0,0 +1,99 @@
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::http::HttpEngine;
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn failed_status_names_the_service() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("upstream exploded"))
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.retry_max = 0;
+    let http = HttpEngine::new(runtime).unwrap();
+
+    let err = http
+        .post_json(
+            "glm_ocr",
+            &server.uri(),
+            HeaderMap::new(),
+            &json!({}),
+            "trace",
+        )
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::ApiStatus { service, status: 500, .. }) if service == "glm_ocr"
+        ),
+        "{err:#}"
+    );
+    assert_eq!(
+        err.to_string(),
+        "glm_ocr API call failed with status 500: upstream exploded"
+    );
+}
+
+#[tokio::test]
+async fn keys_echoed_in_an_error_body_are_redacted() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": {
+                "message": "Incorrect API key provided: relay-7f3e9c2a1b. Did you mean sk-proj-a1b2c3d4e5f6 or AIzaSyA1b2C3d4E5f6G7h8I9j0K?",
+                "type": "invalid_request_error"
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.retry_max = 0;
+    let http = HttpEngine::new(runtime).unwrap();
+    let mut headers = HeaderMap::new();
+    headers.insert("authorization", "Bearer relay-7f3e9c2a1b".parse().unwrap());
+
+    let err = http
+        .post_json(
+            "llm_openai_compatible",
+            &format!("{}/chat?key=relay-7f3e9c2a1b", server.uri()),
+            headers,
+            &json!({}),
+            "trace",
+        )
+        .await
+        .unwrap_err();
+
+    let message = format!("{err:#}");
+    for secret in [
+        "relay-7f3e9c2a1b",
+        "sk-proj-a1b2c3d4e5f6",
+        "AIzaSyA1b2C3d4E5f6G7h8I9j0K",
+    ] {
+        assert!(!message.contains(secret), "{message}");
+    }
+    assert!(
+        message.contains("Incorrect API key provided: [REDACTED]"),
+        "{message}"
+    );
+
+    // A message built by hand, without going through the engine, is still
+    // scrubbed when displayed.
+    let built = AppError::ApiStatus {
+        service: "llm_gemini".to_string(),
+        status: 400,
+        message: "bad request for ?key=AIzaSyZ9y8X7w6V5u4T3s2R1q0P&alt=json".to_string(),
+    };
+    assert_eq!(
+        built.to_string(),
+        "llm_gemini API call failed with status 400: bad request for ?key=[REDACTED]&alt=json"
+    );
+}
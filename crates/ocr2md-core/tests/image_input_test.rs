@@ -0,0 +1,54 @@
+mod common;
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat, RgbImage};
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{GlmOcrClient, TRUNCATION_MARKER};
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn png() -> Vec<u8> {
+    let mut out = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(RgbImage::new(8, 8))
+        .write_to(&mut out, ImageFormat::Png)
+        .unwrap();
+    out.into_inner()
+}
+
+#[tokio::test]
+async fn image_is_sent_as_data_url_and_result_is_limited() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "receipt total 42.00"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut cfg = common::glm_config(server.uri());
+    cfg.max_ocr_chars = 7;
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let text = GlmOcrClient::new(http, cfg)
+        .extract_text(InputKind::Png, &png(), "trace-test")
+        .await
+        .unwrap();
+
+    assert_eq!(text, format!("receipt\n\n{TRUNCATION_MARKER}"));
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let part = &body["messages"][0]["content"][0];
+    assert_eq!(part["type"], "image_url");
+    assert!(
+        part["image_url"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/png;base64,")
+    );
+}
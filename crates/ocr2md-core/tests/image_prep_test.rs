@@ -0,0 +1,69 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, GenericImageView, ImageFormat, RgbImage};
+use ocr2md_core::image_prep::{auto_rotate, downscale, prepare};
+
+fn jpeg(width: u32, height: u32) -> Vec<u8> {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(width, height));
+    let mut out = Cursor::new(Vec::new());
+    image.write_to(&mut out, ImageFormat::Jpeg).unwrap();
+    out.into_inner()
+}
+
+// Inserts an APP1 segment carrying a single EXIF orientation tag.
+fn with_exif_orientation(jpeg: &[u8], orientation: u16) -> Vec<u8> {
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"MM\0\x2a\0\0\0\x08");
+    tiff.extend_from_slice(&1u16.to_be_bytes());
+    tiff.extend_from_slice(&0x0112u16.to_be_bytes());
+    tiff.extend_from_slice(&3u16.to_be_bytes());
+    tiff.extend_from_slice(&1u32.to_be_bytes());
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]);
+    tiff.extend_from_slice(&0u32.to_be_bytes());
+
+    let mut segment = b"Exif\0\0".to_vec();
+    segment.extend_from_slice(&tiff);
+
+    let mut out = jpeg[..2].to_vec();
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&((segment.len() + 2) as u16).to_be_bytes());
+    out.extend_from_slice(&segment);
+    out.extend_from_slice(&jpeg[2..]);
+    out
+}
+
+#[test]
+fn exif_rotated_image_is_turned_upright() {
+    let rotated = with_exif_orientation(&jpeg(40, 20), 6);
+
+    let upright = auto_rotate(&rotated).unwrap().expect("rotation applied");
+    let decoded = image::load_from_memory(&upright).unwrap();
+
+    assert_eq!(decoded.dimensions(), (20, 40));
+}
+
+#[test]
+fn upright_image_is_left_untouched() {
+    assert!(auto_rotate(&jpeg(40, 20)).unwrap().is_none());
+}
+
+#[test]
+fn oversized_image_is_downscaled_to_longest_side() {
+    let scaled = downscale(&jpeg(400, 100), 200)
+        .unwrap()
+        .expect("downscaled");
+    let decoded = image::load_from_memory(&scaled).unwrap();
+    assert_eq!(decoded.dimensions(), (200, 50));
+
+    assert!(downscale(&jpeg(100, 40), 200).unwrap().is_none());
+    assert!(downscale(&jpeg(400, 100), 0).unwrap().is_none());
+}
+
+#[test]
+fn prepare_rotates_then_caps_dimension() {
+    let rotated = with_exif_orientation(&jpeg(400, 100), 6);
+    let prepared = prepare(&rotated, true, 200).unwrap();
+    let decoded = image::load_from_memory(&prepared).unwrap();
+    assert_eq!(decoded.dimensions(), (50, 200));
+}
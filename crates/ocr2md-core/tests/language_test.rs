@@ -0,0 +1,74 @@
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::language::detect_language;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ENGLISH: &str = "The quarterly report summarises revenue, operating costs and the \
+outlook for the next financial year. All figures are unaudited and subject to change.";
+const CHINESE: &str = "本合同由甲乙双方在平等自愿的基础上订立，双方应当按照约定全面履行各自的义务。\
+合同履行过程中发生争议的，应当协商解决；协商不成的，提交仲裁委员会仲裁。";
+const JAPANESE: &str = "この契約書は、甲と乙の間で締結されるものであり、双方はここに定める条件に従って\
+誠実に義務を履行するものとします。";
+
+fn client(server: &MockServer, language: Option<&str>) -> LlmClient {
+    let runtime = RuntimeConfig::from_env();
+    let mut cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(server.uri()),
+        Some("llm-test".to_string()),
+        None,
+    )
+    .unwrap();
+    cfg.document_language = language.map(str::to_string);
+    LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime)
+}
+
+async fn expect_prompt_containing(server: &MockServer, needle: &str) {
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains(needle))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "# Doc"}}]
+        })))
+        .expect(1)
+        .mount(server)
+        .await;
+}
+
+#[test]
+fn dominant_language_is_detected() {
+    assert_eq!(detect_language(ENGLISH).as_deref(), Some("English"));
+    assert_eq!(detect_language(CHINESE).as_deref(), Some("Mandarin"));
+    assert_eq!(detect_language(JAPANESE).as_deref(), Some("Japanese"));
+    assert_eq!(detect_language("42"), None);
+
+    let mixed = format!("Master Services Agreement\n\n{CHINESE}\n\n{CHINESE}");
+    assert_eq!(detect_language(&mixed).as_deref(), Some("Mandarin"));
+}
+
+#[tokio::test]
+async fn detected_language_reaches_the_prompt() {
+    let server = MockServer::start().await;
+    expect_prompt_containing(&server, "文档主要语言为 English").await;
+
+    let result = client(&server, None)
+        .to_markdown(ENGLISH, "trace-lang")
+        .await
+        .unwrap();
+    assert_eq!(result.markdown, "# Doc");
+}
+
+#[tokio::test]
+async fn explicit_language_skips_detection() {
+    let server = MockServer::start().await;
+    expect_prompt_containing(&server, "文档主要语言为 ja").await;
+
+    client(&server, Some("ja"))
+        .to_markdown(ENGLISH, "trace-lang")
+        .await
+        .unwrap();
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn completion(content: &str) -> serde_json::Value {
+    json!({"choices": [{"message": {"content": content}}]})
+}
+
+#[tokio::test]
+async fn compare_returns_candidates_in_input_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/slow/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(completion("# slow"))
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/fast/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("# fast")))
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.http_max_concurrency = 2;
+    let http = HttpEngine::new(runtime.clone()).unwrap();
+
+    let candidates: Vec<LlmClient> = ["slow", "fast"]
+        .iter()
+        .map(|name| {
+            let cfg = LlmConfig::from_sources(
+                LlmProvider::OpenaiCompatible,
+                Some("key".to_string()),
+                Some(format!("{}/{name}", server.uri())),
+                Some(format!("model-{name}")),
+                None,
+            )
+            .unwrap();
+            LlmClient::new(http.clone(), cfg, runtime.clone())
+        })
+        .collect();
+
+    let results = LlmClient::compare(&candidates, "text", "trace").await;
+
+    let order: Vec<(usize, String)> = results
+        .iter()
+        .map(|result| (result.index, result.result.as_ref().unwrap().clone()))
+        .collect();
+    assert_eq!(
+        order,
+        vec![(0, "# slow".to_string()), (1, "# fast".to_string())]
+    );
+    assert_eq!(results[1].model, "model-fast");
+}
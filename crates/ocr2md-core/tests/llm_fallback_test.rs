@@ -0,0 +1,124 @@
+mod common;
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::{ProcessOptions, process_file_with_fallback};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn mount(server: &MockServer, route: &str, response: ResponseTemplate) {
+    Mock::given(method("POST"))
+        .and(path(route))
+        .respond_with(response)
+        .mount(server)
+        .await;
+}
+
+fn content(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({"choices": [{"message": {"content": text}}]}))
+}
+
+fn llm(server: &MockServer, prefix: &str, model: &str) -> LlmConfig {
+    LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/{prefix}", server.uri())),
+        Some(model.to_string()),
+        None,
+    )
+    .unwrap()
+}
+
+async fn run(
+    server: &MockServer,
+    llms: &[LlmConfig],
+) -> (tempfile::TempDir, anyhow::Result<usize>) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.retry_max = 0;
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+
+    let result = process_file_with_fallback(
+        &input,
+        &dir.path().join("scan.md"),
+        glm_cfg,
+        llms,
+        runtime,
+        &ProcessOptions::default(),
+        "trace",
+    )
+    .await;
+    (dir, result)
+}
+
+#[tokio::test]
+async fn next_profile_takes_over_after_a_provider_failure() {
+    let server = MockServer::start().await;
+    mount(&server, "/glm/chat/completions", content("ocr text")).await;
+    mount(
+        &server,
+        "/down/chat/completions",
+        ResponseTemplate::new(503),
+    )
+    .await;
+    mount(&server, "/up/chat/completions", content("# Fallback")).await;
+
+    let llms = [
+        llm(&server, "down", "primary"),
+        llm(&server, "up", "backup"),
+    ];
+    let (dir, result) = run(&server, &llms).await;
+
+    assert_eq!(result.unwrap(), 1);
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("scan.md")).unwrap(),
+        "# Fallback"
+    );
+    let ocr_calls = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().starts_with("/glm"))
+        .count();
+    assert_eq!(ocr_calls, 1);
+}
+
+#[tokio::test]
+async fn client_errors_do_not_fall_back() {
+    let server = MockServer::start().await;
+    mount(&server, "/glm/chat/completions", content("ocr text")).await;
+    mount(&server, "/bad/chat/completions", ResponseTemplate::new(400)).await;
+    mount(&server, "/up/chat/completions", content("# Unused")).await;
+
+    let llms = [llm(&server, "bad", "primary"), llm(&server, "up", "backup")];
+    let (_dir, result) = run(&server, &llms).await;
+
+    assert!(result.unwrap_err().to_string().contains("400"));
+    let requests = server.received_requests().await.unwrap();
+    assert!(
+        requests
+            .iter()
+            .all(|request| !request.url.path().starts_with("/up"))
+    );
+}
+
+#[tokio::test]
+async fn exhausted_chain_names_every_profile() {
+    let server = MockServer::start().await;
+    mount(&server, "/glm/chat/completions", content("ocr text")).await;
+    mount(&server, "/a/chat/completions", ResponseTemplate::new(500)).await;
+    mount(&server, "/b/chat/completions", ResponseTemplate::new(401)).await;
+
+    let llms = [llm(&server, "a", "first"), llm(&server, "b", "second")];
+    let (_dir, result) = run(&server, &llms).await;
+
+    let message = format!("{:#}", result.unwrap_err());
+    assert!(message.contains("OpenaiCompatible/first"));
+    assert!(message.contains("OpenaiCompatible/second"));
+    assert!(message.contains("401"));
+}
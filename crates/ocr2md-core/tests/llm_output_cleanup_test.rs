@@ -0,0 +1,71 @@
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn content(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "choices": [{"message": {"content": text}}],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 5}
+    }))
+}
+
+fn client(server: &MockServer) -> LlmClient {
+    let runtime = RuntimeConfig::from_env();
+    let cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(server.uri()),
+        Some("llm-test".to_string()),
+        None,
+    )
+    .unwrap();
+    LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime)
+}
+
+#[tokio::test]
+async fn prose_preamble_is_retried_once_with_a_stricter_prompt() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("再次强调"))
+        .respond_with(content("```markdown\n# Invoice\n```"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(content("Here is the Markdown you asked for:\n\n# Invoice"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = client(&server)
+        .to_markdown("INVOICE", "trace-prose")
+        .await
+        .unwrap();
+
+    assert_eq!(result.markdown, "# Invoice");
+    let usage = result.usage.unwrap();
+    assert_eq!((usage.input_tokens, usage.output_tokens), (20, 10));
+}
+
+#[tokio::test]
+async fn fenced_markdown_is_unwrapped_without_a_retry() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(content("```\n# Receipt\n\n- Pen\n```"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = client(&server)
+        .to_markdown("RECEIPT", "trace-fence")
+        .await
+        .unwrap();
+
+    assert_eq!(result.markdown, "# Receipt\n\n- Pen");
+}
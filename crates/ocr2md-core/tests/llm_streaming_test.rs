@@ -0,0 +1,167 @@
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig, TokenUsage};
+use serde_json::json;
+use wiremock::matchers::{body_partial_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn gemini_client(server: &MockServer, require_streaming: bool) -> LlmClient {
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{"content": {"parts": [{"text": "# Gemini"}]}}]
+        })))
+        .mount(server)
+        .await;
+
+    let runtime = RuntimeConfig::from_env();
+    let mut cfg = LlmConfig::from_sources(
+        LlmProvider::Gemini,
+        Some("key".to_string()),
+        Some(server.uri()),
+        None,
+        None,
+    )
+    .unwrap();
+    cfg.require_streaming = require_streaming;
+    LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime)
+}
+
+#[tokio::test]
+async fn unsupported_provider_falls_back_to_single_chunk() {
+    let server = MockServer::start().await;
+    let client = gemini_client(&server, false).await;
+
+    let mut chunks = Vec::new();
+    let markdown = client
+        .to_markdown_streaming("text", "trace", |chunk| chunk_push(&mut chunks, chunk))
+        .await
+        .unwrap();
+
+    assert_eq!(markdown.markdown, "# Gemini");
+    assert_eq!(chunks, vec!["# Gemini".to_string()]);
+}
+
+#[tokio::test]
+async fn require_streaming_rejects_unsupported_provider() {
+    let server = MockServer::start().await;
+    let client = gemini_client(&server, true).await;
+
+    let mut chunks = Vec::new();
+    let err = client
+        .to_markdown_streaming("text", "trace", |chunk| chunk_push(&mut chunks, chunk))
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("streaming is not available"));
+    assert!(chunks.is_empty());
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+fn chunk_push(chunks: &mut Vec<String>, chunk: &str) {
+    chunks.push(chunk.to_string());
+}
+
+fn client_for(server: &MockServer, provider: LlmProvider) -> LlmClient {
+    let runtime = RuntimeConfig::from_env();
+    let cfg = LlmConfig::from_sources(
+        provider,
+        Some("key".to_string()),
+        Some(server.uri()),
+        None,
+        None,
+    )
+    .unwrap();
+    LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime)
+}
+
+fn event_stream(body: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .insert_header("content-type", "text/event-stream")
+        .set_body_string(body)
+}
+
+#[tokio::test]
+async fn openai_deltas_reach_the_callback() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(event_stream(concat!(
+            "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"# Ti\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"tle\"}}]}\n\n",
+            "data: [DONE]\n\n"
+        )))
+        .mount(&server)
+        .await;
+    let client = client_for(&server, LlmProvider::OpenaiCompatible);
+
+    let mut chunks = Vec::new();
+    let markdown = client
+        .to_markdown_streaming("text", "trace", |chunk| chunk_push(&mut chunks, chunk))
+        .await
+        .unwrap();
+
+    assert_eq!(markdown.markdown, "# Title");
+    assert_eq!(chunks, vec!["# Ti".to_string(), "tle".to_string()]);
+}
+
+#[tokio::test]
+async fn anthropic_deltas_reach_the_callback() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/messages"))
+        .and(body_partial_json(json!({"stream": true})))
+        .respond_with(event_stream(concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":12,\"output_tokens\":1}}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"# A\"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"B\"}}\n\n",
+            "event: message_delta\n",
+            "data: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":5}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}\n\n"
+        )))
+        .mount(&server)
+        .await;
+    let client = client_for(&server, LlmProvider::Anthropic);
+
+    let mut chunks = Vec::new();
+    let markdown = client
+        .to_markdown_streaming("text", "trace", |chunk| chunk_push(&mut chunks, chunk))
+        .await
+        .unwrap();
+
+    assert_eq!(markdown.markdown, "# AB");
+    assert_eq!(
+        markdown.usage,
+        Some(TokenUsage {
+            input_tokens: 12,
+            output_tokens: 5
+        })
+    );
+    assert_eq!(chunks, vec!["# A".to_string(), "B".to_string()]);
+}
+
+#[tokio::test]
+async fn relay_that_ignores_stream_flag_still_works() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "# Plain"}}]
+        })))
+        .mount(&server)
+        .await;
+    let client = client_for(&server, LlmProvider::OpenaiCompatible);
+
+    let mut chunks = Vec::new();
+    let markdown = client
+        .to_markdown_streaming("text", "trace", |chunk| chunk_push(&mut chunks, chunk))
+        .await
+        .unwrap();
+
+    assert_eq!(markdown.markdown, "# Plain");
+    assert_eq!(chunks, vec!["# Plain".to_string()]);
+}
@@ -0,0 +1,102 @@
+use std::path::Path;
+
+use anyhow::anyhow;
+use ocr2md_core::manifest::{JobOutcome, Manifest};
+use serde_json::{Value, json};
+
+#[test]
+fn job_outcome_serializes_success_and_failure() {
+    let ok = JobOutcome::from_result(
+        Path::new("in/a.pdf"),
+        Path::new("out/a.md"),
+        Some(1200),
+        &Ok(()),
+    );
+    let failed = JobOutcome::from_result(
+        Path::new("in/b.pdf"),
+        Path::new("out/b.md"),
+        None,
+        &Err(anyhow!("connection refused").context("OCR request failed")),
+    );
+
+    assert_eq!(
+        serde_json::to_value(&ok).unwrap(),
+        json!({
+            "input": "in/a.pdf",
+            "output_path": "out/a.md",
+            "success": true,
+            "error": null,
+            "ocr_chars": 1200
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(&failed).unwrap(),
+        json!({
+            "input": "in/b.pdf",
+            "output_path": null,
+            "success": false,
+            "error": "OCR request failed: connection refused",
+            "ocr_chars": null
+        })
+    );
+
+    let round_trip: JobOutcome =
+        serde_json::from_value(serde_json::to_value(&failed).unwrap()).unwrap();
+    assert_eq!(round_trip, failed);
+}
+
+#[test]
+fn manifest_file_is_identical_across_runs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("reports/manifest.json");
+    let jobs = vec![
+        JobOutcome::from_result(Path::new("a.pdf"), Path::new("a.md"), Some(10), &Ok(())),
+        JobOutcome::from_result(
+            Path::new("b.pdf"),
+            Path::new("b.md"),
+            Some(0),
+            &Err(anyhow!("LLM returned an empty response")),
+        ),
+    ];
+
+    Manifest::new(jobs.clone()).write_to(&path).unwrap();
+    let first = std::fs::read_to_string(&path).unwrap();
+    Manifest::new(jobs).write_to(&path).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), first);
+
+    let body: Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(body["version"], 1);
+    assert_eq!(body["succeeded"], 1);
+    assert_eq!(body["failed"], 1);
+    assert_eq!(body["jobs"][1]["input"], "b.pdf");
+    assert!(!dir.path().join("reports/manifest.json.tmp").exists());
+}
+
+#[test]
+fn recorded_outcome_replaces_the_entry_for_its_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+    let mut manifest = Manifest::new(vec![JobOutcome::from_result(
+        &input,
+        Path::new("scan.md"),
+        None,
+        &Err(anyhow!("timed out")),
+    )]);
+    assert!(!manifest.is_done(&input));
+
+    manifest.record(JobOutcome::from_result(
+        &input,
+        Path::new("scan.md"),
+        Some(8),
+        &Ok(()),
+    ));
+    assert_eq!(
+        (manifest.jobs.len(), manifest.succeeded, manifest.failed),
+        (1, 1, 0)
+    );
+    assert!(manifest.is_done(&input));
+
+    std::fs::write(&input, b"%PDF-1.7 rescanned").unwrap();
+    assert!(!manifest.is_done(&input));
+}
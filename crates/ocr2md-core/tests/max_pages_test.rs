@@ -0,0 +1,72 @@
+mod common;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pdf::page_count;
+use ocr2md_core::pipeline::process_file;
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::common::pdf_with_pages;
+
+#[tokio::test]
+async fn document_over_page_cap_is_truncated_with_notice() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "first pages"}}]})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Doc"}}]})),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("long.pdf");
+    let output = dir.path().join("long.md");
+    std::fs::write(&input, pdf_with_pages(5)).unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let mut glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    glm_cfg.max_pages = 2;
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+
+    process_file(&input, &output, glm_cfg, llm_cfg, runtime, "trace")
+        .await
+        .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let ocr_body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let data_url = ocr_body["messages"][0]["content"][0]["file_url"]["url"]
+        .as_str()
+        .unwrap();
+    let uploaded = STANDARD
+        .decode(data_url.split_once(";base64,").unwrap().1)
+        .unwrap();
+    assert_eq!(page_count(&uploaded).unwrap(), 2);
+
+    let llm_body = String::from_utf8(requests[1].body.clone()).unwrap();
+    assert!(!llm_body.contains("ocr2md: stopped"));
+
+    assert_eq!(
+        std::fs::read_to_string(output).unwrap(),
+        "# Doc\n\n<!-- ocr2md: stopped after 2 pages -->\n"
+    );
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::GlmOcrClient;
+use ocr2md_core::ocr_cache::OcrCache;
+use serde_json::json;
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer, model: &str, cache: &OcrCache) -> GlmOcrClient {
+    let mut cfg = common::glm_config(server.uri());
+    cfg.ocr_model = model.to_string();
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    GlmOcrClient::new(http, cfg).with_cache(Some(cache.clone()))
+}
+
+#[tokio::test]
+async fn repeated_ocr_is_served_from_cache() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "scanned text"}}]
+        })))
+        .mount(&server)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let cache = OcrCache::new(dir.path().join("ocr"));
+    let kind = InputKind::Png;
+
+    let first = client(&server, "glm-a", &cache)
+        .extract_text(kind, b"same bytes", "trace")
+        .await
+        .unwrap();
+    let second = client(&server, "glm-a", &cache)
+        .extract_text(kind, b"same bytes", "trace")
+        .await
+        .unwrap();
+    assert_eq!(first, "scanned text");
+    assert_eq!(second, first);
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+    client(&server, "glm-b", &cache)
+        .extract_text(kind, b"same bytes", "trace")
+        .await
+        .unwrap();
+    client(&server, "glm-a", &cache)
+        .extract_text(kind, b"other bytes", "trace")
+        .await
+        .unwrap();
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[test]
+fn cache_round_trips_and_misses_cleanly() {
+    let dir = tempfile::tempdir().unwrap();
+    let cache = OcrCache::new(dir.path());
+    let key = OcrCache::key(b"bytes", "glm", "settings");
+
+    assert_eq!(cache.get(&key), None);
+    cache.put(&key, "第一页").unwrap();
+    assert_eq!(cache.get(&key).as_deref(), Some("第一页"));
+    assert_ne!(key, OcrCache::key(b"bytes", "glm", "other settings"));
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::time::Duration;
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::GlmOcrClient;
+use ocr2md_core::pipeline::Pipeline;
+use ocr2md_core::single_flight::SingleFlight;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn runtime() -> RuntimeConfig {
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    runtime.retry_max = 0;
+    runtime
+}
+
+fn slow_text(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200)
+        .set_body_json(json!({"choices": [{"message": {"content": text}}]}))
+        .set_delay(Duration::from_millis(300))
+}
+
+#[tokio::test]
+async fn concurrent_identical_inputs_share_one_ocr_call() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(slow_text("scanned text"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut runtime = runtime();
+    runtime.ocr_dedup = true;
+    let pipeline = Pipeline::new(common::glm_config(server.uri()), runtime).unwrap();
+    let (first, second) = (pipeline.clone(), pipeline.clone());
+
+    let (a, b) = tokio::join!(
+        first
+            .ocr_client()
+            .extract_text(InputKind::Pdf, b"%PDF-1.7 same", "copy-a"),
+        second
+            .ocr_client()
+            .extract_text(InputKind::Pdf, b"%PDF-1.7 same", "copy-b"),
+    );
+
+    assert_eq!(a.unwrap(), "scanned text");
+    assert_eq!(b.unwrap(), "scanned text");
+}
+
+#[tokio::test]
+async fn waiters_make_their_own_call_when_the_shared_one_fails() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(500).set_delay(Duration::from_millis(300)))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(slow_text("second try"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let http = HttpEngine::new(runtime()).unwrap();
+    let client = GlmOcrClient::new(http, common::glm_config(server.uri()))
+        .with_single_flight(Some(SingleFlight::default()));
+    let waiter = client.clone();
+
+    let (leader, waiter) = tokio::join!(
+        client.extract_text(InputKind::Pdf, b"%PDF-1.7 same", "copy-a"),
+        async {
+            // Join only once the first call is in flight.
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            waiter
+                .extract_text(InputKind::Pdf, b"%PDF-1.7 same", "copy-b")
+                .await
+        },
+    );
+
+    assert!(leader.is_err());
+    assert_eq!(waiter.unwrap(), "second try");
+}
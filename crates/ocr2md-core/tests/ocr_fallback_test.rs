@@ -0,0 +1,126 @@
+mod common;
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{GlmConfig, GlmOcrClient, OcrFallback};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn glm_config(server: &MockServer, fallback: OcrFallback) -> GlmConfig {
+    let mut cfg = common::glm_config(server.uri());
+    cfg.fallback = fallback;
+    cfg
+}
+
+async fn mount_failing_vision(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(400).set_body_string("model error"))
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/files/parse"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"content": "parsed text"})))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn pdf_falls_back_to_file_parse_when_vision_fails() {
+    let server = MockServer::start().await;
+    mount_failing_vision(&server).await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let client = GlmOcrClient::new(http, glm_config(&server, OcrFallback::FileParse));
+    let text = client
+        .extract_text(InputKind::Pdf, b"%PDF-1.7", "trace-test")
+        .await
+        .unwrap();
+
+    assert_eq!(text, "parsed text");
+}
+
+#[tokio::test]
+async fn pdf_vision_failure_is_returned_without_fallback() {
+    let server = MockServer::start().await;
+    mount_failing_vision(&server).await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let client = GlmOcrClient::new(http, glm_config(&server, OcrFallback::None));
+    let result = client
+        .extract_text(InputKind::Pdf, b"%PDF-1.7", "trace-test")
+        .await;
+
+    assert!(result.is_err());
+}
+
+async fn mount_partial_parse(server: &MockServer) {
+    Mock::given(method("POST"))
+        .and(path("/files/parse"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "error": {"code": "1214", "message": "page 3 could not be read"},
+            "partial_content": "pages 1-2 text"
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn file_parse_returns_partial_content_when_allowed() {
+    let server = MockServer::start().await;
+    mount_partial_parse(&server).await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let mut cfg = glm_config(&server, OcrFallback::None);
+    cfg.continue_on_partial = true;
+    let client = GlmOcrClient::new(http, cfg);
+    let text = client
+        .extract_text(InputKind::Docx, b"PK", "trace-test")
+        .await
+        .unwrap();
+
+    assert_eq!(text, "pages 1-2 text");
+}
+
+#[tokio::test]
+async fn file_parse_partial_content_is_an_error_by_default() {
+    let server = MockServer::start().await;
+    mount_partial_parse(&server).await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let client = GlmOcrClient::new(http, glm_config(&server, OcrFallback::None));
+    let err = client
+        .extract_text(InputKind::Docx, b"PK", "trace-test")
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("missing extracted text"));
+}
+
+#[tokio::test]
+async fn oversized_input_is_refused_before_upload() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let mut cfg = glm_config(&server, OcrFallback::FileParse);
+    cfg.max_input_bytes = 4;
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let client = GlmOcrClient::new(http, cfg);
+    let err = client
+        .extract_text(InputKind::Pdf, b"%PDF-1.7", "trace-test")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::InputTooLarge { size: 8, limit: 4 })
+    ));
+    assert!(err.to_string().contains("4 over the 4-byte upload limit"));
+}
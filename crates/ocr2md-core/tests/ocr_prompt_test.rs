@@ -0,0 +1,86 @@
+mod common;
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{DEFAULT_OCR_PROMPT, DocType, GlmConfig, GlmOcrClient};
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn sent_prompt(cfg: GlmConfig, server: &MockServer) -> String {
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    GlmOcrClient::new(http, cfg)
+        .extract_text(InputKind::Pdf, b"%PDF-1.7", "trace-test")
+        .await
+        .unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    body["messages"][0]["content"][1]["text"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+async fn ocr_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "text"}}]
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn doc_type_hint_composes_with_language_hint() {
+    let server = ocr_server().await;
+    let mut cfg = common::glm_config(server.uri());
+    cfg.doc_type = Some("receipt".parse().unwrap());
+    cfg.language_hint = Some("ja".to_string());
+
+    let prompt = sent_prompt(cfg, &server).await;
+
+    let receipt = prompt.find("收据").expect("doc type hint");
+    let language = prompt.find("文档主要语言为ja").expect("language hint");
+    assert!(receipt < language);
+}
+
+#[tokio::test]
+async fn prompt_has_no_hint_by_default() {
+    let server = ocr_server().await;
+    let prompt = sent_prompt(common::glm_config(server.uri()), &server).await;
+
+    assert_eq!(
+        prompt,
+        "请提取文档完整内容，尽量保留标题、段落和表格结构，输出纯文本。"
+    );
+    assert_eq!(prompt, DEFAULT_OCR_PROMPT);
+}
+
+#[tokio::test]
+async fn configured_prompt_replaces_the_default_and_keeps_hints() {
+    let server = ocr_server().await;
+    let mut cfg = common::glm_config(server.uri());
+    cfg.ocr_prompt = Some("Extract all text; keep tables as-is.".to_string());
+    cfg.language_hint = Some("en".to_string());
+
+    let prompt = sent_prompt(cfg, &server).await;
+
+    assert!(prompt.starts_with("Extract all text; keep tables as-is."));
+    assert!(prompt.contains("文档主要语言为en"));
+    assert!(!prompt.contains(DEFAULT_OCR_PROMPT));
+}
+
+#[test]
+fn unknown_doc_type_falls_back_to_freeform() {
+    assert_eq!("Invoice".parse::<DocType>().unwrap(), DocType::Invoice);
+    assert_eq!(
+        "shipping label".parse::<DocType>().unwrap(),
+        DocType::Other("shipping label".to_string())
+    );
+    assert!(" ".parse::<DocType>().is_err());
+}
@@ -0,0 +1,86 @@
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{GlmConfig, GlmOcrClient, OcrProvider, OpenAiOcrConfig};
+use serde_json::{Value, json};
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn openai_config(server: &MockServer) -> GlmConfig {
+    let mut cfg = GlmConfig::from_sources_for(
+        OcrProvider::Glm,
+        Some("glm-key".to_string()),
+        Some(format!("{}/glm", server.uri())),
+        None,
+        None,
+        None,
+        10_000,
+    )
+    .unwrap();
+    cfg.provider = OcrProvider::Openai;
+    cfg.openai = Some(OpenAiOcrConfig {
+        api_key: "openai-key".to_string(),
+        base_url: format!("{}/v1", server.uri()),
+        model: "gpt-test".to_string(),
+    });
+    cfg
+}
+
+#[tokio::test]
+async fn image_is_sent_to_openai_chat_completions() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("authorization", "Bearer openai-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "openai text"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let text = GlmOcrClient::new(http, openai_config(&server))
+        .extract_text(InputKind::Png, b"\x89PNG\r\n\x1a\n", "trace-test")
+        .await
+        .unwrap();
+    assert_eq!(text, "openai text");
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["model"], "gpt-test");
+    let content = &body["messages"][0]["content"];
+    assert!(
+        content[0]["image_url"]["url"]
+            .as_str()
+            .unwrap()
+            .starts_with("data:image/png;base64,")
+    );
+    assert!(
+        content[1]["text"]
+            .as_str()
+            .unwrap()
+            .contains("请提取文档完整内容")
+    );
+}
+
+#[tokio::test]
+async fn pdfs_are_rejected_without_calling_any_api() {
+    let server = MockServer::start().await;
+
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let err = GlmOcrClient::new(http, openai_config(&server))
+        .extract_text(InputKind::Pdf, b"%PDF-1.7", "trace-test")
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::UnsupportedInputType(_))
+        ),
+        "{err:#}"
+    );
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
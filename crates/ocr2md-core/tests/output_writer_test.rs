@@ -0,0 +1,108 @@
+use ocr2md_core::output::{APPEND_SEPARATOR, StreamingWriter};
+
+#[test]
+fn streamed_chunks_are_concatenated_into_final_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.md");
+
+    let mut writer = StreamingWriter::create(&path).unwrap();
+    for chunk in ["# Title\n", "\n", "body ", "text\n"] {
+        writer.write_chunk(chunk).unwrap();
+    }
+    assert!(!path.exists());
+
+    let bytes = writer.finish().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written, "# Title\n\nbody text\n");
+    assert_eq!(bytes, written.len());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn unfinished_writer_removes_partial_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.md");
+
+    let mut writer = StreamingWriter::create(&path).unwrap();
+    writer.write_chunk("partial").unwrap();
+    drop(writer);
+
+    assert!(!path.exists());
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn failed_rename_removes_the_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    // A directory in the way of the final path makes the rename fail.
+    let path = dir.path().join("out.md");
+    std::fs::create_dir(&path).unwrap();
+    std::fs::write(path.join("keep"), "x").unwrap();
+
+    let mut writer = StreamingWriter::create(&path).unwrap();
+    writer.write_chunk("text").unwrap();
+    assert!(writer.finish().is_err());
+
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["out.md"]);
+}
+
+#[test]
+fn dash_path_writes_to_stdout_without_touching_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let previous = std::env::current_dir().unwrap();
+    std::env::set_current_dir(dir.path()).unwrap();
+
+    let writer = StreamingWriter::create(std::path::Path::new("-"));
+    std::env::set_current_dir(previous).unwrap();
+
+    assert_eq!(writer.unwrap().finish().unwrap(), 0);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn append_creates_the_file_then_adds_behind_a_separator() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("book.md");
+
+    for page in ["# Page 1\n", "# Page 2\n"] {
+        let mut writer = StreamingWriter::append(&path).unwrap();
+        writer.write_chunk(page).unwrap();
+        writer.finish().unwrap();
+    }
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(written, format!("# Page 1\n{APPEND_SEPARATOR}# Page 2\n"));
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn concurrent_appends_do_not_interleave() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("book.md");
+
+    let handles: Vec<_> = (0..8)
+        .map(|job| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut writer = StreamingWriter::append(&path).unwrap();
+                for _ in 0..100 {
+                    writer.write_chunk(&job.to_string()).unwrap();
+                }
+                writer.finish().unwrap();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let written = std::fs::read_to_string(&path).unwrap();
+    let mut parts: Vec<_> = written.split(APPEND_SEPARATOR).collect();
+    parts.sort();
+    let expected: Vec<_> = (0..8).map(|job| job.to_string().repeat(100)).collect();
+    assert_eq!(parts, expected);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::error::AppError;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::GlmOcrClient;
+use ocr2md_core::pdf::{PageRanges, page_count};
+use serde_json::{Value, json};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::common::pdf_with_pages;
+
+fn pages(ranges: &str, total: usize) -> Vec<usize> {
+    ranges
+        .parse::<PageRanges>()
+        .unwrap()
+        .resolve(total)
+        .unwrap()
+}
+
+#[test]
+fn open_ended_ranges_run_to_the_last_page() {
+    assert_eq!(
+        pages("5-12,20,30-", 32),
+        [5, 6, 7, 8, 9, 10, 11, 12, 20, 30, 31, 32]
+    );
+    assert_eq!(pages("3-", 3), [3]);
+}
+
+#[test]
+fn overlapping_segments_are_merged_in_page_order() {
+    assert_eq!(pages("4-6, 2 ,5-8,2", 10), [2, 4, 5, 6, 7, 8]);
+    assert_eq!(
+        "4-6, 2 ,5-".parse::<PageRanges>().unwrap().to_string(),
+        "4-6,2,5-"
+    );
+}
+
+#[test]
+fn malformed_ranges_are_config_errors() {
+    for ranges in ["", "3,", "0-2", "a-3", "7-5", "1-2-3", "-4"] {
+        assert!(
+            matches!(
+                ranges.parse::<PageRanges>(),
+                Err(AppError::InvalidConfig(_))
+            ),
+            "{ranges:?}"
+        );
+    }
+}
+
+#[test]
+fn ranges_past_the_last_page_are_rejected() {
+    for ranges in ["4", "2-9", "9-"] {
+        let err = ranges
+            .parse::<PageRanges>()
+            .unwrap()
+            .resolve(3)
+            .unwrap_err();
+        assert!(err.to_string().contains("3 page(s)"), "{err}");
+    }
+}
+
+#[tokio::test]
+async fn only_selected_pages_are_uploaded() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "choices": [{"message": {"content": "selected"}}]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut cfg = common::glm_config(server.uri());
+    cfg.pages = Some("2-3,5-".parse().unwrap());
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    let text = GlmOcrClient::new(http, cfg)
+        .extract_text(InputKind::Pdf, &pdf_with_pages(6), "trace-test")
+        .await
+        .unwrap();
+    assert_eq!(text, "selected");
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let url = body["messages"][0]["content"][0]["file_url"]["url"]
+        .as_str()
+        .unwrap();
+    let encoded = url.strip_prefix("data:application/pdf;base64,").unwrap();
+    assert_eq!(page_count(&STANDARD.decode(encoded).unwrap()).unwrap(), 4);
+}
@@ -0,0 +1,115 @@
+mod common;
+
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use lopdf::Document;
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::ocr::{GlmConfig, GlmOcrClient, page_marker};
+use ocr2md_core::pdf::{page_count, split_into_batches};
+use serde_json::{Value, json};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::common::{pdf_with_page_widths, pdf_with_pages};
+
+// Answers with one form-feed separated line per uploaded page, naming the
+// page by its width (page N is N * 100 wide). The batch holding page 1 is
+// the slowest, so out-of-order completion would show up in the result.
+struct EchoPages;
+
+impl Respond for EchoPages {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        let url = body["messages"][0]["content"][0]["file_url"]["url"]
+            .as_str()
+            .unwrap();
+        let encoded = url.strip_prefix("data:application/pdf;base64,").unwrap();
+        let document = Document::load_mem(&STANDARD.decode(encoded).unwrap()).unwrap();
+        let numbers: Vec<i64> = document
+            .get_pages()
+            .into_values()
+            .map(|id| {
+                let page = document.get_dictionary(id).unwrap();
+                page.get(b"MediaBox").unwrap().as_array().unwrap()[2]
+                    .as_i64()
+                    .unwrap()
+                    / 100
+            })
+            .collect();
+        let text = numbers
+            .iter()
+            .map(|number| format!("content of page {number}"))
+            .collect::<Vec<_>>()
+            .join("\x0c");
+        let delay = if numbers.contains(&1) { 300 } else { 0 };
+        ResponseTemplate::new(200)
+            .set_body_json(json!({"choices": [{"message": {"content": text}}]}))
+            .set_delay(Duration::from_millis(delay))
+    }
+}
+
+fn glm_config(server: &MockServer, batch_pages: usize) -> GlmConfig {
+    let mut cfg = common::glm_config(server.uri());
+    cfg.pdf_batch_pages = batch_pages;
+    cfg.pdf_batch_concurrency = 3;
+    cfg
+}
+
+async fn extract(cfg: GlmConfig, pdf: &[u8]) -> String {
+    let http = HttpEngine::new(RuntimeConfig::from_env()).unwrap();
+    GlmOcrClient::new(http, cfg)
+        .extract_text(InputKind::Pdf, pdf, "trace-batch")
+        .await
+        .unwrap()
+}
+
+#[test]
+fn pdf_splits_into_consecutive_batches() {
+    let batches = split_into_batches(&pdf_with_pages(5), 2).unwrap().unwrap();
+    let shape: Vec<(usize, usize)> = batches
+        .iter()
+        .map(|batch| (batch.first_page, page_count(&batch.bytes).unwrap()))
+        .collect();
+    assert_eq!(shape, vec![(1, 2), (3, 2), (5, 1)]);
+
+    assert!(split_into_batches(&pdf_with_pages(2), 2).unwrap().is_none());
+    assert!(split_into_batches(&pdf_with_pages(5), 0).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn batches_are_merged_in_page_order() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(EchoPages)
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let pdf = pdf_with_page_widths(&[100, 200, 300, 400, 500]);
+    let text = extract(glm_config(&server, 2), &pdf).await;
+
+    let expected = (1..=5)
+        .map(|number| format!("{}\n\ncontent of page {number}", page_marker(number)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    assert_eq!(text, expected);
+}
+
+#[tokio::test]
+async fn pdf_under_the_batch_size_is_sent_whole() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(EchoPages)
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let text = extract(glm_config(&server, 20), &pdf_with_page_widths(&[100, 200])).await;
+    assert_eq!(
+        text,
+        "--- page 1 ---\n\ncontent of page 1\n\n--- page 2 ---\n\ncontent of page 2"
+    );
+}
@@ -0,0 +1,348 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::error::AppError;
+use ocr2md_core::format::OutputFormat;
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::ocr::{GlmOcrClient, TRUNCATION_MARKER};
+use ocr2md_core::pipeline::{Emit, ProcessOptions, ocr_only, ocr_sidecar_path, process_file_with};
+use ocr2md_core::progress::{ProgressEvent, ProgressSink};
+use serde_json::json;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn emit_writes_ocr_text_and_markdown_from_one_ocr_call() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "raw ocr text"}}]})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .and(body_string_contains("raw ocr text"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Doc"}}]})),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+    let options = ProcessOptions {
+        emit: vec![Emit::Ocr, Emit::Markdown],
+        ..ProcessOptions::default()
+    };
+
+    process_file_with(
+        &input, &output, glm_cfg, llm_cfg, runtime, &options, "trace",
+    )
+    .await
+    .unwrap();
+
+    let ocr_path = ocr_sidecar_path(&output);
+    assert_eq!(ocr_path, dir.path().join("scan.ocr.txt"));
+    assert_eq!(std::fs::read_to_string(ocr_path).unwrap(), "raw ocr text");
+    assert_eq!(std::fs::read_to_string(output).unwrap(), "# Doc");
+}
+
+#[tokio::test]
+async fn cancelled_token_aborts_in_flight_ocr() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "late"}}]}))
+                .set_delay(std::time::Duration::from_secs(5)),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+    let options = ProcessOptions::default();
+    let cancel = options.cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        cancel.cancel();
+    });
+
+    let started = std::time::Instant::now();
+    let err = process_file_with(
+        &input, &output, glm_cfg, llm_cfg, runtime, &options, "trace",
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::Cancelled)
+    ));
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert!(!output.exists());
+}
+
+#[tokio::test]
+async fn ocr_only_writes_capped_sidecar_without_markdown() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "0123456789"}}]})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let mut glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    glm_cfg.max_ocr_chars = 4;
+    let client = GlmOcrClient::new(HttpEngine::new(runtime).unwrap(), glm_cfg);
+
+    let written = ocr_only(
+        &input,
+        &output,
+        &client,
+        &ProcessOptions::default(),
+        "trace",
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(written, dir.path().join("scan.ocr.txt"));
+    assert_eq!(
+        std::fs::read_to_string(written).unwrap(),
+        format!("0123\n\n{TRUNCATION_MARKER}")
+    );
+    assert!(!output.exists());
+}
+
+#[tokio::test]
+async fn progress_events_follow_the_pipeline_stages() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "raw ocr"}}]})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Doc"}}]})),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink_events = events.clone();
+    let options = ProcessOptions {
+        progress: Some(ProgressSink::new(move |event| {
+            sink_events.lock().unwrap().push(event);
+        })),
+        ..ProcessOptions::default()
+    };
+
+    process_file_with(
+        &input, &output, glm_cfg, llm_cfg, runtime, &options, "trace",
+    )
+    .await
+    .unwrap();
+
+    let events = events.lock().unwrap().clone();
+    assert_eq!(
+        events,
+        [
+            ProgressEvent::Reading,
+            ProgressEvent::OcrStarted,
+            ProgressEvent::OcrDone { chars: 7 },
+            ProgressEvent::LlmStarted,
+            ProgressEvent::Written { bytes: 5 },
+        ]
+    );
+    let percents: Vec<u8> = events.iter().map(ProgressEvent::percent).collect();
+    assert!(percents.is_sorted(), "{percents:?}");
+    assert_eq!(
+        serde_json::to_value(&events[2]).unwrap(),
+        json!({"stage": "ocr_done", "chars": 7})
+    );
+}
+
+#[tokio::test]
+async fn frontmatter_is_prepended_when_enabled() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "raw ocr"}}]})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Doc"}}]})),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("q\"3\".pdf");
+    let output = dir.path().join("q3.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let mut glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    glm_cfg.ocr_model = "glm-test".to_string();
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        Some("llm-test".to_string()),
+        None,
+    )
+    .unwrap();
+    let options = ProcessOptions {
+        frontmatter: true,
+        ..ProcessOptions::default()
+    };
+
+    process_file_with(
+        &input, &output, glm_cfg, llm_cfg, runtime, &options, "trace-fm",
+    )
+    .await
+    .unwrap();
+
+    let markdown = std::fs::read_to_string(output).unwrap();
+    let (frontmatter, body) = markdown.split_once("---\n\n").unwrap();
+    assert_eq!(body, "# Doc");
+    assert!(
+        frontmatter.starts_with("---\nsource: \"q\\\"3\\\".pdf\"\n"),
+        "{frontmatter}"
+    );
+    assert!(frontmatter.contains("ocr_model: \"glm-test\"\n"));
+    assert!(frontmatter.contains("llm_model: \"llm-test\"\n"));
+    assert!(frontmatter.contains("trace_id: \"trace-fm\"\n"));
+}
+
+#[tokio::test]
+async fn html_format_renders_the_llm_markdown() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "raw ocr"}}]})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Doc\n\n- item"}}]})),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("doc.pdf");
+    let output = dir.path().join("doc.html");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let runtime = RuntimeConfig::from_env();
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        Some("llm-test".to_string()),
+        None,
+    )
+    .unwrap();
+    let options = ProcessOptions {
+        stream: true,
+        frontmatter: true,
+        format: OutputFormat::Html,
+        ..ProcessOptions::default()
+    };
+
+    process_file_with(
+        &input,
+        &output,
+        glm_cfg,
+        llm_cfg,
+        runtime,
+        &options,
+        "trace-html",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(output).unwrap(),
+        "<h1>Doc</h1>\n<ul>\n<li>item</li>\n</ul>\n"
+    );
+}
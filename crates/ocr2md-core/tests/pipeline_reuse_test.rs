@@ -0,0 +1,80 @@
+mod common;
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::Pipeline;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn content(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({"choices": [{"message": {"content": text}}]}))
+}
+
+fn pipeline(server: &MockServer) -> Pipeline {
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    runtime.retry_max = 0;
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        Some("model".to_string()),
+        None,
+    )
+    .unwrap();
+    Pipeline::new(glm_cfg, runtime)
+        .unwrap()
+        .with_llm_configs(vec![llm_cfg])
+}
+
+#[tokio::test]
+async fn one_pipeline_converts_several_files() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(content("ocr text"))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(content("# Title"))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let pipeline = pipeline(&server);
+    for name in ["a", "b"] {
+        let input = dir.path().join(format!("{name}.pdf"));
+        std::fs::write(&input, b"%PDF-1.7").unwrap();
+        let output = dir.path().join(format!("{name}.md"));
+
+        let used = pipeline.convert(&input, &output, name).await.unwrap();
+
+        assert_eq!(used, 0);
+        assert!(
+            std::fs::read_to_string(&output)
+                .unwrap()
+                .contains("# Title")
+        );
+    }
+}
+
+#[tokio::test]
+async fn a_pipeline_without_llm_configs_is_rejected() {
+    let server = MockServer::start().await;
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let err = pipeline(&server)
+        .with_llm_configs(Vec::new())
+        .convert(&input, &dir.path().join("scan.md"), "trace")
+        .await
+        .unwrap_err();
+
+    assert!(err.to_string().contains("no LLM config"), "{err:#}");
+}
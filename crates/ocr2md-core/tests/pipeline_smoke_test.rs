@@ -1,9 +1,64 @@
 use std::path::Path;
 
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
 use ocr2md_core::file_kind::{InputKind, detect_input_kind};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::ocr::{GlmConfig, MOCK_OCR_TEXT, OcrProvider};
+use ocr2md_core::pipeline::{convert_bytes, process_file};
 
 #[test]
 fn detects_pdf_kind() {
     let kind = detect_input_kind(Path::new("demo.pdf")).unwrap();
     assert_eq!(kind, InputKind::Pdf);
 }
+
+fn mock_configs() -> (GlmConfig, LlmConfig, RuntimeConfig) {
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    let glm_cfg = GlmConfig::from_sources_for(
+        OcrProvider::Mock,
+        None,
+        None,
+        None,
+        None,
+        None,
+        runtime.max_ocr_chars,
+    )
+    .unwrap();
+    let llm_cfg = LlmConfig::from_sources(LlmProvider::Mock, None, None, None, None).unwrap();
+    (glm_cfg, llm_cfg, runtime)
+}
+
+#[tokio::test]
+async fn mock_providers_run_the_pipeline_offline() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF-1.7\n").unwrap();
+
+    let (glm_cfg, llm_cfg, runtime) = mock_configs();
+    process_file(&input, &output, glm_cfg, llm_cfg, runtime, "trace")
+        .await
+        .unwrap();
+
+    let markdown = std::fs::read_to_string(&output).unwrap();
+    assert_eq!(markdown, format!("# Mock document\n\n{MOCK_OCR_TEXT}\n"));
+}
+
+#[tokio::test]
+async fn bytes_in_memory_convert_to_the_same_markdown_as_the_file() {
+    let (glm_cfg, llm_cfg, runtime) = mock_configs();
+
+    let markdown = convert_bytes(
+        InputKind::Png,
+        b"\x89PNG\r\n\x1a\n",
+        glm_cfg,
+        llm_cfg,
+        runtime,
+        "trace",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(markdown, format!("# Mock document\n\n{MOCK_OCR_TEXT}\n"));
+}
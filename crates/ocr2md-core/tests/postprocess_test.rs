@@ -0,0 +1,49 @@
+use ocr2md_core::postprocess::{
+    Postprocess, apply, collapse_blanks, halfwidth_punctuation, normalize_headings, trim_trailing,
+};
+
+#[test]
+fn trailing_whitespace_is_trimmed_outside_code() {
+    let markdown = "标题  \ntext\t\n```\nkeep   \n```\n".to_string();
+    assert_eq!(trim_trailing(markdown), "标题\ntext\n```\nkeep   \n```\n");
+}
+
+#[test]
+fn blank_runs_collapse_to_one_line() {
+    let markdown = "a\n\n\n\nb\n  \n\t\nc\n```\n\n\n```".to_string();
+    assert_eq!(collapse_blanks(markdown), "a\n\nb\n  \nc\n```\n\n\n```");
+}
+
+#[test]
+fn headings_get_one_space_and_blank_lines_around() {
+    let markdown =
+        "intro\n##概述\ntext\n#   Title  \n\n```bash\n#comment\n```\n####### not a heading\n"
+            .to_string();
+    assert_eq!(
+        normalize_headings(markdown),
+        "intro\n\n## 概述\n\ntext\n\n# Title\n\n```bash\n#comment\n```\n####### not a heading\n"
+    );
+}
+
+#[test]
+fn fullwidth_forms_fold_to_ascii() {
+    let markdown = "价格：（Ａ１）　合计１００％\n`，`".to_string();
+    assert_eq!(halfwidth_punctuation(markdown), "价格:(A1) 合计100%\n`,`");
+}
+
+#[test]
+fn passes_run_in_the_order_given_and_none_by_default() {
+    let markdown = "#Title   \n\n\n\nbody  \n".to_string();
+    assert_eq!(apply(&[], markdown.clone()), markdown);
+    assert_eq!(
+        apply(
+            &[
+                Postprocess::CollapseBlanks,
+                Postprocess::TrimTrailing,
+                Postprocess::NormalizeHeadings
+            ],
+            markdown
+        ),
+        "# Title\n\nbody\n"
+    );
+}
@@ -1,4 +1,10 @@
-use ocr2md_core::profile_store::{ProfileStore, ProviderProfile};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use ocr2md_core::error::AppError;
+use ocr2md_core::profile_store::{EXPORT_WARNING, ProfileStore, ProviderProfile};
+use ocr2md_core::secret_backend::{FileSecrets, SecretBackend};
+use ocr2md_core::secure_config::decrypt_blob;
 
 #[test]
 fn save_and_load_profiles() {
@@ -10,3 +16,161 @@ fn save_and_load_profiles() {
     assert_eq!(loaded.len(), 1);
     assert_eq!(loaded[0].name, "work");
 }
+
+#[test]
+fn change_passphrase_rotates_the_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = ProfileStore::new(dir.path().join("config.enc"));
+    let p = ProviderProfile::openai("work", "https://api.openai.com/v1", "k1", "gpt-4o-mini");
+    store.save_all("old", std::slice::from_ref(&p)).unwrap();
+
+    let wrong = store.change_passphrase("nope", "new").unwrap_err();
+    assert!(matches!(
+        wrong.downcast_ref::<AppError>(),
+        Some(AppError::WrongPassphrase)
+    ));
+
+    store.change_passphrase("old", "new").unwrap();
+    assert_eq!(store.load_all("new").unwrap(), vec![p]);
+    let stale = store.load_all("old").unwrap_err();
+    assert!(matches!(
+        stale.downcast_ref::<AppError>(),
+        Some(AppError::WrongPassphrase)
+    ));
+    assert!(!dir.path().join("config.enc.tmp").exists());
+}
+
+#[test]
+fn export_and_import_round_trip_under_a_new_passphrase() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = ProfileStore::new(dir.path().join("a.enc"));
+    let p = ProviderProfile::openai("work", "https://api.openai.com/v1", "k1", "gpt-4o-mini");
+    source.save_all("pass", std::slice::from_ref(&p)).unwrap();
+
+    let json = source.export_json("pass").unwrap();
+    assert!(json.contains(EXPORT_WARNING));
+    assert!(json.contains("\"api_key\": \"k1\""));
+
+    let target = ProfileStore::new(dir.path().join("b.enc"));
+    assert_eq!(target.import_json("other", &json).unwrap(), vec![p.clone()]);
+    assert_eq!(target.load_all("other").unwrap(), vec![p]);
+}
+
+#[test]
+fn import_rejects_the_whole_file_on_an_unknown_provider() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = ProfileStore::new(dir.path().join("config.enc"));
+    let json = r#"[
+        {"name": "ok", "provider": "openai", "base_url": "", "api_key": "k", "model": "m"},
+        {"name": "bad", "provider": "mystery", "base_url": "", "api_key": "k", "model": "m"}
+    ]"#;
+
+    let err = store.import_json("pass", json).unwrap_err();
+    assert!(format!("{err:#}").contains("profile 2 (bad)"));
+    assert!(!dir.path().join("config.enc").exists());
+}
+
+#[test]
+fn profiles_keep_an_optional_system_prompt() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = ProfileStore::new(dir.path().join("config.enc"));
+    let json = r#"[
+        {"name": "plain", "provider": "openai", "base_url": "", "api_key": "k", "model": "m"},
+        {"name": "legal", "provider": "openai", "base_url": "", "api_key": "k", "model": "m",
+         "system_prompt": "Keep clause numbering."}
+    ]"#;
+
+    store.import_json("pass", json).unwrap();
+    let loaded = store.load_all("pass").unwrap();
+    assert_eq!(loaded[0].system_prompt, None);
+    assert_eq!(
+        loaded[1].system_prompt.as_deref(),
+        Some("Keep clause numbering.")
+    );
+}
+
+// Keys kept in memory by profile name, standing in for the OS keychain.
+#[derive(Debug, Default)]
+struct MemorySecrets(Mutex<HashMap<String, String>>);
+
+impl SecretBackend for MemorySecrets {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn stash(&self, profiles: &mut [ProviderProfile]) -> anyhow::Result<()> {
+        let mut keys = self.0.lock().unwrap();
+        for profile in profiles {
+            keys.insert(profile.name.clone(), std::mem::take(&mut profile.api_key));
+        }
+        Ok(())
+    }
+
+    fn fill(&self, profiles: &mut [ProviderProfile]) -> anyhow::Result<()> {
+        let keys = self.0.lock().unwrap();
+        for profile in profiles {
+            profile.api_key = keys.get(&profile.name).cloned().unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    fn forget(&self, names: &[String]) -> anyhow::Result<()> {
+        let mut keys = self.0.lock().unwrap();
+        for name in names {
+            keys.remove(name);
+        }
+        Ok(())
+    }
+}
+
+fn stored_json(path: &std::path::Path, passphrase: &str) -> String {
+    String::from_utf8(decrypt_blob(&std::fs::read(path).unwrap(), passphrase).unwrap()).unwrap()
+}
+
+#[test]
+fn file_backend_keeps_keys_in_the_encrypted_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.enc");
+    let store = ProfileStore::new(&path).with_secrets(Arc::new(FileSecrets));
+    let p = ProviderProfile::openai(
+        "work",
+        "https://api.openai.com/v1",
+        "sk-file",
+        "gpt-4o-mini",
+    );
+
+    store.save_all("pass", std::slice::from_ref(&p)).unwrap();
+
+    assert!(stored_json(&path, "pass").contains("sk-file"));
+    assert_eq!(store.load_all("pass").unwrap(), vec![p]);
+}
+
+#[test]
+fn other_backends_keep_keys_out_of_the_file_and_rejoin_them_by_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.enc");
+    let secrets = Arc::new(MemorySecrets::default());
+    let store = ProfileStore::new(&path).with_secrets(secrets.clone());
+    let work = ProviderProfile::openai("work", "https://a", "sk-work", "m");
+    let home = ProviderProfile::openai("home", "https://b", "sk-home", "m");
+
+    store
+        .save_all("pass", &[work.clone(), home.clone()])
+        .unwrap();
+    let stored = stored_json(&path, "pass");
+    assert!(!stored.contains("sk-work") && !stored.contains("sk-home"));
+    assert_eq!(store.load_all("pass").unwrap(), vec![work.clone(), home]);
+
+    // Removing a profile drops its key; a passphrase change keeps the rest.
+    store.save_all("pass", std::slice::from_ref(&work)).unwrap();
+    assert_eq!(secrets.0.lock().unwrap().len(), 1);
+    store.change_passphrase("pass", "new").unwrap();
+    assert_eq!(store.load_all("new").unwrap(), vec![work]);
+
+    // Reading the store with the file backend would hand out empty keys.
+    let err = ProfileStore::new(&path).load_all("new").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AppError>(),
+        Some(AppError::InvalidConfig(message)) if message.contains("OCR2MD_SECRET_BACKEND=memory")
+    ));
+}
@@ -0,0 +1,50 @@
+use ocr2md_core::progress::{BatchEvent, BatchProgress, ProgressDisplay};
+
+fn started(file: &str) -> BatchEvent {
+    BatchEvent::Started {
+        file: file.to_string(),
+    }
+}
+
+fn succeeded(file: &str) -> BatchEvent {
+    BatchEvent::Succeeded {
+        file: file.to_string(),
+    }
+}
+
+#[test]
+fn aggregator_tracks_running_completed_and_failed() {
+    let mut progress = BatchProgress::new(3);
+    progress.apply(started("a.pdf"));
+    progress.apply(started("b.pdf"));
+    assert_eq!(progress.summary(), "0/3 done; running: a.pdf, b.pdf");
+
+    progress.apply(succeeded("a.pdf"));
+    progress.apply(started("c.pdf"));
+    progress.apply(BatchEvent::Failed {
+        file: "b.pdf".to_string(),
+        error: "timeout".to_string(),
+    });
+    assert_eq!(progress.summary(), "2/3 done, 1 failed; running: c.pdf");
+    assert!(!progress.is_done());
+
+    progress.apply(succeeded("c.pdf"));
+    assert!(progress.is_done());
+    assert_eq!(progress.succeeded, 2);
+    assert_eq!(
+        progress.failures,
+        vec![("b.pdf".to_string(), "timeout".to_string())]
+    );
+    assert_eq!(progress.summary(), "3/3 done, 1 failed");
+}
+
+#[test]
+fn quiet_display_aggregates_the_same_state() {
+    let mut display = ProgressDisplay::new(2, true);
+    display.handle(started("a.pdf"));
+    display.handle(succeeded("a.pdf"));
+    assert_eq!(display.progress().summary(), "1/2 done");
+
+    let progress = display.finish();
+    assert_eq!(progress.completed(), 1);
+}
@@ -1,11 +1,460 @@
-use ocr2md_core::queue::{JobState, Queue};
+use std::time::Duration;
+
+use ocr2md_core::error::AppError;
+use ocr2md_core::queue::{AttemptOutcome, Enqueued, JobState, PRIORITY_HIGH, PRIORITY_LOW, Queue};
 
 #[test]
 fn job_state_transitions_to_success() {
     let mut q = Queue::default();
-    let id = q.enqueue("demo.pdf");
+    let id = q.enqueue("demo.pdf").unwrap();
     q.mark_running(id, "ocr");
     q.mark_running(id, "llm");
-    q.mark_success(id);
+    q.mark_success(id, "out.md");
     assert_eq!(q.get(id).unwrap().state, JobState::Success);
 }
+
+#[test]
+fn skipped_run_is_a_success_with_a_note_and_no_output() {
+    let mut q = Queue::default();
+    let id = q.enqueue("blank.pdf").unwrap();
+    q.mark_running(id, "processing");
+    q.mark_skipped(id, "OCR found no text");
+
+    let job = q.get(id).unwrap();
+    assert_eq!(job.state, JobState::Success);
+    assert_eq!(job.output, None);
+    assert_eq!(job.note.as_deref(), Some("OCR found no text"));
+    assert_eq!(
+        job.attempts.last().unwrap().outcome,
+        AttemptOutcome::Success
+    );
+
+    q.mark_running(id, "retry");
+    assert_eq!(q.get(id).unwrap().note, None);
+}
+
+#[test]
+fn success_records_the_output_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("queue.json");
+    let mut q = Queue::default();
+    let id = q.enqueue("demo.pdf").unwrap();
+    q.mark_running(id, "ocr");
+    assert_eq!(q.get(id).unwrap().output, None);
+
+    q.mark_success(id, "/docs/demo.md");
+    q.save_to(&path).unwrap();
+
+    let (loaded, _) = Queue::load_from(&path).unwrap();
+    assert_eq!(
+        loaded.get(id).unwrap().output.as_deref(),
+        Some("/docs/demo.md")
+    );
+}
+
+#[test]
+fn each_run_appends_an_attempt_record() {
+    let mut q = Queue::default();
+    let id = q.enqueue("demo.pdf").unwrap();
+
+    q.mark_running(id, "starting");
+    q.mark_running(id, "processing");
+    q.mark_retrying(id, "failed_retry", "status 429");
+    q.mark_running(id, "starting");
+    q.mark_retrying(id, "failed_retry", "timeout");
+    q.mark_running(id, "starting");
+    q.mark_success(id, "out.md");
+
+    let job = q.get(id).unwrap();
+    let outcomes: Vec<AttemptOutcome> = job
+        .attempts
+        .iter()
+        .map(|attempt| attempt.outcome.clone())
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            AttemptOutcome::Failed {
+                error: "status 429".to_string()
+            },
+            AttemptOutcome::Failed {
+                error: "timeout".to_string()
+            },
+            AttemptOutcome::Success,
+        ]
+    );
+    assert!(
+        job.attempts
+            .iter()
+            .all(|attempt| attempt.finished_at.is_some())
+    );
+
+    let snapshot = serde_json::to_value(job).unwrap();
+    assert_eq!(snapshot["attempts"][1]["outcome"]["error"], "timeout");
+}
+
+#[test]
+fn queue_round_trips_through_persistence_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("queue.json");
+
+    let mut q = Queue::default();
+    let done = q.enqueue("done.pdf").unwrap();
+    q.mark_running(done, "ocr");
+    q.mark_success(done, "out.md");
+    q.enqueue("waiting.pdf").unwrap();
+    q.save_to(&path).unwrap();
+
+    let (loaded, report) = Queue::load_from(&path).unwrap();
+    assert_eq!(report.recovered, 2);
+    assert_eq!(report.lost, 0);
+    assert_eq!(loaded.get(done).unwrap().state, JobState::Success);
+    assert_eq!(loaded.get_next_pending(), Some(done + 1));
+}
+
+#[test]
+fn truncated_persistence_file_recovers_complete_jobs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("queue.json");
+
+    let mut q = Queue::default();
+    for name in ["a.pdf", "b.pdf", "c.pdf"] {
+        q.enqueue(name).unwrap();
+    }
+    q.save_to(&path).unwrap();
+
+    let full = std::fs::read_to_string(&path).unwrap();
+    let cut = full.find("c.pdf").unwrap();
+    std::fs::write(&path, &full[..cut]).unwrap();
+
+    let (mut loaded, report) = Queue::load_from(&path).unwrap();
+    assert_eq!(report.recovered, 2);
+    assert_eq!(report.lost, 1);
+    assert!(report.backup.is_none());
+    assert_eq!(loaded.get(1).unwrap().input, "a.pdf");
+    assert_eq!(loaded.get(2).unwrap().input, "b.pdf");
+    assert!(loaded.get(3).is_none());
+    assert_eq!(loaded.enqueue("d.pdf").unwrap(), 3);
+}
+
+#[test]
+fn unreadable_persistence_file_is_backed_up() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("queue.json");
+    std::fs::write(&path, b"\0\0garbage").unwrap();
+
+    let (loaded, report) = Queue::load_from(&path).unwrap();
+    let backup = report.backup.expect("backup path");
+    assert!(backup.exists());
+    assert!(!path.exists());
+    assert_eq!(loaded.get_next_pending(), None);
+}
+
+#[test]
+fn claimed_job_is_not_handed_out_twice() {
+    let mut q = Queue::default();
+    let first = q.enqueue("a.pdf").unwrap();
+    let second = q.enqueue("b.pdf").unwrap();
+
+    assert_eq!(q.claim_next_pending("starting"), Some(first));
+    assert_eq!(q.get(first).unwrap().state, JobState::Running);
+    assert_eq!(q.claim_next_pending("starting"), Some(second));
+    assert_eq!(q.claim_next_pending("starting"), None);
+}
+
+#[test]
+fn running_job_comes_back_queued_after_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("queue.json");
+
+    let mut q = Queue::default();
+    let first = q.enqueue("first.pdf").unwrap();
+    let second = q.enqueue("second.pdf").unwrap();
+    q.mark_running(first, "processing");
+    q.save_to(&path).unwrap();
+
+    let (mut loaded, _) = Queue::load_from(&path).unwrap();
+    let job = loaded.get(first).unwrap();
+    assert_eq!(job.state, JobState::Queued);
+    assert_eq!(
+        job.attempts[0].outcome,
+        AttemptOutcome::Failed {
+            error: "interrupted".to_string()
+        }
+    );
+    assert_eq!(loaded.get_next_pending(), Some(first));
+    assert_eq!(loaded.claim_next_pending("starting"), Some(first));
+    assert_eq!(loaded.get_next_pending(), Some(second));
+}
+
+#[test]
+fn repaired_queue_keeps_jobs_that_are_still_running() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("queue.json");
+
+    let mut live = Queue::default();
+    let first = live.enqueue("first.pdf").unwrap();
+    let second = live.enqueue("second.pdf").unwrap();
+    live.save_to(&path).unwrap();
+    assert_eq!(live.claim_next_pending("processing"), Some(first));
+
+    let (mut repaired, _) = Queue::load_from(&path).unwrap();
+    repaired.keep_running_from(&live);
+    assert_eq!(repaired.get(first).unwrap().state, JobState::Running);
+    assert_eq!(repaired.claim_next_pending("starting"), Some(second));
+    assert_eq!(repaired.claim_next_pending("starting"), None);
+}
+
+#[test]
+fn cancelled_job_is_final_and_never_picked() {
+    let mut q = Queue::default();
+    let queued = q.enqueue("queued.pdf").unwrap();
+    let running = q.enqueue("running.pdf").unwrap();
+    q.mark_running(running, "processing");
+
+    assert!(q.mark_cancelled(queued));
+    assert!(q.mark_cancelled(running));
+    assert_eq!(
+        q.get(running).unwrap().attempts[0].outcome,
+        AttemptOutcome::Cancelled
+    );
+
+    q.mark_retrying(running, "failed_retry", "late error");
+    q.mark_success(running, "out.md");
+    assert_eq!(q.get(running).unwrap().state, JobState::Cancelled);
+    assert_eq!(q.get_next_pending(), None);
+    assert!(!q.mark_cancelled(running));
+}
+
+#[test]
+fn all_jobs_lists_history_in_id_order_with_finish_times() {
+    let mut q = Queue::default();
+    let first = q.enqueue("a.pdf").unwrap();
+    let second = q.enqueue("b.pdf").unwrap();
+    let third = q.enqueue("c.pdf").unwrap();
+    q.mark_running(second, "ocr");
+    q.mark_success(second, "out.md");
+    q.mark_cancelled(third);
+
+    let jobs = q.all_jobs();
+    let ids: Vec<u64> = jobs.iter().map(|job| job.id).collect();
+    assert_eq!(ids, vec![first, second, third]);
+    assert_eq!(jobs[0].finished_at, None);
+    assert_eq!(jobs[0].duration_ms(), None);
+    assert!(
+        jobs[1]
+            .finished_at
+            .is_some_and(|at| at >= jobs[1].created_at)
+    );
+    assert!(jobs[1].duration_ms().is_some());
+    assert!(jobs[2].finished_at.is_some());
+
+    q.mark_failed(first, "boom");
+    q.mark_running(first, "retry");
+    assert_eq!(q.get(first).unwrap().finished_at, None);
+}
+
+#[test]
+fn clear_completed_keeps_active_jobs_and_never_reuses_ids() {
+    let mut q = Queue::default();
+    let done = q.enqueue("done.pdf").unwrap();
+    let failed = q.enqueue("failed.pdf").unwrap();
+    let cancelled = q.enqueue("cancelled.pdf").unwrap();
+    let running = q.enqueue("running.pdf").unwrap();
+    let retrying = q.enqueue("retrying.pdf").unwrap();
+    let queued = q.enqueue("queued.pdf").unwrap();
+    q.mark_running(done, "ocr");
+    q.mark_success(done, "out.md");
+    q.mark_running(failed, "ocr");
+    q.mark_failed(failed, "boom");
+    q.mark_cancelled(cancelled);
+    q.mark_running(running, "ocr");
+    q.mark_running(retrying, "ocr");
+    q.mark_retrying(retrying, "failed_retry", "timeout");
+
+    assert_eq!(q.clear_completed(), 3);
+    let ids: Vec<u64> = q.all_jobs().iter().map(|job| job.id).collect();
+    assert_eq!(ids, vec![running, retrying, queued]);
+    assert_eq!(q.clear_completed(), 0);
+
+    assert!(q.enqueue("next.pdf").unwrap() > queued);
+}
+
+#[test]
+fn enqueue_dedup_reuses_active_jobs_but_allows_reruns() {
+    let mut q = Queue::default();
+    let first = q.enqueue_dedup("scan.pdf").unwrap();
+    assert!(matches!(first, Enqueued::New(_)));
+    assert_eq!(
+        q.enqueue_dedup("scan.pdf").unwrap(),
+        Enqueued::Existing(first.id())
+    );
+
+    q.mark_running(first.id(), "processing");
+    q.mark_retrying(first.id(), "failed_retry", "status 429");
+    assert_eq!(
+        q.enqueue_dedup("scan.pdf").unwrap(),
+        Enqueued::Existing(first.id())
+    );
+
+    q.mark_running(first.id(), "processing");
+    q.mark_success(first.id(), "out.md");
+    let rerun = q.enqueue_dedup("scan.pdf").unwrap();
+    assert!(matches!(rerun, Enqueued::New(id) if id != first.id()));
+    assert_eq!(q.all_jobs().len(), 2);
+}
+
+#[test]
+fn full_queue_rejects_enqueues_until_jobs_finish() {
+    let mut q = Queue::default();
+    q.set_capacity(2);
+    let first = q.enqueue("a.pdf").unwrap();
+    let second = q.enqueue("b.pdf").unwrap();
+
+    assert!(matches!(
+        q.enqueue("c.pdf"),
+        Err(AppError::QueueFull { capacity: 2 })
+    ));
+    assert_eq!(q.enqueue_dedup("a.pdf").unwrap(), Enqueued::Existing(first));
+    assert_eq!(q.all_jobs().len(), 2);
+
+    q.mark_running(first, "processing");
+    q.mark_success(first, "out.md");
+    let third = q.enqueue("c.pdf").unwrap();
+    assert!(q.enqueue("d.pdf").is_err());
+
+    q.mark_cancelled(second);
+    q.clear_completed();
+    assert!(q.enqueue("d.pdf").unwrap() > third);
+    assert_eq!(q.active_len(), 2);
+}
+
+#[test]
+fn higher_priority_jobs_run_first_and_ties_stay_fifo() {
+    let mut q = Queue::default();
+    let bulk_a = q.enqueue("bulk-a.pdf").unwrap();
+    let bulk_b = q.enqueue("bulk-b.pdf").unwrap();
+    let background = q
+        .enqueue_with_priority("archive.pdf", PRIORITY_LOW)
+        .unwrap();
+    let urgent = q
+        .enqueue_with_priority("contract.pdf", PRIORITY_HIGH)
+        .unwrap();
+
+    let mut order = Vec::new();
+    while let Some(id) = q.claim_next_pending("processing") {
+        order.push(id);
+        q.mark_success(id, "out.md");
+    }
+    assert_eq!(order, vec![urgent, bulk_a, bulk_b, background]);
+}
+
+#[test]
+fn set_priority_bumps_waiting_jobs_and_survives_retries() {
+    let mut q = Queue::default();
+    let first = q.enqueue("first.pdf").unwrap();
+    let second = q.enqueue("second.pdf").unwrap();
+
+    assert!(q.set_priority(second, PRIORITY_HIGH));
+    assert_eq!(q.get_next_pending(), Some(second));
+
+    q.mark_running(second, "processing");
+    q.mark_retrying(second, "failed_retry", "status 503");
+    assert_eq!(q.get(second).unwrap().priority, PRIORITY_HIGH);
+    assert_eq!(q.get_next_pending(), Some(second));
+
+    q.mark_running(first, "processing");
+    q.mark_success(first, "out.md");
+    assert!(!q.set_priority(first, PRIORITY_HIGH));
+    assert!(!q.set_priority(99, PRIORITY_HIGH));
+}
+
+#[test]
+fn failing_job_is_retried_exactly_retry_max_times_then_fails() {
+    let mut q = Queue::default();
+    q.set_retry_max(2);
+    let id = q.enqueue("flaky.pdf").unwrap();
+
+    let mut states = Vec::new();
+    while let Some(claimed) = q.claim_next_pending("starting") {
+        assert_eq!(claimed, id);
+        states.push(q.mark_run_failed(id, "status 503").unwrap());
+    }
+
+    assert_eq!(
+        states,
+        vec![JobState::Retrying, JobState::Retrying, JobState::Failed]
+    );
+    let job = q.get(id).unwrap();
+    assert_eq!(job.retries, 2);
+    assert_eq!(job.attempts.len(), 3);
+}
+
+#[test]
+fn retried_job_is_not_served_before_its_backoff_elapses() {
+    let mut q = Queue::default();
+    q.set_retry_max(3);
+    q.set_retry_backoff(60_000);
+    let flaky = q.enqueue("flaky.pdf").unwrap();
+    q.claim_next_pending("starting");
+    q.mark_run_failed(flaky, "status 429");
+
+    let due = q.get(flaky).unwrap().retry_after.unwrap();
+    assert_eq!(q.get(flaky).unwrap().state, JobState::Retrying);
+    assert_eq!(q.get_next_pending(), None);
+    assert_eq!(q.get_next_pending_at(due - 1), None);
+    assert_eq!(q.get_next_pending_at(due), Some(flaky));
+    let wait = q.next_retry_in().unwrap();
+    assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+
+    // Other work goes ahead while the failed job waits.
+    let other = q.enqueue("other.pdf").unwrap();
+    assert_eq!(q.get_next_pending(), Some(other));
+
+    // The second failure waits twice as long.
+    q.mark_running(flaky, "starting");
+    assert_eq!(q.get(flaky).unwrap().retry_after, None);
+    q.mark_run_failed(flaky, "status 429");
+    assert!(q.next_retry_in().unwrap() > Duration::from_secs(110));
+}
+
+#[test]
+fn zero_retry_max_fails_on_the_first_error() {
+    let mut q = Queue::default();
+    q.set_retry_max(0);
+    let id = q.enqueue("flaky.pdf").unwrap();
+    q.claim_next_pending("starting");
+
+    assert_eq!(q.mark_run_failed(id, "timeout"), Some(JobState::Failed));
+    assert_eq!(q.get_next_pending(), None);
+}
+
+#[test]
+fn requeue_failed_serves_failed_jobs_again_in_id_order() {
+    let mut q = Queue::default();
+    let ids: Vec<_> = ["a.pdf", "b.pdf", "c.pdf", "d.pdf", "e.pdf"]
+        .into_iter()
+        .map(|file| q.enqueue(file).unwrap())
+        .collect();
+    for _ in 0..ids.len() {
+        q.claim_next_pending("starting");
+    }
+    q.mark_failed(ids[3], "status 503");
+    q.mark_success(ids[1], "out.md");
+    q.mark_failed(ids[0], "timeout");
+    q.mark_cancelled(ids[4]);
+    // ids[2] stays running.
+
+    assert_eq!(q.requeue_failed(), vec![ids[0], ids[3], ids[4]]);
+
+    assert_eq!(q.get(ids[1]).unwrap().state, JobState::Success);
+    assert_eq!(q.get(ids[2]).unwrap().state, JobState::Running);
+    for id in [ids[0], ids[3], ids[4]] {
+        let job = q.get(id).unwrap();
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.error, None);
+        assert_eq!(job.finished_at, None);
+    }
+    let served: Vec<_> = std::iter::from_fn(|| q.claim_next_pending("starting")).collect();
+    assert_eq!(served, vec![ids[0], ids[3], ids[4]]);
+}
@@ -0,0 +1,40 @@
+use ocr2md_core::redact::Redactor;
+
+#[test]
+fn builtin_patterns_mask_common_pii() {
+    let redactor = Redactor::builtin();
+    let text = "联系 zhang.san@example.com.cn 或电话13812345678，\n\
+                US office (415) 555-0132, intl +86 10 6552 9988.\n\
+                身份证 110105199003078888。";
+
+    assert_eq!(
+        redactor.redact(text),
+        "联系 [EMAIL] 或电话[PHONE]，\n\
+         US office [PHONE], intl [PHONE].\n\
+         身份证 [ID]。"
+    );
+}
+
+#[test]
+fn builtin_patterns_leave_lookalikes_alone() {
+    let redactor = Redactor::builtin();
+    let text = "Dated 2024-01-15, version 1.2.3, total 1,234,567.89, \
+                order 20240115123456789, ISBN 978-7-111-54742-6, \
+                invoice 13812345678901, see @handle.";
+
+    assert_eq!(redactor.redact(text), text);
+}
+
+#[test]
+fn custom_patterns_use_uppercased_label() {
+    let redactor = Redactor::builtin()
+        .with_pattern("contract=HT-\\d{6}")
+        .unwrap();
+
+    assert_eq!(
+        redactor.redact("合同 HT-202401 mail a@b.io"),
+        "合同 [CONTRACT] mail [EMAIL]"
+    );
+    assert!(Redactor::builtin().with_pattern("no-separator").is_err());
+    assert!(Redactor::builtin().with_pattern("bad=(").is_err());
+}
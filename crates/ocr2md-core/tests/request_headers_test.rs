@@ -0,0 +1,42 @@
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::http::HttpEngine;
+use ocr2md_core::llm::{LlmClient, LlmConfig};
+use serde_json::json;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn user_agent_and_extra_headers_reach_the_relay() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("user-agent", "acme-gateway/2.1"))
+        .and(header("x-org-id", "acme"))
+        .and(header("authorization", "Bearer relay-key"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Title"}}]})),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.retry_max = 0;
+    runtime.user_agent = Some("acme-gateway/2.1".to_string());
+    let cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("relay-key".to_string()),
+        Some(format!("{}/v1", server.uri())),
+        Some("gpt-4o-mini".to_string()),
+        None,
+    )
+    .unwrap()
+    .with_extra_headers([("X-Org-Id", "acme"), ("Authorization", "Bearer other")])
+    .unwrap();
+    let client = LlmClient::new(HttpEngine::new(runtime.clone()).unwrap(), cfg, runtime);
+
+    let result = client.to_markdown("ocr text", "trace").await.unwrap();
+
+    assert_eq!(result.markdown, "# Title");
+}
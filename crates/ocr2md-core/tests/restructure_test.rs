@@ -0,0 +1,68 @@
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::{ProcessOptions, restructure_dir};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn restructure_calls_llm_once_per_sidecar_without_ocr() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Regenerated"}}]})),
+        )
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let ocr_dir = dir.path().join("ocr");
+    let output_dir = dir.path().join("out");
+    std::fs::create_dir_all(&ocr_dir).unwrap();
+    std::fs::write(ocr_dir.join("a.ocr.txt"), "alpha").unwrap();
+    std::fs::write(ocr_dir.join("b.ocr.txt"), "beta").unwrap();
+    std::fs::write(ocr_dir.join("b.md"), "# old").unwrap();
+    std::fs::write(ocr_dir.join("orphan.md"), "# no sidecar").unwrap();
+
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+
+    let report = restructure_dir(
+        &ocr_dir,
+        &output_dir,
+        llm_cfg,
+        RuntimeConfig::from_env(),
+        &ProcessOptions::default(),
+        "trace",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        report.written,
+        vec![output_dir.join("a.md"), output_dir.join("b.md")]
+    );
+    assert!(report.failed.is_empty());
+    assert_eq!(report.missing_sidecars, vec![ocr_dir.join("orphan.md")]);
+    assert_eq!(
+        std::fs::read_to_string(output_dir.join("a.md")).unwrap(),
+        "# Regenerated"
+    );
+
+    // Only the LLM mock was mounted, so any OCR request would hit a 404.
+    let requests = server.received_requests().await.unwrap();
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.url.path() == "/llm/chat/completions")
+    );
+}
@@ -0,0 +1,70 @@
+use ocr2md_core::schema::{
+    ANTHROPIC_MESSAGES_RULES, GEMINI_GENERATE_RULES, OPENAI_CHAT_RULES, validate,
+};
+use serde_json::json;
+
+fn error(rules: &[ocr2md_core::schema::FieldRule], body: serde_json::Value) -> String {
+    validate("svc", &body, rules).unwrap_err().to_string()
+}
+
+#[test]
+fn openai_malformed_bodies_name_the_field() {
+    let message = error(OPENAI_CHAT_RULES, json!({"choices": []}));
+    assert!(message.contains("expected non-empty array at /choices, found array of 0 items"));
+
+    let message = error(OPENAI_CHAT_RULES, json!({"error": "boom"}));
+    assert!(message.contains("expected non-empty array at /choices, found nothing"));
+
+    let message = error(
+        OPENAI_CHAT_RULES,
+        json!({"choices": [{"message": {"content": null}}]}),
+    );
+    assert!(message.contains("expected string or array at /choices/0/message/content, found null"));
+
+    assert!(
+        validate(
+            "svc",
+            &json!({"choices": [{"message": {"content": "ok"}}]}),
+            OPENAI_CHAT_RULES
+        )
+        .is_ok()
+    );
+}
+
+#[test]
+fn anthropic_malformed_bodies_name_the_field() {
+    let message = error(ANTHROPIC_MESSAGES_RULES, json!({"content": "text"}));
+    assert!(message.contains("expected array at /content, found string of 4 chars"));
+
+    let message = error(ANTHROPIC_MESSAGES_RULES, json!({"type": "error"}));
+    assert!(message.contains("expected array at /content, found nothing"));
+}
+
+#[test]
+fn gemini_malformed_bodies_name_the_field() {
+    let message = error(
+        GEMINI_GENERATE_RULES,
+        json!({"candidates": [{"finishReason": "SAFETY"}]}),
+    );
+    assert!(message.contains("expected object at /candidates/0/content, found nothing"));
+
+    let message = error(
+        GEMINI_GENERATE_RULES,
+        json!({"candidates": [{"content": {"parts": {"text": "x"}}}]}),
+    );
+    assert!(
+        message.contains(
+            "expected array at /candidates/0/content/parts, found object with keys [text]"
+        )
+    );
+}
+
+#[test]
+fn error_snippet_hides_string_contents() {
+    let message = error(
+        OPENAI_CHAT_RULES,
+        json!({"choices": [], "echo": "sk-secret-value"}),
+    );
+    assert!(!message.contains("sk-secret-value"));
+    assert!(message.contains("<15 chars>"));
+}
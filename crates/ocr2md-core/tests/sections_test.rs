@@ -0,0 +1,114 @@
+mod common;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pdf::page_count;
+use ocr2md_core::pipeline::rerun_sections;
+use ocr2md_core::sections::{SectionedDocument, ocr_confidence};
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use crate::common::pdf_with_pages;
+
+fn completion(content: &str) -> Value {
+    json!({"choices": [{"message": {"content": content}}]})
+}
+
+#[test]
+fn sections_follow_page_markers_and_score_noise() {
+    let document = SectionedDocument::from_ocr_text(
+        "--- page 1 ---\n\nclean text\n\n--- page 2 ---\n\n[?] ▒▒▒▒ [?]",
+    );
+
+    assert_eq!(document.sections.len(), 2);
+    assert_eq!(document.sections[0].pages, vec![1]);
+    assert_eq!(document.sections[1].ocr_text, "[?] ▒▒▒▒ [?]");
+    assert_eq!(ocr_confidence("clean text"), 1.0);
+    assert_eq!(document.low_confidence(0.8), vec![1]);
+}
+
+#[test]
+fn text_before_the_first_page_marker_opens_the_first_section() {
+    let document = SectionedDocument::from_ocr_text(
+        "Recognized text:\n--- page 1 ---\nfirst\n--- page 2 ---\nsecond",
+    );
+
+    assert_eq!(document.sections.len(), 2);
+    assert_eq!(document.sections[0].pages, vec![1]);
+    assert_eq!(document.sections[0].ocr_text, "Recognized text:\nfirst");
+    assert_eq!(document.sections[1].ocr_text, "second");
+}
+
+#[tokio::test]
+async fn rerun_section_is_merged_back_in_place() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("page two, reread")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(completion("## Page two fixed")))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("report.pdf");
+    std::fs::write(&input, pdf_with_pages(3)).unwrap();
+
+    let mut document = SectionedDocument::from_ocr_text(
+        "--- page 1 ---\n\none\n\n--- page 2 ---\n\nt▒▒▒▒o\n\n--- page 3 ---\n\nthree",
+    );
+    for (section, markdown) in document
+        .sections
+        .iter_mut()
+        .zip(["# One", "garbled", "## Three"])
+    {
+        section.markdown = markdown.to_string();
+    }
+
+    let runtime = RuntimeConfig::from_env();
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+
+    rerun_sections(
+        &input,
+        &mut document,
+        &[1],
+        glm_cfg,
+        llm_cfg,
+        runtime,
+        "trace",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        document.to_markdown(),
+        "# One\n\n## Page two fixed\n\n## Three"
+    );
+    assert_eq!(document.sections[1].pages, vec![2]);
+    assert_eq!(document.sections[1].ocr_text, "page two, reread");
+
+    let requests = server.received_requests().await.unwrap();
+    let ocr_body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let data_url = ocr_body["messages"][0]["content"][0]["file_url"]["url"]
+        .as_str()
+        .unwrap();
+    let uploaded = STANDARD
+        .decode(data_url.split_once(";base64,").unwrap().1)
+        .unwrap();
+    assert_eq!(page_count(&uploaded).unwrap(), 1);
+}
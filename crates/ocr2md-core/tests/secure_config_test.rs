@@ -1,4 +1,4 @@
-use ocr2md_core::secure_config::{decrypt_blob, encrypt_blob};
+use ocr2md_core::secure_config::{DecryptError, decrypt_blob, encrypt_blob};
 
 #[test]
 fn encrypt_decrypt_roundtrip() {
@@ -7,3 +7,47 @@ fn encrypt_decrypt_roundtrip() {
     let back = decrypt_blob(&cipher, "passphrase").unwrap();
     assert_eq!(back, plain);
 }
+
+#[test]
+fn wrong_passphrase_fails_the_tag_check() {
+    let cipher = encrypt_blob(b"secret", "passphrase").unwrap();
+    assert_eq!(
+        decrypt_blob(&cipher, "other"),
+        Err(DecryptError::WrongPassphraseOrTampered)
+    );
+}
+
+#[test]
+fn flipped_ciphertext_byte_fails_the_tag_check() {
+    let mut cipher = encrypt_blob(b"secret", "passphrase").unwrap();
+    let last = cipher.len() - 1;
+    cipher[last] ^= 0x01;
+    assert_eq!(
+        decrypt_blob(&cipher, "passphrase"),
+        Err(DecryptError::WrongPassphraseOrTampered)
+    );
+}
+
+#[test]
+fn damaged_envelopes_are_not_blamed_on_the_passphrase() {
+    let cipher = encrypt_blob(b"secret", "passphrase").unwrap();
+
+    assert!(matches!(
+        decrypt_blob(&cipher[..20], "passphrase"),
+        Err(DecryptError::BadEnvelope(_))
+    ));
+
+    let mut magic = cipher.clone();
+    magic[0] = b'X';
+    assert!(matches!(
+        decrypt_blob(&magic, "passphrase"),
+        Err(DecryptError::BadEnvelope(_))
+    ));
+
+    let mut version = cipher;
+    version[4] = 9;
+    assert_eq!(
+        decrypt_blob(&version, "passphrase"),
+        Err(DecryptError::UnsupportedVersion(9))
+    );
+}
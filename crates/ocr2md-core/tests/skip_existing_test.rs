@@ -0,0 +1,152 @@
+mod common;
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::{ProcessOptions, is_up_to_date, process_file_with};
+use ocr2md_core::progress::{ProgressEvent, ProgressSink};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn set_mtime(path: &Path, mtime: SystemTime) {
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+}
+
+// Converts `scan.pdf` into `scan.md`, whose mtime is `output_age` relative to
+// the input's (`None` for no existing output). Returns the Markdown and the
+// skip reason reported, if any.
+async fn convert(
+    output_age: Option<i64>,
+    skip_if_exists: bool,
+    conversions: u64,
+) -> (String, Option<String>) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "Invoice 42"}}]})),
+        )
+        .expect(conversions)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "# Invoice 42"}}]})),
+        )
+        .expect(conversions)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+    let input_mtime = SystemTime::now() - Duration::from_secs(3600);
+    set_mtime(&input, input_mtime);
+    if let Some(age) = output_age {
+        std::fs::write(&output, "# Old\n").unwrap();
+        let offset = Duration::from_secs(age.unsigned_abs());
+        let mtime = if age >= 0 {
+            input_mtime + offset
+        } else {
+            input_mtime - offset
+        };
+        set_mtime(&output, mtime);
+    }
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+    let skipped = Arc::new(Mutex::new(None));
+    let seen = skipped.clone();
+    let options = ProcessOptions {
+        skip_if_exists,
+        progress: Some(ProgressSink::new(move |event| {
+            if let ProgressEvent::Skipped { reason } = event {
+                *seen.lock().unwrap() = Some(reason);
+            }
+        })),
+        ..ProcessOptions::default()
+    };
+
+    process_file_with(
+        &input, &output, glm_cfg, llm_cfg, runtime, &options, "trace",
+    )
+    .await
+    .unwrap();
+    let markdown = std::fs::read_to_string(&output).unwrap();
+    let reason = skipped.lock().unwrap().take();
+    (markdown, reason)
+}
+
+#[tokio::test]
+async fn missing_output_is_converted() {
+    let (markdown, skipped) = convert(None, true, 1).await;
+
+    assert!(markdown.contains("# Invoice 42"), "{markdown}");
+    assert_eq!(skipped, None);
+}
+
+#[tokio::test]
+async fn newer_output_skips_ocr_and_the_llm() {
+    let (markdown, skipped) = convert(Some(60), true, 0).await;
+
+    assert_eq!(markdown, "# Old\n");
+    assert_eq!(skipped.as_deref(), Some("output is up to date"));
+}
+
+#[tokio::test]
+async fn older_output_is_converted_again() {
+    let (markdown, skipped) = convert(Some(-60), true, 1).await;
+
+    assert!(markdown.contains("# Invoice 42"), "{markdown}");
+    assert_eq!(skipped, None);
+}
+
+#[tokio::test]
+async fn without_skip_if_exists_a_newer_output_is_replaced() {
+    let (markdown, skipped) = convert(Some(60), false, 1).await;
+
+    assert!(markdown.contains("# Invoice 42"), "{markdown}");
+    assert_eq!(skipped, None);
+}
+
+#[test]
+fn up_to_date_needs_a_strictly_newer_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    let output = dir.path().join("scan.md");
+    std::fs::write(&input, b"%PDF").unwrap();
+    assert!(!is_up_to_date(&input, &output));
+
+    let now = SystemTime::now();
+    std::fs::write(&output, b"# Scan").unwrap();
+    set_mtime(&input, now);
+    set_mtime(&output, now);
+    assert!(!is_up_to_date(&input, &output));
+
+    set_mtime(&output, now + Duration::from_secs(1));
+    assert!(is_up_to_date(&input, &output));
+    assert!(!is_up_to_date(&dir.path().join("gone.pdf"), &output));
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::error::AppError;
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::process_file;
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn content(text: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({"choices": [{"message": {"content": text}}]}))
+}
+
+// Each request alone stays well inside `request_timeout_ms`; only the stage
+// ceiling can stop it.
+async fn run(server: &MockServer) -> (tempfile::TempDir, anyhow::Result<()>, Duration) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("scan.pdf");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    runtime.request_timeout_ms = 10_000;
+    runtime.retry_max = 0;
+    runtime.ocr_stage_timeout_ms = 300;
+    runtime.llm_stage_timeout_ms = 300;
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        Some("model".to_string()),
+        None,
+    )
+    .unwrap();
+
+    let started = Instant::now();
+    let result = process_file(
+        &input,
+        &dir.path().join("scan.md"),
+        glm_cfg,
+        llm_cfg,
+        runtime,
+        "trace",
+    )
+    .await;
+    (dir, result, started.elapsed())
+}
+
+fn assert_stage_timeout(result: anyhow::Result<()>, expected: &str) {
+    let err = result.unwrap_err();
+    assert!(
+        matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::StageTimeout { stage, timeout_ms: 300 }) if stage == expected
+        ),
+        "{err:#}"
+    );
+}
+
+#[tokio::test]
+async fn stalled_ocr_stage_is_aborted() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(content("ocr text").set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let (_dir, result, elapsed) = run(&server).await;
+
+    assert_stage_timeout(result, "ocr");
+    assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+}
+
+#[tokio::test]
+async fn stalled_llm_stage_is_aborted_without_output() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(content("ocr text"))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(content("# Title").set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let (dir, result, elapsed) = run(&server).await;
+
+    assert_stage_timeout(result, "llm");
+    assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+    assert!(!dir.path().join("scan.md").exists());
+}
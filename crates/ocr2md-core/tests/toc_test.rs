@@ -0,0 +1,92 @@
+mod common;
+
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::llm::LlmConfig;
+use ocr2md_core::pipeline::{ProcessOptions, process_file_with};
+use ocr2md_core::toc::TocTarget;
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const MARKDOWN: &str = "# Report\n\n## Summary\n\n```\n# not a heading\n```\n\n## Summary\n";
+
+async fn convert(toc: TocTarget, dir: &tempfile::TempDir) -> String {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/glm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": "raw ocr text"}}]})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/llm/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"choices": [{"message": {"content": MARKDOWN}}]})),
+        )
+        .mount(&server)
+        .await;
+
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("report.md");
+    std::fs::write(&input, b"%PDF-1.7").unwrap();
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.ocr_cache_dir = None;
+    let glm_cfg = common::glm_config(format!("{}/glm", server.uri()));
+    let llm_cfg = LlmConfig::from_sources(
+        LlmProvider::OpenaiCompatible,
+        Some("llm-key".to_string()),
+        Some(format!("{}/llm", server.uri())),
+        None,
+        None,
+    )
+    .unwrap();
+    let options = ProcessOptions {
+        toc: Some(toc),
+        ..ProcessOptions::default()
+    };
+
+    process_file_with(
+        &input, &output, glm_cfg, llm_cfg, runtime, &options, "trace",
+    )
+    .await
+    .unwrap();
+    std::fs::read_to_string(output).unwrap()
+}
+
+#[tokio::test]
+async fn inline_toc_is_prepended_to_the_markdown() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let markdown = convert(TocTarget::Inline, &dir).await;
+
+    assert_eq!(
+        markdown,
+        format!(
+            "- [Report](#report)\n  - [Summary](#summary)\n  - [Summary](#summary-1)\n\n{MARKDOWN}"
+        )
+    );
+}
+
+#[tokio::test]
+async fn json_outline_leaves_the_output_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let outline = dir.path().join("outlines/report.json");
+
+    let markdown = convert(TocTarget::Json(outline.clone()), &dir).await;
+
+    assert_eq!(markdown, MARKDOWN);
+    let outline: Value = serde_json::from_str(&std::fs::read_to_string(outline).unwrap()).unwrap();
+    assert_eq!(
+        outline,
+        json!([
+            {"level": 1, "text": "Report", "slug": "report", "line": 1},
+            {"level": 2, "text": "Summary", "slug": "summary", "line": 3},
+            {"level": 2, "text": "Summary", "slug": "summary-1", "line": 9},
+        ])
+    );
+}
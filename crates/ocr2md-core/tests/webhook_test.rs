@@ -0,0 +1,94 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use ocr2md_core::config::RuntimeConfig;
+use ocr2md_core::queue::{JobState, Queue};
+use ocr2md_core::webhook::{JobNotification, WEBHOOK_TIMEOUT_MS, Webhook};
+use serde_json::{Value, json};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn finished_job_is_posted_as_json() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let webhook =
+        Webhook::new(format!("{}/hook", server.uri()), &RuntimeConfig::from_env()).unwrap();
+    let notification = JobNotification::from_outcome(
+        "trace-1",
+        Path::new("in/scan.pdf"),
+        Ok(Path::new("out/scan.md")),
+    );
+    webhook.notify(&notification).await;
+
+    let requests = server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "job_id": "trace-1",
+            "input": "in/scan.pdf",
+            "state": "Success",
+            "output_path": "out/scan.md",
+            "error": null
+        })
+    );
+}
+
+#[tokio::test]
+async fn failing_or_slow_receiver_is_given_up_on_quickly() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(30)))
+        .mount(&server)
+        .await;
+
+    let mut runtime = RuntimeConfig::from_env();
+    runtime.request_timeout_ms = 60_000;
+    runtime.retry_max = 5;
+    let webhook = Webhook::new(server.uri(), &runtime).unwrap();
+    let notification =
+        JobNotification::from_outcome("trace-2", Path::new("scan.pdf"), Err("boom".to_string()));
+
+    let started = Instant::now();
+    webhook.notify(&notification).await;
+
+    assert!(started.elapsed() < Duration::from_millis(WEBHOOK_TIMEOUT_MS + 2_000));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[test]
+fn queued_jobs_notify_only_in_a_final_state() {
+    let mut queue = Queue::default();
+    let id = queue.enqueue("scan.pdf").unwrap();
+    assert!(JobNotification::from_job("job-1", queue.get(id).unwrap()).is_none());
+
+    queue.mark_failed(id, "bad scan");
+    let notification = JobNotification::from_job("job-1", queue.get(id).unwrap()).unwrap();
+    assert_eq!(notification.state, JobState::Failed);
+    assert_eq!(notification.output_path, None);
+    assert_eq!(notification.error.as_deref(), Some("bad scan"));
+}
+
+#[test]
+fn skipped_jobs_notify_success_without_an_output() {
+    let mut queue = Queue::default();
+    let converted = queue.enqueue("a.pdf").unwrap();
+    let blank = queue.enqueue("b.pdf").unwrap();
+    queue.mark_running(converted, "processing");
+    queue.mark_success(converted, "a.md");
+    queue.mark_running(blank, "processing");
+    queue.mark_skipped(blank, "OCR found no text");
+
+    let notification = JobNotification::from_job("job-1", queue.get(converted).unwrap()).unwrap();
+    assert_eq!(notification.output_path.as_deref(), Some("a.md"));
+    let notification = JobNotification::from_job("job-2", queue.get(blank).unwrap()).unwrap();
+    assert_eq!(notification.state, JobState::Success);
+    assert_eq!(notification.output_path, None);
+}
@@ -1,34 +1,74 @@
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
-use clap::Parser;
-use ocr2md_core::config::LlmProvider;
+use clap::{ArgAction, Parser, Subcommand};
+use ocr2md_core::config::ProviderChoice;
+use ocr2md_core::file_kind::InputKind;
+use ocr2md_core::format::OutputFormat;
+use ocr2md_core::ocr::{DocType, OcrFallback, OcrProvider};
+use ocr2md_core::pdf::PageRanges;
+use ocr2md_core::pipeline::{Emit, EmptyOcrPolicy};
+use ocr2md_core::postprocess::Postprocess;
+use ocr2md_core::toc::TocTarget;
 
 #[derive(Debug, Parser)]
 #[command(
     name = "ocr2md",
     version,
-    about = "Cross-platform OCR to structured Markdown pipeline (Windows/macOS)"
+    about = "Cross-platform OCR to structured Markdown pipeline (Windows/macOS)",
+    subcommand_negates_reqs = true
 )]
 pub struct Cli {
-    #[arg(value_name = "INPUT_FILE", help = "input file path (.pdf/.doc/.docx)")]
-    pub input: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(
+        value_name = "INPUT",
+        required = true,
+        help = "input files or glob patterns (.pdf/.doc/.docx/.png/.jpg/.webp); - reads one file from stdin"
+    )]
+    pub input: Vec<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KIND",
+        help = "type of a file piped in as input -, used when its magic bytes are not recognised"
+    )]
+    pub stdin_kind: Option<InputKind>,
 
     #[arg(
         short,
         long,
         value_name = "OUTPUT_MD",
-        help = "output markdown file path"
+        help = "output markdown file path; - writes to stdout"
     )]
     pub output: Option<PathBuf>,
 
+    #[arg(
+        long,
+        value_name = "DIR",
+        conflicts_with = "output",
+        help = "write each {stem}.md into DIR instead of next to its input"
+    )]
+    pub output_dir: Option<PathBuf>,
+
     #[arg(
         long,
         value_enum,
+        value_delimiter = ',',
+        default_value = "markdown",
+        help = "artifacts to write: ocr ({stem}.ocr.txt) and/or markdown"
+    )]
+    pub emit: Vec<Emit>,
+
+    #[arg(
+        long,
         env = "LLM_PROVIDER",
         default_value = "openai-compatible",
-        help = "commercial LLM provider"
+        help = "commercial LLM provider: openai, anthropic, gemini, openai-compatible, ollama or azure; deepseek, moonshot and kimi are openai-compatible with their own default base URL and model"
     )]
-    pub provider: LlmProvider,
+    pub provider: ProviderChoice,
 
     #[arg(long, env = "LLM_MODEL", help = "LLM model name")]
     pub llm_model: Option<String>,
@@ -58,6 +98,111 @@ pub struct Cli {
     #[arg(long, env = "GLM_OCR_MODEL", help = "GLM OCR model name")]
     pub glm_ocr_model: Option<String>,
 
+    #[arg(
+        long,
+        env = "GLM_OCR_PROMPT",
+        help = "extraction prompt sent with images and PDFs (document type and language hints are still appended)"
+    )]
+    pub ocr_prompt: Option<String>,
+
+    #[arg(
+        long,
+        env = "GLM_FILE_PARSE_PROMPT",
+        help = "extraction prompt sent with Word files to the GLM file parser"
+    )]
+    pub file_parse_prompt: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        env = "OCR_PROVIDER",
+        default_value = "glm",
+        help = "vision API that reads images and PDFs (gemini uses GEMINI_API_KEY, openai uses OPENAI_API_KEY and reads images only)"
+    )]
+    pub ocr_provider: OcrProvider,
+
+    #[arg(
+        long,
+        value_enum,
+        env = "OCR_FALLBACK",
+        default_value = "none",
+        help = "fallback used when vision OCR fails on a PDF"
+    )]
+    pub ocr_fallback: OcrFallback,
+
+    #[arg(
+        long,
+        env = "OCR2MD_CONTINUE_ON_PARTIAL",
+        help = "accept partial_content from a failed file parse instead of erroring"
+    )]
+    pub continue_on_partial: bool,
+
+    #[arg(
+        long,
+        value_name = "TYPE",
+        env = "OCR2MD_DOC_TYPE",
+        help = "OCR hint: receipt, invoice, form, handwritten, table, academic or free text"
+    )]
+    pub doc_type: Option<DocType>,
+
+    #[arg(
+        long,
+        value_name = "LANGUAGE",
+        env = "OCR2MD_OCR_LANGUAGE",
+        help = "main document language hint for OCR, e.g. zh, en, ja"
+    )]
+    pub ocr_language: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "CODE",
+        env = "OCR2MD_LANG",
+        help = "document language for the structuring prompt, e.g. en, zh, ja (skips detection)"
+    )]
+    pub lang: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "OCR at most N pages of a PDF (default OCR2MD_MAX_PAGES, 0 = no cap)"
+    )]
+    pub max_pages: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "RANGES",
+        help = "OCR only these PDF pages, e.g. 5-12,20,30- (1-based)"
+    )]
+    pub pages: Option<PageRanges>,
+
+    #[arg(
+        long,
+        env = "OCR2MD_AUTO_ROTATE",
+        help = "turn rotated or landscape scans upright before OCR"
+    )]
+    pub auto_rotate: bool,
+
+    #[arg(
+        long,
+        value_name = "PX",
+        env = "OCR2MD_MAX_DIMENSION",
+        help = "downscale images so the longest side is at most PX before OCR (0 disables)"
+    )]
+    pub max_dimension: Option<u32>,
+
+    #[arg(
+        long,
+        help = "ignore OCR2MD_CACHE_DIR and call the OCR endpoint even for files seen before"
+    )]
+    pub no_cache: bool,
+
+    #[arg(
+        long,
+        env = "OCR2MD_RAW_PAGE_BREAKS",
+        help = "keep form feeds and \"Page N of M\" lines instead of converting them to page markers"
+    )]
+    pub raw_page_breaks: bool,
+
     #[arg(
         long,
         env = "SYSTEM_PROMPT",
@@ -65,6 +210,285 @@ pub struct Cli {
     )]
     pub system_prompt: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "read the markdown structuring system prompt from a file (takes precedence over --system-prompt)"
+    )]
+    pub system_prompt_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        env = "OCR2MD_ON_EMPTY_OCR",
+        default_value = "warn",
+        help = "when OCR finds no text: warn and still call the LLM, skip the file without writing anything, or fail it"
+    )]
+    pub on_empty_ocr: EmptyOcrPolicy,
+
+    #[arg(
+        long,
+        help = "run OCR only and write the raw text to <output>.ocr.txt; no LLM credentials needed"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        env = "OCR2MD_MOCK",
+        help = "offline demo: canned OCR text and a template LLM answer; no API calls or keys"
+    )]
+    pub mock: bool,
+
+    #[arg(
+        long = "stop",
+        value_name = "SEQUENCE",
+        help = "LLM stop sequence (repeatable)"
+    )]
+    pub stop: Vec<String>,
+
+    #[arg(
+        long,
+        env = "LLM_TEMPERATURE",
+        help = "LLM sampling temperature, 0 to 2 (default 0.1)"
+    )]
+    pub temperature: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        env = "LLM_MAX_TOKENS",
+        help = "cap on LLM output tokens (default: provider's own; ANTHROPIC_MAX_TOKENS for Anthropic)"
+    )]
+    pub max_tokens: Option<u32>,
+
+    #[arg(
+        long,
+        env = "OCR2MD_FRONTMATTER",
+        help = "start each Markdown file with YAML frontmatter (source, models, trace id)"
+    )]
+    pub frontmatter: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        env = "OCR2MD_FORMAT",
+        default_value = "md",
+        help = "output format: Markdown as returned by the LLM, or HTML / plain text rendered from it"
+    )]
+    pub format: OutputFormat,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        env = "OCR2MD_WEBHOOK_URL",
+        help = "POST a JSON summary here after each file finishes"
+    )]
+    pub webhook_url: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "write a JSON manifest of every input's outcome here, even when some fail"
+    )]
+    pub manifest: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "manifest",
+        help = "continue the batch recorded in this manifest: skip inputs it lists as converted and unchanged since, and keep it updated after each file"
+    )]
+    pub resume: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "add each conversion to the end of the output file, separated by ---, instead of replacing it; lets several inputs share one --output"
+    )]
+    pub append: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "1",
+        help = "convert up to N files of a batch at once, sharing one HTTP client"
+    )]
+    pub concurrency: NonZeroUsize,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "also write each Markdown table as <output-stem>.tableN.csv into DIR"
+    )]
+    pub extract_tables: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH|inline",
+        help = "write the Markdown's headings as a JSON outline to PATH, or `inline` to put a linked table of contents at the top of the output"
+    )]
+    pub toc: Option<TocTarget>,
+
+    #[arg(
+        long,
+        help = "write Markdown to the output file as the LLM produces it"
+    )]
+    pub stream: bool,
+
+    #[arg(
+        long,
+        help = "fail instead of falling back when the provider cannot stream (implies --stream)"
+    )]
+    pub require_streaming: bool,
+
+    #[arg(
+        long,
+        env = "OCR2MD_CJK_NORMALIZE",
+        help = "normalize CJK/Latin spacing and punctuation in the Markdown (code is left as-is)"
+    )]
+    pub cjk_normalize: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "PASS",
+        env = "OCR2MD_POSTPROCESS",
+        help = "cleanups to run over the Markdown, in order: trim-trailing, collapse-blanks, normalize-headings, halfwidth-punctuation (code blocks are left as-is)"
+    )]
+    pub postprocess: Vec<Postprocess>,
+
+    #[arg(
+        long,
+        env = "OCR2MD_STRIP_RUNNING_HEADERS",
+        help = "drop page headers, footers and page-number lines repeated across the OCR text before the LLM sees it"
+    )]
+    pub strip_running_headers: bool,
+
+    #[arg(
+        long,
+        env = "OCR2MD_SKIP_EXISTING",
+        help = "skip inputs whose output already exists and is newer than the input, without calling OCR or the LLM"
+    )]
+    pub skip_existing: bool,
+
+    #[arg(long, help = "convert every input even when --skip-existing is set")]
+    pub force: bool,
+
+    #[arg(
+        long,
+        env = "OCR2MD_REDACT_PII",
+        help = "best-effort masking of emails, phone numbers and IDs before the LLM step"
+    )]
+    pub redact_pii: bool,
+
+    #[arg(
+        long = "redact-pattern",
+        value_name = "LABEL=REGEX",
+        help = "extra pattern masked as [LABEL] (repeatable, implies --redact-pii)"
+    )]
+    pub redact_patterns: Vec<String>,
+
     #[arg(long, env = "TRACE_ID", help = "override trace id")]
     pub trace_id: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        env = "OCR2MD_TRACE_FILE",
+        help = "also append this run's events (those carrying its trace id) to PATH as JSON lines; console logging is unchanged"
+    )]
+    pub trace_file: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        action = ArgAction::Count,
+        global = true,
+        conflicts_with = "quiet",
+        help = "log more: -v for debug, -vv for trace (RUST_LOG still wins)"
+    )]
+    pub verbose: u8,
+
+    #[arg(
+        short,
+        long,
+        global = true,
+        help = "log errors only (RUST_LOG still wins)"
+    )]
+    pub quiet: bool,
+
+    // Read from the raw arguments before parsing (see `env_file_args` in
+    // main.rs) so the files can feed every `env = ...` default below.
+    #[arg(
+        long,
+        value_name = "PATH",
+        global = true,
+        help = "load variables from this dotenv file instead of ./.env; repeatable, later files win, real environment variables win over all"
+    )]
+    pub env_file: Vec<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    #[command(about = "print supported formats, providers and features as JSON")]
+    Capabilities,
+
+    #[command(
+        about = "print the version and effective configuration as JSON; API keys only show as set or missing"
+    )]
+    Info,
+
+    #[command(
+        about = "estimate pages, tokens and cost of converting the inputs as JSON; no API is called"
+    )]
+    Estimate {
+        #[arg(
+            value_name = "INPUT",
+            required = true,
+            help = "input files or glob patterns"
+        )]
+        input: Vec<String>,
+
+        #[arg(
+            long,
+            value_name = "FILE",
+            env = "OCR2MD_RATE_TABLE",
+            help = "JSON rate table overriding the built-in placeholder rates"
+        )]
+        rates: Option<PathBuf>,
+    },
+
+    #[command(about = "regenerate Markdown from saved .ocr.txt sidecars without re-running OCR")]
+    Restructure {
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "directory containing .ocr.txt sidecars"
+        )]
+        ocr_dir: PathBuf,
+
+        #[arg(
+            long,
+            value_name = "DIR",
+            help = "directory for the regenerated .md files"
+        )]
+        output_dir: PathBuf,
+    },
+
+    #[command(about = "convert new .pdf/.doc/.docx/image files as they land in a directory")]
+    Watch {
+        #[arg(value_name = "DIR", help = "directory to watch")]
+        dir: PathBuf,
+
+        #[arg(long, help = "convert even when a newer .md already exists")]
+        force: bool,
+
+        #[arg(
+            long,
+            value_name = "MS",
+            default_value_t = 2000,
+            help = "wait until a file has not changed for MS milliseconds"
+        )]
+        settle_ms: u64,
+    },
 }
@@ -1,31 +1,187 @@
 mod cli;
+mod trace_file;
+mod watch;
 
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::future::Future;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use ocr2md_core::config::RuntimeConfig;
+use futures::stream::{self, StreamExt};
+use ocr2md_core::capabilities::capabilities;
+use ocr2md_core::config::{LlmProvider, RuntimeConfig};
+use ocr2md_core::estimate::{RateTable, estimate_batch};
+use ocr2md_core::file_kind::is_stdin;
+use ocr2md_core::format::OutputFormat;
+use ocr2md_core::info::{ConfigReport, LlmSummary, OcrSummary};
 use ocr2md_core::llm::LlmConfig;
-use ocr2md_core::ocr::GlmConfig;
-use ocr2md_core::pipeline::process_file;
+use ocr2md_core::manifest::{JobOutcome, Manifest};
+use ocr2md_core::ocr::{GlmConfig, OcrProvider};
+use ocr2md_core::output::STDOUT_PATH;
+use ocr2md_core::pipeline::{
+    Pipeline, ProcessOptions, ocr_only, ocr_sidecar_path, process_file_with, restructure_dir,
+};
+use ocr2md_core::progress::{BatchEvent, ProgressDisplay, ProgressEvent, ProgressSink};
+use ocr2md_core::redact::Redactor;
+use ocr2md_core::toc::TocTarget;
+use ocr2md_core::webhook::{JobNotification, Webhook};
+use tracing::level_filters::LevelFilter;
+use tracing::{info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
 
-use crate::cli::Cli;
+use crate::cli::{Cli, Command};
+use crate::watch::WatchJob;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    dotenvy::dotenv().ok();
-    init_tracing();
+    load_env_files(&env_file_args(std::env::args_os()))?;
+    let mut cli = Cli::parse();
+    let filter = std::env::var("RUST_LOG")
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| log_filter(cli.verbose, cli.quiet).to_string());
+    let trace_id = cli.trace_id.take().unwrap_or_else(default_trace_id);
+    // `--quiet` only quiets the console; the trace file keeps info events.
+    let trace_layer = cli
+        .trace_file
+        .as_deref()
+        .map(|path| trace_file::layer(path, &trace_id, trace_file_level(cli.verbose)))
+        .transpose()?;
+    init_tracing(&filter, trace_layer);
 
-    let cli = Cli::parse();
-    let trace_id = cli.trace_id.unwrap_or_else(default_trace_id);
+    cli.output = take_stdout_output(&mut cli.input, cli.output);
 
-    let input_path = cli.input;
-    let output_path = resolve_output_path(&input_path, cli.output);
+    if let Some(Command::Capabilities) = cli.command {
+        println!("{}", serde_json::to_string_pretty(&capabilities())?);
+        return Ok(());
+    }
+
+    // The mock providers have no CLI value of their own, so --mock overrides
+    // whatever --provider/--ocr-provider resolved to.
+    if cli.mock {
+        warn!("mock mode: OCR and LLM output are canned, no API is called");
+        cli.provider = LlmProvider::Mock.into();
+        cli.ocr_provider = OcrProvider::Mock;
+    }
+
+    let mut runtime = RuntimeConfig::from_env();
+    if cli.no_cache {
+        runtime.ocr_cache_dir = None;
+    }
+
+    if let Some(Command::Info) = cli.command {
+        let report = config_report(&cli, runtime);
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    if let Some(Command::Estimate { input, rates }) = &cli.command {
+        let table = match rates {
+            Some(path) => RateTable::load(path)?,
+            None => RateTable::default(),
+        };
+        // Rates are looked up under the model and provider a real run with
+        // these flags would use.
+        let report = config_report(&cli, runtime);
+        let pricing = table.pricing(
+            &report.ocr.provider,
+            report.ocr.model.as_deref(),
+            &report.llm.provider,
+            report.llm.model.as_deref(),
+        );
+        let estimate = estimate_batch(&expand_inputs(input)?, &pricing)?;
+        println!("{}", serde_json::to_string_pretty(&estimate)?);
+        return Ok(());
+    }
+
+    let system_prompt =
+        match &cli.system_prompt_file {
+            Some(path) => Some(std::fs::read_to_string(path).with_context(|| {
+                format!("failed to read system prompt file: {}", path.display())
+            })?),
+            None => cli.system_prompt,
+        };
+    // A dry run never builds an LLM config, so no LLM credentials are needed.
+    let llm_cfg = if cli.dry_run {
+        None
+    } else {
+        let mut llm_cfg = LlmConfig::from_choice(
+            cli.provider,
+            cli.llm_api_key,
+            cli.llm_base_url,
+            cli.llm_model,
+            system_prompt,
+        )?
+        .with_stop(cli.stop)?
+        .with_sampling(cli.temperature, cli.max_tokens)?;
+        llm_cfg.require_streaming = cli.require_streaming;
+        llm_cfg.document_language = cli.lang.filter(|lang| !lang.trim().is_empty());
+        Some(llm_cfg)
+    };
+
+    let redact = if cli.redact_pii || !cli.redact_patterns.is_empty() {
+        let mut redactor = Redactor::builtin();
+        for spec in &cli.redact_patterns {
+            redactor = redactor.with_pattern(spec)?;
+        }
+        Some(redactor)
+    } else {
+        None
+    };
 
-    let runtime = RuntimeConfig::from_env();
+    let options = ProcessOptions {
+        emit: cli.emit,
+        stream: cli.stream || cli.require_streaming,
+        redact,
+        cjk_normalize: cli.cjk_normalize,
+        postprocess: cli.postprocess.clone(),
+        strip_running_headers: cli.strip_running_headers,
+        frontmatter: cli.frontmatter,
+        format: cli.format,
+        append: cli.append,
+        extract_tables: cli.extract_tables.clone(),
+        toc: cli.toc.clone(),
+        stdin_kind: cli.stdin_kind,
+        on_empty_ocr: cli.on_empty_ocr,
+        skip_if_exists: skips_existing(cli.skip_existing, cli.force),
+        ..ProcessOptions::default()
+    };
+
+    if let Some(Command::Restructure {
+        ocr_dir,
+        output_dir,
+    }) = &cli.command
+    {
+        let llm_cfg = llm_cfg.context("restructure needs the LLM; drop --dry-run")?;
+        check_toc_target(options.toc.as_ref(), true)?;
+        let report =
+            restructure_dir(ocr_dir, output_dir, llm_cfg, runtime, &options, &trace_id).await?;
+        for markdown in &report.missing_sidecars {
+            eprintln!("missing OCR sidecar for {}", markdown.display());
+        }
+        for (sidecar, error) in &report.failed {
+            eprintln!("failed {}: {error}", sidecar.display());
+        }
+        println!(
+            "restructured {} file(s), {} failed, {} missing sidecar(s)",
+            report.written.len(),
+            report.failed.len(),
+            report.missing_sidecars.len()
+        );
+        if !report.failed.is_empty() {
+            anyhow::bail!("{} file(s) failed to restructure", report.failed.len());
+        }
+        return Ok(());
+    }
 
-    let glm_cfg = GlmConfig::from_sources(
+    let mut glm_cfg = GlmConfig::from_sources_for(
+        cli.ocr_provider,
         cli.glm_api_key,
         cli.glm_base_url,
         cli.glm_ocr_model,
@@ -33,36 +189,562 @@ async fn main() -> Result<()> {
         cli.glm_file_parse_url,
         runtime.max_ocr_chars,
     )?;
+    glm_cfg.fallback = cli.ocr_fallback;
+    glm_cfg.auto_rotate = cli.auto_rotate;
+    glm_cfg.continue_on_partial = cli.continue_on_partial;
+    glm_cfg.doc_type = cli.doc_type;
+    glm_cfg.language_hint = cli.ocr_language;
+    if let Some(prompt) = cli.ocr_prompt.filter(|prompt| !prompt.trim().is_empty()) {
+        glm_cfg.ocr_prompt = Some(prompt);
+    }
+    if let Some(prompt) = cli
+        .file_parse_prompt
+        .filter(|prompt| !prompt.trim().is_empty())
+    {
+        glm_cfg.file_parse_prompt = Some(prompt);
+    }
+    glm_cfg.normalize_page_breaks = !cli.raw_page_breaks;
+    if let Some(max_pages) = cli.max_pages {
+        glm_cfg.max_pages = max_pages;
+    }
+    glm_cfg.pages = cli.pages;
+    if let Some(max_dimension) = cli.max_dimension {
+        glm_cfg.max_dimension = max_dimension;
+    }
 
-    let llm_cfg = LlmConfig::from_sources(
-        cli.provider,
-        cli.llm_api_key,
-        cli.llm_base_url,
-        cli.llm_model,
-        cli.system_prompt,
-    )?;
+    if let Some(Command::Watch {
+        dir,
+        force,
+        settle_ms,
+    }) = &cli.command
+    {
+        let llm_cfg = llm_cfg.context("watch needs the LLM; drop --dry-run")?;
+        check_toc_target(options.toc.as_ref(), true)?;
+        let pipeline = Pipeline::new(glm_cfg, runtime)?;
+        let llm_client = pipeline.llm_client(&llm_cfg);
+        return watch::watch_dir(
+            dir,
+            WatchJob {
+                ocr_client: pipeline.ocr_client(),
+                llm_client: &llm_client,
+                options: &options,
+                output_dir: cli.output_dir.as_deref(),
+                settle: Duration::from_millis(*settle_ms),
+                force: *force,
+                trace_id: &trace_id,
+            },
+        )
+        .await;
+    }
+
+    let inputs = expand_inputs(&cli.input)?;
+    check_stdin_input(&inputs, cli.output_dir.as_deref())?;
+    if cli.output.is_some() && inputs.len() > 1 && !cli.append {
+        anyhow::bail!(
+            "--output takes a single input; use --output-dir for several files or --append to combine them"
+        );
+    }
+    check_toc_target(options.toc.as_ref(), inputs.len() > 1)?;
+    if cli.append && cli.concurrency.get() > 1 {
+        anyhow::bail!("--append combines files in input order and cannot run with --concurrency");
+    }
+    let log = ManifestLog::open(cli.manifest.clone(), cli.resume.clone())?;
+    let all_inputs = inputs;
+    let inputs = log.pending(&all_inputs);
+    if inputs.is_empty() {
+        println!("nothing to resume: every input is already converted");
+        return Ok(());
+    }
+    if inputs.len() < all_inputs.len() {
+        info!(
+            skipped = all_inputs.len() - inputs.len(),
+            "resume_skipped_done"
+        );
+    }
+    let webhook = cli
+        .webhook_url
+        .filter(|url| !url.trim().is_empty())
+        .map(|url| Webhook::new(url.trim(), &runtime))
+        .transpose()?;
+
+    let Some(llm_cfg) = llm_cfg else {
+        info!("dry run: skipping LLM structuring, writing raw OCR text");
+        let report = Manifest::new(
+            dry_run(
+                &inputs,
+                cli.output,
+                cli.output_dir.as_deref(),
+                &Pipeline::new(glm_cfg, runtime)?,
+                &options,
+                &log,
+                &trace_id,
+            )
+            .await?,
+        );
+        log.finish(&all_inputs)?;
+        if report.failed > 0 {
+            anyhow::bail!("{} file(s) failed OCR", report.failed);
+        }
+        return Ok(());
+    };
+
+    if let [input_path] = inputs.as_slice() {
+        let output_path = resolve_output_path(
+            input_path,
+            cli.output,
+            cli.output_dir.as_deref(),
+            cli.format,
+        );
+        let (options, report) = tracking_file_report(&options);
+        let result = process_file_with(
+            input_path,
+            &output_path,
+            glm_cfg,
+            llm_cfg,
+            runtime,
+            &options,
+            &trace_id,
+        )
+        .await;
+        notify(
+            webhook.as_ref(),
+            &trace_id,
+            input_path,
+            &output_path,
+            &result,
+        )
+        .await;
+        let outcome = report
+            .lock()
+            .unwrap()
+            .outcome(input_path, &output_path, &result);
+        log.record(outcome);
+        log.finish(&all_inputs)?;
+        return result;
+    }
+
+    if let Some(dir) = &cli.output_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create output dir: {}", dir.display()))?;
+    }
+
+    let pipeline = Pipeline::new(glm_cfg, runtime)?.with_llm_configs(vec![llm_cfg]);
+    let display = Mutex::new(ProgressDisplay::new(inputs.len(), cli.quiet));
+    let conversions = inputs.iter().enumerate().map(|(index, input_path)| {
+        let output_path = resolve_output_path(
+            input_path,
+            cli.output.clone(),
+            cli.output_dir.as_deref(),
+            cli.format,
+        );
+        let (pipeline, options, webhook, display, log) =
+            (&pipeline, &options, webhook.as_ref(), &display, &log);
+        let file_trace = format!("{trace_id}-{index}");
+        async move {
+            let file = input_path.display().to_string();
+            display
+                .lock()
+                .unwrap()
+                .handle(BatchEvent::Started { file: file.clone() });
+            let (file_options, report) = tracking_file_report(options);
+            let result = pipeline
+                .convert_with(input_path, &output_path, &file_options, &file_trace)
+                .await
+                .map(|_| ());
+            notify(webhook, &file_trace, input_path, &output_path, &result).await;
+            let outcome = report
+                .lock()
+                .unwrap()
+                .outcome(input_path, &output_path, &result);
+            display.lock().unwrap().handle(match result {
+                Ok(()) => BatchEvent::Succeeded { file },
+                Err(err) => BatchEvent::Failed {
+                    file,
+                    error: format!("{err:#}"),
+                },
+            });
+            log.record(outcome.clone());
+            outcome
+        }
+    });
+    let report = Manifest::new(run_concurrently(conversions, cli.concurrency.get()).await);
+    display.into_inner().unwrap().finish();
+
+    for job in report.jobs.iter().filter(|job| !job.success) {
+        eprintln!(
+            "failed {}: {}",
+            job.input,
+            job.error.as_deref().unwrap_or_default()
+        );
+    }
+    log.finish(&all_inputs)?;
+    println!(
+        "converted {} file(s), {} failed",
+        report.succeeded, report.failed
+    );
+    if report.failed > 0 {
+        anyhow::bail!("{} file(s) failed to convert", report.failed);
+    }
 
-    process_file(
-        &input_path,
-        &output_path,
-        glm_cfg,
-        llm_cfg,
+    Ok(())
+}
+
+// Runs up to `limit` jobs at once. Results come back in job order rather
+// than completion order, so summaries and manifests stay deterministic.
+async fn run_concurrently<T>(
+    jobs: impl Iterator<Item = impl Future<Output = T>>,
+    limit: usize,
+) -> Vec<T> {
+    let mut results: Vec<(usize, T)> = stream::iter(
+        jobs.enumerate()
+            .map(|(index, job)| async move { (index, job.await) }),
+    )
+    .buffer_unordered(limit)
+    .collect()
+    .await;
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// Resolves the configuration the same way a conversion would, without any
+// network call; keys are reported as set or missing, never by value.
+fn config_report(cli: &Cli, runtime: RuntimeConfig) -> ConfigReport {
+    let llm = LlmSummary::resolve(
+        cli.provider,
+        cli.llm_base_url.clone(),
+        cli.llm_model.clone(),
+    );
+    let ocr = OcrSummary::resolve(
+        cli.ocr_provider,
+        cli.glm_base_url.clone(),
+        cli.glm_ocr_model.clone(),
+        cli.glm_ocr_url.clone(),
+        cli.glm_file_parse_url.clone(),
+        &runtime,
+    );
+    let gemini_key = std::env::var("GEMINI_API_KEY").ok();
+    let openai_key = std::env::var("OPENAI_API_KEY").ok();
+    ConfigReport::new(
+        llm,
+        ocr,
+        &[
+            ("LLM_API_KEY", cli.llm_api_key.as_deref()),
+            ("GLM_API_KEY", cli.glm_api_key.as_deref()),
+            ("GEMINI_API_KEY", gemini_key.as_deref()),
+            ("OPENAI_API_KEY", openai_key.as_deref()),
+        ],
         runtime,
-        &trace_id,
     )
-    .await?;
+}
+
+async fn notify(
+    webhook: Option<&Webhook>,
+    trace_id: &str,
+    input_path: &Path,
+    output_path: &Path,
+    result: &Result<()>,
+) {
+    let Some(webhook) = webhook else {
+        return;
+    };
+    let outcome = match result {
+        Ok(()) => Ok(output_path),
+        Err(err) => Err(format!("{err:#}")),
+    };
+    webhook
+        .notify(&JobNotification::from_outcome(
+            trace_id, input_path, outcome,
+        ))
+        .await;
+}
+
+// The manifest of this run (`--manifest`, or the one `--resume` continues),
+// rewritten after every file so an interrupted batch loses at most the
+// files still in flight.
+struct ManifestLog {
+    path: Option<PathBuf>,
+    manifest: Mutex<Manifest>,
+}
+
+impl ManifestLog {
+    // A missing resume manifest starts a fresh one, so the same command line
+    // both starts a batch and picks it back up.
+    fn open(manifest: Option<PathBuf>, resume: Option<PathBuf>) -> Result<Self> {
+        let previous = match &resume {
+            Some(path) if path.exists() => Manifest::load(path)?,
+            _ => Manifest::new(Vec::new()),
+        };
+        Ok(Self {
+            path: resume.or(manifest),
+            manifest: Mutex::new(previous),
+        })
+    }
+
+    fn pending(&self, inputs: &[PathBuf]) -> Vec<PathBuf> {
+        let manifest = self.manifest.lock().unwrap();
+        inputs
+            .iter()
+            .filter(|input| !manifest.is_done(input))
+            .cloned()
+            .collect()
+    }
+
+    // A failed write only costs resumability until the next file; `finish`
+    // reports it for real.
+    fn record(&self, outcome: JobOutcome) {
+        let mut manifest = self.manifest.lock().unwrap();
+        manifest.record(outcome);
+        if let Some(path) = &self.path
+            && let Err(err) = manifest.write_to(path)
+        {
+            warn!(manifest = %path.display(), error = %err, "manifest_write_failed");
+        }
+    }
+
+    fn finish(&self, inputs: &[PathBuf]) -> Result<()> {
+        let mut manifest = self.manifest.lock().unwrap();
+        manifest.sort_by_inputs(inputs);
+        match &self.path {
+            Some(path) => manifest.write_to(path),
+            None => Ok(()),
+        }
+    }
+}
+
+// What the pipeline reported about one file, for its manifest entry.
+#[derive(Debug, Default)]
+struct FileReport {
+    ocr_chars: Option<usize>,
+    skip_reason: Option<String>,
+}
+
+impl FileReport {
+    fn outcome(&self, input: &Path, output: &Path, result: &Result<()>) -> JobOutcome {
+        let outcome = JobOutcome::from_result(input, output, self.ocr_chars, result);
+        if self.skip_reason.is_some() {
+            outcome.skipped()
+        } else {
+            outcome
+        }
+    }
+}
+
+// Wraps the caller's progress sink so the OCR character count of one file,
+// and whether it was skipped, can be read back once it finishes.
+fn tracking_file_report(options: &ProcessOptions) -> (ProcessOptions, Arc<Mutex<FileReport>>) {
+    let report = Arc::new(Mutex::new(FileReport::default()));
+    let seen = report.clone();
+    let inner = options.progress.clone();
+    let options = ProcessOptions {
+        progress: Some(ProgressSink::new(move |event| {
+            match &event {
+                ProgressEvent::OcrDone { chars } => seen.lock().unwrap().ocr_chars = Some(*chars),
+                ProgressEvent::Skipped { reason } => {
+                    seen.lock().unwrap().skip_reason = Some(reason.clone())
+                }
+                _ => {}
+            }
+            if let Some(inner) = &inner {
+                inner.report(event);
+            }
+        })),
+        ..options.clone()
+    };
+    (options, report)
+}
+
+async fn dry_run(
+    inputs: &[PathBuf],
+    output: Option<PathBuf>,
+    output_dir: Option<&Path>,
+    pipeline: &Pipeline,
+    options: &ProcessOptions,
+    log: &ManifestLog,
+    trace_id: &str,
+) -> Result<Vec<JobOutcome>> {
+    if let Some(dir) = output_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create output dir: {}", dir.display()))?;
+    }
+
+    let mut outcomes = Vec::with_capacity(inputs.len());
+    for (index, input_path) in inputs.iter().enumerate() {
+        let output_path =
+            resolve_output_path(input_path, output.clone(), output_dir, options.format);
+        let (file_options, report) = tracking_file_report(options);
+        let result = ocr_only(
+            input_path,
+            &output_path,
+            pipeline.ocr_client(),
+            &file_options,
+            &format!("{trace_id}-{index}"),
+        )
+        .await
+        .map(|written| match written {
+            Some(ocr_path) => println!("wrote {}", ocr_path.display()),
+            None => println!(
+                "skipped {}: {}",
+                input_path.display(),
+                report
+                    .lock()
+                    .unwrap()
+                    .skip_reason
+                    .as_deref()
+                    .unwrap_or_default()
+            ),
+        });
+        if let Err(err) = &result {
+            eprintln!("failed {}: {err:#}", input_path.display());
+        }
+        let outcome =
+            report
+                .lock()
+                .unwrap()
+                .outcome(input_path, &ocr_sidecar_path(&output_path), &result);
+        log.record(outcome.clone());
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+// Arguments containing glob metacharacters are expanded (sorted, files only);
+// anything else is taken literally so a missing file still fails loudly later.
+fn expand_inputs(args: &[String]) -> Result<Vec<PathBuf>> {
+    let mut inputs = Vec::new();
+    for arg in args {
+        if !arg.contains(['*', '?', '[']) {
+            inputs.push(PathBuf::from(arg));
+            continue;
+        }
+
+        let mut matches = glob::glob(arg)
+            .with_context(|| format!("invalid glob pattern: {arg}"))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            eprintln!("no files match {arg}");
+        }
+        matches.sort();
+        inputs.extend(matches);
+    }
+
+    let mut seen = HashSet::new();
+    inputs.retain(|path| seen.insert(path.clone()));
+    if inputs.is_empty() {
+        anyhow::bail!("no input files matched");
+    }
+    Ok(inputs)
+}
+
+// Stdin can only be read once and has no directory or stem to name an
+// output after, so it stands alone and writes to stdout or `--output`.
+fn check_stdin_input(inputs: &[PathBuf], output_dir: Option<&Path>) -> Result<()> {
+    if !inputs.iter().any(|input| is_stdin(input)) {
+        return Ok(());
+    }
+    if inputs.len() > 1 {
+        anyhow::bail!("stdin (-) must be the only input");
+    }
+    if output_dir.is_some() {
+        anyhow::bail!("stdin input writes to stdout or --output, not --output-dir");
+    }
+    Ok(())
+}
 
+// A JSON outline path names one file, so it cannot serve several outputs.
+fn check_toc_target(toc: Option<&TocTarget>, several_outputs: bool) -> Result<()> {
+    if several_outputs && matches!(toc, Some(TocTarget::Json(_))) {
+        anyhow::bail!("--toc <path> takes a single input; use --toc inline for several files");
+    }
     Ok(())
 }
 
-fn init_tracing() {
-    let filter = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
-    let _ = tracing_subscriber::fmt()
-        .with_env_filter(filter)
+// The console and the optional trace file are separate layers with their own
+// filters, so adding the file never changes what reaches stderr.
+fn init_tracing(filter: &str, trace_file: Option<impl Layer<Registry> + Send + Sync>) {
+    let console = tracing_subscriber::fmt::layer()
         .with_target(false)
+        .with_writer(std::io::stderr);
+    // JSON lines put event fields (trace_id, status, latency_ms, ...) at the
+    // top level of each object so log pipelines can index them directly.
+    let console = if json_logs(std::env::var("OCR2MD_LOG_FORMAT").ok().as_deref()) {
+        console
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .boxed()
+    } else {
+        console.boxed()
+    };
+    let _ = tracing_subscriber::registry()
+        .with(trace_file)
+        .with(console.with_filter(EnvFilter::new(filter)))
         .try_init();
 }
 
+// `--env-file` values, picked out of the raw arguments: clap resolves the
+// `env = ...` defaults while parsing, so the files must be loaded first.
+fn env_file_args(args: impl IntoIterator<Item = OsString>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--env-file" {
+            files.extend(args.next().map(PathBuf::from));
+        } else if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--env-file=")) {
+            files.push(PathBuf::from(path));
+        }
+    }
+    files
+}
+
+// Without `--env-file` a missing ./.env is fine; a named file must exist.
+// dotenvy never replaces a variable that is already set, so the files are
+// loaded last-first to let later ones win.
+fn load_env_files(files: &[PathBuf]) -> Result<()> {
+    if files.is_empty() {
+        dotenvy::dotenv().ok();
+        return Ok(());
+    }
+    for path in files.iter().rev() {
+        dotenvy::from_path(path)
+            .with_context(|| format!("failed to load env file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+// Default filter picked by `-q` / `-v` / `-vv`; an explicit `RUST_LOG`
+// overrides it.
+fn log_filter(verbose: u8, quiet: bool) -> &'static str {
+    match (quiet, verbose) {
+        (true, _) => "error",
+        (false, 0) => "info",
+        (false, 1) => "debug",
+        (false, _) => "trace",
+    }
+}
+
+// `--force` wins over `--skip-existing`, including one set through the
+// environment.
+fn skips_existing(skip_existing: bool, force: bool) -> bool {
+    skip_existing && !force
+}
+
+fn trace_file_level(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
+
+fn json_logs(format: Option<&str>) -> bool {
+    format.is_some_and(|format| format.trim().eq_ignore_ascii_case("json"))
+}
+
 fn default_trace_id() -> String {
     let ts = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -71,35 +753,280 @@ fn default_trace_id() -> String {
     format!("trace-{ts}-{}", std::process::id())
 }
 
-fn resolve_output_path(input: &Path, output: Option<PathBuf>) -> PathBuf {
+// `ocr2md in.pdf -` pipes the Markdown to stdout, same as `-o -`.
+fn take_stdout_output(inputs: &mut Vec<String>, output: Option<PathBuf>) -> Option<PathBuf> {
+    if output.is_none() && inputs.len() > 1 && inputs.last().is_some_and(|last| last == STDOUT_PATH)
+    {
+        inputs.pop();
+        return Some(PathBuf::from(STDOUT_PATH));
+    }
+    output
+}
+
+fn resolve_output_path(
+    input: &Path,
+    output: Option<PathBuf>,
+    output_dir: Option<&Path>,
+    format: OutputFormat,
+) -> PathBuf {
     if let Some(path) = output {
         return path;
     }
+    if is_stdin(input) {
+        return PathBuf::from(STDOUT_PATH);
+    }
 
+    let extension = format.extension();
     if let Some(stem) = input.file_stem().and_then(|value| value.to_str()) {
-        let mut path = input.to_path_buf();
-        path.set_file_name(format!("{stem}.md"));
-        path
+        let file_name = format!("{stem}.{extension}");
+        match output_dir {
+            Some(dir) => dir.join(file_name),
+            None => input.with_file_name(file_name),
+        }
     } else {
-        PathBuf::from("output.md")
+        output_dir
+            .unwrap_or(Path::new(""))
+            .join(format!("output.{extension}"))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
 
-    use super::resolve_output_path;
+    use super::{
+        ManifestLog, check_stdin_input, env_file_args, expand_inputs, json_logs, load_env_files,
+        log_filter, resolve_output_path, run_concurrently, skips_existing, take_stdout_output,
+    };
+    use crate::cli::Cli;
+    use clap::Parser;
     use ocr2md_core::file_kind::{InputKind, detect_input_kind};
+    use ocr2md_core::format::OutputFormat;
+    use ocr2md_core::manifest::{JobOutcome, Manifest};
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn log_format_is_text_unless_json_is_asked_for() {
+        assert!(json_logs(Some("json")));
+        assert!(json_logs(Some(" JSON ")));
+        assert!(!json_logs(Some("text")));
+        assert!(!json_logs(Some("")));
+        assert!(!json_logs(None));
+    }
+
+    #[test]
+    fn env_files_are_taken_from_raw_arguments_in_order() {
+        let args = [
+            "ocr2md",
+            "scan.pdf",
+            "--env-file",
+            "openai.env",
+            "--env-file=local.env",
+            "--",
+            "--env-file",
+            "not-a-flag.env",
+        ]
+        .map(Into::into);
+
+        assert_eq!(
+            env_file_args(args),
+            vec![
+                std::path::PathBuf::from("openai.env"),
+                std::path::PathBuf::from("local.env")
+            ]
+        );
+    }
+
+    #[test]
+    fn later_env_files_win_and_missing_ones_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.env");
+        let second = dir.path().join("second.env");
+        std::fs::write(
+            &first,
+            "OCR2MD_TEST_ENV_FILE=first\nOCR2MD_TEST_ENV_ONLY_FIRST=1\n",
+        )
+        .unwrap();
+        std::fs::write(&second, "OCR2MD_TEST_ENV_FILE=second\n").unwrap();
+
+        load_env_files(&[first, second]).unwrap();
+        assert_eq!(std::env::var("OCR2MD_TEST_ENV_FILE").unwrap(), "second");
+        assert_eq!(std::env::var("OCR2MD_TEST_ENV_ONLY_FIRST").unwrap(), "1");
+
+        let err = load_env_files(&[dir.path().join("missing.env")]).unwrap_err();
+        assert!(err.to_string().contains("missing.env"), "{err}");
+    }
+
+    #[test]
+    fn verbosity_flags_pick_the_log_filter() {
+        assert_eq!(log_filter(0, false), "info");
+        assert_eq!(log_filter(1, false), "debug");
+        assert_eq!(log_filter(2, false), "trace");
+        assert_eq!(log_filter(5, false), "trace");
+        assert_eq!(log_filter(0, true), "error");
+    }
+
+    #[test]
+    fn force_overrides_skip_existing() {
+        let parse = |args: &[&str]| {
+            let cli =
+                Cli::try_parse_from(["ocr2md"].iter().chain(args).chain(&["scan.pdf"])).unwrap();
+            skips_existing(cli.skip_existing, cli.force)
+        };
+
+        assert!(!parse(&[]));
+        assert!(parse(&["--skip-existing"]));
+        assert!(!parse(&["--skip-existing", "--force"]));
+        assert!(!parse(&["--force"]));
+    }
+
     #[test]
     fn output_path_defaults_to_same_dir_md() {
         let input = Path::new("/tmp/demo.pdf");
-        let out = resolve_output_path(input, None);
+        let out = resolve_output_path(input, None, None, OutputFormat::Md);
         assert_eq!(out.to_string_lossy(), "/tmp/demo.md");
     }
 
+    #[test]
+    fn output_dir_collects_markdown() {
+        let input = Path::new("/scans/demo.pdf");
+        let out = resolve_output_path(input, None, Some(Path::new("/out")), OutputFormat::Md);
+        assert_eq!(out.to_string_lossy(), "/out/demo.md");
+    }
+
+    #[test]
+    fn output_extension_follows_the_format() {
+        let input = Path::new("/scans/demo.pdf");
+        let html = resolve_output_path(input, None, None, OutputFormat::Html);
+        assert_eq!(html.to_string_lossy(), "/scans/demo.html");
+        let txt = resolve_output_path(input, None, Some(Path::new("/out")), OutputFormat::Txt);
+        assert_eq!(txt.to_string_lossy(), "/out/demo.txt");
+        let explicit = resolve_output_path(
+            input,
+            Some(Path::new("/x/notes.md").to_path_buf()),
+            None,
+            OutputFormat::Html,
+        );
+        assert_eq!(explicit.to_string_lossy(), "/x/notes.md");
+    }
+
+    #[test]
+    fn trailing_dash_means_stdout() {
+        let mut inputs = vec!["in.pdf".to_string(), "-".to_string()];
+        let output = take_stdout_output(&mut inputs, None);
+        assert_eq!(output.as_deref(), Some(Path::new("-")));
+        assert_eq!(inputs, vec!["in.pdf".to_string()]);
+
+        let mut inputs = vec!["in.pdf".to_string()];
+        assert_eq!(take_stdout_output(&mut inputs, None), None);
+    }
+
+    #[test]
+    fn stdin_input_stands_alone_and_defaults_to_stdout() {
+        let stdin = Path::new("-");
+        assert_eq!(
+            resolve_output_path(stdin, None, None, OutputFormat::Md),
+            Path::new("-")
+        );
+        assert_eq!(
+            resolve_output_path(stdin, Some("scan.md".into()), None, OutputFormat::Md),
+            Path::new("scan.md")
+        );
+
+        assert!(check_stdin_input(&["-".into()], None).is_ok());
+        assert!(check_stdin_input(&["-".into(), "a.pdf".into()], None).is_err());
+        assert!(check_stdin_input(&["-".into()], Some(Path::new("out"))).is_err());
+        assert!(check_stdin_input(&["a.pdf".into()], Some(Path::new("out"))).is_ok());
+    }
+
+    #[tokio::test]
+    async fn concurrent_jobs_keep_their_order_and_limit() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let jobs = [30, 10, 20, 0].into_iter().map(|delay_ms| {
+            let (running, peak) = (&running, &peak);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                delay_ms
+            }
+        });
+
+        assert_eq!(run_concurrently(jobs, 2).await, vec![30, 10, 20, 0]);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn globs_expand_to_sorted_unique_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["b.pdf", "a.pdf", "notes.txt"] {
+            std::fs::write(dir.path().join(name), b"x").unwrap();
+        }
+        let pattern = dir.path().join("*.pdf").to_string_lossy().into_owned();
+        let literal = dir.path().join("a.pdf").to_string_lossy().into_owned();
+
+        let inputs = expand_inputs(&[pattern.clone(), literal]).unwrap();
+        assert_eq!(
+            inputs,
+            vec![dir.path().join("a.pdf"), dir.path().join("b.pdf")]
+        );
+
+        let empty = dir.path().join("*.docx").to_string_lossy().into_owned();
+        assert!(expand_inputs(&[empty]).is_err());
+    }
+
+    #[test]
+    fn resume_redoes_failed_changed_and_unlisted_inputs_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let [done, changed, failed, fresh] =
+            ["a.pdf", "b.pdf", "c.pdf", "d.pdf"].map(|name| dir.path().join(name));
+        for input in [&done, &changed, &failed, &fresh] {
+            std::fs::write(input, b"%PDF-1.7").unwrap();
+        }
+        let ok = |input: &Path| JobOutcome::from_result(input, Path::new("x.md"), Some(1), &Ok(()));
+        let error = Err(anyhow::anyhow!("interrupted"));
+        let manifest_path = dir.path().join("manifest.json");
+        Manifest::new(vec![
+            ok(&done),
+            ok(&changed),
+            JobOutcome::from_result(&failed, Path::new("x.md"), None, &error),
+        ])
+        .write_to(&manifest_path)
+        .unwrap();
+        std::fs::write(&changed, b"%PDF-1.7 edited since").unwrap();
+
+        let log = ManifestLog::open(None, Some(manifest_path.clone())).unwrap();
+        let inputs = vec![done.clone(), changed.clone(), failed.clone(), fresh.clone()];
+        assert_eq!(
+            log.pending(&inputs),
+            [changed.clone(), failed.clone(), fresh.clone()]
+        );
+
+        // Each file lands in the manifest as soon as it finishes.
+        log.record(ok(&fresh));
+        let on_disk = Manifest::load(&manifest_path).unwrap();
+        assert_eq!(on_disk.jobs.len(), 4);
+        assert!(on_disk.is_done(&fresh));
+
+        log.record(ok(&failed));
+        log.record(ok(&changed));
+        log.finish(&inputs).unwrap();
+        let on_disk = Manifest::load(&manifest_path).unwrap();
+        assert_eq!((on_disk.succeeded, on_disk.failed), (4, 0));
+        assert!(inputs.iter().all(|input| on_disk.is_done(input)));
+        assert_eq!(on_disk.jobs[3].input, fresh.display().to_string());
+        assert!(
+            ManifestLog::open(None, Some(manifest_path))
+                .unwrap()
+                .pending(&inputs)
+                .is_empty()
+        );
+    }
+
     #[test]
     fn detect_supported_kinds() {
         assert_eq!(
@@ -114,5 +1041,9 @@ mod tests {
             detect_input_kind(Path::new("c.docx")).ok(),
             Some(InputKind::Docx)
         );
+        assert_eq!(
+            detect_input_kind(Path::new("d.JPG")).ok(),
+            Some(InputKind::Jpeg)
+        );
     }
 }
@@ -0,0 +1,151 @@
+use std::fmt;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context as _, Result};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::{Context, Filter};
+use tracing_subscriber::registry::LookupSpan;
+
+// JSON events of one run, appended to `path` next to the console output.
+// Each event is written whole under the mutex and the file is opened in
+// append mode, so jobs of a batch (and other runs sharing the file) never
+// split each other's lines. Writes are unbuffered: every line is in the file
+// as soon as it is logged, so nothing is lost when the process exits.
+pub fn layer<S>(
+    path: &Path,
+    trace_id: &str,
+    level: LevelFilter,
+) -> Result<impl Layer<S> + Send + Sync + 'static>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(parent).context("failed to create trace file directory")?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open trace file: {}", path.display()))?;
+
+    Ok(tracing_subscriber::fmt::layer()
+        .json()
+        .flatten_event(true)
+        .with_current_span(true)
+        .with_target(false)
+        .with_ansi(false)
+        .with_writer(Mutex::new(file))
+        .with_filter(RunFilter {
+            trace_id: trace_id.to_string(),
+            level,
+        }))
+}
+
+// Keeps events whose `trace_id` is this run's or one derived from it, as
+// batch files (`<id>-0`, `<id>-1`, ...) and sections (`<id>-s2`) use.
+struct RunFilter {
+    trace_id: String,
+    level: LevelFilter,
+}
+
+impl RunFilter {
+    fn matches(&self, trace_id: &str) -> bool {
+        trace_id
+            .strip_prefix(&self.trace_id)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+    }
+}
+
+impl<S> Filter<S> for RunFilter {
+    fn enabled(&self, meta: &Metadata<'_>, _: &Context<'_, S>) -> bool {
+        meta.is_event() && self.level >= *meta.level() && meta.fields().field("trace_id").is_some()
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, _: &Context<'_, S>) -> bool {
+        let mut visitor = TraceIdVisitor(None);
+        event.record(&mut visitor);
+        visitor.0.is_some_and(|trace_id| self.matches(&trace_id))
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.level)
+    }
+}
+
+struct TraceIdVisitor(Option<String>);
+
+impl Visit for TraceIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "trace_id" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    // `trace_id = %id` arrives here as a Display wrapper, which prints the
+    // bare value.
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "trace_id" {
+            self.0 = Some(format!("{value:?}"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing::level_filters::LevelFilter;
+    use tracing::{debug, info};
+    use tracing_subscriber::Registry;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::layer;
+
+    #[test]
+    fn keeps_this_runs_events_in_whole_lines_across_threads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs/run.jsonl");
+        let file_layer = layer::<Registry>(&path, "run-7", LevelFilter::INFO).unwrap();
+        let dispatch = tracing::Dispatch::new(tracing_subscriber::registry().with(file_layer));
+
+        let threads: Vec<_> = (0..8)
+            .map(|job| {
+                let dispatch = dispatch.clone();
+                std::thread::spawn(move || {
+                    tracing::dispatcher::with_default(&dispatch, || {
+                        let file_trace = format!("run-7-{job}");
+                        for n in 0..50 {
+                            info!(trace_id = %file_trace, n, body = "x".repeat(512), "http_response");
+                        }
+                        info!(trace_id = "run-70", "other_run");
+                        info!(trace_id = "run-8-1", "other_run");
+                        debug!(trace_id = %file_trace, "too_verbose");
+                        info!("no_trace_id");
+                    });
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        tracing::dispatcher::with_default(&dispatch, || {
+            info!(trace_id = "run-7", "pipeline_start");
+        });
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let events: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).expect(line))
+            .collect();
+        assert_eq!(events.len(), 8 * 50 + 1);
+        assert!(events.iter().all(
+            |event| event["message"] == "http_response" || event["message"] == "pipeline_start"
+        ));
+    }
+}
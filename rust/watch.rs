@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use notify::event::{AccessKind, AccessMode};
+use notify::{EventKind, RecursiveMode, Watcher};
+use ocr2md_core::file_kind::detect_input_kind;
+use ocr2md_core::llm::LlmClient;
+use ocr2md_core::ocr::GlmOcrClient;
+use ocr2md_core::pipeline::{ProcessOptions, is_up_to_date, process_file_using};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct WatchJob<'a> {
+    pub ocr_client: &'a GlmOcrClient,
+    pub llm_client: &'a LlmClient,
+    pub options: &'a ProcessOptions,
+    pub output_dir: Option<&'a Path>,
+    pub settle: Duration,
+    pub force: bool,
+    pub trace_id: &'a str,
+}
+
+// Converts supported files that appear in `dir` until the process is stopped.
+// Files already present when the watch starts are left alone.
+pub async fn watch_dir(dir: &Path, job: WatchJob<'_>) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let _ = tx.send(event);
+    })
+    .context("failed to start file watcher")?;
+    watcher
+        .watch(dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("failed to watch {}", dir.display()))?;
+    info!(dir = %dir.display(), "watch_started");
+
+    let mut pending = PendingFiles::new(job.settle);
+    let mut converted = 0usize;
+    let mut ticker = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = rx.recv() => {
+                let Some(event) = event else { break };
+                match event {
+                    Ok(event) if is_write_event(&event.kind) => {
+                        for path in event.paths.into_iter().filter(|path| is_convertible(path)) {
+                            pending.observe(path, Instant::now());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(err) => warn!(error = %err, "watch_event_error"),
+                }
+            }
+            _ = ticker.tick() => {
+                for input_path in pending.settled(Instant::now(), file_len) {
+                    let output_path = crate::resolve_output_path(
+                        &input_path,
+                        None,
+                        job.output_dir,
+                        job.options.format,
+                    );
+                    if !job.force && is_up_to_date(&input_path, &output_path) {
+                        info!(input = %input_path.display(), "watch_skip_converted");
+                        continue;
+                    }
+
+                    let trace_id = format!("{}-w{converted}", job.trace_id);
+                    converted += 1;
+                    match process_file_using(
+                        &input_path,
+                        &output_path,
+                        job.ocr_client,
+                        job.llm_client,
+                        job.options,
+                        &trace_id,
+                    )
+                    .await
+                    {
+                        Ok(()) => println!("converted {}", input_path.display()),
+                        Err(err) => eprintln!("failed {}: {err:#}", input_path.display()),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_write_event(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_)
+            | EventKind::Modify(_)
+            | EventKind::Access(AccessKind::Close(AccessMode::Write))
+    )
+}
+
+fn is_convertible(path: &Path) -> bool {
+    detect_input_kind(path).is_ok()
+}
+
+fn file_len(path: &Path) -> Option<u64> {
+    std::fs::metadata(path).ok().map(|meta| meta.len())
+}
+
+// Debounces bursts of events per path: a file is handed out once no event
+// arrived and its size stayed the same for `settle`, i.e. the writer is done.
+#[derive(Debug)]
+pub struct PendingFiles {
+    settle: Duration,
+    files: HashMap<PathBuf, (Instant, Option<u64>)>,
+}
+
+impl PendingFiles {
+    pub fn new(settle: Duration) -> Self {
+        Self {
+            settle,
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, path: PathBuf, now: Instant) {
+        let size = self.files.get(&path).and_then(|(_, size)| *size);
+        self.files.insert(path, (now, size));
+    }
+
+    pub fn settled<F>(&mut self, now: Instant, len: F) -> Vec<PathBuf>
+    where
+        F: Fn(&Path) -> Option<u64>,
+    {
+        let mut ready = Vec::new();
+        self.files.retain(|path, (last_change, last_size)| {
+            let size = len(path);
+            if size.is_none() {
+                return false;
+            }
+            if size != *last_size {
+                *last_change = now;
+                *last_size = size;
+                return true;
+            }
+            if now.duration_since(*last_change) < self.settle {
+                return true;
+            }
+            ready.push(path.clone());
+            false
+        });
+        ready.sort();
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    use pretty_assertions::assert_eq;
+
+    use super::PendingFiles;
+
+    #[test]
+    fn file_is_ready_once_size_stops_changing() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let size = Cell::new(10);
+        let len = |_: &std::path::Path| Some(size.get());
+        let mut pending = PendingFiles::new(Duration::from_millis(500));
+
+        pending.observe(PathBuf::from("scan.pdf"), at(0));
+        assert!(pending.settled(at(100), len).is_empty());
+
+        size.set(20);
+        assert!(pending.settled(at(400), len).is_empty());
+        assert!(pending.settled(at(800), len).is_empty());
+
+        assert_eq!(
+            pending.settled(at(900), len),
+            vec![PathBuf::from("scan.pdf")]
+        );
+        assert!(pending.settled(at(2000), len).is_empty());
+    }
+
+    #[test]
+    fn repeated_events_push_the_deadline_back() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let len = |_: &std::path::Path| Some(1);
+        let mut pending = PendingFiles::new(Duration::from_millis(500));
+
+        pending.observe(PathBuf::from("a.docx"), at(0));
+        assert!(pending.settled(at(10), len).is_empty());
+        pending.observe(PathBuf::from("a.docx"), at(400));
+        assert!(pending.settled(at(600), len).is_empty());
+        assert_eq!(pending.settled(at(950), len), vec![PathBuf::from("a.docx")]);
+    }
+
+    #[test]
+    fn deleted_files_are_dropped() {
+        let mut pending = PendingFiles::new(Duration::ZERO);
+        pending.observe(PathBuf::from("gone.pdf"), Instant::now());
+        assert!(pending.settled(Instant::now(), |_| None).is_empty());
+        assert!(pending.files.is_empty());
+    }
+}